@@ -7,6 +7,10 @@ pub enum Error {
     #[error("Assertion failed: {0}")]
     AssertionFailed(String),
 
+    /// An auth event does not answer the relay's challenge
+    #[error("Auth event does not answer the challenge")]
+    AuthChallengeMismatch,
+
     /// Bad Encrypted Message
     #[error("Bad Encrypted Message")]
     BadEncryptedMessage,
@@ -19,18 +23,50 @@ pub enum Error {
     #[error("Bech32 Error: {0}")]
     Bech32(#[from] bech32::Error),
 
+    /// BIP-39 mnemonic error
+    #[error("Mnemonic Error: {0}")]
+    Bip39(#[from] bip39::Error),
+
+    /// An event chain is not linked up as it should be
+    #[error("Event chain broken at event {0}: {1}")]
+    BrokenChain(usize, String),
+
+    /// An identity cannot sign here
+    #[error("Cannot sign: {0}")]
+    CannotSign(String),
+
+    /// CBOR decoding error
+    #[cfg(feature = "cbor")]
+    #[error("CBOR Decoding Error: {0}")]
+    CborDecode(#[from] ciborium::de::Error<std::io::Error>),
+
+    /// CBOR encoding error
+    #[cfg(feature = "cbor")]
+    #[error("CBOR Encoding Error: {0}")]
+    CborEncode(#[from] ciborium::ser::Error<std::io::Error>),
+
+    /// Compact binary encoding error
+    #[error("Compact Encoding Error: {0}")]
+    Compact(String),
+
+    /// Another error, with what was being done when it happened
+    #[error("{context}: {source}")]
+    Context {
+        /// What was being done
+        context: String,
+        /// The underlying error
+        #[source]
+        source: Box<Error>,
+    },
+
+    /// Elliptic curve error
+    #[error("Elliptic Curve Error: {0}")]
+    Curve(#[from] k256::elliptic_curve::Error),
+
     /// Encryption/Decryption Error
     #[error("Encryption/Decryption Error")]
     Encryption,
 
-    /// Bech32 error
-    #[error("Wrong Bech32 Kind: Expected {0} found {0}")]
-    WrongBech32(String, String),
-
-    /// Signature error
-    #[error("ECDSA Signature Error: {0}")]
-    Signature(#[from] k256::ecdsa::Error),
-
     /// Event is in the future
     #[error("Event is in the future")]
     EventInFuture,
@@ -47,10 +83,18 @@ pub enum Error {
     #[error("Hex Decode Error: {0}")]
     HexDecode(#[from] hex::FromHexError),
 
+    /// Not enough proof-of-work
+    #[error("Insufficient proof of work: {0} bits, {1} required")]
+    InsufficientPow(u8, u8),
+
     /// Invalid encrypted private key
     #[error("Invalid Encrypted Private Key")]
     InvalidEncryptedPrivateKey,
 
+    /// Event breaks a rule for its kind
+    #[error("Invalid event: {0}")]
+    InvalidEvent(String),
+
     /// Invalid Event Addr
     #[error("Invalid event addr")]
     InvalidEventAddr,
@@ -59,6 +103,10 @@ pub enum Error {
     #[error("Invalid event pointer")]
     InvalidEventPointer,
 
+    /// Invalid external identity (NIP-39)
+    #[error("Invalid external identity: {0}")]
+    InvalidExternalIdentity(String),
+
     /// Invalid event Id
     #[error("Invalid event Id")]
     InvalidId,
@@ -67,10 +115,22 @@ pub enum Error {
     #[error("Invalid event Id Prefix")]
     InvalidIdPrefix,
 
+    /// Invalid torrent infohash
+    #[error("Invalid infohash: {0}")]
+    InvalidInfohash(String),
+
     /// Invalid digest length
     #[error("Invalid digest length")]
     InvalidLength(#[from] hmac::digest::InvalidLength),
 
+    /// Invalid price amount
+    #[error("Invalid price amount: {0}")]
+    InvalidPrice(String),
+
+    /// Invalid private key
+    #[error("Invalid Private Key")]
+    InvalidPrivateKey,
+
     /// Invalid Profile
     #[error("Invalid Profile")]
     InvalidProfile,
@@ -83,6 +143,14 @@ pub enum Error {
     #[error("Invalid Public Key Prefix")]
     InvalidPublicKeyPrefix,
 
+    /// Invalid signature
+    #[error("Invalid Signature")]
+    InvalidSignature,
+
+    /// Invalid time string
+    #[error("Invalid time: \"{0}\"")]
+    InvalidTime(String),
+
     /// Invalid URL
     #[error("Invalid URL: \"{0}\"")]
     InvalidUrl(#[from] url::ParseError),
@@ -99,10 +167,40 @@ pub enum Error {
     #[error("Invalid URL Scheme: \"{0}\"")]
     InvalidUrlScheme(String),
 
+    /// Invalid zap request (NIP-57)
+    #[error("Invalid zap request: {0}")]
+    InvalidZapRequest(String),
+
     /// Missing URL Authority
     #[error("Missing URL Authority")]
     InvalidUrlMissingAuthority,
 
+    /// I/O error
+    #[error("I/O Error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// JSON is longer than the parse limit
+    #[error("JSON too long: more than {0} bytes")]
+    JsonTooLong(usize),
+
+    /// MessagePack decoding error
+    #[cfg(feature = "msgpack")]
+    #[error("MessagePack Decoding Error: {0}")]
+    MessagePackDecode(#[from] rmp_serde::decode::Error),
+
+    /// MessagePack encoding error
+    #[cfg(feature = "msgpack")]
+    #[error("MessagePack Encoding Error: {0}")]
+    MessagePackEncode(#[from] rmp_serde::encode::Error),
+
+    /// JSON is valid but not in the canonical form
+    #[error("Non-canonical JSON: {0}")]
+    NonCanonicalJson(String),
+
+    /// OpenTimestamps error
+    #[error("OpenTimestamps Error: {0}")]
+    Ots(String),
+
     /// Pad error
     #[error("Encryption/Decryption padding error")]
     Pad(#[from] inout::PadError),
@@ -111,22 +209,62 @@ pub enum Error {
     #[error("Parse integer error")]
     ParseInt(#[from] std::num::ParseIntError),
 
+    /// The nonce tag does not commit to a proof-of-work target
+    #[error("Proof of work target missing")]
+    PowTargetMissing,
+
+    /// The nonce tag commits to a lower proof-of-work target than required
+    #[error("Proof of work target too low: {0} bits, {1} required")]
+    PowTargetTooLow(u32, u8),
+
     /// Scrypt error
     #[error("Scrypt invalid output length")]
-    Scrypt,
+    Scrypt(#[from] scrypt::errors::InvalidOutputLen),
+
+    /// Scrypt parameter error
+    #[error("Scrypt invalid parameters: {0}")]
+    ScryptParams(#[from] scrypt::errors::InvalidParams),
+
+    /// A seal is not signed by the author of the rumor inside it
+    #[error("Seal is not signed by the rumor author")]
+    SealMismatch,
 
     /// Serialization error
     #[error("JSON (de)serialization error: {0}")]
     SerdeJson(#[from] serde_json::Error),
 
+    /// Signature error
+    #[error("ECDSA Signature Error: {0}")]
+    Signature(#[from] k256::ecdsa::Error),
+
     /// Try from slice error
     #[error("Try From Slice error: {0}")]
     Slice(#[from] std::array::TryFromSliceError),
 
+    /// A string is longer than the parse limit
+    #[error("String too long: more than {0} bytes")]
+    StringTooLong(usize),
+
+    /// Tag is not of the expected type
+    #[error("Tag mismatch")]
+    TagMismatch,
+
+    /// Tag is missing a required field
+    #[error("Tag is missing a required field (has {0} fields)")]
+    TagMissingField(usize),
+
     /// Time error
     #[error("System Time Error: {0}")]
     Time(#[from] std::time::SystemTimeError),
 
+    /// A tag has more elements than the parse limit
+    #[error("Too many tag elements: more than {0}")]
+    TooManyTagElements(usize),
+
+    /// An event has more tags than the parse limit
+    #[error("Too many tags: more than {0}")]
+    TooManyTags(usize),
+
     /// Unknown event kind
     #[error("Unknown event kind = {0}")]
     UnknownEventKind(u32),
@@ -139,6 +277,14 @@ pub enum Error {
     #[error("Decryption error: {0}")]
     Unpad(#[from] aes::cipher::block_padding::UnpadError),
 
+    /// Input is not any kind of nostr entity that is recognized
+    #[error("Not a recognized nostr entity: {0}")]
+    UnrecognizedEntity(String),
+
+    /// Unsupported encryption version
+    #[error("Unsupported encryption version = {0}")]
+    UnsupportedEncryptionVersion(u8),
+
     /// Url Error
     #[error("Not a valid nostr relay url: {0}")]
     Url(String),
@@ -159,11 +305,30 @@ pub enum Error {
     #[error("Wrong length bytes for event kind")]
     WrongLengthKindBytes,
 
+    /// A bech32 string has the wrong prefix
+    #[error("Wrong Bech32 Kind: Expected {0} found {1}")]
+    WrongBech32(String, String),
+
     /// Wrong Decryption Password
     #[error("Wrong decryption password")]
     WrongDecryptionPassword,
 
+    /// A key does not belong to the identity it is used for
+    #[error("Key does not match the identity")]
+    WrongPublicKey,
+
     /// Zap Receipt issue
     #[error("Invalid Zap Receipt: {0}")]
     ZapReceipt(String),
 }
+
+impl Error {
+    /// Wrap this error with a note on what was being done when it happened.
+    /// This error is kept as the source.
+    pub fn context(self, context: impl Into<String>) -> Error {
+        Error::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+}
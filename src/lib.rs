@@ -30,13 +30,44 @@ mod error;
 pub use error::Error;
 
 mod types;
+#[cfg(feature = "rayon")]
+pub use types::par_verify;
 pub use types::{
-    find_nostr_bech32_pos, find_nostr_url_pos, ClientMessage, ContentSegment, DelegationConditions,
-    EncryptedPrivateKey, Event, EventAddr, EventDelegation, EventKind, EventKindIterator,
-    EventKindOrRange, EventPointer, Fee, Filter, Id, IdHex, IdHexPrefix, KeySecurity, Metadata,
-    MilliSatoshi, Nip05, NostrBech32, NostrUrl, PayRequestData, PreEvent, PrivateKey, Profile,
-    PublicKey, PublicKeyHex, PublicKeyHexPrefix, RelayFees, RelayInformationDocument,
-    RelayLimitation, RelayMessage, RelayRetention, RelayUrl, ShatteredContent, Signature,
-    SignatureHex, SimpleRelayList, SimpleRelayUsage, Span, SubscriptionId, Tag, UncheckedUrl,
-    Unixtime, Url, ZapData,
+    dedup_events, find_nostr_bech32_pos, find_nostr_url_pos, merge_sorted_event_streams,
+    normalize_wiki_topic, parse_any, parse_relay_frame, pow_estimate_iterations, read_jsonl,
+    verify_chain, write_jsonl, AddressReference, Attachment, AttachmentSource, AuthState,
+    CalendarDate, CalendarEvent, CalendarEventTime, CalendarParticipant, CalendarRsvp,
+    ChainBuilder, ClassifiedListing, ClassifiedStatus, ClientMessage, Clock, CommentScope,
+    Community, CommunityApproval, CommunityModerator, CommunityRelay, ContentSegment,
+    ContentVisibility, ConversationKey, CountResult, DelegationConditions, DeletionRequest,
+    DiffLine, Difficulty, EncryptedPrivateKey, Event, EventAddr, EventDelegation, EventDiff,
+    EventEnvelope, EventKind, EventKindIterator, EventKindOrRange, EventPointer, EventReference,
+    EventStore, ExternalIdentity, Fee, Filter, Highlight, HighlightAttribution, HighlightSource,
+    Id, IdHasher, IdHex, IdHexPrefix, Identity, IdentityPlatform, JobAmount, JobFeedback, JobInput,
+    JobInputType, JobRequest, JobResult, JobStatus, JsonlError, JsonlErrorMode, JsonlReader,
+    KeyMigration, KeySecurity, KeySource, Keyring, LiveEvent, LiveParticipant, LiveStatus,
+    MachineReadablePrefix, ManualClock, MergedEvents, Metadata, MetadataChange, MetadataLimits,
+    MilliSatoshi, Nip05, NostrBech32, NostrEntity, NostrUrl, OtsAttestation, Parity, ParseLimits,
+    PartialSignature, PayRequestData, PersonReference, Policy, PolicyViolation, Poll, PollOption,
+    PollResponse, PollTally, PollType, PowMiner, PowProgress, PowStatus, PreEvent, Price,
+    PrivateDirectMessage, PrivateKey, Profile, PublicKey, PublicKeyHex, PublicKeyHexPrefix,
+    RawEvent, RelayAssignment, RelayFees, RelayFit, RelayFrame, RelayHint, RelayHintSource,
+    RelayInformationDocument, RelayLimitation, RelayMessage, RelayOrigin, RelayReason,
+    RelayRetention, RelayScore, RelayUrl, RelayUsage, RelayUsagePicture, Report, ReportTarget,
+    ReportType, RetentionPolicy, RsvpStatus, Rumor, SanitizeAction, SanitizedField,
+    ShatteredContent, Signature, SignatureHex, SigningSession, SimpleRelayList, SimpleRelayUsage,
+    Span, Subscription, SubscriptionId, SubscriptionSet, SubscriptionState, Tag, Thread, Torrent,
+    TorrentFile, TransactionType, UncheckedUrl, Unixtime, UnsignedEvent, Url, VerifiedEvent,
+    WalletError, WalletErrorCode, WalletInfo, WalletRequest, WalletResponse, WalletResult,
+    WalletTransaction, WikiArticle, ZapData, CHAIN_MARKER,
 };
+#[cfg(feature = "std")]
+pub use types::{set_clock, with_clock, DmGiftWrap, SystemClock};
+#[cfg(feature = "ots")]
+pub use types::{verify_ots, OtsRequest};
+
+#[cfg(feature = "test_utils")]
+pub mod test_utils;
+
+#[cfg(feature = "test_vectors")]
+pub mod test_vectors;
@@ -0,0 +1,205 @@
+//! Deterministic test data (the `test_utils` feature)
+//!
+//! An `EventFactory` generates keys and signed events from a seed, so a test
+//! gets the same events, ids and signatures on every run without keeping JSON
+//! fixtures around.
+//!
+//! The keys it makes are derived from the seed and are not secret. Never use
+//! them for anything but tests.
+
+use crate::{Error, Event, EventKind, PreEvent, PrivateKey, PublicKey, Tag, Unixtime};
+use serde_json::Map;
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Generates deterministic keys and signed events from a seed
+///
+/// ```
+/// # use nostr_types::test_utils::EventFactory;
+/// # use nostr_types::EventKind;
+/// let mut factory = EventFactory::new(7).kinds(vec![EventKind::TextNote, EventKind::Reaction]);
+/// let events = factory.events(4);
+/// assert_eq!(events[1].kind, EventKind::Reaction);
+/// assert_eq!(events, EventFactory::new(7).kinds(vec![EventKind::TextNote, EventKind::Reaction]).events(4));
+/// ```
+pub struct EventFactory {
+    seed: u64,
+    counter: u64,
+    private_key: PrivateKey,
+    kinds: Vec<EventKind>,
+    tags: Vec<Tag>,
+    start: Unixtime,
+    interval: i64,
+    made: u64,
+}
+
+impl fmt::Debug for EventFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventFactory")
+            .field("seed", &self.seed)
+            .field("public_key", &self.private_key.public_key())
+            .field("kinds", &self.kinds)
+            .field("made", &self.made)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventFactory {
+    /// A factory making text notes a minute apart from 2023-11-14, signed by
+    /// a key derived from `seed`
+    pub fn new(seed: u64) -> EventFactory {
+        let mut factory = EventFactory {
+            seed,
+            counter: 0,
+            private_key: PrivateKey::generate(),
+            kinds: vec![EventKind::TextNote],
+            tags: vec![],
+            start: Unixtime(1_700_000_000),
+            interval: 60,
+            made: 0,
+        };
+        factory.private_key = factory.new_private_key();
+        factory
+    }
+
+    /// Sign events with this key instead of the one derived from the seed
+    pub fn private_key(mut self, private_key: PrivateKey) -> EventFactory {
+        self.private_key = private_key;
+        self
+    }
+
+    /// Make events of these kinds, taking each in turn
+    pub fn kinds(mut self, kinds: Vec<EventKind>) -> EventFactory {
+        if !kinds.is_empty() {
+            self.kinds = kinds;
+        }
+        self
+    }
+
+    /// Add this tag to every event
+    pub fn tag(mut self, tag: Tag) -> EventFactory {
+        self.tags.push(tag);
+        self
+    }
+
+    /// The `created_at` of the first event
+    pub fn starting_at(mut self, start: Unixtime) -> EventFactory {
+        self.start = start;
+        self
+    }
+
+    /// The number of seconds between the `created_at` of each event and the
+    /// next
+    pub fn interval(mut self, seconds: i64) -> EventFactory {
+        self.interval = seconds;
+        self
+    }
+
+    /// The public key events are signed with
+    pub fn public_key(&self) -> PublicKey {
+        self.private_key.public_key()
+    }
+
+    /// The next 32 bytes derived from the seed
+    pub fn random_bytes(&mut self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(b"nostr-types EventFactory");
+        hasher.update(self.seed.to_le_bytes());
+        hasher.update(self.counter.to_le_bytes());
+        self.counter += 1;
+        hasher.finalize().into()
+    }
+
+    /// The next private key derived from the seed, e.g. for the other people
+    /// in a test
+    pub fn new_private_key(&mut self) -> PrivateKey {
+        loop {
+            // Nearly every 32 bytes is a valid key, so this rarely loops
+            let bytes = self.random_bytes();
+            if let Ok(private_key) = PrivateKey::try_from_hex_string(&hex::encode(bytes)) {
+                return private_key;
+            }
+        }
+    }
+
+    /// The next unsigned event
+    pub fn pre_event(&mut self) -> PreEvent {
+        let n = self.made;
+        self.made += 1;
+        PreEvent {
+            pubkey: self.private_key.public_key(),
+            created_at: Unixtime(self.start.0 + n as i64 * self.interval),
+            kind: self.kinds[(n % self.kinds.len() as u64) as usize],
+            tags: self.tags.clone(),
+            content: format!("Event {n} from seed {}", self.seed),
+            ots: None,
+        }
+    }
+
+    /// The next signed event
+    pub fn event(&mut self) -> Event {
+        let pre = self.pre_event();
+        self.sign(pre).expect("the factory signs its own events")
+    }
+
+    /// The next `count` signed events, oldest first
+    pub fn events(&mut self, count: usize) -> Vec<Event> {
+        (0..count).map(|_| self.event()).collect()
+    }
+
+    /// Sign an event reproducibly. It must be by the factory's public key.
+    pub fn sign(&mut self, input: PreEvent) -> Result<Event, Error> {
+        if input.pubkey != self.private_key.public_key() {
+            return Err(Error::WrongPublicKey);
+        }
+        let id = input.hash();
+        let aux_rand = self.random_bytes();
+        let sig = self.private_key.sign_id_with_aux_rand(id, &aux_rand)?;
+        Ok(Event {
+            id,
+            pubkey: input.pubkey,
+            created_at: input.created_at,
+            kind: input.kind,
+            tags: input.tags,
+            content: input.content,
+            ots: input.ots,
+            sig,
+            extra: Map::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_event_factory() {
+        let make = |seed| {
+            EventFactory::new(seed)
+                .kinds(vec![EventKind::TextNote, EventKind::Repost])
+                .tag(Tag::new_hashtag("test".to_owned()))
+                .interval(10)
+        };
+
+        let mut factory = make(1);
+        let events = factory.events(3);
+        assert_eq!(events, make(1).events(3));
+        assert_ne!(events[0].pubkey, make(2).event().pubkey);
+        for event in &events {
+            assert!(event.verify(None).is_ok());
+            assert_eq!(event.tags, vec![Tag::new_hashtag("test".to_owned())]);
+        }
+        assert_eq!(events[1].kind, EventKind::Repost);
+        assert_eq!(events[2].kind, EventKind::TextNote);
+        assert_eq!(events[2].created_at.0 - events[0].created_at.0, 20);
+
+        let other = factory.new_private_key();
+        let mut pre = factory.pre_event();
+        pre.pubkey = other.public_key();
+        assert!(matches!(factory.sign(pre), Err(Error::WrongPublicKey)));
+
+        let mut factory = make(1).private_key(other);
+        assert_eq!(factory.event().pubkey, factory.public_key());
+    }
+}
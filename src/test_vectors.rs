@@ -0,0 +1,212 @@
+//! Canonical fixtures for conformance tests (the `test_vectors` feature)
+//!
+//! These are the same data this crate tests itself against, so downstream
+//! crates can check that they agree with it byte for byte, for example that a
+//! storage layer hands back exactly the event that was signed. Hex and bech32
+//! values are lowercase, as they appear on the wire.
+//!
+//! The `assert_*` functions panic with a description of the first mismatch,
+//! for use in tests.
+
+use crate::{
+    ConversationKey, Event, PreEvent, PrivateKey, Profile, PublicKey, Signature, Tag, UncheckedUrl,
+};
+
+/// A real-world signed event (NIP-01), exactly as it appeared on the wire
+pub const NIP01_EVENT_JSON: &str = r##"{"id":"7760408f6459b9546c3a4e70e3e56756421fba34526b7d460db3fcfd2f8817db","pubkey":"460c25e682fda7832b52d1f22d3d22b3176d972f60dcdc3212ed8c92ef85065c","created_at":1687616920,"kind":1,"tags":[["p","1bc70a0148b3f316da33fe3c89f23e3e71ac4ff998027ec712b905cd24f6a411","","mention"],["a","30311:1bc70a0148b3f316da33fe3c89f23e3e71ac4ff998027ec712b905cd24f6a411:1687612774","","mention"]],"content":"Watching Karnage's stream to see if I learn something about design. \n\nnostr:naddr1qq9rzd3cxumrzv3hxu6qygqmcu9qzj9n7vtd5vl78jyly037wxkyl7vcqflvwy4eqhxjfa4yzypsgqqqwens0qfplk","sig":"dbc5d05a24bfe990a1faaedfcb81a98940d86a105711dbdad9145d05b0ad0f46e3e24eaa3fc283818f27e057fe836a029fd9a68e7f1de06ff477493199d64064"}"##;
+
+/// The Id of [`NIP01_EVENT_JSON`]
+pub const NIP01_EVENT_ID: &str = "7760408f6459b9546c3a4e70e3e56756421fba34526b7d460db3fcfd2f8817db";
+
+/// The serialization of [`NIP01_EVENT_JSON`] that its Id is the SHA-256 hash of
+pub const NIP01_EVENT_SERIALIZED: &str = r##"[0,"460c25e682fda7832b52d1f22d3d22b3176d972f60dcdc3212ed8c92ef85065c",1687616920,1,[["p","1bc70a0148b3f316da33fe3c89f23e3e71ac4ff998027ec712b905cd24f6a411","","mention"],["a","30311:1bc70a0148b3f316da33fe3c89f23e3e71ac4ff998027ec712b905cd24f6a411:1687612774","","mention"]],"Watching Karnage's stream to see if I learn something about design. \n\nnostr:naddr1qq9rzd3cxumrzv3hxu6qygqmcu9qzj9n7vtd5vl78jyly037wxkyl7vcqflvwy4eqhxjfa4yzypsgqqqwens0qfplk"]"##;
+
+/// A public key in hex, and its npub (NIP-19)
+pub const NIP19_NPUB: (&str, &str) = (
+    "7e7e9c42a91bfef19fa929e5fda1b72e0ebc1a4c1141673e2794234d86addf4e",
+    "npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg",
+);
+
+/// A private key in hex, and its nsec (NIP-19)
+pub const NIP19_NSEC: (&str, &str) = (
+    "67dea2ed018072d675f5415ecfaed7d2597555e202d85b3d65ea4e58d2d92ffa",
+    "nsec1vl029mgpspedva04g90vltkh6fvh240zqtv9k0t9af8935ke9laqsnlfe5",
+);
+
+/// The public key in [`NIP19_NPROFILE`], in hex
+pub const NIP19_NPROFILE_PUBKEY: &str =
+    "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d";
+
+/// The relays in [`NIP19_NPROFILE`]
+pub const NIP19_NPROFILE_RELAYS: &[&str] = &["wss://r.x.com", "wss://djbas.sadkb.com"];
+
+/// A profile with a public key and relays (NIP-19)
+pub const NIP19_NPROFILE: &str = "nprofile1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaksjlyr9p";
+
+/// A delegation tag (NIP-26), with the delegator's public key, the conditions
+/// and the token
+pub const NIP26_DELEGATION_TAG: &str = r#"["delegation","1a459a8a6aa6441d480ba665fb8fb21a4cfe8bcacb7d87300f8046a558a3fce4","kind=1&created_at>1676067553&created_at<1678659553","369aed09c1ad52fceb77ecd6c16f2433eac4a3803fc41c58876a5b60f4f36b9493d5115e5ec5a0ce6c3668ffe5b58d47f2cbc97233833bb7e908f66dbbbd9d36"]"#;
+
+/// The public key [`NIP26_DELEGATION_TAG`] delegates to, in hex
+pub const NIP26_DELEGATEE_PUBKEY: &str =
+    "bea8aeb6c1657e33db5ac75a83910f77e8ec6145157e476b5b88c6e85b1fab34";
+
+/// The first private key of the NIP-44 vector, in hex
+pub const NIP44_SEC1: &str = "0000000000000000000000000000000000000000000000000000000000000001";
+
+/// The second private key of the NIP-44 vector, in hex
+pub const NIP44_SEC2: &str = "0000000000000000000000000000000000000000000000000000000000000002";
+
+/// The conversation key shared by [`NIP44_SEC1`] and [`NIP44_SEC2`], in hex
+pub const NIP44_CONVERSATION_KEY: &str =
+    "c41c775356fd92eadc63ff5a0dc1da211b268cbea22316767095b2871ea1412d";
+
+/// The nonce of the NIP-44 vector, in hex
+pub const NIP44_NONCE: &str = "0000000000000000000000000000000000000000000000000000000000000001";
+
+/// The plaintext of the NIP-44 vector
+pub const NIP44_PLAINTEXT: &str = "a";
+
+/// [`NIP44_PLAINTEXT`] encrypted with [`NIP44_CONVERSATION_KEY`] and
+/// [`NIP44_NONCE`]
+pub const NIP44_PAYLOAD: &str = "AgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABee0G5VSK0/9YypIObAtDKfYEAjD35uVkHyB0F4DwrcNaCXlCWZKaArsGrY6M9wnuTMxWfp1RTN9Xga8no+kF5Vsb";
+
+/// The event in [`NIP01_EVENT_JSON`]
+pub fn nip01_event() -> Event {
+    serde_json::from_str(NIP01_EVENT_JSON).expect("NIP-01 vector parses")
+}
+
+/// Assert that `event` is exactly the NIP-01 vector event: it serializes to
+/// the same bytes, hashes to the same Id, and its signature verifies.
+pub fn assert_nip01_event(event: &Event) {
+    let pre = PreEvent {
+        pubkey: event.pubkey,
+        created_at: event.created_at,
+        kind: event.kind,
+        tags: event.tags.clone(),
+        content: event.content.clone(),
+        ots: None,
+    };
+    assert_eq!(
+        pre.serialize_inner(),
+        NIP01_EVENT_SERIALIZED,
+        "serialization for the Id differs"
+    );
+    assert_eq!(
+        pre.signing_payload(),
+        NIP01_EVENT_SERIALIZED.as_bytes(),
+        "signing payload differs"
+    );
+    assert_eq!(event.id.as_hex_string(), NIP01_EVENT_ID, "Id differs");
+    assert_eq!(
+        serde_json::to_string(event).expect("event serializes"),
+        NIP01_EVENT_JSON,
+        "JSON differs"
+    );
+    assert_eq!(
+        event.to_canonical_json().expect("event serializes"),
+        NIP01_EVENT_JSON,
+        "canonical JSON differs"
+    );
+    if let Err(e) = event.verify(None) {
+        panic!("event does not verify: {e}");
+    }
+}
+
+/// Pass the NIP-01 vector event through `store` (for example, save it and
+/// load it back) and assert that what comes back is exactly the same event.
+pub fn assert_event_round_trip<F>(store: F)
+where
+    F: FnOnce(Event) -> Event,
+{
+    assert_nip01_event(&store(nip01_event()));
+}
+
+/// Pass the NIP-01 vector JSON through `store` and assert that exactly the
+/// same bytes come back.
+pub fn assert_json_round_trip<F>(store: F)
+where
+    F: FnOnce(&str) -> String,
+{
+    assert_eq!(store(NIP01_EVENT_JSON), NIP01_EVENT_JSON, "JSON differs");
+}
+
+/// Assert that this crate agrees with every vector. Downstream crates can run
+/// this to check the build they link against.
+pub fn assert_conformance() {
+    assert_nip01_event(&nip01_event());
+
+    let (hex, npub) = NIP19_NPUB;
+    let pubkey = PublicKey::try_from_bech32_string(npub).expect("npub parses");
+    assert_eq!(pubkey.as_hex_string(), hex, "npub decodes wrongly");
+    assert_eq!(pubkey.as_bech32_string(), npub, "npub encodes wrongly");
+
+    let (hex, nsec) = NIP19_NSEC;
+    let mut privkey = PrivateKey::try_from_bech32_string(nsec).expect("nsec parses");
+    assert_eq!(privkey.as_hex_string(), hex, "nsec decodes wrongly");
+    assert_eq!(privkey.as_bech32_string(), nsec, "nsec encodes wrongly");
+
+    let profile = Profile {
+        pubkey: PublicKey::try_from_hex_string(NIP19_NPROFILE_PUBKEY).expect("pubkey parses"),
+        relays: NIP19_NPROFILE_RELAYS
+            .iter()
+            .map(|r| UncheckedUrl::from_str(r))
+            .collect(),
+    };
+    assert_eq!(
+        profile.as_bech32_string(),
+        NIP19_NPROFILE,
+        "nprofile encodes wrongly"
+    );
+    assert_eq!(
+        Profile::try_from_bech32_string(NIP19_NPROFILE).expect("nprofile parses"),
+        profile,
+        "nprofile decodes wrongly"
+    );
+
+    let tag: Tag = serde_json::from_str(NIP26_DELEGATION_TAG).expect("delegation tag parses");
+    let (delegator, conditions, token) = tag.parse_delegation().expect("delegation tag parses");
+    let delegatee = PublicKey::try_from_hex_string(NIP26_DELEGATEE_PUBKEY).expect("pubkey parses");
+    let delegator = PublicKey::try_from(delegator).expect("delegator parses");
+    let token = Signature::try_from(token).expect("token parses");
+    if let Err(e) = conditions.verify_signature(&delegator, &delegatee, token) {
+        panic!("delegation token does not verify: {e}");
+    }
+
+    let sec1 = PrivateKey::try_from_hex_string(NIP44_SEC1).expect("key parses");
+    let sec2 = PrivateKey::try_from_hex_string(NIP44_SEC2).expect("key parses");
+    let key = ConversationKey::new(&sec1, &sec2.public_key());
+    assert_eq!(
+        hex::encode(key.as_bytes()),
+        NIP44_CONVERSATION_KEY,
+        "conversation key differs"
+    );
+    let mut nonce = [0u8; 32];
+    hex::decode_to_slice(NIP44_NONCE, &mut nonce).expect("nonce parses");
+    assert_eq!(
+        key.encrypt_with_nonce(NIP44_PLAINTEXT, &nonce)
+            .expect("encrypts"),
+        NIP44_PAYLOAD,
+        "payload differs"
+    );
+    assert_eq!(
+        sec2.nip44_decrypt(&sec1.public_key(), NIP44_PAYLOAD)
+            .expect("decrypts"),
+        NIP44_PLAINTEXT,
+        "plaintext differs"
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_conformance() {
+        assert_conformance();
+        assert_event_round_trip(|event| event);
+        assert_json_round_trip(|json| {
+            serde_json::to_string(&serde_json::from_str::<Event>(json).unwrap()).unwrap()
+        });
+    }
+}
@@ -0,0 +1,303 @@
+use super::{Event, EventKind, Tag, UncheckedUrl};
+use linkify::{LinkFinder, LinkKind};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+
+/// Where an `Attachment` was first found
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub enum AttachmentSource {
+    /// The tags of a file metadata event (NIP-94, kind 1063)
+    FileMetadata,
+
+    /// An 'imeta' tag (NIP-92)
+    Imeta,
+
+    /// A URL in the content
+    Content,
+
+    /// An 'r' tag with an http(s) URL
+    Reference,
+}
+
+/// A file attached to an event, gathered from wherever the event mentions it
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct Attachment {
+    /// Where the file can be downloaded
+    pub url: UncheckedUrl,
+
+    /// The MIME type, as given or else guessed from the URL's file extension
+    pub mime_type: Option<String>,
+
+    /// The SHA-256 of the file as lowercase hex, from 'x'
+    pub sha256: Option<String>,
+
+    /// The size in bytes
+    pub size: Option<u64>,
+
+    /// The dimensions in pixels, like "1920x1080"
+    pub dim: Option<String>,
+
+    /// A blurhash to show while the file loads
+    pub blurhash: Option<String>,
+
+    /// A description for those who cannot see the file
+    pub alt: Option<String>,
+
+    /// Where this attachment was first found
+    pub source: AttachmentSource,
+}
+
+impl Attachment {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> Attachment {
+        Attachment {
+            url: UncheckedUrl("https://example.com/cat.jpg".to_owned()),
+            mime_type: Some("image/jpeg".to_owned()),
+            sha256: None,
+            size: Some(52_000),
+            dim: Some("640x480".to_owned()),
+            blurhash: None,
+            alt: Some("A cat".to_owned()),
+            source: AttachmentSource::Imeta,
+        }
+    }
+
+    fn new(url: &str, source: AttachmentSource) -> Attachment {
+        Attachment {
+            url: UncheckedUrl::from_str(url),
+            mime_type: None,
+            sha256: None,
+            size: None,
+            dim: None,
+            blurhash: None,
+            alt: None,
+            source,
+        }
+    }
+
+    // Set one NIP-94 field, ignoring ones we do not keep
+    fn set(&mut self, key: &str, value: &str) {
+        let value = value.trim();
+        if value.is_empty() {
+            return;
+        }
+        match key {
+            "m" => self.mime_type = Some(value.to_lowercase()),
+            "x" => self.sha256 = Some(value.to_lowercase()),
+            "size" => self.size = value.parse().ok(),
+            "dim" => self.dim = Some(value.to_owned()),
+            "blurhash" => self.blurhash = Some(value.to_owned()),
+            "alt" => self.alt = Some(value.to_owned()),
+            _ => {}
+        }
+    }
+
+    // Fill in whatever this does not know from `other`, about the same URL
+    fn merge(&mut self, other: Attachment) {
+        self.mime_type = self.mime_type.take().or(other.mime_type);
+        self.sha256 = self.sha256.take().or(other.sha256);
+        self.size = self.size.or(other.size);
+        self.dim = self.dim.take().or(other.dim);
+        self.blurhash = self.blurhash.take().or(other.blurhash);
+        self.alt = self.alt.take().or(other.alt);
+    }
+
+    // Read a NIP-92 'imeta' tag, whose fields are each "key value"
+    fn from_imeta(tag: &Tag) -> Option<Attachment> {
+        let fields: Vec<(&str, &str)> = tag
+            .fields()
+            .iter()
+            .skip(1)
+            .filter_map(|f| f.split_once(' '))
+            .collect();
+        let (_, url) = fields.iter().find(|(key, _)| *key == "url")?;
+        let mut attachment = Attachment::new(url.trim(), AttachmentSource::Imeta);
+        for (key, value) in fields {
+            attachment.set(key, value);
+        }
+        Some(attachment)
+    }
+
+    // Read the tags of a NIP-94 file metadata event
+    fn from_file_metadata(tags: &[Tag]) -> Option<Attachment> {
+        let url = tags.iter().find(|t| t.tagname() == "url")?.value();
+        let mut attachment = Attachment::new(url.trim(), AttachmentSource::FileMetadata);
+        for tag in tags {
+            attachment.set(tag.tagname(), tag.value());
+        }
+        Some(attachment)
+    }
+
+    /// Guess a MIME type from the file extension of a URL, for the common
+    /// image, video and audio formats
+    pub fn guess_mime_type(url: &str) -> Option<&'static str> {
+        let path = url.split(['?', '#']).next().unwrap_or_default();
+        let file = path.rsplit('/').next().unwrap_or_default();
+        let (_, extension) = file.rsplit_once('.')?;
+        Some(match extension.to_ascii_lowercase().as_str() {
+            "jpg" | "jpeg" => "image/jpeg",
+            "png" => "image/png",
+            "gif" => "image/gif",
+            "webp" => "image/webp",
+            "avif" => "image/avif",
+            "svg" => "image/svg+xml",
+            "bmp" => "image/bmp",
+            "heic" => "image/heic",
+            "mp4" | "m4v" => "video/mp4",
+            "webm" => "video/webm",
+            "mov" => "video/quicktime",
+            "m3u8" => "application/vnd.apple.mpegurl",
+            "mp3" => "audio/mpeg",
+            "m4a" => "audio/mp4",
+            "ogg" => "audio/ogg",
+            "wav" => "audio/wav",
+            "flac" => "audio/flac",
+            "pdf" => "application/pdf",
+            _ => return None,
+        })
+    }
+
+    /// Is this an image?
+    pub fn is_image(&self) -> bool {
+        self.mime_type
+            .as_deref()
+            .is_some_and(|m| m.starts_with("image/"))
+    }
+
+    /// Is this a video?
+    pub fn is_video(&self) -> bool {
+        self.mime_type
+            .as_deref()
+            .is_some_and(|m| m.starts_with("video/") || m == "application/vnd.apple.mpegurl")
+    }
+
+    /// Is this audio?
+    pub fn is_audio(&self) -> bool {
+        self.mime_type
+            .as_deref()
+            .is_some_and(|m| m.starts_with("audio/"))
+    }
+}
+
+fn is_http_url(s: &str) -> bool {
+    s.starts_with("https://") || s.starts_with("http://")
+}
+
+impl Event {
+    /// The files this event attaches, from the tags of a file metadata event
+    /// (kind 1063), 'imeta' tags, http(s) URLs in the content and 'r' tags
+    /// with http(s) URLs, in that order. Each URL appears once, with what
+    /// each source says about it merged, the first source taking precedence.
+    /// Missing MIME types are guessed from the file extension.
+    ///
+    /// Every URL in the content is included, so a link to a web page is an
+    /// attachment with no MIME type.
+    pub fn attachments(&self) -> Vec<Attachment> {
+        let mut found: Vec<Attachment> = Vec::new();
+        let mut add = |attachment: Attachment| {
+            if !is_http_url(attachment.url.as_str()) {
+                return;
+            }
+            match found.iter_mut().find(|a| a.url == attachment.url) {
+                Some(existing) => existing.merge(attachment),
+                None => found.push(attachment),
+            }
+        };
+
+        if self.kind == EventKind::FileMetadata {
+            if let Some(attachment) = Attachment::from_file_metadata(&self.tags) {
+                add(attachment);
+            }
+        }
+        for tag in self.tags.iter().filter(|t| t.tagname() == "imeta") {
+            if let Some(attachment) = Attachment::from_imeta(tag) {
+                add(attachment);
+            }
+        }
+        for link in LinkFinder::new()
+            .kinds(&[LinkKind::Url])
+            .links(&self.content)
+        {
+            add(Attachment::new(link.as_str(), AttachmentSource::Content));
+        }
+        for tag in self.tags.iter().filter(|t| t.tagname() == "r") {
+            add(Attachment::new(tag.value(), AttachmentSource::Reference));
+        }
+
+        for attachment in found.iter_mut() {
+            if attachment.mime_type.is_none() {
+                attachment.mime_type =
+                    Attachment::guess_mime_type(attachment.url.as_str()).map(|m| m.to_owned());
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {Attachment, test_attachment_serde}
+
+    #[test]
+    fn test_attachments() {
+        let mut event = Event::mock();
+        event.kind = EventKind::TextNote;
+        event.content = "Look https://example.com/cat.jpg and https://example.com/clip.MP4?x=1 \
+                         via https://example.com/page"
+            .to_owned();
+        event.tags = vec![
+            Tag::new(&[
+                "imeta",
+                "url https://example.com/cat.jpg",
+                "m image/jpeg",
+                "dim 640x480",
+                "alt A cat",
+            ]),
+            Tag::new(&["imeta", "m image/png"]),
+            Tag::new(&["r", "https://example.com/song.mp3"]),
+            Tag::new(&["r", "wss://relay.example.com"]),
+        ];
+
+        let attachments = event.attachments();
+        let urls: Vec<&str> = attachments.iter().map(|a| a.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/cat.jpg",
+                "https://example.com/clip.MP4?x=1",
+                "https://example.com/page",
+                "https://example.com/song.mp3",
+            ]
+        );
+        assert_eq!(attachments[0].source, AttachmentSource::Imeta);
+        assert_eq!(attachments[0].dim.as_deref(), Some("640x480"));
+        assert_eq!(attachments[0].alt.as_deref(), Some("A cat"));
+        assert!(attachments[0].is_image());
+        assert_eq!(attachments[1].source, AttachmentSource::Content);
+        assert!(attachments[1].is_video());
+        assert_eq!(attachments[2].mime_type, None);
+        assert_eq!(attachments[3].source, AttachmentSource::Reference);
+        assert!(attachments[3].is_audio());
+
+        event.kind = EventKind::FileMetadata;
+        event.content = "My cat".to_owned();
+        event.tags = vec![
+            Tag::new(&["url", "https://example.com/cat"]),
+            Tag::new(&["m", "image/webp"]),
+            Tag::new(&["size", "1024"]),
+            Tag::new(&["x", "AB12"]),
+        ];
+        let attachments = event.attachments();
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].source, AttachmentSource::FileMetadata);
+        assert_eq!(attachments[0].mime_type.as_deref(), Some("image/webp"));
+        assert_eq!(attachments[0].size, Some(1024));
+        assert_eq!(attachments[0].sha256.as_deref(), Some("ab12"));
+    }
+}
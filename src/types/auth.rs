@@ -0,0 +1,203 @@
+use super::{ClientMessage, Event, EventKind, Id, RelayMessage, RelayReason};
+use crate::Error;
+
+/// Tracks where a connection to one relay is in NIP-42 authentication.
+///
+/// Feed it every message from the relay with `handle_relay_message()`. When it
+/// has a challenge to answer, `respond()` will produce the AUTH message to send,
+/// using the caller's function to create and sign the auth event (see
+/// `PreEvent::new_auth()`). The relay's OK reply then moves it to
+/// `Authenticated` or `Failed`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum AuthState {
+    /// The relay has not asked us to authenticate
+    #[default]
+    NotRequired,
+
+    /// The relay sent this challenge, which we have not yet answered
+    Challenged(String),
+
+    /// We answered the challenge with the auth event with this Id, and are
+    /// waiting for the relay to accept it
+    Sent {
+        /// The challenge answered
+        challenge: String,
+
+        /// The Id of our auth event
+        id: Id,
+    },
+
+    /// The relay accepted our auth event
+    Authenticated {
+        /// The challenge answered
+        challenge: String,
+
+        /// The Id of our auth event
+        id: Id,
+    },
+
+    /// The relay rejected our auth event
+    Failed {
+        /// The challenge answered
+        challenge: String,
+
+        /// The Id of our auth event
+        id: Id,
+
+        /// The relay's reason
+        reason: RelayReason,
+    },
+}
+
+impl AuthState {
+    /// Create a new AuthState for a fresh connection
+    pub fn new() -> AuthState {
+        AuthState::NotRequired
+    }
+
+    /// Update the state from a message the relay sent. Returns true if the
+    /// state changed.
+    ///
+    /// A new challenge always replaces the current state, as relays may ask
+    /// again at any time.
+    pub fn handle_relay_message(&mut self, message: &RelayMessage) -> bool {
+        let new_state = match (&*self, message) {
+            (_, RelayMessage::Auth(challenge)) => AuthState::Challenged(challenge.clone()),
+            (AuthState::Sent { challenge, id }, RelayMessage::Ok(ok_id, ok, reason))
+                if ok_id == id =>
+            {
+                if *ok {
+                    AuthState::Authenticated {
+                        challenge: challenge.clone(),
+                        id: *id,
+                    }
+                } else {
+                    AuthState::Failed {
+                        challenge: challenge.clone(),
+                        id: *id,
+                        reason: reason.clone(),
+                    }
+                }
+            }
+            _ => return false,
+        };
+        if *self == new_state {
+            return false;
+        }
+        *self = new_state;
+        true
+    }
+
+    /// If there is a challenge waiting to be answered, create the AUTH message
+    /// that answers it.
+    ///
+    /// `producer` is given the challenge and must return a signed auth event
+    /// (kind 22242) for it. Returns Ok(None) if there is nothing to answer.
+    pub fn respond<F>(&mut self, producer: F) -> Result<Option<ClientMessage>, Error>
+    where
+        F: FnOnce(&str) -> Result<Event, Error>,
+    {
+        let AuthState::Challenged(challenge) = &*self else {
+            return Ok(None);
+        };
+        let event = producer(challenge)?;
+        if event.kind != EventKind::Auth {
+            return Err(Error::WrongEventKind);
+        }
+        if !event
+            .tags
+            .iter()
+            .any(|t| t.tagname() == "challenge" && t.value() == challenge)
+        {
+            return Err(Error::AuthChallengeMismatch);
+        }
+        *self = AuthState::Sent {
+            challenge: challenge.clone(),
+            id: event.id,
+        };
+        Ok(Some(ClientMessage::Auth(Box::new(event))))
+    }
+
+    /// The challenge most recently received, if any
+    pub fn challenge(&self) -> Option<&str> {
+        match self {
+            AuthState::NotRequired => None,
+            AuthState::Challenged(challenge)
+            | AuthState::Sent { challenge, .. }
+            | AuthState::Authenticated { challenge, .. }
+            | AuthState::Failed { challenge, .. } => Some(challenge),
+        }
+    }
+
+    /// Is there a challenge waiting to be answered?
+    pub fn needs_response(&self) -> bool {
+        matches!(self, AuthState::Challenged(_))
+    }
+
+    /// Has the relay accepted our authentication?
+    pub fn is_authenticated(&self) -> bool {
+        matches!(self, AuthState::Authenticated { .. })
+    }
+}
+
+// Everything here needs the system clock to build auth events
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::types::{PreEvent, PrivateKey, RelayUrl};
+
+    #[test]
+    fn test_auth_state() {
+        let privkey = PrivateKey::mock();
+        let relay = RelayUrl::try_from_str("wss://relay.example.com").unwrap();
+        let sign = |challenge: &str| {
+            let pre = PreEvent::new_auth(privkey.public_key(), &relay, challenge);
+            Event::new(pre, &privkey)
+        };
+
+        let mut state = AuthState::new();
+        assert_eq!(state.respond(sign).unwrap(), None);
+        assert!(!state.handle_relay_message(&RelayMessage::Notice("hi".to_owned())));
+
+        assert!(state.handle_relay_message(&RelayMessage::Auth("abc".to_owned())));
+        assert!(state.needs_response());
+        assert_eq!(state.challenge(), Some("abc"));
+        let Some(ClientMessage::Auth(event)) = state.respond(sign).unwrap() else {
+            panic!("no AUTH message");
+        };
+        let id = event.id;
+        assert_eq!(
+            state,
+            AuthState::Sent {
+                challenge: "abc".to_owned(),
+                id
+            }
+        );
+        assert_eq!(state.respond(sign).unwrap(), None);
+
+        // Replies about other events are ignored
+        assert!(!state.handle_relay_message(&RelayMessage::Ok(Id::mock(), true, "".into())));
+        assert!(state.handle_relay_message(&RelayMessage::Ok(id, true, "".into())));
+        assert!(state.is_authenticated());
+
+        // The relay may challenge again
+        assert!(state.handle_relay_message(&RelayMessage::Auth("def".to_owned())));
+        let Some(ClientMessage::Auth(event)) = state.respond(sign).unwrap() else {
+            panic!("no AUTH message");
+        };
+        assert!(state.handle_relay_message(&RelayMessage::Ok(
+            event.id,
+            false,
+            "restricted: no".into()
+        )));
+        assert!(matches!(state, AuthState::Failed { .. }));
+
+        // The producer must answer the right challenge
+        let mut state = AuthState::Challenged("xyz".to_owned());
+        assert!(matches!(
+            state.respond(|_| sign("abc")),
+            Err(Error::AuthChallengeMismatch)
+        ));
+        assert!(state.needs_response());
+    }
+}
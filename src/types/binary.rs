@@ -0,0 +1,103 @@
+//! Binary encodings (CBOR, MessagePack) as alternatives to JSON
+//!
+//! These use the same serde representation as JSON, so everything (including
+//! hex ids, keys and signatures) roundtrips exactly and signatures stay valid.
+
+use super::{ClientMessage, Event, Filter, RelayMessage};
+use crate::Error;
+
+macro_rules! impl_binary_encodings {
+    ($($t:ty),*) => {
+        $(
+            impl $t {
+                /// Encode as CBOR
+                #[cfg(feature = "cbor")]
+                pub fn to_cbor(&self) -> Result<Vec<u8>, Error> {
+                    let mut output: Vec<u8> = Vec::new();
+                    ciborium::ser::into_writer(self, &mut output)?;
+                    Ok(output)
+                }
+
+                /// Decode from CBOR
+                #[cfg(feature = "cbor")]
+                pub fn from_cbor(bytes: &[u8]) -> Result<$t, Error> {
+                    Ok(ciborium::de::from_reader(bytes)?)
+                }
+
+                /// Encode as MessagePack
+                #[cfg(feature = "msgpack")]
+                pub fn to_msgpack(&self) -> Result<Vec<u8>, Error> {
+                    Ok(rmp_serde::to_vec_named(self)?)
+                }
+
+                /// Decode from MessagePack
+                #[cfg(feature = "msgpack")]
+                pub fn from_msgpack(bytes: &[u8]) -> Result<$t, Error> {
+                    Ok(rmp_serde::from_slice(bytes)?)
+                }
+            }
+        )*
+    };
+}
+
+impl_binary_encodings!(Event, Filter, ClientMessage, RelayMessage);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{SubscriptionId, Unixtime};
+
+    fn samples() -> (Event, Filter, ClientMessage, RelayMessage) {
+        let event = Event::mock();
+        let mut filter = Filter::mock();
+        filter.since = Some(Unixtime::mock());
+        let client = ClientMessage::Req(SubscriptionId::mock(), vec![filter.clone()]);
+        let relay = RelayMessage::Event(SubscriptionId::mock(), Box::new(event.clone()));
+        (event, filter, client, relay)
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor_roundtrip() {
+        let (event, filter, client, relay) = samples();
+        let decoded = Event::from_cbor(&event.to_cbor().unwrap()).unwrap();
+        assert_eq!(decoded, event);
+        assert!(decoded.verify(None).is_ok());
+        assert!(event.to_cbor().unwrap().len() < serde_json::to_vec(&event).unwrap().len());
+        assert_eq!(
+            Filter::from_cbor(&filter.to_cbor().unwrap()).unwrap(),
+            filter
+        );
+        assert_eq!(
+            ClientMessage::from_cbor(&client.to_cbor().unwrap()).unwrap(),
+            client
+        );
+        assert_eq!(
+            RelayMessage::from_cbor(&relay.to_cbor().unwrap()).unwrap(),
+            relay
+        );
+        assert!(Event::from_cbor(&[0xff, 0x00]).is_err());
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack_roundtrip() {
+        let (event, filter, client, relay) = samples();
+        let decoded = Event::from_msgpack(&event.to_msgpack().unwrap()).unwrap();
+        assert_eq!(decoded, event);
+        assert!(decoded.verify(None).is_ok());
+        assert_eq!(
+            Filter::from_msgpack(&filter.to_msgpack().unwrap()).unwrap(),
+            filter
+        );
+        assert_eq!(
+            ClientMessage::from_msgpack(&client.to_msgpack().unwrap()).unwrap(),
+            client
+        );
+        assert_eq!(
+            RelayMessage::from_msgpack(&relay.to_msgpack().unwrap()).unwrap(),
+            relay
+        );
+        assert!(Event::from_msgpack(&[0xc1]).is_err());
+    }
+}
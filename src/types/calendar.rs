@@ -0,0 +1,555 @@
+use super::{EventAddr, EventKind, Id, PublicKeyHex, Tag, UncheckedUrl, Unixtime};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+use std::fmt;
+
+/// A calendar date without a time or timezone, as used by date-based calendar
+/// events (NIP-52). On the wire this is written `YYYY-MM-DD`.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct CalendarDate {
+    /// Year
+    pub year: u16,
+
+    /// Month, 1 to 12
+    pub month: u8,
+
+    /// Day of the month, 1 to 31
+    pub day: u8,
+}
+
+impl CalendarDate {
+    /// Parse a `YYYY-MM-DD` date
+    pub fn parse(s: &str) -> Result<CalendarDate, Error> {
+        let err = || Error::InvalidTime(s.to_owned());
+        if s.len() != 10 {
+            return Err(err());
+        }
+        // Let Unixtime check the date is a real one
+        let _ = Unixtime::from_rfc3339(&format!("{s}T00:00:00Z")).map_err(|_| err())?;
+        let field = |range: std::ops::Range<usize>| s.get(range).and_then(|f| f.parse().ok());
+        Ok(CalendarDate {
+            year: field(0..4).ok_or_else(err)?,
+            month: field(5..7).ok_or_else(err)? as u8,
+            day: field(8..10).ok_or_else(err)? as u8,
+        })
+    }
+
+    /// The start of this date in UTC
+    pub fn to_unixtime(&self) -> Unixtime {
+        // This cannot fail, the date having been checked when it was created
+        Unixtime::from_rfc3339(&format!("{self}T00:00:00Z")).unwrap_or(Unixtime(0))
+    }
+}
+
+impl fmt::Display for CalendarDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// When a calendar event (NIP-52) happens
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub enum CalendarEventTime {
+    /// A date-based event (kind 31922), such as a holiday, which happens on
+    /// the same dates wherever you are
+    Date {
+        /// The first day
+        start: CalendarDate,
+
+        /// The day after the last day (exclusive). None for a single day.
+        end: Option<CalendarDate>,
+    },
+
+    /// A time-based event (kind 31923), such as a meeting, which happens at a
+    /// particular instant
+    Time {
+        /// When the event starts
+        start: Unixtime,
+
+        /// When the event ends (exclusive)
+        end: Option<Unixtime>,
+
+        /// The IANA timezone the event is in, e.g. "America/Costa_Rica"
+        start_tzid: Option<String>,
+
+        /// The IANA timezone of the end, if different from the start
+        end_tzid: Option<String>,
+    },
+}
+
+/// A participant in a calendar event (NIP-52), from a 'p' tag
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct CalendarParticipant {
+    /// The participant
+    pub pubkey: PublicKeyHex,
+
+    /// A relay where the participant can be found
+    pub relay: Option<UncheckedUrl>,
+
+    /// Their role in the event, e.g. "speaker"
+    pub role: Option<String>,
+}
+
+/// A calendar event (NIP-52, kind 31922 or 31923)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct CalendarEvent {
+    /// The 'd' identifier
+    pub d: String,
+
+    /// Title
+    pub title: Option<String>,
+
+    /// A short summary
+    pub summary: Option<String>,
+
+    /// An image
+    pub image: Option<UncheckedUrl>,
+
+    /// The full description. This is the event content.
+    pub description: String,
+
+    /// When it happens. This also determines the kind of event.
+    pub time: CalendarEventTime,
+
+    /// Where it happens: addresses, a room, or a call link
+    pub locations: Vec<String>,
+
+    /// Geohash of the location
+    pub geohash: Option<String>,
+
+    /// The people taking part
+    pub participants: Vec<CalendarParticipant>,
+
+    /// Hashtags
+    pub hashtags: Vec<String>,
+
+    /// Links to web pages, documents, video calls, etc.
+    pub references: Vec<UncheckedUrl>,
+}
+
+impl CalendarEvent {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> CalendarEvent {
+        CalendarEvent {
+            d: "nostrasia".to_owned(),
+            title: Some("Nostrasia".to_owned()),
+            summary: None,
+            image: None,
+            description: "A conference".to_owned(),
+            time: CalendarEventTime::Time {
+                start: Unixtime(1698800400),
+                end: Some(Unixtime(1698948000)),
+                start_tzid: Some("Asia/Tokyo".to_owned()),
+                end_tzid: None,
+            },
+            locations: vec!["Tokyo".to_owned()],
+            geohash: Some("xn76urx".to_owned()),
+            participants: vec![CalendarParticipant {
+                pubkey: PublicKeyHex::mock_deterministic(),
+                relay: None,
+                role: Some("speaker".to_owned()),
+            }],
+            hashtags: vec!["nostr".to_owned()],
+            references: vec![UncheckedUrl::mock()],
+        }
+    }
+
+    /// The kind of event this is written as
+    pub fn kind(&self) -> EventKind {
+        match self.time {
+            CalendarEventTime::Date { .. } => EventKind::DateBasedCalendarEvent,
+            CalendarEventTime::Time { .. } => EventKind::TimeBasedCalendarEvent,
+        }
+    }
+
+    /// Read a calendar event from the kind, tags and content of an event.
+    /// Returns None if it is not a calendar event kind or has no valid start.
+    pub fn from_tags(kind: EventKind, tags: &[Tag], content: &str) -> Option<CalendarEvent> {
+        let get = |name: &str| {
+            tags.iter()
+                .find(|t| t.tagname() == name)
+                .map(|t| t.get_index(1))
+                .filter(|v| !v.is_empty())
+        };
+        let time = match kind {
+            EventKind::DateBasedCalendarEvent => CalendarEventTime::Date {
+                start: CalendarDate::parse(get("start")?).ok()?,
+                end: get("end").and_then(|v| CalendarDate::parse(v).ok()),
+            },
+            EventKind::TimeBasedCalendarEvent => {
+                let time = |v: &str| v.parse::<i64>().ok().map(Unixtime);
+                CalendarEventTime::Time {
+                    start: time(get("start")?)?,
+                    end: get("end").and_then(time),
+                    start_tzid: get("start_tzid").map(|v| v.to_owned()),
+                    end_tzid: get("end_tzid").map(|v| v.to_owned()),
+                }
+            }
+            _ => return None,
+        };
+
+        let mut event = CalendarEvent {
+            d: get("d").unwrap_or_default().to_owned(),
+            title: get("title").map(|v| v.to_owned()),
+            summary: get("summary").map(|v| v.to_owned()),
+            image: get("image").map(UncheckedUrl::from_str),
+            description: content.to_owned(),
+            time,
+            locations: vec![],
+            geohash: get("g").map(|v| v.to_owned()),
+            participants: vec![],
+            hashtags: vec![],
+            references: vec![],
+        };
+        for tag in tags {
+            let value = tag.get_index(1);
+            match tag.tagname() {
+                "location" if !value.is_empty() => event.locations.push(value.to_owned()),
+                "t" if !value.is_empty() => event.hashtags.push(value.to_owned()),
+                "r" if !value.is_empty() => event.references.push(UncheckedUrl::from_str(value)),
+                "p" => {
+                    if let Ok((pubkey, relay, role)) = tag.parse_pubkey() {
+                        event.participants.push(CalendarParticipant {
+                            pubkey,
+                            relay,
+                            role,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        Some(event)
+    }
+
+    /// Write this calendar event out as tags. The description goes in the
+    /// content. Time-based events also get the 'D' day tags which let relays
+    /// filter them by day, at most one year's worth.
+    pub fn to_tags(&self) -> Vec<Tag> {
+        let mut tags = vec![Tag::new_identifier(self.d.clone())];
+        if let Some(title) = &self.title {
+            tags.push(Tag::new_title(title.clone()));
+        }
+        if let Some(summary) = &self.summary {
+            tags.push(Tag::new(&["summary", summary]));
+        }
+        if let Some(image) = &self.image {
+            tags.push(Tag::new(&["image", image.as_str()]));
+        }
+        match &self.time {
+            CalendarEventTime::Date { start, end } => {
+                tags.push(Tag::new(&["start", &start.to_string()]));
+                if let Some(end) = end {
+                    tags.push(Tag::new(&["end", &end.to_string()]));
+                }
+            }
+            CalendarEventTime::Time {
+                start,
+                end,
+                start_tzid,
+                end_tzid,
+            } => {
+                tags.push(Tag::new(&["start", &start.0.to_string()]));
+                if let Some(end) = end {
+                    tags.push(Tag::new(&["end", &end.0.to_string()]));
+                }
+                if let Some(tzid) = start_tzid {
+                    tags.push(Tag::new(&["start_tzid", tzid]));
+                }
+                if let Some(tzid) = end_tzid {
+                    tags.push(Tag::new(&["end_tzid", tzid]));
+                }
+                let first_day = start.0.div_euclid(86400);
+                let last_day = end
+                    .map(|e| (e.0 - 1).div_euclid(86400))
+                    .unwrap_or(first_day);
+                for day in first_day..=last_day.min(first_day + 365) {
+                    tags.push(Tag::new(&["D", &day.to_string()]));
+                }
+            }
+        }
+        for location in &self.locations {
+            tags.push(Tag::new(&["location", location]));
+        }
+        if let Some(geohash) = &self.geohash {
+            tags.push(Tag::new(&["g", geohash]));
+        }
+        for p in &self.participants {
+            tags.push(Tag::new_pubkey(
+                p.pubkey.clone(),
+                p.relay.clone(),
+                p.role.clone(),
+            ));
+        }
+        for hashtag in &self.hashtags {
+            tags.push(Tag::new_hashtag(hashtag.clone()));
+        }
+        for reference in &self.references {
+            tags.push(Tag::new(&["r", reference.as_str()]));
+        }
+        tags
+    }
+}
+
+/// A response to a calendar event invitation (NIP-52)
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+#[serde(rename_all = "lowercase")]
+pub enum RsvpStatus {
+    /// Will attend
+    Accepted,
+
+    /// Will not attend
+    Declined,
+
+    /// Might attend
+    Tentative,
+}
+
+impl RsvpStatus {
+    /// The string used on the wire for this status
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            RsvpStatus::Accepted => "accepted",
+            RsvpStatus::Declined => "declined",
+            RsvpStatus::Tentative => "tentative",
+        }
+    }
+
+    /// Parse a status from the wire, or None if it is not recognized
+    pub fn parse(s: &str) -> Option<RsvpStatus> {
+        match s {
+            "accepted" => Some(RsvpStatus::Accepted),
+            "declined" => Some(RsvpStatus::Declined),
+            "tentative" => Some(RsvpStatus::Tentative),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RsvpStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// An RSVP to a calendar event (NIP-52, kind 31925)
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct CalendarRsvp {
+    /// The 'd' identifier
+    pub d: String,
+
+    /// The calendar event being responded to
+    pub event: EventAddr,
+
+    /// The specific revision of the calendar event being responded to
+    pub event_id: Option<Id>,
+
+    /// The response
+    pub status: RsvpStatus,
+
+    /// Whether the responder would be busy for the duration. Only given when
+    /// the status is not declined.
+    pub busy: Option<bool>,
+
+    /// The author of the calendar event
+    pub author: Option<PublicKeyHex>,
+
+    /// A note to go with the response. This is the event content.
+    pub note: String,
+}
+
+impl CalendarRsvp {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> CalendarRsvp {
+        CalendarRsvp {
+            d: "rsvp-nostrasia".to_owned(),
+            event: EventAddr {
+                d: "nostrasia".to_owned(),
+                relays: vec![],
+                kind: EventKind::TimeBasedCalendarEvent,
+                author: PublicKeyHex::mock_deterministic().try_into().unwrap(),
+            },
+            event_id: None,
+            status: RsvpStatus::Accepted,
+            busy: Some(true),
+            author: Some(PublicKeyHex::mock_deterministic()),
+            note: "See you there".to_owned(),
+        }
+    }
+
+    /// Read an RSVP from the tags and content of a kind 31925 event. Returns
+    /// None if it does not refer to a calendar event or has no valid status.
+    pub fn from_tags(tags: &[Tag], content: &str) -> Option<CalendarRsvp> {
+        let mut d = String::new();
+        let mut event = None;
+        let mut event_id = None;
+        let mut status = None;
+        let mut busy = None;
+        let mut author = None;
+        for tag in tags {
+            match tag.tagname() {
+                "d" => d = tag.get_index(1).to_owned(),
+                "a" => {
+                    if let Ok((ea, _)) = tag.parse_address() {
+                        event = Some(ea);
+                    }
+                }
+                "e" => event_id = tag.parse_event().ok().map(|(id, _, _)| id),
+                "status" => status = RsvpStatus::parse(tag.get_index(1)),
+                "fb" => {
+                    busy = match tag.get_index(1) {
+                        "busy" => Some(true),
+                        "free" => Some(false),
+                        _ => None,
+                    }
+                }
+                "p" => author = tag.parse_pubkey().ok().map(|(pk, _, _)| pk),
+                _ => {}
+            }
+        }
+        let event = event.filter(|ea| {
+            matches!(
+                ea.kind,
+                EventKind::DateBasedCalendarEvent | EventKind::TimeBasedCalendarEvent
+            )
+        })?;
+        Some(CalendarRsvp {
+            d,
+            event,
+            event_id,
+            status: status?,
+            busy,
+            author,
+            note: content.to_owned(),
+        })
+    }
+
+    /// Write this RSVP out as tags. The note goes in the content.
+    pub fn to_tags(&self) -> Vec<Tag> {
+        let mut tags = vec![
+            Tag::new_identifier(self.d.clone()),
+            Tag::new_address(&self.event, None),
+        ];
+        if let Some(id) = self.event_id {
+            tags.push(Tag::new_event(id, None, None));
+        }
+        tags.push(Tag::new(&["status", self.status.as_str()]));
+        if self.status != RsvpStatus::Declined {
+            if let Some(busy) = self.busy {
+                tags.push(Tag::new(&["fb", if busy { "busy" } else { "free" }]));
+            }
+        }
+        if let Some(author) = &self.author {
+            tags.push(Tag::new_pubkey(author.clone(), None, None));
+        }
+        tags
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {CalendarEvent, test_calendar_event_serde}
+    test_serde! {CalendarRsvp, test_calendar_rsvp_serde}
+
+    #[test]
+    fn test_calendar_date() {
+        let date = CalendarDate::parse("2024-02-29").unwrap();
+        assert_eq!(
+            date,
+            CalendarDate {
+                year: 2024,
+                month: 2,
+                day: 29
+            }
+        );
+        assert_eq!(date.to_string(), "2024-02-29");
+        assert_eq!(date.to_unixtime(), Unixtime(1709164800));
+        for bad in [
+            "2023-02-29",
+            "2024-2-29",
+            "2024-02-29T00:00:00Z",
+            "tomorrow",
+        ] {
+            assert!(CalendarDate::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_calendar_event_tags() {
+        let event = CalendarEvent::mock();
+        let tags = event.to_tags();
+        // 2023-11-01 through 2023-11-02
+        let days: Vec<&str> = tags
+            .iter()
+            .filter(|t| t.tagname() == "D")
+            .map(|t| t.get_index(1))
+            .collect();
+        assert_eq!(days, vec!["19662", "19663"]);
+        assert_eq!(
+            CalendarEvent::from_tags(event.kind(), &tags, &event.description),
+            Some(event)
+        );
+
+        let event = CalendarEvent {
+            time: CalendarEventTime::Date {
+                start: CalendarDate::parse("2023-12-25").unwrap(),
+                end: None,
+            },
+            ..CalendarEvent::mock()
+        };
+        assert_eq!(event.kind(), EventKind::DateBasedCalendarEvent);
+        let tags = event.to_tags();
+        assert!(tags.contains(&Tag::new(&["start", "2023-12-25"])));
+        assert_eq!(
+            CalendarEvent::from_tags(event.kind(), &tags, &event.description),
+            Some(event.clone())
+        );
+        // The start must match the kind
+        assert_eq!(
+            CalendarEvent::from_tags(EventKind::TimeBasedCalendarEvent, &tags, ""),
+            None
+        );
+    }
+
+    #[test]
+    fn test_calendar_rsvp_tags() {
+        let rsvp = CalendarRsvp::mock();
+        let tags = rsvp.to_tags();
+        assert!(tags.contains(&Tag::new(&["fb", "busy"])));
+        assert_eq!(
+            CalendarRsvp::from_tags(&tags, &rsvp.note),
+            Some(rsvp.clone())
+        );
+
+        let declined = CalendarRsvp {
+            status: RsvpStatus::Declined,
+            busy: None,
+            ..rsvp.clone()
+        };
+        let tags = CalendarRsvp {
+            busy: Some(true),
+            ..declined.clone()
+        }
+        .to_tags();
+        assert_eq!(CalendarRsvp::from_tags(&tags, &rsvp.note), Some(declined));
+
+        let tags: Vec<Tag> = rsvp
+            .to_tags()
+            .into_iter()
+            .filter(|t| t.tagname() != "status")
+            .collect();
+        assert_eq!(CalendarRsvp::from_tags(&tags, ""), None);
+    }
+}
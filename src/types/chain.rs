@@ -0,0 +1,154 @@
+use super::{Event, Id, PreEvent, PrivateKey, Tag};
+use crate::Error;
+
+/// The marker on the 'e' tag that links an event to the one before it in a
+/// chain
+pub const CHAIN_MARKER: &str = "previous";
+
+/// Builds a chain of events for use as an append-only log, each one tagging
+/// the event before it with an 'e' tag marked `CHAIN_MARKER`. Check a chain
+/// with `verify_chain`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChainBuilder {
+    last: Option<Id>,
+}
+
+impl ChainBuilder {
+    /// Start a new chain
+    pub fn new() -> ChainBuilder {
+        ChainBuilder::default()
+    }
+
+    /// Continue a chain whose latest event is `last`
+    pub fn after(last: Id) -> ChainBuilder {
+        ChainBuilder { last: Some(last) }
+    }
+
+    /// The id of the latest event in the chain, if there is one
+    pub fn last(&self) -> Option<Id> {
+        self.last
+    }
+
+    /// Link an event to the end of the chain, sign it, and make it the end.
+    /// Any chain link already in `input` is replaced.
+    pub fn append(&mut self, mut input: PreEvent, privkey: &PrivateKey) -> Result<Event, Error> {
+        input.tags.retain(|t| chain_link(t).is_none());
+        if let Some(last) = self.last {
+            input
+                .tags
+                .push(Tag::new_event(last, None, Some(CHAIN_MARKER.to_owned())));
+        }
+        let event = Event::new(input, privkey)?;
+        self.last = Some(event.id);
+        Ok(event)
+    }
+}
+
+impl Event {
+    /// If this event is part of a chain (see `ChainBuilder`), the id of the
+    /// event before it
+    pub fn previous_in_chain(&self) -> Option<Id> {
+        self.tags.iter().find_map(chain_link)
+    }
+}
+
+fn chain_link(tag: &Tag) -> Option<Id> {
+    match tag.parse_event() {
+        Ok((id, _, Some(marker))) if marker == CHAIN_MARKER => Some(id),
+        _ => None,
+    }
+}
+
+/// Check that events form an intact chain, oldest first: each is validly
+/// signed by the same author, links to the one before it (the first links to
+/// nothing) and is not older than it. The error says which event is at fault.
+pub fn verify_chain(events: &[Event]) -> Result<(), Error> {
+    let broken = |index: usize, reason: &str| Err(Error::BrokenChain(index, reason.to_owned()));
+    let mut previous: Option<&Event> = None;
+    for (index, event) in events.iter().enumerate() {
+        if let Err(e) = event.verify(None) {
+            return broken(index, &e.to_string());
+        }
+        match previous {
+            None => {
+                if event.previous_in_chain().is_some() {
+                    return broken(index, "the first event links to an earlier one");
+                }
+            }
+            Some(previous) => {
+                if event.pubkey != previous.pubkey {
+                    return broken(index, "a different author");
+                }
+                if event.previous_in_chain() != Some(previous.id) {
+                    return broken(index, "not linked to the event before it");
+                }
+                if event.created_at < previous.created_at {
+                    return broken(index, "older than the event before it");
+                }
+            }
+        }
+        previous = Some(event);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{EventKind, Unixtime};
+
+    fn entry(privkey: &PrivateKey, created_at: i64) -> PreEvent {
+        PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime(created_at),
+            kind: EventKind::TextNote,
+            tags: vec![],
+            content: format!("log entry at {created_at}"),
+            ots: None,
+        }
+    }
+
+    #[test]
+    fn test_chain() {
+        let privkey = PrivateKey::mock();
+        let mut builder = ChainBuilder::new();
+        let events: Vec<Event> = (0..4)
+            .map(|i| builder.append(entry(&privkey, 100 + i), &privkey).unwrap())
+            .collect();
+        assert_eq!(events[0].previous_in_chain(), None);
+        assert_eq!(events[3].previous_in_chain(), Some(events[2].id));
+        assert_eq!(builder.last(), Some(events[3].id));
+        assert!(verify_chain(&events).is_ok());
+        assert!(verify_chain(&[]).is_ok());
+
+        // Out of order
+        let mut swapped = events.clone();
+        swapped.swap(1, 2);
+        assert!(matches!(
+            verify_chain(&swapped),
+            Err(Error::BrokenChain(1, _))
+        ));
+
+        // A gap
+        let mut gap = events.clone();
+        let _ = gap.remove(2);
+        assert!(matches!(verify_chain(&gap), Err(Error::BrokenChain(2, _))));
+
+        // Continuing a chain replaces any link the caller put in
+        let mut builder = ChainBuilder::after(events[3].id);
+        let mut pre = entry(&privkey, 50);
+        pre.tags.push(Tag::new_event(
+            events[0].id,
+            None,
+            Some(CHAIN_MARKER.to_owned()),
+        ));
+        let next = builder.append(pre, &privkey).unwrap();
+        assert_eq!(next.previous_in_chain(), Some(events[3].id));
+        let mut extended = events.clone();
+        extended.push(next);
+        assert!(matches!(
+            verify_chain(&extended),
+            Err(Error::BrokenChain(4, _))
+        ));
+    }
+}
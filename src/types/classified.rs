@@ -0,0 +1,326 @@
+use super::{MilliSatoshi, Tag, UncheckedUrl, Unixtime};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+use std::fmt;
+
+/// The price of a classified listing (NIP-99)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct Price {
+    /// The amount, as a decimal string such as "50" or "0.0025".
+    /// This is kept as written so that it round-trips exactly.
+    amount: String,
+
+    /// The currency, an ISO 4217 code such as "USD", or "BTC" or "SAT"
+    pub currency: String,
+
+    /// How often the price is paid, e.g. "hour", "day", "week", "month" or
+    /// "year". None for a one-off price.
+    pub frequency: Option<String>,
+}
+
+impl Price {
+    /// Create a price. The amount must be a non-negative decimal number.
+    pub fn new(amount: &str, currency: &str, frequency: Option<&str>) -> Result<Price, Error> {
+        if !is_decimal(amount) {
+            return Err(Error::InvalidPrice(amount.to_owned()));
+        }
+        Ok(Price {
+            amount: amount.to_owned(),
+            currency: currency.to_owned(),
+            frequency: frequency.map(|f| f.to_owned()),
+        })
+    }
+
+    /// A price in bitcoin, expressed in satoshis
+    pub fn from_sats(sats: u64, frequency: Option<&str>) -> Price {
+        Price {
+            amount: sats.to_string(),
+            currency: "SAT".to_owned(),
+            frequency: frequency.map(|f| f.to_owned()),
+        }
+    }
+
+    /// The amount, as a decimal string
+    pub fn amount(&self) -> &str {
+        &self.amount
+    }
+
+    /// The amount as a floating point number. This may lose precision, so
+    /// use it for display and sorting, not for accounting.
+    pub fn amount_f64(&self) -> f64 {
+        self.amount.parse().unwrap_or(0.0)
+    }
+
+    /// If the price is in bitcoin ("BTC", "SAT" or "MSAT", any case), get it
+    /// in millisatoshis. Returns None for other currencies, or for amounts
+    /// finer than a millisatoshi or too large to represent.
+    pub fn as_millisatoshi(&self) -> Option<MilliSatoshi> {
+        let scale = match self.currency.to_ascii_lowercase().as_str() {
+            "btc" => 11,
+            "sat" | "sats" => 3,
+            "msat" | "msats" => 0,
+            _ => return None,
+        };
+        decimal_to_scaled(&self.amount, scale).map(MilliSatoshi)
+    }
+
+    /// Parse a 'price' tag
+    pub fn from_tag(tag: &Tag) -> Result<Price, Error> {
+        if tag.tagname() != "price" {
+            return Err(Error::TagMismatch);
+        }
+        let frequency = match tag.get_index(3) {
+            "" => None,
+            f => Some(f),
+        };
+        Price::new(tag.get_index(1), tag.get_index(2), frequency)
+    }
+
+    /// Create a 'price' tag
+    pub fn to_tag(&self) -> Tag {
+        let mut tag = Tag::new(&["price", &self.amount, &self.currency]);
+        if let Some(frequency) = &self.frequency {
+            tag.push_value(frequency.clone());
+        }
+        tag
+    }
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.currency)?;
+        if let Some(frequency) = &self.frequency {
+            write!(f, " per {frequency}")?;
+        }
+        Ok(())
+    }
+}
+
+// digits, optionally with a fractional part
+fn is_decimal(s: &str) -> bool {
+    let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+    !whole.is_empty()
+        && whole.bytes().all(|b| b.is_ascii_digit())
+        && frac.bytes().all(|b| b.is_ascii_digit())
+        && !(s.contains('.') && frac.is_empty())
+}
+
+// Convert a decimal string to an integer count of 10^-scale units, if it is
+// exact and fits
+pub(super) fn decimal_to_scaled(s: &str, scale: u32) -> Option<u64> {
+    if !is_decimal(s) {
+        return None;
+    }
+    let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+    let frac = frac.trim_end_matches('0');
+    if frac.len() > scale as usize {
+        return None;
+    }
+    let mut value: u64 = whole.parse().ok()?;
+    value = value.checked_mul(10u64.checked_pow(scale)?)?;
+    if !frac.is_empty() {
+        let f: u64 = frac.parse().ok()?;
+        value = value.checked_add(f * 10u64.pow(scale - frac.len() as u32))?;
+    }
+    Some(value)
+}
+
+/// The status of a classified listing (NIP-99)
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+#[serde(rename_all = "lowercase")]
+pub enum ClassifiedStatus {
+    /// Still available
+    Active,
+
+    /// No longer available
+    Sold,
+}
+
+impl ClassifiedStatus {
+    /// The string used on the wire for this status
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ClassifiedStatus::Active => "active",
+            ClassifiedStatus::Sold => "sold",
+        }
+    }
+
+    /// Parse a status from the wire, or None if it is not recognized
+    pub fn parse(s: &str) -> Option<ClassifiedStatus> {
+        match s {
+            "active" => Some(ClassifiedStatus::Active),
+            "sold" => Some(ClassifiedStatus::Sold),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ClassifiedStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A classified listing (NIP-99, kind 30402, or 30403 for a draft)
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct ClassifiedListing {
+    /// The 'd' identifier
+    pub d: String,
+
+    /// Title
+    pub title: Option<String>,
+
+    /// A short tagline or summary
+    pub summary: Option<String>,
+
+    /// The full description, in markdown. This is the event content.
+    pub description: String,
+
+    /// When the listing was first published
+    pub published_at: Option<Unixtime>,
+
+    /// Where the item or service is
+    pub location: Option<String>,
+
+    /// Geohash of the location
+    pub geohash: Option<String>,
+
+    /// The price, if given and valid
+    pub price: Option<Price>,
+
+    /// Images of the item
+    pub images: Vec<UncheckedUrl>,
+
+    /// Status, if given and recognized
+    pub status: Option<ClassifiedStatus>,
+
+    /// Hashtags
+    pub hashtags: Vec<String>,
+}
+
+impl ClassifiedListing {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> ClassifiedListing {
+        ClassifiedListing {
+            d: "lorem-ipsum".to_owned(),
+            title: Some("Bicycle".to_owned()),
+            summary: Some("Barely used".to_owned()),
+            description: "A *very* nice bicycle".to_owned(),
+            published_at: Some(Unixtime(1296962229)),
+            location: Some("NYC".to_owned()),
+            geohash: Some("dr5regw".to_owned()),
+            price: Some(Price::new("100", "USD", None).unwrap()),
+            images: vec![UncheckedUrl::mock()],
+            status: Some(ClassifiedStatus::Active),
+            hashtags: vec!["bikes".to_owned()],
+        }
+    }
+
+    /// Read a classified listing from the tags and content of a kind 30402
+    /// or 30403 event
+    pub fn from_tags(tags: &[Tag], content: &str) -> ClassifiedListing {
+        let mut listing = ClassifiedListing {
+            description: content.to_owned(),
+            ..Default::default()
+        };
+        for tag in tags {
+            let value = tag.get_index(1);
+            match tag.tagname() {
+                "d" => listing.d = value.to_owned(),
+                "title" => listing.title = Some(value.to_owned()),
+                "summary" => listing.summary = Some(value.to_owned()),
+                "published_at" => listing.published_at = value.parse::<i64>().ok().map(Unixtime),
+                "location" => listing.location = Some(value.to_owned()),
+                "g" => listing.geohash = Some(value.to_owned()),
+                "price" => listing.price = Price::from_tag(tag).ok(),
+                "image" if !value.is_empty() => listing.images.push(UncheckedUrl::from_str(value)),
+                "status" => listing.status = ClassifiedStatus::parse(value),
+                "t" => listing.hashtags.push(value.to_owned()),
+                _ => {}
+            }
+        }
+        listing
+    }
+
+    /// Write this listing out as tags for a kind 30402 or 30403 event. The
+    /// description goes in the content.
+    pub fn to_tags(&self) -> Vec<Tag> {
+        let mut tags = vec![Tag::new_identifier(self.d.clone())];
+        if let Some(title) = &self.title {
+            tags.push(Tag::new_title(title.clone()));
+        }
+        if let Some(summary) = &self.summary {
+            tags.push(Tag::new(&["summary", summary]));
+        }
+        if let Some(published_at) = self.published_at {
+            tags.push(Tag::new_published_at(published_at));
+        }
+        if let Some(location) = &self.location {
+            tags.push(Tag::new(&["location", location]));
+        }
+        if let Some(geohash) = &self.geohash {
+            tags.push(Tag::new(&["g", geohash]));
+        }
+        if let Some(price) = &self.price {
+            tags.push(price.to_tag());
+        }
+        for image in &self.images {
+            tags.push(Tag::new(&["image", image.as_str()]));
+        }
+        if let Some(status) = self.status {
+            tags.push(Tag::new(&["status", status.as_str()]));
+        }
+        for hashtag in &self.hashtags {
+            tags.push(Tag::new_hashtag(hashtag.clone()));
+        }
+        tags
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {ClassifiedListing, test_classified_listing_serde}
+
+    #[test]
+    fn test_classified_listing_tags() {
+        let listing = ClassifiedListing::mock();
+        let tags = listing.to_tags();
+        assert_eq!(
+            ClassifiedListing::from_tags(&tags, &listing.description),
+            listing
+        );
+    }
+
+    #[test]
+    fn test_price() {
+        let tag = Tag::new(&["price", "15", "EUR", "month"]);
+        let price = Price::from_tag(&tag).unwrap();
+        assert_eq!(price.amount(), "15");
+        assert_eq!(price.currency, "EUR");
+        assert_eq!(price.frequency.as_deref(), Some("month"));
+        assert_eq!(price.to_tag(), tag);
+        assert_eq!(price.to_string(), "15 EUR per month");
+        assert_eq!(price.as_millisatoshi(), None);
+
+        let price = Price::new("0.00012", "btc", None).unwrap();
+        assert_eq!(price.as_millisatoshi(), Some(MilliSatoshi(12_000_000)));
+        assert_eq!(price.amount_f64(), 0.00012);
+        let price = Price::from_sats(2100, None);
+        assert_eq!(price.as_millisatoshi(), Some(MilliSatoshi(2_100_000)));
+        let price = Price::new("1.5", "msat", None).unwrap();
+        assert_eq!(price.as_millisatoshi(), None);
+
+        for bad in ["", "-1", "1,000", "1.", ".5", "ten", "1e3"] {
+            assert!(Price::new(bad, "USD", None).is_err(), "{bad}");
+        }
+        assert!(Price::from_tag(&Tag::new(&["amount", "1", "USD"])).is_err());
+    }
+}
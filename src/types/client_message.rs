@@ -1,4 +1,5 @@
-use super::{Event, Filter, SubscriptionId};
+use super::{Event, Filter, ParseLimits, SubscriptionId};
+use crate::Error;
 use serde::de::Error as DeError;
 use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
 use serde::ser::{Serialize, SerializeSeq, Serializer};
@@ -21,9 +22,18 @@ pub enum ClientMessage {
 
     /// Used to send authentication events
     Auth(Box<Event>),
+
+    /// A request for the number of events matching filters (NIP-45)
+    Count(SubscriptionId, Vec<Filter>),
 }
 
 impl ClientMessage {
+    /// Parse a message from untrusted JSON, failing as soon as an event in it
+    /// exceeds `limits`
+    pub fn from_json_limited(json: &str, limits: &ParseLimits) -> Result<ClientMessage, Error> {
+        super::parse_limits::parse_client_message(json, limits)
+    }
+
     // Mock data for testing
     #[allow(dead_code)]
     pub(crate) fn mock() -> ClientMessage {
@@ -64,6 +74,15 @@ impl Serialize for ClientMessage {
                 seq.serialize_element(&event)?;
                 seq.end()
             }
+            ClientMessage::Count(id, filters) => {
+                let mut seq = serializer.serialize_seq(Some(2 + filters.len()))?;
+                seq.serialize_element("COUNT")?;
+                seq.serialize_element(&id)?;
+                for filter in filters {
+                    seq.serialize_element(&filter)?;
+                }
+                seq.end()
+            }
         }
     }
 }
@@ -90,7 +109,7 @@ impl<'de> Visitor<'de> for ClientMessageVisitor {
     where
         A: SeqAccess<'de>,
     {
-        let word: &str = seq
+        let word: String = seq
             .next_element()?
             .ok_or_else(|| DeError::custom("Message missing initial string field"))?;
         if word == "EVENT" {
@@ -98,7 +117,7 @@ impl<'de> Visitor<'de> for ClientMessageVisitor {
                 .next_element()?
                 .ok_or_else(|| DeError::custom("Message missing event field"))?;
             Ok(ClientMessage::Event(Box::new(event)))
-        } else if word == "REQ" {
+        } else if word == "REQ" || word == "COUNT" {
             let id: SubscriptionId = seq
                 .next_element()?
                 .ok_or_else(|| DeError::custom("Message missing id field"))?;
@@ -110,7 +129,11 @@ impl<'de> Visitor<'de> for ClientMessageVisitor {
                     Some(fil) => filters.push(fil),
                 }
             }
-            Ok(ClientMessage::Req(id, filters))
+            if word == "REQ" {
+                Ok(ClientMessage::Req(id, filters))
+            } else {
+                Ok(ClientMessage::Count(id, filters))
+            }
         } else if word == "CLOSE" {
             let id: SubscriptionId = seq
                 .next_element()?
@@ -132,4 +155,16 @@ mod test {
     use super::*;
 
     test_serde! {ClientMessage, test_client_message_serde}
+
+    #[test]
+    fn test_count() {
+        let json = r#"["COUNT","c1",{"kinds":[3]},{"kinds":[7]}]"#;
+        let message: ClientMessage = serde_json::from_str(json).unwrap();
+        let ClientMessage::Count(ref id, ref filters) = message else {
+            panic!("Expected COUNT, got {message:?}");
+        };
+        assert_eq!(id.0, "c1");
+        assert_eq!(filters.len(), 2);
+        assert_eq!(serde_json::to_string(&message).unwrap(), json);
+    }
 }
@@ -0,0 +1,158 @@
+use super::Unixtime;
+#[cfg(feature = "std")]
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicI64, Ordering};
+#[cfg(feature = "std")]
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// A source of the current time. `Unixtime::now_with()` reads a given
+/// clock. With the `std` feature, `Unixtime::now()` and so every `PreEvent`
+/// constructor read the system clock, unless another is set with
+/// `set_clock()` or `with_clock()`.
+pub trait Clock: Send + Sync {
+    /// The current time
+    fn now(&self) -> Unixtime;
+}
+
+/// The system clock
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> Unixtime {
+        match std::time::UNIX_EPOCH.elapsed() {
+            Ok(d) => Unixtime(d.as_secs() as i64),
+            // System clock is set before 1970
+            Err(e) => Unixtime(-(e.duration().as_secs() as i64)),
+        }
+    }
+}
+
+/// A clock that only moves when told to, for tests
+#[derive(Debug)]
+pub struct ManualClock(AtomicI64);
+
+impl ManualClock {
+    /// A clock stopped at `time`
+    pub fn new(time: Unixtime) -> ManualClock {
+        ManualClock(AtomicI64::new(time.0))
+    }
+
+    /// Move the clock to `time`, which may be in its past
+    pub fn set(&self, time: Unixtime) {
+        self.0.store(time.0, Ordering::SeqCst);
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, duration: Duration) {
+        let _ = self
+            .0
+            .fetch_add(duration.as_secs() as i64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Unixtime {
+        Unixtime(self.0.load(Ordering::SeqCst))
+    }
+}
+
+#[cfg(feature = "std")]
+static GLOBAL_CLOCK: RwLock<Option<Arc<dyn Clock>>> = RwLock::new(None);
+
+#[cfg(feature = "std")]
+thread_local! {
+    static THREAD_CLOCK: RefCell<Option<Arc<dyn Clock>>> = const { RefCell::new(None) };
+}
+
+/// Set the clock for the whole process, or go back to the system clock with
+/// None. Tests run in parallel, so they should use `with_clock()` instead.
+#[cfg(feature = "std")]
+pub fn set_clock(clock: Option<Arc<dyn Clock>>) {
+    let mut global = GLOBAL_CLOCK.write().unwrap_or_else(|e| e.into_inner());
+    *global = clock;
+}
+
+/// Run `f` with `clock` as the clock on this thread only. Other threads,
+/// including any `f` starts, still see the process clock.
+#[cfg(feature = "std")]
+pub fn with_clock<F, R>(clock: Arc<dyn Clock>, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    // Put the previous clock back even if `f` panics
+    struct Restore(Option<Arc<dyn Clock>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            THREAD_CLOCK.with(|c| *c.borrow_mut() = previous);
+        }
+    }
+
+    let _restore = Restore(THREAD_CLOCK.with(|c| c.borrow_mut().replace(clock)));
+    f()
+}
+
+// The current time by the clock in force on this thread
+#[cfg(feature = "std")]
+pub(crate) fn now() -> Unixtime {
+    if let Some(time) = THREAD_CLOCK.with(|c| c.borrow().as_ref().map(|clock| clock.now())) {
+        return time;
+    }
+    let global = GLOBAL_CLOCK.read().unwrap_or_else(|e| e.into_inner());
+    match global.as_ref() {
+        Some(clock) => clock.now(),
+        None => SystemClock.now(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "std")]
+    use crate::types::{EventKind, PreEvent, PrivateKey, PublicKey};
+
+    #[test]
+    fn test_now_with() {
+        let clock = ManualClock::new(Unixtime(1_000));
+        assert_eq!(Unixtime::now_with(&clock), Unixtime(1_000));
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(Unixtime::now_with(&clock), Unixtime(1_060));
+        clock.set(Unixtime(5));
+        assert_eq!(Unixtime::now_with(&clock), Unixtime(5));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_with_clock() {
+        let clock = Arc::new(ManualClock::new(Unixtime(1_000)));
+        let system = SystemClock.now();
+
+        let (before, after, pubkey) = with_clock(clock.clone(), || {
+            let before = Unixtime::now();
+            clock.advance(Duration::from_secs(60));
+            let pubkey: PublicKey = PrivateKey::mock().public_key();
+            let pre = PreEvent::new_dm_relays(pubkey, &[]);
+            (before, pre.created_at, pubkey)
+        });
+        assert_eq!(before, Unixtime(1_000));
+        assert_eq!(after, Unixtime(1_060));
+        assert!(Unixtime::now() >= system);
+
+        // Nested clocks are restored in turn, even after a panic
+        let outer = Arc::new(ManualClock::new(Unixtime(5)));
+        with_clock(outer, || {
+            let result = std::panic::catch_unwind(|| {
+                with_clock(Arc::new(ManualClock::new(Unixtime(6))), || panic!("oops"))
+            });
+            assert!(result.is_err());
+            assert_eq!(Unixtime::now(), Unixtime(5));
+            let pre = PreEvent::new_dm_relays(pubkey, &[]);
+            assert_eq!(pre.kind, EventKind::DmRelayList);
+            assert_eq!(pre.created_at, Unixtime(5));
+        });
+    }
+}
@@ -0,0 +1,507 @@
+use super::{Event, EventAddr, EventKind, Id, PublicKey, Tag, UncheckedUrl};
+#[cfg(feature = "std")]
+use super::{PreEvent, PublicKeyHex, Unixtime};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+
+/// A moderator of a community (NIP-72), from a 'p' tag
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct CommunityModerator {
+    /// The moderator
+    pub pubkey: PublicKey,
+
+    /// A relay where the moderator can be found
+    pub relay: Option<UncheckedUrl>,
+}
+
+/// A relay a community uses (NIP-72), from a 'relay' tag
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct CommunityRelay {
+    /// The relay
+    pub url: UncheckedUrl,
+
+    /// What the relay is used for: "author", "requests" or "approvals", or
+    /// None for general use
+    pub marker: Option<String>,
+}
+
+/// A moderated community definition (NIP-72, kind 34550)
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct Community {
+    /// The 'd' identifier
+    pub d: String,
+
+    /// The display name, if different from the identifier
+    pub name: Option<String>,
+
+    /// Description
+    pub description: Option<String>,
+
+    /// Image
+    pub image: Option<UncheckedUrl>,
+
+    /// The moderators, whose approvals make posts visible in the community
+    pub moderators: Vec<CommunityModerator>,
+
+    /// Relays the community uses
+    pub relays: Vec<CommunityRelay>,
+}
+
+impl Community {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> Community {
+        Community {
+            d: "rust".to_owned(),
+            name: Some("Rust".to_owned()),
+            description: Some("All things Rust".to_owned()),
+            image: Some(UncheckedUrl::mock()),
+            moderators: vec![CommunityModerator {
+                pubkey: PublicKey::mock_deterministic(),
+                relay: Some(UncheckedUrl("wss://relay.example.com".to_owned())),
+            }],
+            relays: vec![CommunityRelay {
+                url: UncheckedUrl("wss://relay.example.com".to_owned()),
+                marker: Some("approvals".to_owned()),
+            }],
+        }
+    }
+
+    /// Read a community from the tags of a kind 34550 event
+    pub fn from_tags(tags: &[Tag]) -> Community {
+        let mut community = Community::default();
+        let optional = |tag: &Tag, i| match tag.get_index(i) {
+            "" => None,
+            s => Some(s.to_owned()),
+        };
+        for tag in tags {
+            match tag.tagname() {
+                "d" => community.d = tag.get_index(1).to_owned(),
+                "name" => community.name = optional(tag, 1),
+                "description" => community.description = optional(tag, 1),
+                "image" => community.image = optional(tag, 1).map(UncheckedUrl),
+                "relay" => {
+                    if let Some(url) = optional(tag, 1) {
+                        community.relays.push(CommunityRelay {
+                            url: UncheckedUrl(url),
+                            marker: optional(tag, 2),
+                        });
+                    }
+                }
+                "p" if tag.get_index(3) == "moderator" => {
+                    let Ok((pubkey, relay, _)) = tag.parse_pubkey() else {
+                        continue;
+                    };
+                    let Ok(pubkey) = PublicKey::try_from(pubkey) else {
+                        continue;
+                    };
+                    community
+                        .moderators
+                        .push(CommunityModerator { pubkey, relay });
+                }
+                _ => {}
+            }
+        }
+        community
+    }
+
+    /// Write this community out as tags for a kind 34550 event
+    pub fn to_tags(&self) -> Vec<Tag> {
+        let mut tags = vec![Tag::new_identifier(self.d.clone())];
+        let mut push = |name: &str, value: Option<&str>| {
+            if let Some(value) = value {
+                tags.push(Tag::new(&[name, value]));
+            }
+        };
+        push("name", self.name.as_deref());
+        push("description", self.description.as_deref());
+        push("image", self.image.as_ref().map(|u| u.as_str()));
+        for moderator in &self.moderators {
+            tags.push(Tag::new_pubkey(
+                moderator.pubkey.into(),
+                moderator.relay.clone(),
+                Some("moderator".to_owned()),
+            ));
+        }
+        for relay in &self.relays {
+            let mut tag = Tag::new(&["relay", relay.url.as_str()]);
+            if let Some(marker) = &relay.marker {
+                tag.push_value(marker.clone());
+            }
+            tags.push(tag);
+        }
+        tags
+    }
+
+    /// The address of this community, as defined by `owner`
+    pub fn address(&self, owner: PublicKey) -> EventAddr {
+        EventAddr {
+            d: self.d.clone(),
+            relays: vec![],
+            kind: EventKind::CommunityDefinition,
+            author: owner,
+        }
+    }
+
+    /// Is `pubkey` one of the moderators?
+    pub fn is_moderator(&self, pubkey: &PublicKey) -> bool {
+        self.moderators.iter().any(|m| m.pubkey == *pubkey)
+    }
+
+    /// The public keys of the moderators, to check approvals against. Many
+    /// clients also accept approvals by the owner, who can be added to these.
+    pub fn moderator_keys(&self) -> Vec<PublicKey> {
+        self.moderators.iter().map(|m| m.pubkey).collect()
+    }
+}
+
+/// A moderator's approval of a post to a community (NIP-72, kind 4550)
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct CommunityApproval {
+    /// The communities the post is approved in, from 'a' tags
+    pub communities: Vec<EventAddr>,
+
+    /// The approved post, from the 'e' tag. This approves only this version
+    /// of a replaceable post.
+    pub post: Option<Id>,
+
+    /// The address of an approved parameterized replaceable post, from an
+    /// 'a' tag. This approves every version of it.
+    pub post_address: Option<EventAddr>,
+
+    /// The author of the post, from the 'p' tag
+    pub author: Option<PublicKey>,
+
+    /// The kind of the post, from the 'k' tag
+    pub kind: Option<EventKind>,
+
+    /// The post as JSON, so that it can be shown without fetching it. This
+    /// is the event content, and may be empty.
+    pub post_json: String,
+}
+
+// Whether two addresses are the same, ignoring relays
+fn same_address(a: &EventAddr, b: &EventAddr) -> bool {
+    a.kind == b.kind && a.author == b.author && a.d == b.d
+}
+
+impl CommunityApproval {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> CommunityApproval {
+        CommunityApproval {
+            communities: vec![Community::mock().address(PublicKey::mock_deterministic())],
+            post: Some(Id::mock()),
+            post_address: None,
+            author: Some(PublicKey::mock_deterministic()),
+            kind: Some(EventKind::Comment),
+            post_json: "".to_owned(),
+        }
+    }
+
+    /// An approval of `post` in `community`
+    pub fn new(community: &EventAddr, post: &Event) -> Result<CommunityApproval, Error> {
+        if community.kind != EventKind::CommunityDefinition {
+            return Err(Error::WrongEventKind);
+        }
+        Ok(CommunityApproval {
+            communities: vec![community.clone()],
+            post: Some(post.id),
+            post_address: post.address(),
+            author: Some(post.pubkey),
+            kind: Some(post.kind),
+            post_json: serde_json::to_string(post)?,
+        })
+    }
+
+    /// Read an approval from the tags and content of a kind 4550 event.
+    /// 'a' tags are taken as communities if they are of kind 34550, and as
+    /// the post otherwise.
+    pub fn from_tags(tags: &[Tag], content: &str) -> CommunityApproval {
+        let mut approval = CommunityApproval {
+            communities: vec![],
+            post: None,
+            post_address: None,
+            author: None,
+            kind: None,
+            post_json: content.to_owned(),
+        };
+        for tag in tags {
+            if let Ok((ea, _)) = tag.parse_address() {
+                if ea.kind == EventKind::CommunityDefinition {
+                    approval.communities.push(ea);
+                } else if approval.post_address.is_none() {
+                    approval.post_address = Some(ea);
+                }
+            } else if let Ok((id, _, _)) = tag.parse_event() {
+                approval.post = approval.post.or(Some(id));
+            } else if let Ok((pubkey, _, _)) = tag.parse_pubkey() {
+                if approval.author.is_none() {
+                    approval.author = PublicKey::try_from(pubkey).ok();
+                }
+            } else if let Ok(kind) = tag.parse_kind() {
+                approval.kind = approval.kind.or(Some(kind));
+            }
+        }
+        approval
+    }
+
+    /// The tags of a kind 4550 event for this approval
+    pub fn to_tags(&self) -> Vec<Tag> {
+        let mut tags: Vec<Tag> = self
+            .communities
+            .iter()
+            .map(|ea| Tag::new_address(ea, None))
+            .collect();
+        if let Some(id) = self.post {
+            tags.push(Tag::new_event(id, None, None));
+        }
+        if let Some(ea) = &self.post_address {
+            tags.push(Tag::new_address(ea, None));
+        }
+        if let Some(author) = self.author {
+            tags.push(Tag::new_pubkey(author.into(), None, None));
+        }
+        if let Some(kind) = self.kind {
+            tags.push(Tag::new_kind(kind));
+        }
+        tags
+    }
+
+    /// Is this an approval in `community`? Relays are ignored.
+    pub fn is_for(&self, community: &EventAddr) -> bool {
+        self.communities
+            .iter()
+            .any(|ea| same_address(ea, community))
+    }
+
+    /// Does this approve `post`? An 'e' tag approves only that version of
+    /// it, while an 'a' tag approves any version of a replaceable post.
+    pub fn approves(&self, post: &Event) -> bool {
+        self.post == Some(post.id)
+            || self
+                .post_address
+                .as_ref()
+                .is_some_and(|ea| post.matches_address(ea))
+    }
+
+    /// The approved post, from the content. It has not been verified.
+    pub fn post_event(&self) -> Result<Event, Error> {
+        Ok(serde_json::from_str(&self.post_json)?)
+    }
+}
+
+impl Event {
+    /// If this is a NIP-72 community definition (kind 34550), get its details
+    pub fn community(&self) -> Option<Community> {
+        if self.kind != EventKind::CommunityDefinition {
+            return None;
+        }
+        Some(Community::from_tags(&self.tags))
+    }
+
+    /// If this is a NIP-72 community post approval (kind 4550), get its
+    /// details
+    pub fn community_approval(&self) -> Option<CommunityApproval> {
+        if self.kind != EventKind::CommunityPostApproval {
+            return None;
+        }
+        Some(CommunityApproval::from_tags(&self.tags, &self.content))
+    }
+
+    /// The communities this event is posted to, from 'A' and 'a' tags
+    /// addressing a community definition (kind 34550)
+    pub fn communities(&self) -> Vec<EventAddr> {
+        let mut communities: Vec<EventAddr> = Vec::new();
+        for tag in &self.tags {
+            let ea = match tag.tagname() {
+                "A" => tag.parse_root_address().ok(),
+                "a" => tag.parse_address().ok().map(|(ea, _)| ea),
+                _ => None,
+            };
+            if let Some(ea) = ea {
+                if ea.kind == EventKind::CommunityDefinition
+                    && !communities.iter().any(|c| same_address(c, &ea))
+                {
+                    communities.push(ea);
+                }
+            }
+        }
+        communities
+    }
+
+    /// Is this post approved in `community`, by one of `moderators`, among
+    /// `approvals`? The post must itself be addressed to the community, and
+    /// an approval counts only if it is a kind 4550 event by a moderator
+    /// that names both the community and this post. The approvals' signatures
+    /// are not checked here.
+    pub fn is_approved_in(
+        &self,
+        community: &EventAddr,
+        moderators: &[PublicKey],
+        approvals: &[Event],
+    ) -> bool {
+        if !self
+            .communities()
+            .iter()
+            .any(|c| same_address(c, community))
+        {
+            return false;
+        }
+        approvals.iter().any(|event| {
+            moderators.contains(&event.pubkey)
+                && event
+                    .community_approval()
+                    .is_some_and(|a| a.is_for(community) && a.approves(self))
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl PreEvent {
+    /// Create a NIP-72 community definition PreEvent (kind 34550)
+    pub fn new_community(pubkey: PublicKey, community: &Community) -> PreEvent {
+        PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::CommunityDefinition,
+            tags: community.to_tags(),
+            content: "".to_owned(),
+            ots: None,
+        }
+    }
+
+    /// Create a NIP-72 top-level post (a NIP-22 comment, kind 1111) to the
+    /// community at `community`
+    pub fn new_community_post(
+        pubkey: PublicKey,
+        community: &EventAddr,
+        content: String,
+    ) -> Result<PreEvent, Error> {
+        if community.kind != EventKind::CommunityDefinition {
+            return Err(Error::WrongEventKind);
+        }
+        let owner: PublicKeyHex = community.author.into();
+        Ok(PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::Comment,
+            tags: vec![
+                Tag::new_root_address(community),
+                Tag::new_root_pubkey(owner.clone(), None),
+                Tag::new_root_kind(EventKind::CommunityDefinition),
+                Tag::new_address(community, None),
+                Tag::new_pubkey(owner, None, None),
+                Tag::new_kind(EventKind::CommunityDefinition),
+            ],
+            content,
+            ots: None,
+        })
+    }
+
+    /// Create a NIP-72 approval (kind 4550) of `post` in `community`
+    pub fn new_community_approval(
+        pubkey: PublicKey,
+        community: &EventAddr,
+        post: &Event,
+    ) -> Result<PreEvent, Error> {
+        let approval = CommunityApproval::new(community, post)?;
+        Ok(PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::CommunityPostApproval,
+            tags: approval.to_tags(),
+            content: approval.post_json,
+            ots: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {Community, test_community_serde}
+    test_serde! {CommunityApproval, test_community_approval_serde}
+
+    #[test]
+    fn test_community_tags() {
+        let community = Community::mock();
+        assert_eq!(Community::from_tags(&community.to_tags()), community);
+
+        let approval = CommunityApproval::mock();
+        assert_eq!(
+            CommunityApproval::from_tags(&approval.to_tags(), &approval.post_json),
+            approval
+        );
+
+        // Only 'p' tags marked as moderators count
+        let tags = vec![
+            Tag::new(&["d", "x"]),
+            Tag::new_pubkey(PublicKey::mock_deterministic().into(), None, None),
+        ];
+        assert!(Community::from_tags(&tags).moderators.is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_community_approval() {
+        use crate::types::PrivateKey;
+
+        let owner = PrivateKey::generate();
+        let moderator = PrivateKey::generate();
+        let author = PrivateKey::generate();
+        let mut community = Community::mock();
+        community.moderators = vec![CommunityModerator {
+            pubkey: moderator.public_key(),
+            relay: None,
+        }];
+        let definition = Event::new(
+            PreEvent::new_community(owner.public_key(), &community),
+            &owner,
+        )
+        .unwrap();
+        assert_eq!(definition.community(), Some(community.clone()));
+        let addr = definition.address().unwrap();
+        assert_eq!(addr, community.address(owner.public_key()));
+
+        let pre = PreEvent::new_community_post(author.public_key(), &addr, "Hi".to_owned());
+        let post = Event::new(pre.unwrap(), &author).unwrap();
+        assert_eq!(post.communities(), vec![addr.clone()]);
+
+        let approve = |signer: &PrivateKey, community: &EventAddr| {
+            let pre = PreEvent::new_community_approval(signer.public_key(), community, &post);
+            Event::new(pre.unwrap(), signer).unwrap()
+        };
+        let approval = approve(&moderator, &addr);
+        let parsed = approval.community_approval().unwrap();
+        assert!(parsed.is_for(&addr));
+        assert!(parsed.approves(&post));
+        assert_eq!(parsed.post_event().unwrap(), post);
+
+        let moderators = community.moderator_keys();
+        assert!(post.is_approved_in(&addr, &moderators, std::slice::from_ref(&approval)));
+
+        // Not by a moderator
+        assert!(!post.is_approved_in(&addr, &moderators, &[approve(&author, &addr)]));
+
+        // For another community
+        let mut other = addr.clone();
+        other.d = "other".to_owned();
+        assert!(!post.is_approved_in(&addr, &moderators, &[approve(&moderator, &other)]));
+        assert!(!post.is_approved_in(&other, &moderators, &[approval]));
+
+        let mut wrong = addr;
+        wrong.kind = EventKind::LongFormContent;
+        assert!(matches!(
+            PreEvent::new_community_post(author.public_key(), &wrong, "".to_owned()),
+            Err(Error::WrongEventKind)
+        ));
+    }
+}
@@ -0,0 +1,443 @@
+//! A compact binary encoding of events for storage
+//!
+//! This holds ids, keys and signatures as raw bytes, lengths as varints, and
+//! common tag fields as references to a dictionary, so it is much smaller
+//! than JSON, and still roundtrips exactly. The layout is:
+//!
+//! * a version byte
+//! * the id, pubkey and signature as 32, 32 and 64 raw bytes
+//! * created_at as a zigzag varint, and the kind as a varint
+//! * a flags byte: 1 if there is an `ots` field, 2 if there are extra fields
+//! * the content as a varint length and UTF-8 bytes
+//! * the number of tags, then for each the number of fields and the fields.
+//!   Each field starts with a varint `(value << 2) | mode`: mode 0 is a
+//!   string of `value` bytes, mode 1 is 64 lowercase hex characters held as
+//!   32 bytes, mode 2 is entry `value` of a fixed dictionary of common
+//!   fields, and mode 3 repeats the `value`th string of mode 0 in the event.
+//! * the `ots` field and the extra fields (as JSON), as strings, if flagged
+//!
+//! When reading, the tag fields may decode to at most 64 times the length of
+//! the input, so that repeats cannot be used to exhaust memory.
+
+use super::{Event, EventKind, Id, PublicKey, Signature, Tag, Unixtime};
+use crate::Error;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+// Version 1 had relay URLs at the end of its dictionary, so is not read
+const COMPACT_VERSION: u8 = 2;
+
+const FLAG_OTS: u8 = 1;
+const FLAG_EXTRA: u8 = 2;
+
+const MODE_STRING: u64 = 0;
+const MODE_HEX: u64 = 1;
+const MODE_DICTIONARY: u64 = 2;
+const MODE_REPEAT: u64 = 3;
+
+// Common tag fields, from the protocol only: tag names, markers and the like.
+// Entries may only ever be added to the end of this, or stored events would
+// decode differently; anything else needs a new version.
+const DICTIONARY: &[&str] = &[
+    "e",
+    "p",
+    "a",
+    "t",
+    "d",
+    "r",
+    "k",
+    "q",
+    "l",
+    "L",
+    "g",
+    "-",
+    "",
+    "root",
+    "reply",
+    "mention",
+    "marker",
+    "nonce",
+    "expiration",
+    "subject",
+    "title",
+    "summary",
+    "image",
+    "published_at",
+    "client",
+    "alt",
+    "relay",
+    "relays",
+    "amount",
+    "bolt11",
+    "description",
+    "preimage",
+    "emoji",
+    "imeta",
+    "proxy",
+    "content-warning",
+    "E",
+    "P",
+    "K",
+    "A",
+    "read",
+    "write",
+];
+
+// Repeating a string costs at least a byte, so short ones are not worth it
+const MIN_REPEAT_LEN: usize = 4;
+
+// Tag fields may decode to at most this many times the length of the input,
+// as a repeat of a long string takes only a few bytes
+const MAX_EXPANSION: usize = 64;
+
+impl Event {
+    /// Append this event to `output` in the compact binary encoding (see the
+    /// module documentation). Events can be written one after another and
+    /// read back with `read_compact()`.
+    pub fn write_compact(&self, output: &mut Vec<u8>) {
+        output.push(COMPACT_VERSION);
+        output.extend_from_slice(&self.id.0);
+        output.extend_from_slice(&self.pubkey.as_bytes());
+        output.extend_from_slice(self.sig.as_bytes());
+        write_varint(output, zigzag(self.created_at.0));
+        write_varint(output, u32::from(self.kind) as u64);
+        let mut flags = 0;
+        if self.ots.is_some() {
+            flags |= FLAG_OTS;
+        }
+        if !self.extra.is_empty() {
+            flags |= FLAG_EXTRA;
+        }
+        output.push(flags);
+        write_string(output, &self.content);
+
+        let mut seen: HashMap<&str, u64> = HashMap::new();
+        write_varint(output, self.tags.len() as u64);
+        for tag in &self.tags {
+            write_varint(output, tag.fields().len() as u64);
+            for field in tag.fields() {
+                write_field(output, field, &mut seen);
+            }
+        }
+
+        if let Some(ots) = &self.ots {
+            write_string(output, ots);
+        }
+        if !self.extra.is_empty() {
+            // Cannot fail: these are JSON values already
+            let extra = serde_json::to_string(&self.extra).unwrap_or_default();
+            write_string(output, &extra);
+        }
+    }
+
+    /// Read an event in the compact binary encoding from the front of
+    /// `input`, advancing it past the event. The event is not verified.
+    pub fn read_compact(input: &mut &[u8]) -> Result<Event, Error> {
+        let budget = input.len().saturating_mul(MAX_EXPANSION);
+        let mut reader = CompactReader(input, budget);
+        if reader.byte()? != COMPACT_VERSION {
+            return Err(Error::Compact("Unknown version".to_owned()));
+        }
+        let id = Id(reader.array()?);
+        let pubkey = PublicKey::from_bytes(&reader.array::<32>()?)?;
+        let sig = Signature(reader.array()?);
+        let created_at = Unixtime(unzigzag(reader.varint()?));
+        let kind = u32::try_from(reader.varint()?)
+            .map_err(|_| Error::Compact("Kind out of range".to_owned()))?;
+        let flags = reader.byte()?;
+        let content = reader.string()?;
+
+        let mut seen: Vec<String> = Vec::new();
+        let tag_count = reader.count()?;
+        let mut tags: Vec<Tag> = Vec::with_capacity(tag_count);
+        for _ in 0..tag_count {
+            let field_count = reader.count()?;
+            let mut fields: Vec<String> = Vec::with_capacity(field_count);
+            for _ in 0..field_count {
+                fields.push(reader.field(&mut seen)?);
+            }
+            tags.push(Tag::from_strings(fields));
+        }
+
+        let ots = if flags & FLAG_OTS != 0 {
+            Some(reader.string()?)
+        } else {
+            None
+        };
+        let extra: Map<String, Value> = if flags & FLAG_EXTRA != 0 {
+            serde_json::from_str(&reader.string()?)?
+        } else {
+            Map::new()
+        };
+
+        Ok(Event {
+            id,
+            pubkey,
+            created_at,
+            kind: EventKind::from(kind),
+            tags,
+            content,
+            ots,
+            sig,
+            extra,
+        })
+    }
+}
+
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn unzigzag(n: u64) -> i64 {
+    ((n >> 1) as i64) ^ -((n & 1) as i64)
+}
+
+fn write_varint(output: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        output.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    output.push(n as u8);
+}
+
+fn write_string(output: &mut Vec<u8>, s: &str) {
+    write_varint(output, s.len() as u64);
+    output.extend_from_slice(s.as_bytes());
+}
+
+// `seen` maps each string written so far in mode 0 to its position, which is
+// how mode 3 refers to it
+fn write_field<'a>(output: &mut Vec<u8>, field: &'a str, seen: &mut HashMap<&'a str, u64>) {
+    if let Some(index) = DICTIONARY.iter().position(|d| *d == field) {
+        write_varint(output, (index as u64) << 2 | MODE_DICTIONARY);
+    } else if field.len() == 64
+        && field
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    {
+        let mut bytes = [0u8; 32];
+        // Cannot fail: checked to be 64 hex characters
+        let _ = hex::decode_to_slice(field, &mut bytes);
+        write_varint(output, MODE_HEX);
+        output.extend_from_slice(&bytes);
+    } else if let Some(index) = seen.get(field) {
+        write_varint(output, index << 2 | MODE_REPEAT);
+    } else {
+        write_varint(output, (field.len() as u64) << 2 | MODE_STRING);
+        output.extend_from_slice(field.as_bytes());
+        if field.len() >= MIN_REPEAT_LEN {
+            let index = seen.len() as u64;
+            let _ = seen.insert(field, index);
+        }
+    }
+}
+
+// The input, and how many more bytes the tag fields may decode to
+struct CompactReader<'a, 'b>(&'a mut &'b [u8], usize);
+
+impl CompactReader<'_, '_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], Error> {
+        if self.0.len() < len {
+            return Err(Error::Compact("Unexpected end of input".to_owned()));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        *self.0 = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn varint(&mut self) -> Result<u64, Error> {
+        let mut n: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(Error::Compact("Varint too long".to_owned()))
+    }
+
+    // A count of things each taking at least a byte, checked against what is
+    // left so a bad count cannot make us allocate a lot
+    fn count(&mut self) -> Result<usize, Error> {
+        let n = self.varint()?;
+        if n > self.0.len() as u64 {
+            return Err(Error::Compact("Count exceeds input".to_owned()));
+        }
+        Ok(n as usize)
+    }
+
+    fn utf8(&mut self, len: u64) -> Result<String, Error> {
+        if len > self.0.len() as u64 {
+            return Err(Error::Compact("Unexpected end of input".to_owned()));
+        }
+        let bytes = self.take(len as usize)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| Error::Compact("Invalid UTF-8".to_owned()))
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let len = self.varint()?;
+        self.utf8(len)
+    }
+
+    fn field(&mut self, seen: &mut Vec<String>) -> Result<String, Error> {
+        let field = self.field_unchecked(seen)?;
+        self.1 = self
+            .1
+            .checked_sub(field.len())
+            .ok_or_else(|| Error::Compact("Tags decode too large".to_owned()))?;
+        Ok(field)
+    }
+
+    fn field_unchecked(&mut self, seen: &mut Vec<String>) -> Result<String, Error> {
+        let header = self.varint()?;
+        let value = header >> 2;
+        match header & 3 {
+            MODE_STRING => {
+                let field = self.utf8(value)?;
+                if field.len() >= MIN_REPEAT_LEN {
+                    seen.push(field.clone());
+                }
+                Ok(field)
+            }
+            MODE_HEX => Ok(hex::encode(self.array::<32>()?)),
+            MODE_DICTIONARY => DICTIONARY
+                .get(value as usize)
+                .map(|d| (*d).to_owned())
+                .ok_or_else(|| Error::Compact("Unknown dictionary entry".to_owned())),
+            _ => seen
+                .get(value as usize)
+                .cloned()
+                .ok_or_else(|| Error::Compact("Unknown repeated string".to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{PreEvent, PrivateKey, UncheckedUrl};
+
+    // A contact list, as most of a cache is
+    fn contact_list() -> Event {
+        let privkey = PrivateKey::mock();
+        let tags: Vec<Tag> = (0..20)
+            .map(|_| {
+                Tag::new_pubkey(
+                    PublicKey::mock().into(),
+                    Some(UncheckedUrl::from_str("wss://relay.example.com")),
+                    None,
+                )
+            })
+            .collect();
+        let pre = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime::mock(),
+            kind: EventKind::ContactList,
+            tags,
+            content: "".to_owned(),
+            ots: None,
+        };
+        Event::new(pre, &privkey).unwrap()
+    }
+
+    #[test]
+    fn test_compact_roundtrip() {
+        let mut event = contact_list();
+        let json = serde_json::to_string(&event).unwrap();
+
+        let mut output: Vec<u8> = Vec::new();
+        event.write_compact(&mut output);
+        let first_len = output.len();
+        assert!(first_len * 2 < json.len());
+
+        event.ots = Some("proof".to_owned());
+        let _ = event
+            .extra
+            .insert("seen_on".to_owned(), Value::from("wss://relay.example.com"));
+        event.write_compact(&mut output);
+        let mock = Event::mock();
+        mock.write_compact(&mut output);
+
+        let mut input = &output[..];
+        let first = Event::read_compact(&mut input).unwrap();
+        assert!(first.verify(None).is_ok());
+        assert_eq!(input.len(), output.len() - first_len);
+        assert_eq!(Event::read_compact(&mut input).unwrap(), event);
+        assert_eq!(Event::read_compact(&mut input).unwrap(), mock);
+        assert!(input.is_empty());
+
+        // Truncated or garbled input is an error, not a panic
+        for len in 0..first_len {
+            assert!(Event::read_compact(&mut &output[..len]).is_err());
+        }
+        let mut garbled = output.clone();
+        garbled[0] = 99;
+        assert!(Event::read_compact(&mut &garbled[..]).is_err());
+
+        // Repeats of a long string cannot decode to much more than the input
+        let mut hostile: Vec<u8> = Vec::new();
+        mock.write_compact(&mut hostile);
+        hostile.truncate(1 + 128);
+        hostile.extend([2, 1, 0, 0]); // created_at, kind, flags, content
+        write_varint(&mut hostile, 5_000);
+        write_varint(&mut hostile, 1);
+        write_varint(&mut hostile, 1000 << 2 | MODE_STRING);
+        hostile.extend([b'x'; 1000]);
+        for _ in 1..5_000 {
+            hostile.extend([1, MODE_REPEAT as u8]);
+        }
+        assert!(matches!(
+            Event::read_compact(&mut &hostile[..]),
+            Err(Error::Compact(e)) if e == "Tags decode too large"
+        ));
+
+        for n in [0, 1, -1, i64::MAX, i64::MIN, 1_700_000_000] {
+            assert_eq!(unzigzag(zigzag(n)), n);
+        }
+    }
+
+    #[test]
+    fn test_compact_repeats() {
+        // Each distinct long string is written once, then referred back to
+        let mut event = contact_list();
+        event.tags = (0..200)
+            .map(|i| Tag::new(&["x", &format!("value {}", i % 50)]))
+            .collect();
+        let mut output: Vec<u8> = Vec::new();
+        event.write_compact(&mut output);
+        // The fixed part, then per tag its length and "x", then the strings
+        // written once and the references back to them
+        let strings: usize = (0..50).map(|i| format!("value {i}").len() + 1).sum();
+        assert!(output.len() <= 140 + 200 * 3 + strings + 150 * 2);
+        assert_eq!(Event::read_compact(&mut &output[..]).unwrap(), event);
+    }
+
+    #[cfg(feature = "speedy")]
+    #[test]
+    fn test_compact_smaller_than_speedy() {
+        use speedy::Writable;
+
+        for event in [contact_list(), Event::mock()] {
+            let mut compact: Vec<u8> = Vec::new();
+            event.write_compact(&mut compact);
+            let speedy = event.write_to_vec().unwrap();
+            assert!(
+                compact.len() < speedy.len(),
+                "compact {} bytes, speedy {} bytes",
+                compact.len(),
+                speedy.len()
+            );
+        }
+    }
+}
@@ -113,12 +113,15 @@ fn shatter_content_2(content: &str) -> Vec<ContentSegment> {
 
     let mut pos = 0;
     for mat in TAG_RE.find_iter(content) {
+        // Too large a number is left as text
+        let Ok(u) = content[mat.start() + 2..mat.end() - 1].parse::<usize>() else {
+            continue;
+        };
+
         let mut inner_segments = shatter_content_3(&content[pos..mat.start()]);
         apply_offset(&mut inner_segments, pos);
         segments.append(&mut inner_segments);
 
-        // If panics on unwrap, something is wrong with Regex.
-        let u: usize = content[mat.start() + 2..mat.end() - 1].parse().unwrap();
         segments.push(ContentSegment::TagReference(u));
         pos = mat.end();
     }
@@ -186,4 +189,15 @@ And referencing this person nostr:npub1acg6thl5psv62405rljzkj8spesceyfz2c32udakc
         let pieces = ShatteredContent::new(content);
         assert_eq!(pieces.segments.len(), 9);
     }
+
+    #[test]
+    fn test_shatter_content_huge_tag_reference() {
+        let pieces = ShatteredContent::new("see #[99999999999999999999999] and #[1]".to_owned());
+        assert_eq!(pieces.segments.len(), 2);
+        assert!(matches!(pieces.segments[0], ContentSegment::Plain(..)));
+        assert!(matches!(
+            pieces.segments[1],
+            ContentSegment::TagReference(1)
+        ));
+    }
 }
@@ -1,4 +1,6 @@
-use super::{EventKind, PrivateKey, PublicKey, PublicKeyHex, Signature, SignatureHex, Unixtime};
+use super::{
+    Event, EventKind, PrivateKey, PublicKey, PublicKeyHex, Signature, SignatureHex, Unixtime,
+};
 use crate::Error;
 use serde::de::Error as DeError;
 use serde::de::{Deserialize, Deserializer, Visitor};
@@ -21,11 +23,16 @@ pub enum EventDelegation {
 }
 
 /// Conditions of delegation
+///
+/// Build them with `DelegationConditions::new()` and the `kind()`,
+/// `created_after()` and `created_before()` methods, e.g.
+/// `DelegationConditions::new().kind(EventKind::TextNote).kind(EventKind::Reaction)`.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "speedy", derive(Readable, Writable))]
 pub struct DelegationConditions {
-    /// If the delegation is only for a given event kind
-    pub kind: Option<EventKind>,
+    /// If the delegation is only for some event kinds. Several `kind=`
+    /// clauses allow any of those kinds. Empty allows every kind.
+    pub kinds: Vec<EventKind>,
 
     /// If the delegation is only for events created after a certain time
     pub created_after: Option<Unixtime>,
@@ -38,6 +45,58 @@ pub struct DelegationConditions {
 }
 
 impl DelegationConditions {
+    /// Conditions that allow everything, to add to with the builder methods
+    pub fn new() -> DelegationConditions {
+        DelegationConditions::default()
+    }
+
+    /// Also allow events of this kind
+    pub fn kind(mut self, kind: EventKind) -> DelegationConditions {
+        if !self.kinds.contains(&kind) {
+            self.kinds.push(kind);
+        }
+        self.full_string = None;
+        self
+    }
+
+    /// Only allow events created after this time
+    pub fn created_after(mut self, time: Unixtime) -> DelegationConditions {
+        self.created_after = Some(time);
+        self.full_string = None;
+        self
+    }
+
+    /// Only allow events created before this time
+    pub fn created_before(mut self, time: Unixtime) -> DelegationConditions {
+        self.created_before = Some(time);
+        self.full_string = None;
+        self
+    }
+
+    /// Whether an event meets these conditions. This does not check the
+    /// delegation signature; see `Event::delegation()` for the full check.
+    pub fn matches_event(&self, event: &Event) -> bool {
+        self.mismatch(event).is_none()
+    }
+
+    // Why an event does not meet these conditions, if it does not
+    pub(crate) fn mismatch(&self, event: &Event) -> Option<&'static str> {
+        if !self.kinds.is_empty() && !self.kinds.contains(&event.kind) {
+            return Some("Event Kind not delegated");
+        }
+        if let Some(created_after) = self.created_after {
+            if event.created_at < created_after {
+                return Some("Event created before delegation started");
+            }
+        }
+        if let Some(created_before) = self.created_before {
+            if event.created_at > created_before {
+                return Some("Event created after delegation ended");
+            }
+        }
+        None
+    }
+
     /// Return in conmpiled string form. If full form is stored, it is returned, otherwise it is compiled from parts.
     pub fn as_string(&self) -> String {
         match &self.full_string {
@@ -49,8 +108,8 @@ impl DelegationConditions {
     /// Compile full string from parts.
     fn compile_full_string(&self) -> String {
         let mut parts: Vec<String> = Vec::new();
-        if let Some(kind) = self.kind {
-            parts.push(format!("kind={}", u32::from(kind)));
+        for kind in &self.kinds {
+            parts.push(format!("kind={}", u32::from(*kind)));
         }
         if let Some(created_after) = self.created_after {
             parts.push(format!("created_at>{}", created_after.0));
@@ -75,7 +134,7 @@ impl DelegationConditions {
             if let Some(kindstr) = part.strip_prefix("kind=") {
                 let event_num = kindstr.parse::<u32>()?;
                 let event_kind: EventKind = From::from(event_num);
-                output.kind = Some(event_kind);
+                output.kinds.push(event_kind);
             }
             if let Some(timestr) = part.strip_prefix("created_at>") {
                 let time = timestr.parse::<i64>()?;
@@ -95,7 +154,7 @@ impl DelegationConditions {
     #[allow(dead_code)]
     pub(crate) fn mock() -> DelegationConditions {
         let mut dc = DelegationConditions {
-            kind: Some(EventKind::Repost),
+            kinds: vec![EventKind::Repost],
             created_after: Some(Unixtime(1677700000)),
             created_before: None,
             full_string: None,
@@ -206,13 +265,7 @@ mod test {
     fn test_delegation_tag_parse_and_verify() {
         let tag_str = "[\"delegation\",\"1a459a8a6aa6441d480ba665fb8fb21a4cfe8bcacb7d87300f8046a558a3fce4\",\"kind=1&created_at>1676067553&created_at<1678659553\",\"369aed09c1ad52fceb77ecd6c16f2433eac4a3803fc41c58876a5b60f4f36b9493d5115e5ec5a0ce6c3668ffe5b58d47f2cbc97233833bb7e908f66dbbbd9d36\"]";
         let dt = serde_json::from_str::<Tag>(tag_str).unwrap();
-        if let Tag::Delegation {
-            pubkey,
-            conditions,
-            sig,
-            ..
-        } = dt
-        {
+        if let Ok((pubkey, conditions, sig)) = dt.parse_delegation() {
             assert_eq!(
                 conditions.as_string(),
                 "kind=1&created_at>1676067553&created_at<1678659553"
@@ -239,13 +292,7 @@ mod test {
         // Clauses in the condition string are not in the canonical order, but this should not matter
         let tag_str = "[\"delegation\",\"05bc52a6117c57f99b73f5315f3105b21cecdcd2c6825dee8d508bd7d972ad6a\",\"kind=1&created_at<1686078180&created_at>1680807780\",\"1016d2f4284cdb4e6dc6eaa4e61dff87b9f4138786154d070d36e9434f817bd623abed2133bb62b9dcfb2fbf54b42e16bcd44cfc23907f8eb5b45c011caaa47c\"]";
         let dt = serde_json::from_str::<Tag>(tag_str).unwrap();
-        if let Tag::Delegation {
-            pubkey,
-            conditions,
-            sig,
-            ..
-        } = dt
-        {
+        if let Ok((pubkey, conditions, sig)) = dt.parse_delegation() {
             assert_eq!(
                 conditions.as_string(),
                 "kind=1&created_at<1686078180&created_at>1680807780"
@@ -285,7 +332,7 @@ mod test {
     #[test]
     fn test_as_string() {
         let dc = DelegationConditions {
-            kind: Some(EventKind::TextNote),
+            kinds: vec![EventKind::TextNote],
             created_before: Some(Unixtime(2000000)),
             created_after: Some(Unixtime(1000000)),
             full_string: None,
@@ -295,4 +342,27 @@ mod test {
             "kind=1&created_at>1000000&created_at<2000000"
         );
     }
+
+    #[test]
+    fn test_multiple_kinds() {
+        let dc = DelegationConditions::try_from_str("kind=1&kind=7&created_at>1000000").unwrap();
+        assert_eq!(dc.kinds, vec![EventKind::TextNote, EventKind::Reaction]);
+
+        let built = DelegationConditions::new()
+            .kind(EventKind::TextNote)
+            .kind(EventKind::Reaction)
+            .created_after(Unixtime(1000000));
+        assert_eq!(built.as_string(), "kind=1&kind=7&created_at>1000000");
+
+        let mut event = Event::mock();
+        event.created_at = Unixtime(1500000);
+        event.kind = EventKind::Reaction;
+        assert!(built.matches_event(&event));
+        event.kind = EventKind::Repost;
+        assert!(!built.matches_event(&event));
+        event.kind = EventKind::TextNote;
+        event.created_at = Unixtime(999999);
+        assert!(!built.matches_event(&event));
+        assert!(DelegationConditions::new().matches_event(&event));
+    }
 }
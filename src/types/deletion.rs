@@ -0,0 +1,169 @@
+use super::{Event, EventAddr, EventKind, Id, PublicKey, Unixtime};
+
+/// A request to delete events (NIP-09), from a kind 5 event.
+///
+/// This does not depend on how events are stored: use `applies_to()` to check
+/// each candidate event.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeletionRequest {
+    /// Who asked for the deletion. Only their own events can be deleted.
+    pub author: PublicKey,
+
+    /// When the deletion was requested. Versions of replaceable events
+    /// created after this are not deleted.
+    pub created_at: Unixtime,
+
+    /// Events to delete, from 'e' tags
+    pub ids: Vec<Id>,
+
+    /// Replaceable events to delete, from 'a' tags
+    pub addresses: Vec<EventAddr>,
+
+    /// The kinds of the events being deleted, from 'k' tags. This is only a
+    /// hint for clients and relays.
+    pub kinds: Vec<EventKind>,
+
+    /// The reason for the deletion
+    pub reason: String,
+}
+
+impl DeletionRequest {
+    /// Read a deletion request from a kind 5 event. Returns None if it is not
+    /// one, or does not ask for anything to be deleted.
+    pub fn from_event(event: &Event) -> Option<DeletionRequest> {
+        if event.kind != EventKind::EventDeletion {
+            return None;
+        }
+        let mut request = DeletionRequest {
+            author: event.pubkey,
+            created_at: event.created_at,
+            ids: vec![],
+            addresses: vec![],
+            kinds: vec![],
+            reason: event.content.clone(),
+        };
+        for tag in event.tags.iter() {
+            if let Ok((id, _, _)) = tag.parse_event() {
+                request.ids.push(id);
+            } else if let Ok((ea, _)) = tag.parse_address() {
+                request.addresses.push(ea);
+            } else if let Ok(kind) = tag.parse_kind() {
+                request.kinds.push(kind);
+            }
+        }
+        if request.ids.is_empty() && request.addresses.is_empty() {
+            None
+        } else {
+            Some(request)
+        }
+    }
+
+    /// Should `event` be deleted because of this request?
+    ///
+    /// It must have the same author, and be either named by Id or be a
+    /// version of a named replaceable event created no later than the request.
+    /// Deletion requests themselves cannot be deleted.
+    pub fn applies_to(&self, event: &Event) -> bool {
+        if event.pubkey != self.author || event.kind == EventKind::EventDeletion {
+            return false;
+        }
+        if self.ids.contains(&event.id) {
+            return true;
+        }
+        let parameterized = event.kind.is_parameterized_replaceable();
+        if event.created_at > self.created_at || !(parameterized || event.kind.is_replaceable()) {
+            return false;
+        }
+        let d = event
+            .tags
+            .iter()
+            .find_map(|t| t.parse_identifier().ok())
+            .unwrap_or_default();
+        self.addresses.iter().any(|ea| {
+            ea.author == self.author && ea.kind == event.kind && (ea.d == d || !parameterized)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{PreEvent, PrivateKey, Tag};
+
+    fn sign(privkey: &PrivateKey, kind: EventKind, tags: Vec<Tag>, created_at: i64) -> Event {
+        let pre = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime(created_at),
+            kind,
+            tags,
+            content: "".to_owned(),
+            ots: None,
+        };
+        Event::new(pre, privkey).unwrap()
+    }
+
+    #[test]
+    fn test_deletion_request() {
+        let privkey = PrivateKey::mock();
+        let note = sign(&privkey, EventKind::TextNote, vec![], 100);
+        let article = sign(
+            &privkey,
+            EventKind::LongFormContent,
+            vec![Tag::new_identifier("post".to_owned())],
+            100,
+        );
+        let relays = sign(&privkey, EventKind::RelayList, vec![], 100);
+        let mut later_article = article.clone();
+        later_article.created_at = Unixtime(300);
+
+        let other_addr = EventAddr {
+            d: "other".to_owned(),
+            ..article.address().unwrap()
+        };
+        let relays_addr = EventAddr {
+            d: "".to_owned(),
+            relays: vec![],
+            kind: EventKind::RelayList,
+            author: privkey.public_key(),
+        };
+        let deletion = sign(
+            &privkey,
+            EventKind::EventDeletion,
+            vec![
+                Tag::new_event(note.id, None, None),
+                Tag::new_address(&article.address().unwrap(), None),
+                Tag::new_address(&relays_addr, None),
+                Tag::new_kind(EventKind::TextNote),
+            ],
+            200,
+        );
+        let request = deletion.deletes().unwrap();
+        assert_eq!(request.ids, vec![note.id]);
+        assert_eq!(request.addresses.len(), 2);
+        assert_eq!(request.kinds, vec![EventKind::TextNote]);
+
+        assert!(request.applies_to(&note));
+        assert!(request.applies_to(&article));
+        assert!(request.applies_to(&relays));
+        assert!(!request.applies_to(&later_article));
+        assert!(!request.applies_to(&deletion));
+
+        let mut other_article = article.clone();
+        other_article.tags = vec![Tag::new_identifier("other".to_owned())];
+        assert!(!request.applies_to(&other_article));
+        assert!(!DeletionRequest {
+            addresses: vec![other_addr],
+            ..request.clone()
+        }
+        .applies_to(&article));
+
+        // Someone else cannot delete our events
+        let mallory = PrivateKey::generate();
+        let mut forged = deletion.clone();
+        forged.pubkey = mallory.public_key();
+        let request = DeletionRequest::from_event(&forged).unwrap();
+        assert!(!request.applies_to(&note));
+
+        assert_eq!(note.deletes(), None);
+    }
+}
@@ -0,0 +1,201 @@
+use super::{Event, Tag};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+
+// Beyond this many cells the line diff is not worth the memory, and the
+// differing middle is shown as removed and then added
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// A line of content in an `EventDiff`
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub enum DiffLine {
+    /// In both versions
+    Unchanged(String),
+
+    /// Only in the newer version
+    Added(String),
+
+    /// Only in the older version
+    Removed(String),
+}
+
+/// What changed between two versions of a replaceable event
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct EventDiff {
+    /// The content, line by line, with lines added and removed marked. This
+    /// is empty if the content did not change.
+    pub content: Vec<DiffLine>,
+
+    /// Tags only in the newer version, in its order. A tag that is in the
+    /// newer version more times than in the older one is in here for each
+    /// extra time.
+    pub added_tags: Vec<Tag>,
+
+    /// Tags only in the older version, in its order
+    pub removed_tags: Vec<Tag>,
+}
+
+impl EventDiff {
+    /// Did the content change?
+    pub fn content_changed(&self) -> bool {
+        !self.content.is_empty()
+    }
+
+    /// Is there no difference at all?
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty() && self.added_tags.is_empty() && self.removed_tags.is_empty()
+    }
+}
+
+// Tags of `a` that are not matched by one in `b`
+fn unmatched_tags(a: &[Tag], b: &[Tag]) -> Vec<Tag> {
+    let mut matched = vec![false; b.len()];
+    let mut unmatched: Vec<Tag> = Vec::new();
+    for tag in a {
+        match (0..b.len()).find(|&i| !matched[i] && b[i] == *tag) {
+            Some(i) => matched[i] = true,
+            None => unmatched.push(tag.clone()),
+        }
+    }
+    unmatched
+}
+
+// A line diff from the longest common subsequence of lines
+fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let unchanged = |s: &&str| DiffLine::Unchanged((*s).to_owned());
+    let mut lines: Vec<DiffLine> = old[..prefix].iter().map(unchanged).collect();
+
+    let (n, m) = (old_middle.len(), new_middle.len());
+    if n.saturating_mul(m) > MAX_DIFF_CELLS {
+        lines.extend(
+            old_middle
+                .iter()
+                .map(|s| DiffLine::Removed((*s).to_owned())),
+        );
+        lines.extend(new_middle.iter().map(|s| DiffLine::Added((*s).to_owned())));
+    } else {
+        // lcs[i][j] is the length of the longest common subsequence of
+        // old_middle[i..] and new_middle[j..]
+        let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if old_middle[i] == new_middle[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && old_middle[i] == new_middle[j] {
+                lines.push(DiffLine::Unchanged(old_middle[i].to_owned()));
+                i += 1;
+                j += 1;
+            } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+                lines.push(DiffLine::Removed(old_middle[i].to_owned()));
+                i += 1;
+            } else {
+                lines.push(DiffLine::Added(new_middle[j].to_owned()));
+                j += 1;
+            }
+        }
+    }
+
+    lines.extend(old[old.len() - suffix..].iter().map(unchanged));
+    lines
+}
+
+impl Event {
+    /// What changed from this version of a replaceable event to `newer`,
+    /// such as two revisions of a long-form article or wiki page. This is
+    /// meant for two versions at the same address (see `matches_address()`),
+    /// but compares whatever it is given.
+    ///
+    /// Tags are compared as a whole, so a tag with one field changed is both
+    /// removed and added. The content is compared line by line.
+    pub fn semantic_diff(&self, newer: &Event) -> EventDiff {
+        let content = if self.content == newer.content {
+            vec![]
+        } else {
+            diff_lines(&self.content, &newer.content)
+        };
+        EventDiff {
+            content,
+            added_tags: unmatched_tags(&newer.tags, &self.tags),
+            removed_tags: unmatched_tags(&self.tags, &newer.tags),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::EventKind;
+
+    #[test]
+    fn test_semantic_diff() {
+        let mut older = Event::mock();
+        older.kind = EventKind::LongFormContent;
+        older.content = "# Title\nFirst\nSecond\nThird".to_owned();
+        older.tags = vec![
+            Tag::new_identifier("post".to_owned()),
+            Tag::new_hashtag("rust".to_owned()),
+            Tag::new(&["title", "Old"]),
+        ];
+        assert!(older.semantic_diff(&older).is_empty());
+
+        let mut newer = older.clone();
+        newer.content = "# Title\nFirst\nSecond, edited\nThird\nFourth".to_owned();
+        newer.tags = vec![
+            Tag::new_identifier("post".to_owned()),
+            Tag::new(&["title", "New"]),
+            Tag::new_hashtag("rust".to_owned()),
+            Tag::new_hashtag("rust".to_owned()),
+        ];
+
+        let diff = older.semantic_diff(&newer);
+        assert!(diff.content_changed());
+        let line = |s: &str| s.to_owned();
+        assert_eq!(
+            diff.content,
+            vec![
+                DiffLine::Unchanged(line("# Title")),
+                DiffLine::Unchanged(line("First")),
+                DiffLine::Removed(line("Second")),
+                DiffLine::Added(line("Second, edited")),
+                DiffLine::Unchanged(line("Third")),
+                DiffLine::Added(line("Fourth")),
+            ]
+        );
+        assert_eq!(
+            diff.added_tags,
+            vec![
+                Tag::new(&["title", "New"]),
+                Tag::new_hashtag("rust".to_owned())
+            ]
+        );
+        assert_eq!(diff.removed_tags, vec![Tag::new(&["title", "Old"])]);
+
+        // Reordering tags is not a change
+        newer.content = older.content.clone();
+        newer.tags = older.tags.iter().rev().cloned().collect();
+        assert!(older.semantic_diff(&newer).is_empty());
+    }
+}
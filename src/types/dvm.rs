@@ -0,0 +1,754 @@
+#[cfg(feature = "std")]
+use super::event::nip04_content;
+use super::event::nip04_plaintext;
+use super::{Event, EventKind, Id, PrivateKey, PublicKey, Tag, UncheckedUrl};
+#[cfg(feature = "std")]
+use super::{PreEvent, Unixtime};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+
+/// What the data of a job input is (NIP-90)
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+#[serde(rename_all = "lowercase")]
+pub enum JobInputType {
+    /// A URL to fetch the input from
+    Url,
+
+    /// The id of an event to use as input
+    Event,
+
+    /// The id of an earlier job request, whose result is the input
+    Job,
+
+    /// The input itself, as text
+    Text,
+}
+
+impl JobInputType {
+    /// The string used on the wire for this input type
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            JobInputType::Url => "url",
+            JobInputType::Event => "event",
+            JobInputType::Job => "job",
+            JobInputType::Text => "text",
+        }
+    }
+
+    /// Parse an input type from the wire, or None if it is not recognized
+    pub fn parse(s: &str) -> Option<JobInputType> {
+        match s {
+            "url" => Some(JobInputType::Url),
+            "event" => Some(JobInputType::Event),
+            "job" => Some(JobInputType::Job),
+            "text" => Some(JobInputType::Text),
+            _ => None,
+        }
+    }
+}
+
+/// An input to a job, from an 'i' tag (NIP-90)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct JobInput {
+    /// The input, or where to find it, depending on `input_type`
+    pub data: String,
+
+    /// What `data` is
+    pub input_type: JobInputType,
+
+    /// A relay where the event or job can be found
+    pub relay: Option<UncheckedUrl>,
+
+    /// How the service should use this input, which is up to the job kind
+    pub marker: Option<String>,
+}
+
+impl JobInput {
+    /// Create an input of the given type with no relay or marker
+    pub fn new(data: String, input_type: JobInputType) -> JobInput {
+        JobInput {
+            data,
+            input_type,
+            relay: None,
+            marker: None,
+        }
+    }
+
+    /// Read an 'i' tag
+    pub fn from_tag(tag: &Tag) -> Option<JobInput> {
+        if tag.tagname() != "i" {
+            return None;
+        }
+        let optional = |i: usize| Some(tag.get_index(i)).filter(|s| !s.is_empty());
+        Some(JobInput {
+            data: tag.value().to_owned(),
+            input_type: JobInputType::parse(tag.get_index(2))?,
+            relay: optional(3).map(UncheckedUrl::from_str),
+            marker: optional(4).map(|s| s.to_owned()),
+        })
+    }
+
+    /// Write an 'i' tag
+    pub fn to_tag(&self) -> Tag {
+        let mut tag = Tag::new(&["i", &self.data, self.input_type.as_str()]);
+        if self.relay.is_some() || self.marker.is_some() {
+            tag.push_value(
+                self.relay
+                    .as_ref()
+                    .map(|r| r.as_str().to_owned())
+                    .unwrap_or_default(),
+            );
+        }
+        if let Some(marker) = &self.marker {
+            tag.push_value(marker.clone());
+        }
+        tag
+    }
+}
+
+/// An amount the service asks to be paid, from an 'amount' tag (NIP-90)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct JobAmount {
+    /// The amount in millisatoshis
+    pub msats: u64,
+
+    /// A lightning invoice to pay it with
+    pub bolt11: Option<String>,
+}
+
+impl JobAmount {
+    fn from_tag(tag: &Tag) -> Option<JobAmount> {
+        if tag.tagname() != "amount" {
+            return None;
+        }
+        Some(JobAmount {
+            msats: tag.value().parse().ok()?,
+            bolt11: Some(tag.get_index(2))
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_owned()),
+        })
+    }
+
+    #[cfg(feature = "std")]
+    fn to_tag(&self) -> Tag {
+        let mut tag = Tag::new(&["amount", &self.msats.to_string()]);
+        if let Some(bolt11) = &self.bolt11 {
+            tag.push_value(bolt11.clone());
+        }
+        tag
+    }
+}
+
+/// Where a job is up to, from the 'status' tag of job feedback (NIP-90)
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+#[serde(rename_all = "kebab-case")]
+pub enum JobStatus {
+    /// The service wants to be paid before it goes on
+    PaymentRequired,
+
+    /// The service is working on the job
+    Processing,
+
+    /// The service could not do the job
+    Error,
+
+    /// The service finished the job
+    Success,
+
+    /// The service has part of the result, which is in the content
+    Partial,
+}
+
+impl JobStatus {
+    /// The string used on the wire for this status
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            JobStatus::PaymentRequired => "payment-required",
+            JobStatus::Processing => "processing",
+            JobStatus::Error => "error",
+            JobStatus::Success => "success",
+            JobStatus::Partial => "partial",
+        }
+    }
+
+    /// Parse a status from the wire, or None if it is not recognized
+    pub fn parse(s: &str) -> Option<JobStatus> {
+        match s {
+            "payment-required" => Some(JobStatus::PaymentRequired),
+            "processing" => Some(JobStatus::Processing),
+            "error" => Some(JobStatus::Error),
+            "success" => Some(JobStatus::Success),
+            "partial" => Some(JobStatus::Partial),
+            _ => None,
+        }
+    }
+}
+
+/// A request for a Data Vending Machine to do a job (NIP-90, kinds 5000-5999)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct JobRequest {
+    /// The kind of job, which is the kind of the request event
+    pub kind: EventKind,
+
+    /// The inputs to the job
+    pub inputs: Vec<JobInput>,
+
+    /// The MIME type the result should be in
+    pub output: Option<String>,
+
+    /// Job parameters, as key and value
+    pub params: Vec<(String, String)>,
+
+    /// The most the customer will pay, in millisatoshis
+    pub bid: Option<u64>,
+
+    /// Relays the service should publish its result and feedback to
+    pub relays: Vec<UncheckedUrl>,
+
+    /// The services the customer wants to do the job. Empty means any.
+    pub providers: Vec<PublicKey>,
+
+    /// Whether the inputs and parameters are encrypted in the content. Until
+    /// they are decrypted with `Event::decrypt_job_request()`, they are empty.
+    pub encrypted: bool,
+}
+
+impl JobRequest {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> JobRequest {
+        JobRequest {
+            kind: EventKind::JobRequest(5002),
+            inputs: vec![JobInput::new("Bonjour".to_owned(), JobInputType::Text)],
+            output: Some("text/plain".to_owned()),
+            params: vec![("language".to_owned(), "en".to_owned())],
+            bid: Some(5000),
+            relays: vec![UncheckedUrl::from_str("wss://relay.example.com")],
+            providers: vec![],
+            encrypted: false,
+        }
+    }
+
+    /// Create a request of the given job kind, with nothing else set
+    pub fn new(kind: EventKind) -> Result<JobRequest, Error> {
+        if !kind.is_job_request() {
+            return Err(Error::WrongEventKind);
+        }
+        Ok(JobRequest::from_tags(kind, &[]))
+    }
+
+    /// The value of a parameter, if given
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Read the tags of a job request of the given kind
+    pub fn from_tags(kind: EventKind, tags: &[Tag]) -> JobRequest {
+        let mut request = JobRequest {
+            kind,
+            inputs: vec![],
+            output: None,
+            params: vec![],
+            bid: None,
+            relays: vec![],
+            providers: vec![],
+            encrypted: false,
+        };
+        request.read_secret_tags(tags);
+        for tag in tags {
+            match tag.tagname() {
+                "output" if !tag.value().is_empty() => {
+                    request.output = Some(tag.value().to_owned())
+                }
+                "bid" => request.bid = tag.value().parse().ok(),
+                "relays" => request.relays.extend(
+                    tag.fields()
+                        .iter()
+                        .skip(1)
+                        .map(|r| UncheckedUrl::from_str(r)),
+                ),
+                "p" => {
+                    if let Ok((pubkey, _, _)) = tag.parse_pubkey_strict() {
+                        request.providers.push(pubkey);
+                    }
+                }
+                "encrypted" => request.encrypted = true,
+                _ => {}
+            }
+        }
+        request
+    }
+
+    // Read the 'i' and 'param' tags, which are the ones that get encrypted
+    fn read_secret_tags(&mut self, tags: &[Tag]) {
+        for tag in tags {
+            if let Some(input) = JobInput::from_tag(tag) {
+                self.inputs.push(input);
+            } else if tag.tagname() == "param" && tag.fields().len() >= 3 {
+                self.params
+                    .push((tag.value().to_owned(), tag.get_index(2).to_owned()));
+            }
+        }
+    }
+
+    // The 'i' and 'param' tags
+    fn secret_tags(&self) -> Vec<Tag> {
+        let mut tags: Vec<Tag> = self.inputs.iter().map(|i| i.to_tag()).collect();
+        tags.extend(self.params.iter().map(|(k, v)| Tag::new(&["param", k, v])));
+        tags
+    }
+
+    // The tags that stay readable when the request is encrypted
+    fn public_tags(&self) -> Vec<Tag> {
+        let mut tags: Vec<Tag> = Vec::new();
+        if let Some(output) = &self.output {
+            tags.push(Tag::new(&["output", output]));
+        }
+        if let Some(bid) = self.bid {
+            tags.push(Tag::new(&["bid", &bid.to_string()]));
+        }
+        if !self.relays.is_empty() {
+            let mut relays: Vec<&str> = vec!["relays"];
+            relays.extend(self.relays.iter().map(|r| r.as_str()));
+            tags.push(Tag::new(&relays));
+        }
+        tags
+    }
+
+    /// Write the tags of this request, unencrypted
+    pub fn to_tags(&self) -> Vec<Tag> {
+        let mut tags = self.secret_tags();
+        tags.extend(self.public_tags());
+        for provider in &self.providers {
+            tags.push(Tag::new_pubkey((*provider).into(), None, None));
+        }
+        tags
+    }
+}
+
+/// The result of a job, published by the Data Vending Machine that did it
+/// (NIP-90, kinds 6000-6999)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct JobResult {
+    /// The id of the job request
+    pub request_id: Id,
+
+    /// The customer who asked for the job
+    pub customer: PublicKey,
+
+    /// The job request event as JSON, if included
+    pub request: Option<String>,
+
+    /// The inputs of the job, copied from an unencrypted request
+    pub inputs: Vec<JobInput>,
+
+    /// What the service asks to be paid
+    pub amount: Option<JobAmount>,
+
+    /// The result itself. If `encrypted`, this is ciphertext until
+    /// decrypted with `Event::decrypt_job_result()`.
+    pub payload: String,
+
+    /// Whether the payload is encrypted to the customer
+    pub encrypted: bool,
+}
+
+impl JobResult {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> JobResult {
+        JobResult {
+            request_id: Id::mock(),
+            customer: PublicKey::mock_deterministic(),
+            request: None,
+            inputs: vec![JobInput::new("Bonjour".to_owned(), JobInputType::Text)],
+            amount: Some(JobAmount {
+                msats: 5000,
+                bolt11: None,
+            }),
+            payload: "Hello".to_owned(),
+            encrypted: false,
+        }
+    }
+}
+
+/// Feedback on a job from a Data Vending Machine, such as that it wants to
+/// be paid or is working on it (NIP-90, kind 7000)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct JobFeedback {
+    /// The id of the job request
+    pub request_id: Id,
+
+    /// The customer who asked for the job
+    pub customer: PublicKey,
+
+    /// Where the job is up to
+    pub status: JobStatus,
+
+    /// More about the status, for people to read
+    pub extra_info: Option<String>,
+
+    /// What the service asks to be paid
+    pub amount: Option<JobAmount>,
+
+    /// A partial result, if any
+    pub payload: String,
+}
+
+impl JobFeedback {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> JobFeedback {
+        JobFeedback {
+            request_id: Id::mock(),
+            customer: PublicKey::mock_deterministic(),
+            status: JobStatus::PaymentRequired,
+            extra_info: None,
+            amount: Some(JobAmount {
+                msats: 5000,
+                bolt11: Some("lnbc50n1...".to_owned()),
+            }),
+            payload: "".to_owned(),
+        }
+    }
+}
+
+// The job request and customer a result or feedback event refers to
+fn job_reference(tags: &[Tag]) -> Option<(Id, PublicKey)> {
+    let request_id = tags.iter().find_map(|t| t.parse_event().ok())?.0;
+    let customer = tags.iter().find_map(|t| t.parse_pubkey_strict().ok())?.0;
+    Some((request_id, customer))
+}
+
+fn is_encrypted(tags: &[Tag]) -> bool {
+    tags.iter().any(|t| t.tagname() == "encrypted")
+}
+
+impl Event {
+    /// If this is a NIP-90 job request, get what it asks for. If the inputs
+    /// and parameters are encrypted, they are left empty; see
+    /// `decrypt_job_request()`.
+    pub fn job_request(&self) -> Option<JobRequest> {
+        if !self.kind.is_job_request() {
+            return None;
+        }
+        Some(JobRequest::from_tags(self.kind, &self.tags))
+    }
+
+    /// If this is a NIP-90 job request, get what it asks for, decrypting the
+    /// inputs and parameters with the service's key if they are encrypted
+    pub fn decrypt_job_request(&self, provider_key: &PrivateKey) -> Result<JobRequest, Error> {
+        let mut request = self.job_request().ok_or(Error::WrongEventKind)?;
+        if request.encrypted {
+            let json = nip04_plaintext(provider_key, &self.pubkey, &self.content)?;
+            let tags: Vec<Tag> = serde_json::from_slice(&json)?;
+            request.read_secret_tags(&tags);
+        }
+        Ok(request)
+    }
+
+    /// If this is a NIP-90 job result, get it. An encrypted payload is left
+    /// as it is; see `decrypt_job_result()`.
+    pub fn job_result(&self) -> Option<JobResult> {
+        if !self.kind.is_job_result() {
+            return None;
+        }
+        let (request_id, customer) = job_reference(&self.tags)?;
+        Some(JobResult {
+            request_id,
+            customer,
+            request: self
+                .tags
+                .iter()
+                .find(|t| t.tagname() == "request")
+                .map(|t| t.value().to_owned()),
+            inputs: self.tags.iter().filter_map(JobInput::from_tag).collect(),
+            amount: self.tags.iter().find_map(JobAmount::from_tag),
+            payload: self.content.clone(),
+            encrypted: is_encrypted(&self.tags),
+        })
+    }
+
+    /// If this is a NIP-90 job result, get it, decrypting the payload with
+    /// the customer's key if it is encrypted
+    pub fn decrypt_job_result(&self, customer_key: &PrivateKey) -> Result<JobResult, Error> {
+        let mut result = self.job_result().ok_or(Error::WrongEventKind)?;
+        if result.encrypted {
+            let plaintext = nip04_plaintext(customer_key, &self.pubkey, &self.content)?;
+            result.payload = String::from_utf8(plaintext)
+                .map_err(|e| Error::InvalidEvent(format!("Job result is not UTF-8: {e}")))?;
+        }
+        Ok(result)
+    }
+
+    /// If this is NIP-90 job feedback, get it
+    pub fn job_feedback(&self) -> Option<JobFeedback> {
+        if self.kind != EventKind::JobFeedback {
+            return None;
+        }
+        let (request_id, customer) = job_reference(&self.tags)?;
+        let status_tag = self.tags.iter().find(|t| t.tagname() == "status")?;
+        Some(JobFeedback {
+            request_id,
+            customer,
+            status: JobStatus::parse(status_tag.value())?,
+            extra_info: Some(status_tag.get_index(2))
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_owned()),
+            amount: self.tags.iter().find_map(JobAmount::from_tag),
+            payload: self.content.clone(),
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl PreEvent {
+    /// Create a NIP-90 job request PreEvent, with everything in the clear
+    pub fn new_job_request(pubkey: PublicKey, request: &JobRequest) -> Result<PreEvent, Error> {
+        if !request.kind.is_job_request() {
+            return Err(Error::WrongEventKind);
+        }
+        Ok(PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: request.kind,
+            tags: request.to_tags(),
+            content: "".to_owned(),
+            ots: None,
+        })
+    }
+
+    /// Create a NIP-90 job request PreEvent for one service, encrypting the
+    /// inputs and parameters to it from the customer's key. The request's
+    /// `providers` are replaced by `provider`.
+    pub fn new_encrypted_job_request(
+        customer_key: &PrivateKey,
+        provider: PublicKey,
+        request: &JobRequest,
+    ) -> Result<PreEvent, Error> {
+        if !request.kind.is_job_request() {
+            return Err(Error::WrongEventKind);
+        }
+        let json = serde_json::to_string(&request.secret_tags())?;
+        let mut tags = request.public_tags();
+        tags.push(Tag::new_pubkey(provider.into(), None, None));
+        tags.push(Tag::new(&["encrypted"]));
+        Ok(PreEvent {
+            pubkey: customer_key.public_key(),
+            created_at: Unixtime::now(),
+            kind: request.kind,
+            tags,
+            content: nip04_content(customer_key, &provider, json.as_bytes())?,
+            ots: None,
+        })
+    }
+
+    // The tags every result of `request` has
+    fn job_result_tags(request: &Event) -> Result<Vec<Tag>, Error> {
+        Ok(vec![
+            Tag::new(&["request", &serde_json::to_string(request)?]),
+            Tag::new_event(request.id, None, None),
+            Tag::new_pubkey(request.pubkey.into(), None, None),
+        ])
+    }
+
+    /// Create a NIP-90 job result PreEvent answering the `request` event,
+    /// with the payload in the clear
+    pub fn new_job_result(
+        pubkey: PublicKey,
+        request: &Event,
+        payload: String,
+        amount: Option<JobAmount>,
+    ) -> Result<PreEvent, Error> {
+        let kind = request
+            .kind
+            .job_result_kind()
+            .ok_or(Error::WrongEventKind)?;
+        let mut tags = PreEvent::job_result_tags(request)?;
+        tags.extend(
+            request
+                .tags
+                .iter()
+                .filter_map(JobInput::from_tag)
+                .map(|i| i.to_tag()),
+        );
+        tags.extend(amount.map(|a| a.to_tag()));
+        Ok(PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind,
+            tags,
+            content: payload,
+            ots: None,
+        })
+    }
+
+    /// Create a NIP-90 job result PreEvent answering the `request` event,
+    /// encrypting the payload to the customer from the service's key. The
+    /// inputs are not copied, as the request may have kept them secret.
+    pub fn new_encrypted_job_result(
+        provider_key: &PrivateKey,
+        request: &Event,
+        payload: &str,
+        amount: Option<JobAmount>,
+    ) -> Result<PreEvent, Error> {
+        let kind = request
+            .kind
+            .job_result_kind()
+            .ok_or(Error::WrongEventKind)?;
+        let mut tags = PreEvent::job_result_tags(request)?;
+        tags.extend(amount.map(|a| a.to_tag()));
+        tags.push(Tag::new(&["encrypted"]));
+        Ok(PreEvent {
+            pubkey: provider_key.public_key(),
+            created_at: Unixtime::now(),
+            kind,
+            tags,
+            content: nip04_content(provider_key, &request.pubkey, payload.as_bytes())?,
+            ots: None,
+        })
+    }
+
+    /// Create a NIP-90 job feedback PreEvent about the `request` event
+    pub fn new_job_feedback(
+        pubkey: PublicKey,
+        request: &Event,
+        status: JobStatus,
+        extra_info: Option<String>,
+        amount: Option<JobAmount>,
+    ) -> Result<PreEvent, Error> {
+        if !request.kind.is_job_request() {
+            return Err(Error::WrongEventKind);
+        }
+        let mut status_tag = Tag::new(&["status", status.as_str()]);
+        if let Some(extra_info) = extra_info {
+            status_tag.push_value(extra_info);
+        }
+        let mut tags = vec![status_tag];
+        tags.extend(amount.map(|a| a.to_tag()));
+        tags.push(Tag::new_event(request.id, None, None));
+        tags.push(Tag::new_pubkey(request.pubkey.into(), None, None));
+        Ok(PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::JobFeedback,
+            tags,
+            content: "".to_owned(),
+            ots: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {JobRequest, test_job_request_serde}
+    test_serde! {JobResult, test_job_result_serde}
+    test_serde! {JobFeedback, test_job_feedback_serde}
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_job_round_trip() {
+        let customer_key = PrivateKey::generate();
+        let provider_key = PrivateKey::generate();
+        let mut job = JobRequest::mock();
+        job.inputs[0].marker = Some("source".to_owned());
+        job.providers = vec![provider_key.public_key()];
+        assert!(JobRequest::new(EventKind::TextNote).is_err());
+
+        let pre = PreEvent::new_job_request(customer_key.public_key(), &job).unwrap();
+        let request = Event::new(pre, &customer_key).unwrap();
+        assert_eq!(request.job_request().unwrap(), job);
+        assert_eq!(request.job_request().unwrap().param("language"), Some("en"));
+
+        let pre = PreEvent::new_job_result(
+            provider_key.public_key(),
+            &request,
+            "Hello".to_owned(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(pre.kind, EventKind::JobResult(6002));
+        let result = Event::new(pre, &provider_key)
+            .unwrap()
+            .job_result()
+            .unwrap();
+        assert_eq!(result.request_id, request.id);
+        assert_eq!(result.customer, customer_key.public_key());
+        assert_eq!(result.inputs, job.inputs);
+        assert_eq!(result.payload, "Hello");
+        let echoed: Event = serde_json::from_str(result.request.as_ref().unwrap()).unwrap();
+        assert_eq!(echoed, request);
+
+        let amount = JobAmount {
+            msats: 1000,
+            bolt11: Some("lnbc10n1...".to_owned()),
+        };
+        let pre = PreEvent::new_job_feedback(
+            provider_key.public_key(),
+            &request,
+            JobStatus::PaymentRequired,
+            Some("Pay first".to_owned()),
+            Some(amount.clone()),
+        )
+        .unwrap();
+        let feedback = Event::new(pre, &provider_key)
+            .unwrap()
+            .job_feedback()
+            .unwrap();
+        assert_eq!(feedback.status, JobStatus::PaymentRequired);
+        assert_eq!(feedback.extra_info.as_deref(), Some("Pay first"));
+        assert_eq!(feedback.amount, Some(amount));
+        assert_eq!(feedback.request_id, request.id);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_encrypted_job() {
+        let customer_key = PrivateKey::generate();
+        let provider_key = PrivateKey::generate();
+        let job = JobRequest::mock();
+
+        let pre =
+            PreEvent::new_encrypted_job_request(&customer_key, provider_key.public_key(), &job)
+                .unwrap();
+        assert!(!pre.content.contains("Bonjour"));
+        let request = Event::new(pre, &customer_key).unwrap();
+
+        let sealed = request.job_request().unwrap();
+        assert!(sealed.encrypted);
+        assert!(sealed.inputs.is_empty());
+        assert_eq!(sealed.bid, job.bid);
+        assert_eq!(sealed.providers, vec![provider_key.public_key()]);
+
+        let opened = request.decrypt_job_request(&provider_key).unwrap();
+        assert_eq!(opened.inputs, job.inputs);
+        assert_eq!(opened.params, job.params);
+        assert!(request
+            .decrypt_job_request(&PrivateKey::generate())
+            .is_err());
+
+        let pre =
+            PreEvent::new_encrypted_job_result(&provider_key, &request, "Hello", None).unwrap();
+        let result = Event::new(pre, &provider_key).unwrap();
+        assert!(result.job_result().unwrap().encrypted);
+        assert!(result.job_result().unwrap().inputs.is_empty());
+        assert_eq!(
+            result.decrypt_job_result(&customer_key).unwrap().payload,
+            "Hello"
+        );
+    }
+}
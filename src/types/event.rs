@@ -1,23 +1,39 @@
 use super::{
-    EventDelegation, EventKind, Id, Metadata, MilliSatoshi, PrivateKey, PublicKey, PublicKeyHex,
-    RelayUrl, Signature, Tag, Unixtime,
+    CalendarEvent, CalendarRsvp, ClassifiedListing, DeletionRequest, EventAddr, EventDelegation,
+    EventKind, ExternalIdentity, Highlight, Id, LiveEvent, Metadata, MilliSatoshi, OtsAttestation,
+    ParseLimits, PrivateKey, PublicKey, PublicKeyHex, RelayHint, RelayHintSource, RelayUrl, Report,
+    ReportTarget, ReportType, Rumor, Signature, Tag, Torrent, UncheckedUrl, Unixtime, WalletInfo,
+    WalletRequest, WalletResponse, WikiArticle,
 };
+#[cfg(feature = "std")]
+use super::{Difficulty, PowProgress};
 use crate::Error;
 use base64::Engine;
 use k256::sha2::{Digest, Sha256};
 use lightning_invoice::Invoice;
+use serde::de::Deserializer;
+use serde::ser::{SerializeMap, Serializer};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 #[cfg(feature = "speedy")]
 use speedy::{Readable, Writable};
+use std::collections::HashMap;
 use std::str::FromStr;
+#[cfg(feature = "std")]
 use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+#[cfg(feature = "std")]
 use std::sync::mpsc::Sender;
+#[cfg(feature = "std")]
 use std::sync::Arc;
+#[cfg(feature = "std")]
 use std::thread;
+#[cfg(feature = "std")]
 use std::thread::JoinHandle;
+#[cfg(feature = "std")]
+use std::time::Instant;
 
 /// The main event type
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "speedy", derive(Readable, Writable))]
 pub struct Event {
     /// The Id of the event, generated as a SHA256 of the inner event data
@@ -39,8 +55,6 @@ pub struct Event {
     pub content: String,
 
     /// An optional verified time for the event (using OpenTimestamp)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(default)]
     pub ots: Option<String>,
 
     /// The signature of the event, which cryptographically verifies that the holder of
@@ -48,20 +62,94 @@ pub struct Event {
     /// The signature is taken over the id field only, but the id field is taken over
     /// the rest of the event data.
     pub sig: Signature,
+
+    /// Any other fields the event arrived with, such as hints some relays
+    /// attach. These are not covered by the id or signature.
+    #[cfg_attr(feature = "speedy", speedy(skip))]
+    pub extra: Map<String, Value>,
 }
 
-macro_rules! serialize_inner_event {
-    ($pubkey:expr, $created_at:expr, $kind:expr, $tags:expr,
-     $content:expr) => {{
-        format!(
-            "[0,{},{},{},{},{}]",
-            serde_json::to_string($pubkey)?,
-            serde_json::to_string($created_at)?,
-            serde_json::to_string($kind)?,
-            serde_json::to_string($tags)?,
-            serde_json::to_string($content)?
-        )
-    }};
+// The NIP-01 serialization of an event, whose hash is its Id
+fn serialize_inner_event(
+    pubkey: &PublicKey,
+    created_at: Unixtime,
+    kind: EventKind,
+    tags: &[Tag],
+    content: &str,
+) -> String {
+    let mut output = format!(
+        "[0,\"{}\",{},{},[",
+        pubkey.as_hex_string(),
+        created_at.0,
+        u32::from(kind)
+    );
+    for (i, tag) in tags.iter().enumerate() {
+        if i > 0 {
+            output.push(',');
+        }
+        push_tag(&mut output, tag, push_nip01_string);
+    }
+    output.push_str("],");
+    push_nip01_string(&mut output, content);
+    output.push(']');
+    output
+}
+
+// Append a tag as a JSON array of strings, each written by `push_string`
+fn push_tag(output: &mut String, tag: &Tag, push_string: fn(&mut String, &str)) {
+    output.push('[');
+    for (i, field) in tag.fields().iter().enumerate() {
+        if i > 0 {
+            output.push(',');
+        }
+        push_string(output, field);
+    }
+    output.push(']');
+}
+
+// Append a string quoted and escaped as NIP-01 requires for the Id. Only `"`,
+// `\`, line feed, carriage return, tab, backspace and form feed are escaped;
+// every other character, including other control characters, is included
+// verbatim. serde_json would write those others as \u00XX, which gives a
+// different Id than other implementations compute.
+fn push_nip01_string(output: &mut String, s: &str) {
+    output.push('"');
+    for c in s.chars() {
+        match nip01_escape(c) {
+            Some(escaped) => output.push_str(escaped),
+            None => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+// Append a string escaped as for the Id, except that the control characters
+// NIP-01 leaves verbatim are written as \u00XX, as JSON requires. Strings
+// without those come out exactly as in the Id preimage.
+fn push_canonical_string(output: &mut String, s: &str) {
+    output.push('"');
+    for c in s.chars() {
+        match nip01_escape(c) {
+            Some(escaped) => output.push_str(escaped),
+            None if c < ' ' => output.push_str(&format!("\\u{:04x}", u32::from(c))),
+            None => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+// The escape NIP-01 uses for a character, if any
+fn nip01_escape(c: char) -> Option<&'static str> {
+    match c {
+        '"' => Some("\\\""),
+        '\\' => Some("\\\\"),
+        '\n' => Some("\\n"),
+        '\r' => Some("\\r"),
+        '\t' => Some("\\t"),
+        '\u{8}' => Some("\\b"),
+        '\u{c}' => Some("\\f"),
+        _ => None,
+    }
 }
 
 /// Data used to construct an event
@@ -81,223 +169,1050 @@ pub struct PreEvent {
     pub ots: Option<String>,
 }
 
+/// Computes an event Id incrementally, one tag at a time, without building the
+/// whole serialized event in memory. Clone it to reuse a common prefix, for
+/// example when mining proof of work.
+#[derive(Clone, Debug)]
+pub struct IdHasher {
+    hasher: Sha256,
+    tags: usize,
+}
+
+impl IdHasher {
+    /// Start hashing an event with these leading fields
+    pub fn new(pubkey: &PublicKey, created_at: Unixtime, kind: EventKind) -> IdHasher {
+        let mut hasher = Sha256::new();
+        hasher.update(
+            format!(
+                "[0,\"{}\",{},{},[",
+                pubkey.as_hex_string(),
+                created_at.0,
+                u32::from(kind)
+            )
+            .as_bytes(),
+        );
+        IdHasher { hasher, tags: 0 }
+    }
+
+    /// Add the next tag
+    pub fn tag(&mut self, tag: &Tag) {
+        if self.tags > 0 {
+            self.hasher.update(b",");
+        }
+        let mut output = String::new();
+        push_tag(&mut output, tag, push_nip01_string);
+        self.hasher.update(output.as_bytes());
+        self.tags += 1;
+    }
+
+    /// Finish with the content, yielding the Id
+    pub fn finish(mut self, content: &str) -> Id {
+        self.hasher.update(b"],");
+        let mut output = String::with_capacity(content.len() + 2);
+        push_nip01_string(&mut output, content);
+        self.hasher.update(output.as_bytes());
+        self.hasher.update(b"]");
+        Id(self.hasher.finalize().into())
+    }
+}
+
 impl PreEvent {
+    /// The canonical serialization of this event as specified in NIP-01. Its
+    /// SHA-256 hash is the event Id, which is what gets signed.
+    ///
+    /// This is `[0,<pubkey>,<created_at>,<kind>,<tags>,<content>]` as UTF-8
+    /// with no whitespace. In strings, only `"` and `\` and five control
+    /// characters are escaped: line feed, carriage return, tab, backspace and
+    /// form feed, as `\n`, `\r`, `\t`, `\b` and `\f`. Everything else,
+    /// including the other control characters, `/` and non-ASCII characters,
+    /// is written as is. Note that this makes it invalid JSON when there are
+    /// other control characters, unlike the event's JSON on the wire.
+    pub fn serialize_inner(&self) -> String {
+        serialize_inner_event(
+            &self.pubkey,
+            self.created_at,
+            self.kind,
+            &self.tags,
+            &self.content,
+        )
+    }
+
+    /// Exactly the bytes whose SHA-256 hash is the event Id (see
+    /// `serialize_inner`), for a signing device to check and hash itself
+    pub fn signing_payload(&self) -> Vec<u8> {
+        self.serialize_inner().into_bytes()
+    }
+
+    /// Compute the Id this event will have. This is what an external signer
+    /// needs to sign.
+    pub fn hash(&self) -> Id {
+        let mut hasher = Sha256::new();
+        hasher.update(self.serialize_inner().as_bytes());
+        Id(hasher.finalize().into())
+    }
+
+    /// Check the rules for this kind of event that relays and other clients
+    /// rely on, so a malformed event can be fixed before it is signed:
+    ///
+    /// * Metadata (kind 0) content must be a JSON object
+    /// * Contact lists (kind 3) may only have valid 'p' tags, and content
+    ///   that is empty or a JSON object (the old relay list)
+    /// * Reactions (kind 7) must refer to an event with an 'e' tag
+    /// * Zap requests (kind 9734) must have one 'p' tag, a 'relays' tag
+    ///   with at least one relay, and a numeric 'amount' if one is given
+    /// * Parameterized replaceable events (kinds 30000-39999) must have a
+    ///   'd' tag
+    ///
+    /// Other kinds have no rules checked here.
+    pub fn validate(&self) -> Result<(), Error> {
+        let invalid = |reason: &str| Err(Error::InvalidEvent(reason.to_owned()));
+        let json_object = || matches!(serde_json::from_str(&self.content), Ok(Value::Object(_)));
+        let count = |name: &str| self.tags.iter().filter(|t| t.tagname() == name).count();
+
+        match self.kind {
+            EventKind::Metadata if !json_object() => {
+                invalid("metadata content is not a JSON object")
+            }
+            EventKind::ContactList if self.tags.iter().any(|t| t.parse_pubkey().is_err()) => {
+                invalid("contact list has a tag that is not a valid 'p' tag")
+            }
+            EventKind::ContactList if !self.content.is_empty() && !json_object() => {
+                invalid("contact list content is not empty or a JSON object")
+            }
+            EventKind::Reaction if !self.tags.iter().any(|t| t.parse_event().is_ok()) => {
+                invalid("reaction has no 'e' tag")
+            }
+            EventKind::ZapRequest if count("p") != 1 => {
+                invalid("zap request must have exactly one 'p' tag")
+            }
+            EventKind::ZapRequest
+                if !self
+                    .tags
+                    .iter()
+                    .any(|t| t.tagname() == "relays" && !t.value().is_empty()) =>
+            {
+                invalid("zap request has no relays")
+            }
+            EventKind::ZapRequest
+                if self
+                    .tags
+                    .iter()
+                    .any(|t| t.tagname() == "amount" && t.value().parse::<u64>().is_err()) =>
+            {
+                invalid("zap request amount is not a number of millisatoshis")
+            }
+            kind if kind.is_parameterized_replaceable() && count("d") == 0 => {
+                invalid("parameterized replaceable event has no 'd' tag")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Set when this event expires (NIP-40), replacing any earlier expiration
+    pub fn set_expiration(&mut self, time: Unixtime) {
+        self.tags.retain(|t| t.tagname() != "expiration");
+        self.tags.push(Tag::new_expiration(time));
+    }
+
+    /// Set when this long-form article (NIP-23) was first published,
+    /// replacing any earlier 'published_at' tag. Keep this the same across
+    /// edits so the article keeps its place in feeds.
+    pub fn set_published_at(&mut self, time: Unixtime) {
+        self.tags.retain(|t| t.tagname() != "published_at");
+        self.tags.push(Tag::new_published_at(time));
+    }
+
+    /// Mark this event as protected (NIP-70), so relays should only accept
+    /// it from its author
+    pub fn mark_protected(&mut self) {
+        if !self.tags.iter().any(|t| t.is_protected()) {
+            self.tags.push(Tag::new_protected());
+        }
+    }
+
     /// Create a NIP-04 EncryptedDirectMessage PreEvent.
     ///
     /// Note that this creates the 'p' tag, but does not add a recommended_relay_url to it,
     /// so the caller should handle that.
+    #[cfg(feature = "std")]
     pub fn new_nip04(
         private_key: &PrivateKey,
         recipient_public_key: PublicKey,
         message: &str,
     ) -> Result<PreEvent, Error> {
-        let input: &[u8] = message.as_bytes();
-        let (iv, ciphertext) = private_key.nip04_encrypt(&recipient_public_key, input)?;
-        let content = format!(
-            "{}?iv={}",
-            base64::engine::general_purpose::STANDARD.encode(ciphertext),
-            base64::engine::general_purpose::STANDARD.encode(iv)
-        );
+        let content = nip04_content(private_key, &recipient_public_key, message.as_bytes())?;
 
         Ok(PreEvent {
             pubkey: private_key.public_key(),
-            created_at: Unixtime::now().unwrap(),
+            created_at: Unixtime::now(),
             kind: EventKind::EncryptedDirectMessage,
-            tags: vec![Tag::Pubkey {
-                pubkey: recipient_public_key.into(),
-                recommended_relay_url: None, // FIXME,
-                petname: None,
-                trailing: Vec::new(),
-            }],
+            tags: vec![Tag::new_pubkey(
+                recipient_public_key.into(),
+                None, // FIXME
+                None,
+            )],
             content,
             ots: None,
         })
     }
-}
-
-/// Data about a Zap
-#[derive(Clone, Debug, Copy)]
-pub struct ZapData {
-    /// The event that was zapped
-    pub id: Id,
 
-    /// The amount that the event was zapped
-    pub amount: MilliSatoshi,
+    /// Create the same NIP-04 EncryptedDirectMessage for several recipients,
+    /// one PreEvent each, in the order given
+    #[cfg(feature = "std")]
+    pub fn new_nip04_multi(
+        private_key: &PrivateKey,
+        recipients: &[PublicKey],
+        message: &str,
+    ) -> Result<Vec<PreEvent>, Error> {
+        recipients
+            .iter()
+            .map(|recipient| PreEvent::new_nip04(private_key, *recipient, message))
+            .collect()
+    }
 
-    /// The public key of the person who provided the zap
-    pub pubkey: PublicKey,
-}
+    /// Create a NIP-17 DM relay list PreEvent (kind 10050), saying which relays
+    /// to send private direct messages to
+    #[cfg(feature = "std")]
+    pub fn new_dm_relays(pubkey: PublicKey, relays: &[RelayUrl]) -> PreEvent {
+        PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::DmRelayList,
+            tags: relays
+                .iter()
+                .map(|r| Tag::new(&["relay", r.as_str()]))
+                .collect(),
+            content: "".to_owned(),
+            ots: None,
+        }
+    }
 
-impl Event {
-    fn hash(input: &PreEvent) -> Result<Id, Error> {
-        let serialized: String = serialize_inner_event!(
-            &input.pubkey,
-            &input.created_at,
-            &input.kind,
-            &input.tags,
-            &input.content
-        );
+    /// Create a NIP-47 wallet info PreEvent, which a wallet service publishes
+    /// to say what it supports
+    #[cfg(feature = "std")]
+    pub fn new_wallet_info(pubkey: PublicKey, info: &WalletInfo) -> PreEvent {
+        PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::WalletInfo,
+            tags: info.to_tags(),
+            content: info.content(),
+            ots: None,
+        }
+    }
 
-        // Hash
-        let mut hasher = Sha256::new();
-        hasher.update(serialized.as_bytes());
-        let id = hasher.finalize();
-        let id: [u8; 32] = id.into();
-        Ok(Id(id))
+    /// Create a NIP-47 wallet request PreEvent, encrypting `request` from the
+    /// client's connection key to the wallet service
+    #[cfg(feature = "std")]
+    pub fn new_wallet_request(
+        client_key: &PrivateKey,
+        wallet: PublicKey,
+        request: &WalletRequest,
+    ) -> Result<PreEvent, Error> {
+        let json = serde_json::to_string(request)?;
+        Ok(PreEvent {
+            pubkey: client_key.public_key(),
+            created_at: Unixtime::now(),
+            kind: EventKind::WalletRequest,
+            tags: vec![Tag::new_pubkey(wallet.into(), None, None)],
+            content: nip04_content(client_key, &wallet, json.as_bytes())?,
+            ots: None,
+        })
     }
 
-    /// Create a new event
-    pub fn new(input: PreEvent, privkey: &PrivateKey) -> Result<Event, Error> {
-        // Generate Id
-        let id = Self::hash(&input)?;
+    /// Create a NIP-47 wallet response PreEvent answering the `request`
+    /// event, encrypting `response` from the wallet service key to the client
+    #[cfg(feature = "std")]
+    pub fn new_wallet_response(
+        wallet_key: &PrivateKey,
+        request: &Event,
+        response: &WalletResponse,
+    ) -> Result<PreEvent, Error> {
+        if request.kind != EventKind::WalletRequest {
+            return Err(Error::WrongEventKind);
+        }
+        let json = serde_json::to_string(response)?;
+        Ok(PreEvent {
+            pubkey: wallet_key.public_key(),
+            created_at: Unixtime::now(),
+            kind: EventKind::WalletResponse,
+            tags: vec![
+                Tag::new_pubkey(request.pubkey.into(), None, None),
+                Tag::new_event(request.id, None, None),
+            ],
+            content: nip04_content(wallet_key, &request.pubkey, json.as_bytes())?,
+            ots: None,
+        })
+    }
 
-        // Generate Signature
-        let signature = privkey.sign_id(id)?;
+    /// Create a NIP-32 Label PreEvent, applying `labels` within `namespace`
+    /// to whatever the `targets` tags ('e', 'p', 'a', 'r' or 't') refer to.
+    #[cfg(feature = "std")]
+    pub fn new_label(
+        pubkey: PublicKey,
+        namespace: &str,
+        labels: &[&str],
+        targets: Vec<Tag>,
+        content: String,
+    ) -> PreEvent {
+        let mut tags: Vec<Tag> = vec![Tag::new_label_namespace(namespace.to_owned())];
+        for label in labels {
+            tags.push(Tag::new_label(
+                (*label).to_owned(),
+                Some(namespace.to_owned()),
+            ));
+        }
+        tags.extend(targets);
 
-        Ok(Event {
-            id,
-            pubkey: input.pubkey,
-            created_at: input.created_at,
-            kind: input.kind,
-            tags: input.tags,
-            content: input.content,
-            ots: input.ots,
-            sig: signature,
-        })
+        PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::Label,
+            tags,
+            content,
+            ots: None,
+        }
     }
 
-    /// Create a new event with proof of work.
+    /// Create a NIP-56 Reporting PreEvent.
     ///
-    /// This can take a long time, and is only cancellable by killing the thread.
-    pub fn new_with_pow(
-        mut input: PreEvent,
-        privkey: &PrivateKey,
-        zero_bits: u8,
-        work_sender: Option<Sender<u8>>,
-    ) -> Result<Event, Error> {
-        let target = Some(format!("{zero_bits}"));
-
-        // Strip any pre-existing nonce tags
-        input.tags.retain(|t| !matches!(t, Tag::Nonce { .. }));
+    /// The report type is placed in the third field of the 'p' tag (and 'e' tag
+    /// if reporting an event) as the NIP specifies.
+    #[cfg(feature = "std")]
+    pub fn new_report(
+        pubkey: PublicKey,
+        target: ReportTarget,
+        report_type: ReportType,
+        comment: String,
+    ) -> PreEvent {
+        let rt = report_type.as_str();
+        let tags = match target {
+            ReportTarget::Pubkey(pk) => vec![Tag::new(&["p", pk.as_str(), rt])],
+            ReportTarget::Event { id, author } => vec![
+                Tag::new(&["e", &id.as_hex_string(), rt]),
+                Tag::new_pubkey(author, None, None),
+            ],
+        };
 
-        // Add nonce tag to the end
-        input.tags.push(Tag::Nonce {
-            nonce: "0".to_string(),
-            target: target.clone(),
-            trailing: Vec::new(),
-        });
-        let index = input.tags.len() - 1;
+        PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::Reporting,
+            tags,
+            content: comment,
+            ots: None,
+        }
+    }
 
-        let cores = num_cpus::get();
+    /// Create a NIP-22 Comment PreEvent replying to `parent`.
+    ///
+    /// The root scope tags ('A', 'E', 'K', 'P') are copied from the parent if it is
+    /// itself a comment, otherwise they refer to the parent. The parent scope tags
+    /// ('a', 'e', 'k', 'p') always refer to the parent.
+    #[cfg(feature = "std")]
+    pub fn new_comment(pubkey: PublicKey, parent: &Event, content: String) -> PreEvent {
+        let mut tags: Vec<Tag> = Vec::new();
+
+        if parent.kind == EventKind::Comment {
+            for tag in parent.tags.iter() {
+                if matches!(tag.tagname(), "A" | "E" | "I" | "K" | "P") {
+                    tags.push(tag.clone());
+                }
+            }
+        } else {
+            let author: PublicKeyHex = parent.pubkey.into();
+            if let Some(ea) = parent.address() {
+                tags.push(Tag::new_root_address(&ea));
+            }
+            tags.push(Tag::new_root_event(parent.id, None, Some(author.clone())));
+            tags.push(Tag::new_root_kind(parent.kind));
+            tags.push(Tag::new_root_pubkey(author, None));
+        }
 
-        let quitting = Arc::new(AtomicBool::new(false));
-        let nonce = Arc::new(AtomicU64::new(0)); // will store the nonce that works
-        let best_work = Arc::new(AtomicU8::new(0));
+        let author: PublicKeyHex = parent.pubkey.into();
+        if let Some(ea) = parent.address() {
+            tags.push(Tag::new_address(&ea, None));
+        }
+        // NIP-22 puts the parent author where NIP-10 puts the marker
+        tags.push(Tag::new(&[
+            "e",
+            &parent.id.as_hex_string(),
+            "",
+            author.as_str(),
+        ]));
+        tags.push(Tag::new_kind(parent.kind));
+        tags.push(Tag::new_pubkey(author, None, None));
+
+        PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::Comment,
+            tags,
+            content,
+            ots: None,
+        }
+    }
 
-        let mut join_handles: Vec<JoinHandle<_>> = Vec::with_capacity(cores);
+    /// Create a NIP-25 reaction PreEvent to `target`. `content` is "+" to
+    /// like, "-" to dislike, or an emoji. The target's author, its kind and
+    /// (if it is addressable) its address are tagged along with its id.
+    #[cfg(feature = "std")]
+    pub fn new_reaction(
+        pubkey: PublicKey,
+        target: &Event,
+        relay_hint: Option<UncheckedUrl>,
+        content: String,
+    ) -> PreEvent {
+        let mut tags = vec![Tag::new_event(target.id, relay_hint.clone(), None)];
+        if let Some(ea) = target.address() {
+            tags.push(Tag::new_address(&ea, None));
+        }
+        tags.push(Tag::new_pubkey(target.pubkey.into(), relay_hint, None));
+        tags.push(Tag::new_kind(target.kind));
 
-        for core in 0..cores {
-            let mut attempt: u64 = core as u64 * (u64::MAX / cores as u64);
-            let mut input = input.clone();
-            let target = target.clone();
-            let index = index;
-            let quitting = quitting.clone();
-            let nonce = nonce.clone();
-            let zero_bits = zero_bits;
-            let best_work = best_work.clone();
-            let work_sender = work_sender.clone();
-            let join_handle = thread::spawn(move || {
-                loop {
-                    // Lower the thread priority so other threads aren't starved
-                    let _ = thread_priority::set_current_thread_priority(
-                        thread_priority::ThreadPriority::Min,
-                    );
+        PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::Reaction,
+            tags,
+            content,
+            ots: None,
+        }
+    }
 
-                    if quitting.load(Ordering::Relaxed) {
-                        break;
-                    }
+    /// Create a NIP-18 repost PreEvent of `target`, with the target's JSON as
+    /// the content. Text notes are reposted with kind 6, anything else with
+    /// kind 16 and a 'k' tag naming its kind. NIP-18 asks for the relay the
+    /// target can be found on, so give `relay_hint` if at all possible.
+    #[cfg(feature = "std")]
+    pub fn new_repost(
+        pubkey: PublicKey,
+        target: &Event,
+        relay_hint: Option<UncheckedUrl>,
+    ) -> Result<PreEvent, Error> {
+        let kind = if target.kind == EventKind::TextNote {
+            EventKind::Repost
+        } else {
+            EventKind::GenericRepost
+        };
 
-                    input.tags[index] = Tag::Nonce {
-                        nonce: format!("{attempt}"),
-                        target: target.clone(),
-                        trailing: Vec::new(),
-                    };
+        let mut tags = vec![Tag::new_event(target.id, relay_hint.clone(), None)];
+        if let Some(ea) = target.address() {
+            tags.push(Tag::new_address(&ea, None));
+        }
+        tags.push(Tag::new_pubkey(target.pubkey.into(), relay_hint, None));
+        if kind == EventKind::GenericRepost {
+            tags.push(Tag::new_kind(target.kind));
+        }
 
-                    let Id(id) = Self::hash(&input).unwrap();
+        Ok(PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind,
+            tags,
+            content: target.to_canonical_json()?,
+            ots: None,
+        })
+    }
 
-                    let leading_zeroes = get_leading_zero_bits(&id);
-                    if leading_zeroes >= zero_bits {
-                        nonce.store(attempt, Ordering::Relaxed);
-                        quitting.store(true, Ordering::Relaxed);
-                        if let Some(sender) = work_sender.clone() {
-                            sender.send(leading_zeroes).unwrap();
-                        }
-                        break;
-                    } else if leading_zeroes > best_work.load(Ordering::Relaxed) {
-                        best_work.store(leading_zeroes, Ordering::Relaxed);
-                        if let Some(sender) = work_sender.clone() {
-                            sender.send(leading_zeroes).unwrap();
-                        }
-                    }
+    /// Create a NIP-42 auth PreEvent (kind 22242) answering `challenge` from
+    /// the relay at `relay`
+    #[cfg(feature = "std")]
+    pub fn new_auth(pubkey: PublicKey, relay: &RelayUrl, challenge: &str) -> PreEvent {
+        PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::Auth,
+            tags: vec![
+                Tag::new(&["relay", relay.as_str()]),
+                Tag::new(&["challenge", challenge]),
+            ],
+            content: "".to_owned(),
+            ots: None,
+        }
+    }
 
-                    attempt += 1;
+    /// Create a NIP-53 live event PreEvent (kind 30311)
+    #[cfg(feature = "std")]
+    pub fn new_live_event(pubkey: PublicKey, live: &LiveEvent) -> PreEvent {
+        PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::LiveEvent,
+            tags: live.to_tags(),
+            content: "".to_owned(),
+            ots: None,
+        }
+    }
 
-                    // We don't update created_at, which is a bit tricky to synchronize.
-                }
-            });
-            join_handles.push(join_handle);
+    /// Create a NIP-52 calendar event PreEvent (kind 31922 or 31923, depending
+    /// on whether it is date-based or time-based)
+    #[cfg(feature = "std")]
+    pub fn new_calendar_event(pubkey: PublicKey, calendar_event: &CalendarEvent) -> PreEvent {
+        PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: calendar_event.kind(),
+            tags: calendar_event.to_tags(),
+            content: calendar_event.description.clone(),
+            ots: None,
         }
+    }
 
-        for joinhandle in join_handles {
-            let _ = joinhandle.join();
+    /// Create a NIP-52 calendar event RSVP PreEvent (kind 31925)
+    #[cfg(feature = "std")]
+    pub fn new_calendar_rsvp(pubkey: PublicKey, rsvp: &CalendarRsvp) -> PreEvent {
+        PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::CalendarEventRsvp,
+            tags: rsvp.to_tags(),
+            content: rsvp.note.clone(),
+            ots: None,
         }
+    }
 
-        // We found the nonce. Do it for reals
-        input.tags[index] = Tag::Nonce {
-            nonce: format!("{}", nonce.load(Ordering::Relaxed)),
-            target,
-            trailing: Vec::new(),
-        };
-        let id = Self::hash(&input).unwrap();
+    /// Create a NIP-54 wiki article PreEvent (kind 30818). The 'd' tag is
+    /// normalized.
+    #[cfg(feature = "std")]
+    pub fn new_wiki_article(pubkey: PublicKey, article: &WikiArticle) -> PreEvent {
+        PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::WikiArticle,
+            tags: article.to_tags(),
+            content: article.content.clone(),
+            ots: None,
+        }
+    }
 
-        // Signature
-        let signature = privkey.sign_id(id)?;
+    /// Create a NIP-84 highlight PreEvent (kind 9802)
+    #[cfg(feature = "std")]
+    pub fn new_highlight(pubkey: PublicKey, highlight: &Highlight) -> PreEvent {
+        PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::Highlight,
+            tags: highlight.to_tags(),
+            content: highlight.text.clone(),
+            ots: None,
+        }
+    }
 
-        Ok(Event {
-            id,
-            pubkey: input.pubkey,
-            created_at: input.created_at,
-            kind: input.kind,
-            tags: input.tags,
-            content: input.content,
-            ots: input.ots,
-            sig: signature,
+    /// Create a NIP-03 OpenTimestamps attestation PreEvent (kind 1040).
+    /// Fails if the proof is not valid base64.
+    #[cfg(feature = "std")]
+    pub fn new_ots_attestation(
+        pubkey: PublicKey,
+        attestation: &OtsAttestation,
+    ) -> Result<PreEvent, Error> {
+        let _ = attestation.proof_bytes()?;
+        Ok(PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::OpenTimestamps,
+            tags: attestation.to_tags(),
+            content: attestation.proof.clone(),
+            ots: None,
         })
     }
 
+    /// Create a NIP-35 torrent PreEvent (kind 2003). Fails if the infohash is
+    /// not 40 hex characters.
+    #[cfg(feature = "std")]
+    pub fn new_torrent(pubkey: PublicKey, torrent: &Torrent) -> Result<PreEvent, Error> {
+        if !Torrent::is_valid_infohash(&torrent.infohash) {
+            return Err(Error::InvalidInfohash(torrent.infohash.clone()));
+        }
+        Ok(PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::Torrent,
+            tags: torrent.to_tags(),
+            content: torrent.description.clone(),
+            ots: None,
+        })
+    }
+
+    /// Create a NIP-99 classified listing PreEvent (kind 30402, or 30403 if
+    /// `draft`)
+    #[cfg(feature = "std")]
+    pub fn new_classified_listing(
+        pubkey: PublicKey,
+        listing: &ClassifiedListing,
+        draft: bool,
+    ) -> PreEvent {
+        PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: if draft {
+                EventKind::DraftClassifiedListing
+            } else {
+                EventKind::ClassifiedListing
+            },
+            tags: listing.to_tags(),
+            content: listing.description.clone(),
+            ots: None,
+        }
+    }
+
+    /// Create a NIP-53 live chat message PreEvent (kind 1311) for the live event
+    /// at `live_event`
+    #[cfg(feature = "std")]
+    pub fn new_live_chat_message(
+        pubkey: PublicKey,
+        live_event: &EventAddr,
+        content: String,
+    ) -> Result<PreEvent, Error> {
+        if live_event.kind != EventKind::LiveEvent {
+            return Err(Error::WrongEventKind);
+        }
+        Ok(PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::LiveChatMessage,
+            tags: vec![Tag::new_address(live_event, Some("root".to_owned()))],
+            content,
+            ots: None,
+        })
+    }
+}
+
+/// Data about a Zap
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZapData {
+    /// The event that was zapped, from the 'e' tag
+    pub id: Option<Id>,
+
+    /// The amount that the event was zapped
+    pub amount: MilliSatoshi,
+
+    /// The public key of the person who provided the zap
+    pub pubkey: PublicKey,
+
+    /// The public key of the person who sent the zap, from the embedded zap
+    /// request if its signature verifies (or the 'P' tag if there is no
+    /// embedded request)
+    pub sender: Option<PublicKey>,
+
+    /// The sender's comment, from the content of the embedded zap request if
+    /// its signature verifies
+    pub comment: Option<String>,
+
+    /// The address of the zapped event, from the 'a' tag, if it is a
+    /// parameterized replaceable event
+    pub address: Option<EventAddr>,
+
+    /// When the zap receipt was created
+    pub created_at: Unixtime,
+}
+
+// See Event::zap_receipt_parts()
+#[derive(Debug, Default)]
+struct ZapReceiptParts {
+    id: Option<Id>,
+    address: Option<EventAddr>,
+    sender: Option<PublicKey>,
+    comment: Option<String>,
+}
+
+/// A person referred to by an event, from a 'p' tag
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PersonReference {
+    /// The public key of the person
+    pub pubkey: PublicKeyHex,
+
+    /// A recommended relay URL to find information on that person
+    pub relay: Option<RelayUrl>,
+
+    /// The recommended relay as given, kept even if it is not a valid relay
+    /// URL and so `relay` is None
+    pub hint: Option<RelayHint>,
+
+    /// A petname given to the person
+    pub petname: Option<String>,
+}
+
+impl PersonReference {
+    fn from_tag(tag: &Tag) -> Option<PersonReference> {
+        let (pubkey, recommended_relay_url, petname) = tag.parse_pubkey().ok()?;
+        let hint = recommended_relay_url.map(|u| RelayHint::new(u, RelayHintSource::PubkeyTag));
+        Some(PersonReference {
+            pubkey,
+            relay: hint.as_ref().and_then(|h| h.relay_url().ok()),
+            hint,
+            petname,
+        })
+    }
+
+    /// The public key as an actual point on the curve. This fails for keys
+    /// that are 64 hex digits but not a valid x-only public key.
+    pub fn public_key(&self) -> Result<PublicKey, Error> {
+        PublicKey::try_from(&self.pubkey).map_err(|e| e.context("Invalid person pubkey"))
+    }
+}
+
+impl From<PersonReference> for (PublicKeyHex, Option<RelayUrl>, Option<String>) {
+    fn from(pr: PersonReference) -> (PublicKeyHex, Option<RelayUrl>, Option<String>) {
+        (pr.pubkey, pr.relay, pr.petname)
+    }
+}
+
+/// An event referred to by an event, from an 'e' tag
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventReference {
+    /// The Id of the event
+    pub id: Id,
+
+    /// A recommended relay URL to find the event
+    pub relay: Option<RelayUrl>,
+
+    /// The recommended relay as given, kept even if it is not a valid relay
+    /// URL and so `relay` is None
+    pub hint: Option<RelayHint>,
+
+    /// The marker ('root', 'reply', 'mention', ...) if any
+    pub marker: Option<String>,
+
+    /// The author of the event, if given
+    pub author: Option<PublicKeyHex>,
+}
+
+impl EventReference {
+    fn from_tag(tag: &Tag) -> Option<EventReference> {
+        let (id, recommended_relay_url, marker) = tag.parse_event().ok()?;
+        let hint = recommended_relay_url.map(|u| RelayHint::new(u, RelayHintSource::EventTag));
+        Some(EventReference {
+            id,
+            relay: hint.as_ref().and_then(|h| h.relay_url().ok()),
+            hint,
+            marker,
+            author: PublicKeyHex::try_from_str(tag.get_index(4)).ok(),
+        })
+    }
+
+    fn from_quote_tag(tag: &Tag) -> Option<EventReference> {
+        let (id, relay_url, pubkey) = tag.parse_quote().ok()?;
+        let hint = relay_url.map(|u| RelayHint::new(u, RelayHintSource::EventTag));
+        Some(EventReference {
+            id,
+            relay: hint.as_ref().and_then(|h| h.relay_url().ok()),
+            hint,
+            marker: None,
+            author: pubkey,
+        })
+    }
+}
+
+impl From<EventReference> for (Id, Option<RelayUrl>, Option<String>) {
+    fn from(er: EventReference) -> (Id, Option<RelayUrl>, Option<String>) {
+        (er.id, er.relay, er.marker)
+    }
+}
+
+/// An address referred to by an event, from an 'a' tag
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AddressReference {
+    /// The address, with any relay hints
+    pub addr: EventAddr,
+
+    /// The marker ('root', 'reply', 'mention', ...) if any
+    pub marker: Option<String>,
+}
+
+impl AddressReference {
+    fn from_tag(tag: &Tag) -> Option<AddressReference> {
+        let (addr, marker) = tag.parse_address().ok()?;
+        Some(AddressReference { addr, marker })
+    }
+}
+
+/// The position of an event within a thread, per NIP-10
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Thread {
+    /// The root of the thread
+    pub root: Option<EventReference>,
+
+    /// The event being directly replied to
+    pub reply_to: Option<EventReference>,
+
+    /// Events that are mentioned or quoted, but not replied to
+    pub mentions: Vec<EventReference>,
+
+    /// The root of the thread, from an 'a' tag marked 'root', when it is an
+    /// addressable event
+    pub root_address: Option<AddressReference>,
+
+    /// The addressable event being directly replied to, from an 'a' tag
+    /// marked 'reply'
+    pub reply_to_address: Option<AddressReference>,
+
+    /// Addresses that are mentioned, from 'a' tags marked 'mention'
+    pub mentioned_addresses: Vec<AddressReference>,
+}
+
+/// The scope of a NIP-22 comment, either its root or its parent
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommentScope {
+    /// The event, from an 'E' or 'e' tag
+    pub event: Option<EventReference>,
+
+    /// The address, from an 'A' or 'a' tag
+    pub address: Option<EventAddr>,
+
+    /// The kind, from a 'K' or 'k' tag
+    pub kind: Option<EventKind>,
+
+    /// The author, from a 'P' or 'p' tag
+    pub pubkey: Option<PublicKeyHex>,
+}
+
+impl Event {
+    /// Create a new event
+    pub fn new(input: PreEvent, privkey: &PrivateKey) -> Result<Event, Error> {
+        // Generate Id
+        let id = input.hash();
+
+        // Generate Signature
+        let signature = privkey.sign_id(id)?;
+
+        Ok(Event {
+            id,
+            pubkey: input.pubkey,
+            created_at: input.created_at,
+            kind: input.kind,
+            tags: input.tags,
+            content: input.content,
+            ots: input.ots,
+            sig: signature,
+            extra: Map::new(),
+        })
+    }
+
+    /// Create a new event, first checking it with `PreEvent::validate()`
+    pub fn new_validated(input: PreEvent, privkey: &PrivateKey) -> Result<Event, Error> {
+        input.validate()?;
+        Event::new(input, privkey)
+    }
+
+    /// Create a new event with proof of work.
+    ///
+    /// This can take a long time, and is only cancellable by killing the thread.
+    /// Progress is sent to `work_sender` whenever better work is found, and
+    /// once more when done. See `PowMiner` to mine without spawning threads.
+    #[cfg(feature = "std")]
+    pub fn new_with_pow(
+        mut input: PreEvent,
+        privkey: &PrivateKey,
+        zero_bits: u8,
+        work_sender: Option<Sender<PowProgress>>,
+    ) -> Result<Event, Error> {
+        let target = Some(zero_bits as u32);
+
+        // Strip any pre-existing nonce tags
+        input.tags.retain(|t| t.tagname() != "nonce");
+
+        // Add nonce tag to the end
+        input.tags.push(Tag::new_nonce(0, target));
+        let index = input.tags.len() - 1;
+
+        let cores = num_cpus::get();
+
+        let quitting = Arc::new(AtomicBool::new(false));
+        let nonce = Arc::new(AtomicU64::new(0)); // will store the nonce that works
+        let best_work = Arc::new(AtomicU8::new(0));
+        let attempts = Arc::new(AtomicU64::new(0));
+        let started = Instant::now();
+        let progress = move |best_work: u8, attempts: u64, done: bool| PowProgress {
+            target: Difficulty(zero_bits),
+            best_work,
+            attempts,
+            elapsed: started.elapsed(),
+            done,
+        };
+
+        let mut join_handles: Vec<JoinHandle<_>> = Vec::with_capacity(cores);
+
+        for core in 0..cores {
+            let mut attempt: u64 = core as u64 * (u64::MAX / cores as u64);
+            let mut input = input.clone();
+            let index = index;
+            let quitting = quitting.clone();
+            let nonce = nonce.clone();
+            let zero_bits = zero_bits;
+            let best_work = best_work.clone();
+            let attempts = attempts.clone();
+            let work_sender = work_sender.clone();
+            let join_handle = thread::spawn(move || {
+                // Attempts by this thread not yet added to the shared count
+                let mut uncounted: u64 = 0;
+                loop {
+                    // Lower the thread priority so other threads aren't starved
+                    let _ = thread_priority::set_current_thread_priority(
+                        thread_priority::ThreadPriority::Min,
+                    );
+
+                    if quitting.load(Ordering::Relaxed) {
+                        break;
+                    }
+
+                    input.tags[index] = Tag::new_nonce(attempt, target);
+
+                    let Id(id) = input.hash();
+
+                    uncounted += 1;
+                    if uncounted == 4096 {
+                        let _ = attempts.fetch_add(uncounted, Ordering::Relaxed);
+                        uncounted = 0;
+                    }
+
+                    // Progress reports are dropped if nobody is listening
+                    let leading_zeroes = get_leading_zero_bits(&id);
+                    if leading_zeroes >= zero_bits {
+                        nonce.store(attempt, Ordering::Relaxed);
+                        quitting.store(true, Ordering::Relaxed);
+                        break;
+                    } else if leading_zeroes > best_work.load(Ordering::Relaxed) {
+                        best_work.store(leading_zeroes, Ordering::Relaxed);
+                        if let Some(sender) = &work_sender {
+                            let total = attempts.load(Ordering::Relaxed) + uncounted;
+                            let _ = sender.send(progress(leading_zeroes, total, false));
+                        }
+                    }
+
+                    attempt += 1;
+
+                    // We don't update created_at, which is a bit tricky to synchronize.
+                }
+                let _ = attempts.fetch_add(uncounted, Ordering::Relaxed);
+            });
+            join_handles.push(join_handle);
+        }
+
+        for joinhandle in join_handles {
+            let _ = joinhandle.join();
+        }
+
+        // We found the nonce. Do it for reals
+        input.tags[index] = Tag::new_nonce(nonce.load(Ordering::Relaxed), target);
+        let id = input.hash();
+        let work = get_leading_zero_bits(&id.0);
+        if work < zero_bits {
+            return Err(Error::InsufficientPow(work, zero_bits));
+        }
+        if let Some(sender) = &work_sender {
+            let _ = sender.send(progress(work, attempts.load(Ordering::Relaxed), true));
+        }
+
+        // Signature
+        let signature = privkey.sign_id(id)?;
+
+        Ok(Event {
+            id,
+            pubkey: input.pubkey,
+            created_at: input.created_at,
+            kind: input.kind,
+            tags: input.tags,
+            content: input.content,
+            ots: input.ots,
+            sig: signature,
+            extra: Map::new(),
+        })
+    }
+
+    /// Parse an event from JSON, rejecting input that other implementations
+    /// might read differently: duplicate keys, escape sequences that NIP-01
+    /// serialization would not produce, or an id that does not match the
+    /// content. The signature is not checked; use `verify()` for that.
+    pub fn from_json_strict(json: &str) -> Result<Event, Error> {
+        super::strict_json::check_strict(json)?;
+        let event: Event = serde_json::from_str(json)?;
+        let pre = PreEvent {
+            pubkey: event.pubkey,
+            created_at: event.created_at,
+            kind: event.kind,
+            tags: event.tags.clone(),
+            content: event.content.clone(),
+            ots: None,
+        };
+        if pre.hash() != event.id {
+            return Err(Error::HashMismatch);
+        }
+        Ok(event)
+    }
+
+    /// The event as JSON in a stable form: fields in NIP-01 order, no
+    /// whitespace, and without any `extra` fields. Strings are escaped with
+    /// the same routine as for the Id (see `PreEvent::serialize_inner`),
+    /// except that the control characters it leaves verbatim are written as
+    /// `\u00XX` so that the result is valid JSON. Without those, the tags and
+    /// content appear exactly as in the Id preimage.
+    pub fn to_canonical_json(&self) -> Result<String, Error> {
+        let mut output = format!(
+            "{{\"id\":\"{}\",\"pubkey\":\"{}\",\"created_at\":{},\"kind\":{},\"tags\":[",
+            self.id.as_hex_string(),
+            self.pubkey.as_hex_string(),
+            self.created_at.0,
+            u32::from(self.kind)
+        );
+        for (i, tag) in self.tags.iter().enumerate() {
+            if i > 0 {
+                output.push(',');
+            }
+            push_tag(&mut output, tag, push_canonical_string);
+        }
+        output.push_str("],\"content\":");
+        push_canonical_string(&mut output, &self.content);
+        if let Some(ots) = &self.ots {
+            output.push_str(",\"ots\":");
+            push_canonical_string(&mut output, ots);
+        }
+        output.push_str(",\"sig\":\"");
+        output.push_str(&self.sig.as_hex_string());
+        output.push_str("\"}");
+        Ok(output)
+    }
+
+    /// The length in bytes of this event serialized as JSON
+    pub fn serialized_len(&self) -> usize {
+        let mut counter = ByteCounter(0);
+        // Cannot fail: an event is only strings and numbers, and the counter
+        // accepts everything
+        let _ = serde_json::to_writer(&mut counter, self);
+        counter.0
+    }
+
+    /// The number of tags
+    pub fn tag_count(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Parse an event from untrusted JSON, failing as soon as it exceeds
+    /// `limits` rather than after it has been fully read into memory.
+    /// Unknown fields are kept in `extra`, each counting as a string
+    /// against `max_string_len` by the length of its JSON.
+    pub fn from_json_limited(json: &str, limits: &ParseLimits) -> Result<Event, Error> {
+        super::parse_limits::parse_event(json, limits)
+    }
+
     /// Check the validity of an event. This is useful if you deserialize an event
     /// from the network. If you create an event using new() it should already be
     /// trustworthy.
     pub fn verify(&self, maxtime: Option<Unixtime>) -> Result<(), Error> {
-        use k256::schnorr::signature::Verifier;
-
-        let serialized: String = serialize_inner_event!(
+        let serialized: String = serialize_inner_event(
             &self.pubkey,
-            &self.created_at,
-            &self.kind,
+            self.created_at,
+            self.kind,
             &self.tags,
-            &self.content
+            &self.content,
         );
-
-        // Verify the signature
-        self.pubkey.0.verify(serialized.as_bytes(), &self.sig.0)?;
-
-        // Also verify the ID is the SHA256
-        // (the above verify function also does it internally,
-        //  so there is room for improvement here)
         let mut hasher = Sha256::new();
         hasher.update(serialized.as_bytes());
-        let id = hasher.finalize();
+        let id = Id(hasher.finalize().into());
+
+        // Verify the signature over the hash of the event, which is what the
+        // Id should be
+        self.sig.verify(&self.pubkey, id)?;
 
         // Optional verify that the message was in the past
         if let Some(mt) = maxtime {
@@ -306,13 +1221,27 @@ impl Event {
             }
         }
 
-        if *id != self.id.0 {
+        if id != self.id {
             Err(Error::HashMismatch)
         } else {
             Ok(())
         }
     }
 
+    /// Verify this event's OpenTimestamps proof, returning the time of the earliest
+    /// bitcoin block attesting to it. See `verify_ots()` for what `block_header` must
+    /// supply.
+    #[cfg(feature = "ots")]
+    pub fn verify_ots<F>(&self, block_header: F) -> Result<Unixtime, Error>
+    where
+        F: Fn(u64) -> Option<[u8; 80]>,
+    {
+        match &self.ots {
+            Some(ots) => crate::types::verify_ots(ots, self.id, block_header),
+            None => Err(Error::Ots("Event has no OpenTimestamps proof".to_owned())),
+        }
+    }
+
     // Mock data for testing
     #[allow(dead_code)]
     pub(crate) fn mock() -> Event {
@@ -342,6 +1271,7 @@ impl Event {
 
     /// Create a ZapRequest event
     /// These events are not published to nostr, they are sent to a lnurl.
+    #[cfg(feature = "std")]
     pub fn new_zap_request(
         privkey: &PrivateKey,
         recipient_pubkey: PublicKeyHex,
@@ -350,37 +1280,26 @@ impl Event {
         relays: Vec<String>,
         content: String,
     ) -> Result<Event, Error> {
+        let mut relays_tag = Tag::new(&["relays"]);
+        for relay in relays {
+            relays_tag.push_value(relay);
+        }
+
         let mut pre_event = PreEvent {
             pubkey: privkey.public_key(),
-            created_at: Unixtime::now().unwrap(),
+            created_at: Unixtime::now(),
             kind: EventKind::ZapRequest,
             tags: vec![
-                Tag::Pubkey {
-                    pubkey: recipient_pubkey,
-                    recommended_relay_url: None,
-                    petname: None,
-                    trailing: Vec::new(),
-                },
-                Tag::Other {
-                    tag: "relays".to_owned(),
-                    data: relays,
-                },
-                Tag::Other {
-                    tag: "amount".to_owned(),
-                    data: vec![format!("{millisatoshis}")],
-                },
+                Tag::new_pubkey(recipient_pubkey, None, None),
+                relays_tag,
+                Tag::new(&["amount", &format!("{millisatoshis}")]),
             ],
             content,
             ots: None,
         };
 
         if let Some(ze) = zapped_event {
-            pre_event.tags.push(Tag::Event {
-                id: ze,
-                recommended_relay_url: None,
-                marker: None,
-                trailing: Vec::new(),
-            });
+            pre_event.tags.push(Tag::new_event(ze, None, None));
         }
 
         Event::new(pre_event, privkey)
@@ -391,68 +1310,141 @@ impl Event {
         if self.kind != EventKind::EncryptedDirectMessage {
             return Err(Error::WrongEventKind);
         }
-        let parts: Vec<&str> = self.content.split("?iv=").collect();
-        if parts.len() != 2 {
-            return Err(Error::BadEncryptedMessage);
-        }
-
-        let ciphertext: Vec<u8> = base64::engine::general_purpose::STANDARD.decode(parts[0])?;
-        let iv_vec: Vec<u8> = base64::engine::general_purpose::STANDARD.decode(parts[1])?;
-        let iv: [u8; 16] = iv_vec.try_into().unwrap();
-
-        let decrypted_bytes = private_key.nip04_decrypt(&self.pubkey, &ciphertext, iv)?;
+        let decrypted_bytes = nip04_plaintext(private_key, &self.pubkey, &self.content)?;
         let s: String = String::from_utf8_lossy(&decrypted_bytes).into();
         Ok(s)
     }
 
-    /// If the event refers to people, get all the PublicKeys it refers to
-    /// along with recommended relay URL and petname for each
-    pub fn people(&self) -> Vec<(PublicKeyHex, Option<RelayUrl>, Option<String>)> {
-        let mut output: Vec<(PublicKeyHex, Option<RelayUrl>, Option<String>)> = Vec::new();
-
-        // All 'p' tags
-        for tag in self.tags.iter() {
-            if let Tag::Pubkey {
-                pubkey,
-                recommended_relay_url,
-                petname,
-                ..
-            } = tag
-            {
-                output.push((
-                    pubkey.to_owned(),
-                    recommended_relay_url
-                        .as_ref()
-                        .and_then(|rru| RelayUrl::try_from_unchecked_url(rru).ok()),
-                    petname.to_owned(),
-                ));
-            }
+    /// If this is an EncryptedDirectMessage (kind 4) between `my_pubkey` and
+    /// someone else, who that someone is: the recipient if we sent it, or the
+    /// author if it was sent to us. A message to ourselves gives our own key.
+    /// Returns None for other kinds and for DMs we are not part of.
+    pub fn dm_counterparty(&self, my_pubkey: &PublicKey) -> Option<PublicKey> {
+        if self.kind != EventKind::EncryptedDirectMessage {
+            return None;
+        }
+        let mut recipients = self
+            .tags
+            .iter()
+            .filter_map(|t| t.parse_pubkey().ok())
+            .filter_map(|(pk, _, _)| PublicKey::try_from(pk).ok());
+        if &self.pubkey == my_pubkey {
+            recipients.next()
+        } else if recipients.any(|pk| &pk == my_pubkey) {
+            Some(self.pubkey)
+        } else {
+            None
+        }
+    }
+
+    /// If this is a NIP-17 DM relay list (kind 10050), get the relays to send
+    /// private direct messages to. Invalid relay URLs are skipped.
+    pub fn dm_relays(&self) -> Option<Vec<RelayUrl>> {
+        if self.kind != EventKind::DmRelayList {
+            return None;
+        }
+        Some(
+            self.tags
+                .iter()
+                .filter(|t| t.tagname() == "relay")
+                .filter_map(|t| RelayUrl::try_from_str(t.value()).ok())
+                .collect(),
+        )
+    }
+
+    /// If this is a NIP-59 gift wrap addressed to `private_key`, open it and
+    /// its seal to get the rumor inside.
+    ///
+    /// The seal signature and rumor Id are checked, as is that the seal was
+    /// signed by the rumor's author, so the rumor's pubkey can be trusted.
+    pub fn unwrap_gift_wrap(&self, private_key: &PrivateKey) -> Result<Rumor, Error> {
+        if self.kind != EventKind::GiftWrap {
+            return Err(Error::WrongEventKind);
+        }
+        let seal_json = private_key.nip44_decrypt(&self.pubkey, &self.content)?;
+        let seal: Event = serde_json::from_str(&seal_json)?;
+        if seal.kind != EventKind::Seal {
+            return Err(Error::WrongEventKind);
+        }
+        seal.verify(None)?;
+        let rumor_json = private_key.nip44_decrypt(&seal.pubkey, &seal.content)?;
+        let rumor: Rumor = serde_json::from_str(&rumor_json)?;
+        rumor.verify_id()?;
+        if rumor.pubkey != seal.pubkey {
+            return Err(Error::SealMismatch);
+        }
+        Ok(rumor)
+    }
+
+    /// If this is a NIP-47 wallet info event, get what the wallet service
+    /// supports
+    pub fn wallet_info(&self) -> Option<WalletInfo> {
+        if self.kind != EventKind::WalletInfo {
+            return None;
+        }
+        Some(WalletInfo::from_tags(&self.tags, &self.content))
+    }
+
+    /// If this is a NIP-47 wallet request, decrypt it with the wallet service
+    /// key
+    pub fn wallet_request(&self, wallet_key: &PrivateKey) -> Result<WalletRequest, Error> {
+        if self.kind != EventKind::WalletRequest {
+            return Err(Error::WrongEventKind);
+        }
+        let json = nip04_plaintext(wallet_key, &self.pubkey, &self.content)?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    /// If this is a NIP-47 wallet response, decrypt it with the client's
+    /// connection key
+    pub fn wallet_response(&self, client_key: &PrivateKey) -> Result<WalletResponse, Error> {
+        if self.kind != EventKind::WalletResponse {
+            return Err(Error::WrongEventKind);
+        }
+        let json = nip04_plaintext(client_key, &self.pubkey, &self.content)?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    /// If the event refers to people, get all the PublicKeys it refers to
+    /// along with recommended relay URL and petname for each
+    pub fn people_refs(&self) -> Vec<PersonReference> {
+        let mut output: Vec<PersonReference> = Vec::new();
+
+        // All 'p' tags
+        for tag in self.tags.iter() {
+            if let Some(pr) = PersonReference::from_tag(tag) {
+                output.push(pr);
+            }
         }
 
         output
     }
 
+    /// Like `people_refs()`, but only the keys that are actual points on the
+    /// curve, as spam often carries 'p' tags with random hex
+    pub fn people_verified(&self) -> Vec<PersonReference> {
+        self.tags
+            .iter()
+            .filter(|tag| tag.parse_pubkey_strict().is_ok())
+            .filter_map(PersonReference::from_tag)
+            .collect()
+    }
+
+    /// Tuple form of `people_refs()`
+    #[deprecated(since = "0.7.0", note = "please use `people_refs` instead")]
+    pub fn people(&self) -> Vec<(PublicKeyHex, Option<RelayUrl>, Option<String>)> {
+        self.people_refs().into_iter().map(|pr| pr.into()).collect()
+    }
+
     /// If the event refers to people, get all the PublicKeys it refers to
     /// along with recommended relay URL and petname for each, but only if they
     /// are referenced within the note.
-    pub fn referenced_people(&self) -> Vec<(PublicKeyHex, Option<RelayUrl>, Option<String>)> {
-        let mut output: Vec<(PublicKeyHex, Option<RelayUrl>, Option<String>)> = Vec::new();
+    pub fn referenced_people_refs(&self) -> Vec<PersonReference> {
+        let mut output: Vec<PersonReference> = Vec::new();
         for (n, tag) in self.tags.iter().enumerate() {
-            if let Tag::Pubkey {
-                pubkey,
-                recommended_relay_url,
-                petname,
-                ..
-            } = tag
-            {
+            if let Some(pr) = PersonReference::from_tag(tag) {
                 if self.content.contains(&format!("#[{n}]")) {
-                    output.push((
-                        pubkey.to_owned(),
-                        recommended_relay_url
-                            .as_ref()
-                            .and_then(|rru| RelayUrl::try_from_unchecked_url(rru).ok()),
-                        petname.to_owned(),
-                    ));
+                    output.push(pr);
                 }
             }
         }
@@ -460,6 +1452,15 @@ impl Event {
         output
     }
 
+    /// Tuple form of `referenced_people_refs()`
+    #[deprecated(since = "0.7.0", note = "please use `referenced_people_refs` instead")]
+    pub fn referenced_people(&self) -> Vec<(PublicKeyHex, Option<RelayUrl>, Option<String>)> {
+        self.referenced_people_refs()
+            .into_iter()
+            .map(|pr| pr.into())
+            .collect()
+    }
+
     /// Is the event a reply?
     #[deprecated(since = "0.2.0", note = "please use `replies_to` instead")]
     pub fn is_reply(&self) -> bool {
@@ -467,13 +1468,7 @@ impl Event {
             return false;
         }
 
-        for tag in self.tags.iter() {
-            if let Tag::Event { .. } = tag {
-                return true;
-            }
-        }
-
-        false
+        self.tags.iter().any(|t| t.tagname() == "e")
     }
 
     /// If this event replies to another, get that other event's Id along with
@@ -489,74 +1484,31 @@ impl Event {
         }
 
         // If there are no 'e' tags, then none
-        let num_e_tags = self
-            .tags
-            .iter()
-            .filter(|e| matches!(e, Tag::Event { .. }))
-            .count();
-        if num_e_tags == 0 {
+        let e_refs = self.referred_event_refs();
+        if e_refs.is_empty() {
             return None;
         }
 
         // look for an 'e' tag with marker 'reply'
-        for tag in self.tags.iter() {
-            if let Tag::Event {
-                id,
-                recommended_relay_url,
-                marker,
-                ..
-            } = tag
-            {
-                if marker.is_some() && marker.as_deref().unwrap() == "reply" {
-                    return Some((
-                        *id,
-                        recommended_relay_url
-                            .as_ref()
-                            .and_then(|rru| RelayUrl::try_from_unchecked_url(rru).ok()),
-                    ));
-                }
-            }
+        if let Some(er) = e_refs
+            .iter()
+            .find(|er| er.marker.as_deref() == Some("reply"))
+        {
+            return Some((er.id, er.relay.clone()));
         }
 
         // look for an 'e' tag with marker 'root'
-        for tag in self.tags.iter() {
-            if let Tag::Event {
-                id,
-                recommended_relay_url,
-                marker,
-                ..
-            } = tag
-            {
-                if marker.is_some() && marker.as_deref().unwrap() == "root" {
-                    return Some((
-                        *id,
-                        recommended_relay_url
-                            .as_ref()
-                            .and_then(|rru| RelayUrl::try_from_unchecked_url(rru).ok()),
-                    ));
-                }
-            }
+        if let Some(er) = e_refs
+            .iter()
+            .find(|er| er.marker.as_deref() == Some("root"))
+        {
+            return Some((er.id, er.relay.clone()));
         }
 
         // Use the last 'e' tag if unmarked
-        if let Some(Tag::Event {
-            id,
-            recommended_relay_url,
-            marker,
-            ..
-        }) = self
-            .tags
-            .iter()
-            .rev()
-            .find(|t| matches!(t, Tag::Event { .. }))
-        {
-            if marker.is_none() {
-                return Some((
-                    *id,
-                    recommended_relay_url
-                        .as_ref()
-                        .and_then(|rru| RelayUrl::try_from_unchecked_url(rru).ok()),
-                ));
+        if let Some(er) = e_refs.last() {
+            if er.marker.is_none() {
+                return Some((er.id, er.relay.clone()));
             }
         }
 
@@ -573,42 +1525,21 @@ impl Event {
             return None;
         }
 
+        let e_refs = self.referred_event_refs();
+
         // look for an 'e' tag with marker 'root'
-        for tag in self.tags.iter() {
-            if let Tag::Event {
-                id,
-                recommended_relay_url,
-                marker,
-                ..
-            } = tag
-            {
-                if marker.is_some() && marker.as_deref().unwrap() == "root" {
-                    return Some((
-                        *id,
-                        recommended_relay_url
-                            .as_ref()
-                            .and_then(|rru| RelayUrl::try_from_unchecked_url(rru).ok()),
-                    ));
-                }
-            }
+        if let Some(er) = e_refs
+            .iter()
+            .find(|er| er.marker.as_deref() == Some("root"))
+        {
+            return Some((er.id, er.relay.clone()));
         }
 
         // otherwise use the first 'e' tag if unmarked
         // (even if there is only 1 'e' tag which means it is both root and reply)
-        if let Some(Tag::Event {
-            id,
-            recommended_relay_url,
-            marker,
-            ..
-        }) = self.tags.iter().find(|t| matches!(t, Tag::Event { .. }))
-        {
-            if marker.is_none() {
-                return Some((
-                    *id,
-                    recommended_relay_url
-                        .as_ref()
-                        .and_then(|rru| RelayUrl::try_from_unchecked_url(rru).ok()),
-                ));
+        if let Some(er) = e_refs.first() {
+            if er.marker.is_none() {
+                return Some((er.id, er.relay.clone()));
             }
         }
 
@@ -617,55 +1548,86 @@ impl Event {
 
     /// All events IDs that this event refers to, whether root, reply, mention, or otherwise
     /// along with optional recommended relay URLs
-    pub fn referred_events(&self) -> Vec<(Id, Option<RelayUrl>, Option<String>)> {
-        let mut output: Vec<(Id, Option<RelayUrl>, Option<String>)> = Vec::new();
+    pub fn referred_event_refs(&self) -> Vec<EventReference> {
+        let mut output: Vec<EventReference> = Vec::new();
 
         // Collect every 'e' tag
         for tag in self.tags.iter() {
-            if let Tag::Event {
-                id,
-                recommended_relay_url,
-                marker,
-                ..
-            } = tag
-            {
-                output.push((
-                    *id,
-                    recommended_relay_url
-                        .as_ref()
-                        .and_then(|rru| RelayUrl::try_from_unchecked_url(rru).ok()),
-                    marker.clone(),
-                ));
+            if let Some(er) = EventReference::from_tag(tag) {
+                output.push(er);
             }
         }
 
         output
     }
 
+    /// Every relay hint in this event's tags that refer to events ('e', 'E'
+    /// and 'q'), people ('p' and 'P') and addresses ('a' and 'A'), in tag
+    /// order, including any that are not valid relay URLs
+    pub fn relay_hints(&self) -> Vec<RelayHint> {
+        let mut output: Vec<RelayHint> = Vec::new();
+        for tag in self.tags.iter() {
+            let source = match tag.tagname() {
+                "e" | "E" | "q" => RelayHintSource::EventTag,
+                "p" | "P" => RelayHintSource::PubkeyTag,
+                "a" | "A" => {
+                    // An 'a' tag may have several relays
+                    let address = match tag.tagname() {
+                        "a" => tag.parse_address().map(|(ea, _)| ea),
+                        _ => tag.parse_root_address(),
+                    };
+                    if let Ok(ea) = address {
+                        output.extend(
+                            ea.relays
+                                .into_iter()
+                                .map(|u| RelayHint::new(u, RelayHintSource::AddressTag)),
+                        );
+                        continue;
+                    }
+                    RelayHintSource::AddressTag
+                }
+                _ => continue,
+            };
+            match tag.get_index(2) {
+                "" => {}
+                url => output.push(RelayHint::new(UncheckedUrl::from_str(url), source)),
+            }
+        }
+        output
+    }
+
+    /// All addresses that this event refers to, from its 'a' tags, whether
+    /// root, reply, mention, or otherwise
+    pub fn referred_addresses(&self) -> Vec<AddressReference> {
+        self.tags
+            .iter()
+            .filter_map(AddressReference::from_tag)
+            .collect()
+    }
+
+    /// Tuple form of `referred_event_refs()`
+    #[deprecated(since = "0.7.0", note = "please use `referred_event_refs` instead")]
+    pub fn referred_events(&self) -> Vec<(Id, Option<RelayUrl>, Option<String>)> {
+        self.referred_event_refs()
+            .into_iter()
+            .map(|er| er.into())
+            .collect()
+    }
+
     /// If this event mentions others, get those other event Ids
     /// and optional recommended relay Urls
-    pub fn mentions(&self) -> Vec<(Id, Option<RelayUrl>)> {
+    pub fn mention_refs(&self) -> Vec<EventReference> {
         if !self.kind.is_feed_displayable() {
             return vec![];
         }
 
-        let mut output: Vec<(Id, Option<RelayUrl>)> = Vec::new();
+        let mut output: Vec<EventReference> = Vec::new();
 
         // For kind=6, all 'e' tags are mentions
         if self.kind == EventKind::Repost {
             for tag in self.tags.iter() {
-                if let Tag::Event {
-                    id,
-                    recommended_relay_url,
-                    ..
-                } = tag
-                {
-                    output.push((
-                        *id,
-                        recommended_relay_url
-                            .as_ref()
-                            .and_then(|rru| RelayUrl::try_from_unchecked_url(rru).ok()),
-                    ));
+                if let Some(er) = EventReference::from_tag(tag) {
+                    output.push(er);
                 }
             }
 
@@ -676,53 +1638,240 @@ impl Event {
 
         // Collect every 'e' tag marked as 'mention'
         for tag in self.tags.iter() {
-            if let Tag::Event {
-                id,
-                recommended_relay_url,
-                marker,
-                ..
-            } = tag
-            {
-                if marker.is_some() && marker.as_deref().unwrap() == "mention" {
-                    output.push((
-                        *id,
-                        recommended_relay_url
-                            .as_ref()
-                            .and_then(|rru| RelayUrl::try_from_unchecked_url(rru).ok()),
-                    ));
+            if let Some(er) = EventReference::from_tag(tag) {
+                if er.marker.as_deref() == Some("mention") {
+                    output.push(er);
                 }
             }
         }
 
         // Collect every unmarked 'e' tag that is not the first or last
-        let e_tags: Vec<&Tag> = self
+        let e_refs = self.referred_event_refs();
+        if e_refs.len() > 2 {
+            // mentions are everything other than first and last
+            for er in &e_refs[1..e_refs.len() - 1] {
+                if er.marker.is_none() {
+                    output.push(er.clone());
+                }
+            }
+        }
+
+        output
+    }
+
+    /// Tuple form of `mention_refs()`
+    #[deprecated(since = "0.7.0", note = "please use `mention_refs` instead")]
+    pub fn mentions(&self) -> Vec<(Id, Option<RelayUrl>)> {
+        self.mention_refs()
+            .into_iter()
+            .map(|er| (er.id, er.relay))
+            .collect()
+    }
+
+    /// Analyze where this event sits within a thread, per NIP-10.
+    ///
+    /// Marked 'e' tags ('root', 'reply', 'mention') are preferred. If no 'e' tag
+    /// is marked 'root' or 'reply', the deprecated positional scheme is used on
+    /// the unmarked 'e' tags. Quoted events ('q' tags) are included as mentions.
+    /// Marked 'a' tags give the addresses in the thread the same way.
+    pub fn thread(&self) -> Thread {
+        let mut thread = Thread::default();
+
+        if !self.kind.is_feed_displayable() {
+            return thread;
+        }
+
+        let a_refs = self.referred_addresses();
+        if self.kind == EventKind::Repost {
+            thread.mentioned_addresses = a_refs;
+        } else {
+            let replies_to_event = self
+                .referred_event_refs()
+                .iter()
+                .any(|er| er.marker.as_deref() == Some("reply"));
+            for ar in a_refs.into_iter() {
+                match ar.marker.as_deref() {
+                    Some("root") if thread.root_address.is_none() => thread.root_address = Some(ar),
+                    Some("reply") if thread.reply_to_address.is_none() => {
+                        thread.reply_to_address = Some(ar)
+                    }
+                    Some("mention") => thread.mentioned_addresses.push(ar),
+                    _ => {}
+                }
+            }
+            // A reply directly to the root only carries the 'root' marker
+            if thread.reply_to_address.is_none() && !replies_to_event {
+                thread.reply_to_address = thread.root_address.clone();
+            }
+        }
+
+        let e_refs: Vec<EventReference> = self
             .tags
             .iter()
-            .filter(|e| matches!(e, Tag::Event { .. }))
+            .filter_map(EventReference::from_tag)
             .collect();
-        if e_tags.len() > 2 {
-            // mentions are everything other than first and last
-            for tag in &e_tags[1..e_tags.len() - 1] {
-                if let Tag::Event {
-                    id,
-                    recommended_relay_url,
-                    marker,
-                    ..
-                } = tag
-                {
-                    if marker.is_none() {
-                        output.push((
-                            *id,
-                            recommended_relay_url
-                                .as_ref()
-                                .and_then(|rru| RelayUrl::try_from_unchecked_url(rru).ok()),
-                        ));
-                    }
+
+        let q_refs = self.quotes();
+
+        // Kind=6 'e' tags are always considered mentions, not replies.
+        if self.kind == EventKind::Repost {
+            thread.mentions = e_refs;
+            thread.mentions.extend(q_refs);
+            return thread;
+        }
+
+        let marked = e_refs
+            .iter()
+            .any(|er| matches!(er.marker.as_deref(), Some("root") | Some("reply")));
+
+        if marked {
+            let mut unmarked: Vec<EventReference> = Vec::new();
+            for er in e_refs.into_iter() {
+                match er.marker.as_deref() {
+                    Some("root") if thread.root.is_none() => thread.root = Some(er),
+                    Some("reply") if thread.reply_to.is_none() => thread.reply_to = Some(er),
+                    Some("mention") => thread.mentions.push(er),
+                    _ => unmarked.push(er),
+                }
+            }
+            // A reply directly to the root only carries the 'root' marker
+            if thread.reply_to.is_none() {
+                thread.reply_to = thread.root.clone();
+            }
+            thread.mentions.extend(unmarked);
+        } else {
+            let mut positional: Vec<EventReference> = Vec::new();
+            for er in e_refs.into_iter() {
+                if er.marker.is_none() {
+                    positional.push(er);
+                } else {
+                    thread.mentions.push(er);
+                }
+            }
+            let len = positional.len();
+            if len > 0 {
+                thread.root = positional.first().cloned();
+                thread.reply_to = positional.last().cloned();
+                if len > 2 {
+                    thread.mentions.extend(positional.drain(1..len - 1));
                 }
             }
         }
 
-        output
+        thread.mentions.extend(q_refs);
+
+        thread
+    }
+
+    /// Get the events this event quotes ('q' tags, NIP-18). These are
+    /// distinct from replies and from 'e' tag mentions.
+    pub fn quotes(&self) -> Vec<EventReference> {
+        self.tags
+            .iter()
+            .filter_map(EventReference::from_quote_tag)
+            .collect()
+    }
+
+    /// If this is a NIP-22 comment, get the root scope of its thread
+    /// from the 'A', 'E', 'K' and 'P' tags
+    pub fn comment_root(&self) -> Option<CommentScope> {
+        if self.kind != EventKind::Comment {
+            return None;
+        }
+
+        let mut scope = CommentScope::default();
+        for tag in self.tags.iter() {
+            match tag.tagname() {
+                "A" if scope.address.is_none() => {
+                    scope.address = tag.parse_root_address().ok();
+                }
+                "E" if scope.event.is_none() => {
+                    scope.event = tag.parse_root_event().ok().map(|(id, rru, pubkey)| {
+                        let hint = rru.map(|u| RelayHint::new(u, RelayHintSource::EventTag));
+                        EventReference {
+                            id,
+                            relay: hint.as_ref().and_then(|h| h.relay_url().ok()),
+                            hint,
+                            marker: None,
+                            author: pubkey,
+                        }
+                    });
+                }
+                "K" if scope.kind.is_none() => {
+                    scope.kind = tag.parse_root_kind().ok();
+                }
+                "P" if scope.pubkey.is_none() => {
+                    scope.pubkey = tag.parse_root_pubkey().ok().map(|(pk, _)| pk);
+                }
+                _ => {}
+            }
+        }
+
+        Some(scope)
+    }
+
+    /// If this is a NIP-22 comment, get the parent it directly replies to
+    /// from the 'a', 'e', 'k' and 'p' tags
+    pub fn comment_parent(&self) -> Option<CommentScope> {
+        if self.kind != EventKind::Comment {
+            return None;
+        }
+
+        let mut scope = CommentScope::default();
+        for tag in self.tags.iter() {
+            match tag.tagname() {
+                "a" if scope.address.is_none() => {
+                    scope.address = tag.parse_address().ok().map(|(ea, _)| ea);
+                }
+                "e" if scope.event.is_none() => {
+                    scope.event = EventReference::from_tag(tag).map(|mut er| {
+                        // NIP-22 puts the author where NIP-10 puts the marker
+                        if let Some(pk) = er.marker.take() {
+                            er.author = PublicKeyHex::try_from_string(pk).ok().or(er.author);
+                        }
+                        er
+                    });
+                }
+                "k" if scope.kind.is_none() => {
+                    scope.kind = tag.parse_kind().ok();
+                }
+                "p" if scope.pubkey.is_none() => {
+                    scope.pubkey = tag.parse_pubkey().ok().map(|(pk, _, _)| pk);
+                }
+                _ => {}
+            }
+        }
+
+        Some(scope)
+    }
+
+    /// If this is a parameterized replaceable event, get its address
+    /// (`kind:pubkey:d` coordinate). A missing 'd' tag is an implicit empty
+    /// identifier. No relays are included.
+    pub fn address(&self) -> Option<EventAddr> {
+        if !self.kind.is_parameterized_replaceable() {
+            return None;
+        }
+        let d = self
+            .tags
+            .iter()
+            .find_map(|t| t.parse_identifier().ok())
+            .unwrap_or_default(); // implicit
+        Some(EventAddr {
+            d,
+            relays: vec![],
+            kind: self.kind,
+            author: self.pubkey,
+        })
+    }
+
+    /// Is this event the parameterized replaceable event at `addr`?
+    /// Relays in `addr` are ignored.
+    pub fn matches_address(&self, addr: &EventAddr) -> bool {
+        match self.address() {
+            Some(ea) => ea.kind == addr.kind && ea.author == addr.author && ea.d == addr.d,
+            None => false,
+        }
     }
 
     /// If this event reacts to another, get that other event's Id,
@@ -733,55 +1882,101 @@ impl Event {
         }
 
         // The last 'e' tag is it
-        if let Some(Tag::Event {
-            id,
-            recommended_relay_url,
-            ..
-        }) = self
-            .tags
-            .iter()
-            .rev()
-            .find(|t| matches!(t, Tag::Event { .. }))
-        {
-            return Some((
-                *id,
-                self.content.clone(),
-                recommended_relay_url
-                    .as_ref()
-                    .and_then(|rru| RelayUrl::try_from_unchecked_url(rru).ok()),
-            ));
+        if let Some(er) = self.referred_event_refs().pop() {
+            return Some((er.id, self.content.clone(), er.relay));
         }
 
         None
     }
 
-    /// If this event deletes others, get all the Ids of the events that it deletes
-    /// along with the reason for the deletion
-    pub fn deletes(&self) -> Option<(Vec<Id>, String)> {
-        if self.kind != EventKind::EventDeletion {
+    /// If this is a repost (NIP-18, kind 6 or 16) with the reposted event in
+    /// its content, get that event. It has not been verified.
+    pub fn reposted_event(&self) -> Option<Event> {
+        if !matches!(self.kind, EventKind::Repost | EventKind::GenericRepost) {
             return None;
         }
+        serde_json::from_str(&self.content).ok()
+    }
 
-        let mut ids: Vec<Id> = Vec::new();
-
-        // All 'e' tags are deleted
-        for tag in self.tags.iter() {
-            if let Tag::Event { id, .. } = tag {
-                ids.push(*id);
-            }
+    /// If this is a reaction (NIP-25), the kind of the event it reacts to,
+    /// from its 'k' tag
+    pub fn reaction_target_kind(&self) -> Option<EventKind> {
+        if self.kind != EventKind::Reaction {
+            return None;
         }
+        self.tags.iter().find_map(|t| t.parse_kind().ok())
+    }
 
-        if ids.is_empty() {
-            None
-        } else {
-            Some((ids, self.content.clone()))
+    /// If this event deletes others (NIP-09), get what it deletes: the Ids,
+    /// the addresses of replaceable events, the kinds, and the reason
+    pub fn deletes(&self) -> Option<DeletionRequest> {
+        DeletionRequest::from_event(self)
+    }
+
+    /// Check a zap request the way an LNURL server must before making an
+    /// invoice for it (NIP-57 appendix D): it is a validly signed kind 9734
+    /// with exactly one 'p' tag, at most one 'e' tag, at most one 'P' tag, a
+    /// 'relays' tag, a valid 'a' tag if any, and an 'amount' tag (if any)
+    /// equal to `amount`, the amount the invoice is requested for.
+    pub fn validate_zap_request(&self, amount: Option<MilliSatoshi>) -> Result<(), Error> {
+        let invalid = |reason: &str| Err(Error::InvalidZapRequest(reason.to_owned()));
+        let count = |name: &str| self.tags.iter().filter(|t| t.tagname() == name).count();
+
+        if self.kind != EventKind::ZapRequest {
+            return Err(Error::WrongEventKind);
+        }
+        self.verify(None)?;
+        if self.tags.is_empty() {
+            return invalid("no tags");
+        }
+        if count("p") != 1
+            || self
+                .tags
+                .iter()
+                .any(|t| t.tagname() == "p" && t.parse_pubkey().is_err())
+        {
+            return invalid("there must be exactly one valid 'p' tag");
+        }
+        if count("e") > 1 {
+            return invalid("there may be at most one 'e' tag");
+        }
+        if count("P") > 1 {
+            return invalid("there may be at most one 'P' tag");
+        }
+        if !self
+            .tags
+            .iter()
+            .any(|t| t.tagname() == "relays" && !t.value().is_empty())
+        {
+            return invalid("there is no 'relays' tag");
+        }
+        if self
+            .tags
+            .iter()
+            .any(|t| t.tagname() == "a" && t.parse_address().is_err())
+        {
+            return invalid("the 'a' tag is not a valid event coordinate");
+        }
+        for tag in self.tags.iter().filter(|t| t.tagname() == "amount") {
+            let Ok(tagged) = tag.value().parse::<u64>() else {
+                return invalid("the 'amount' tag is not a number of millisatoshis");
+            };
+            if let Some(amount) = amount {
+                if MilliSatoshi(tagged) != amount {
+                    return invalid("the 'amount' tag does not match the amount requested");
+                }
+            }
         }
+        Ok(())
     }
 
-    /// If this event zaps another event, get data about that.
+    /// If this event zaps another event, by Id or by address, get data about
+    /// that.
     ///
-    /// That includes the Id, the amount, and the public key of the provider,
-    /// all of which should be verified by the caller.
+    /// That includes the Id or address, the amount, and the public key of the
+    /// provider, all of which should be verified by the caller. The sender
+    /// and comment are taken from the embedded zap request, and are left out
+    /// if its signature does not verify.
     ///
     /// Errors returned from this are not fatal, but may be useful for
     /// explaining to a user why a zap receipt is invalid.
@@ -790,26 +1985,28 @@ impl Event {
             return Ok(None);
         }
 
-        let mut zapped_id: Option<Id> = None;
         let mut zapped_amount: Option<MilliSatoshi> = None;
         let mut zapped_pubkey: Option<PublicKey> = None;
 
+        let parts = self.zap_receipt_parts();
+        if parts.id.is_none() && parts.address.is_none() {
+            // This probably means a person was zapped, not a note. So not an error.
+            return Ok(None);
+        }
+
         for tag in self.tags.iter() {
-            if let Tag::Other { tag, data } = tag {
-                // Find the bolt11 tag
-                if tag != "bolt11" {
-                    continue;
-                }
-                if data.is_empty() {
+            if tag.tagname() == "bolt11" {
+                if tag.fields().len() < 2 {
                     return Err(Error::ZapReceipt("missing bolt11 tag value".to_string()));
                 }
 
                 // Extract as an Invoice
-                let result = Invoice::from_str(&data[0]);
-                if let Err(e) = result {
-                    return Err(Error::ZapReceipt(format!("bolt11 failed to parse: {}", e)));
-                }
-                let invoice = result.unwrap();
+                let invoice = match Invoice::from_str(tag.value()) {
+                    Ok(invoice) => invoice,
+                    Err(e) => {
+                        return Err(Error::ZapReceipt(format!("bolt11 failed to parse: {}", e)))
+                    }
+                };
 
                 // Verify the signature
                 if let Err(e) = invoice.check_signature() {
@@ -842,78 +2039,301 @@ impl Event {
                     ));
                 }
             }
-            if let Tag::Event { id, .. } = tag {
-                zapped_id = Some(*id);
-            }
         }
 
-        if zapped_id.is_none() {
-            // This probably means a person was zapped, not a note. So not an error.
-            return Ok(None);
-        }
-        if zapped_amount.is_none() {
+        let Some(amount) = zapped_amount else {
             return Err(Error::ZapReceipt("Missing amount".to_string()));
-        }
-        if zapped_pubkey.is_none() {
+        };
+        let Some(pubkey) = zapped_pubkey else {
             return Err(Error::ZapReceipt("Missing payee public key".to_string()));
-        }
+        };
 
         Ok(Some(ZapData {
-            id: zapped_id.unwrap(),
-            amount: zapped_amount.unwrap(),
-            pubkey: zapped_pubkey.unwrap(),
+            id: parts.id,
+            amount,
+            pubkey,
+            sender: parts.sender,
+            comment: parts.comment,
+            address: parts.address,
+            created_at: self.created_at,
         }))
     }
 
-    /// If this event specifies the client that created it, return that client string
-    pub fn client(&self) -> Option<String> {
+    // What a zap receipt says apart from its invoice: what was zapped, and
+    // who by from the embedded zap request, which is only believed if it is
+    // a zap request with a valid signature
+    fn zap_receipt_parts(&self) -> ZapReceiptParts {
+        let mut parts = ZapReceiptParts::default();
+        let mut description: Option<&str> = None;
+        let mut p_sender: Option<PublicKey> = None;
         for tag in self.tags.iter() {
-            if let Tag::Other { tag, data } = tag {
-                if tag == "client" && !data.is_empty() {
-                    return Some(data[0].clone());
+            match tag.tagname() {
+                "description" => description = Some(tag.value()),
+                "a" if parts.address.is_none() => {
+                    parts.address = tag.parse_address().ok().map(|(ea, _)| ea)
+                }
+                "e" => {
+                    if let Ok((id, _, _)) = tag.parse_event() {
+                        parts.id = Some(id);
+                    }
                 }
+                "P" => p_sender = PublicKey::try_from_hex_string(tag.value()).ok(),
+                _ => {}
             }
         }
-
-        None
+        match description {
+            Some(json) => {
+                let request = serde_json::from_str::<Event>(json)
+                    .ok()
+                    .filter(|r| r.kind == EventKind::ZapRequest && r.verify(None).is_ok());
+                if let Some(request) = request {
+                    parts.sender = Some(request.pubkey);
+                    parts.comment = Some(request.content).filter(|c| !c.is_empty());
+                }
+            }
+            None => parts.sender = p_sender,
+        }
+        parts
     }
 
-    /// If this event specifies a subject, return that subject string
-    pub fn subject(&self) -> Option<String> {
+    /// If this event specifies the client that created it, return that client string
+    pub fn client(&self) -> Option<String> {
         for tag in self.tags.iter() {
-            if let Tag::Subject { subject, .. } = tag {
-                return Some(subject.clone());
+            if tag.tagname() == "client" && tag.fields().len() > 1 {
+                return Some(tag.value().to_owned());
             }
         }
 
         None
     }
 
-    /// If this event specifies a content warning, return that subject string
-    pub fn content_warning(&self) -> Option<String> {
-        for tag in self.tags.iter() {
-            if let Tag::ContentWarning { warning, .. } = tag {
-                return Some(warning.clone());
-            }
+    /// If this event specifies a subject, return that subject string
+    pub fn subject(&self) -> Option<String> {
+        self.tags.iter().find_map(|t| t.parse_subject().ok())
+    }
+
+    /// If this event has a content warning (NIP-36), return its reason, which
+    /// is empty if none was given. The warning may be a 'content-warning'
+    /// tag, or a label in the "content-warning" namespace (NIP-32).
+    pub fn content_warning(&self) -> Option<String> {
+        if let Some(warning) = self
+            .tags
+            .iter()
+            .find_map(|t| t.parse_content_warning().ok())
+        {
+            return Some(warning.unwrap_or_default());
         }
+        self.tags.iter().find_map(|t| match t.parse_label() {
+            Ok((reason, Some(namespace))) if namespace == "content-warning" => Some(reason),
+            _ => None,
+        })
+    }
 
-        None
+    /// The short description of this event for clients that do not know its
+    /// kind, from its 'alt' tag (NIP-31)
+    pub fn alt_description(&self) -> Option<String> {
+        self.tags.iter().find_map(|t| t.parse_alt().ok())
+    }
+
+    /// When this event expires (NIP-40), if it does
+    pub fn expiration(&self) -> Option<Unixtime> {
+        self.tags.iter().find_map(|t| t.parse_expiration().ok())
+    }
+
+    /// Whether this event has expired (NIP-40) as of `now`
+    pub fn is_expired(&self, now: Unixtime) -> bool {
+        self.expiration()
+            .is_some_and(|expiration| expiration <= now)
+    }
+
+    /// When this long-form article (NIP-23) was first published, from its
+    /// 'published_at' tag. The `created_at` of an edited article is the time
+    /// of the last edit.
+    pub fn published_at(&self) -> Option<Unixtime> {
+        self.tags.iter().find_map(|t| t.parse_published_at().ok())
+    }
+
+    /// The time to show and order this event by: when it was first
+    /// published, if it says so and that is not after it was created,
+    /// otherwise when it was created
+    pub fn display_time(&self) -> Unixtime {
+        match self.published_at() {
+            Some(published_at) if published_at <= self.created_at => published_at,
+            _ => self.created_at,
+        }
+    }
+
+    /// Is this event protected (NIP-70)? Only its author may publish it.
+    pub fn is_protected(&self) -> bool {
+        self.tags.iter().any(|t| t.is_protected())
+    }
+
+    /// Whether a relay may accept this event over a connection that has
+    /// authenticated (NIP-42) as `authenticated`. Protected events are only
+    /// accepted from their author; a relay should send an AUTH challenge
+    /// when this fails and the connection has not authenticated.
+    pub fn may_be_published_by(&self, authenticated: Option<&PublicKey>) -> bool {
+        !self.is_protected() || authenticated == Some(&self.pubkey)
     }
 
     /// If this is a parameterized event, get the parameter
     pub fn parameter(&self) -> Option<String> {
         if self.kind.is_parameterized_replaceable() {
-            for tag in self.tags.iter() {
-                if let Tag::Parameter { param, .. } = tag {
-                    return Some(param.to_owned());
-                }
-            }
-            Some("".to_owned()) // implicit
+            Some(
+                self.tags
+                    .iter()
+                    .find_map(|t| t.parse_parameter().ok())
+                    .unwrap_or_default(), // implicit
+            )
         } else {
             None
         }
     }
 
+    /// If this is a NIP-56 report, get what is being reported and why.
+    /// The reporter's comment is the event content.
+    pub fn report(&self) -> Option<Report> {
+        if self.kind != EventKind::Reporting {
+            return None;
+        }
+
+        // The report type is in the third field of the 'p' and 'e' tags
+        let report_type = |tag: &Tag| match tag.get_index(2) {
+            "" => None,
+            rt => Some(ReportType::from(rt)),
+        };
+
+        let ptag = self.tags.iter().find(|t| t.parse_pubkey().is_ok())?;
+        let (author, _, _) = ptag.parse_pubkey().ok()?;
+        let author_rt = report_type(ptag);
+
+        for tag in self.tags.iter() {
+            if let Ok((id, _, _)) = tag.parse_event() {
+                return Some(Report {
+                    target: ReportTarget::Event { id, author },
+                    report_type: report_type(tag).or(author_rt).unwrap_or(ReportType::Other),
+                });
+            }
+        }
+
+        Some(Report {
+            target: ReportTarget::Pubkey(author),
+            report_type: author_rt.unwrap_or(ReportType::Other),
+        })
+    }
+
+    /// If this is a NIP-53 live event (kind 30311), get its details
+    pub fn live_event(&self) -> Option<LiveEvent> {
+        if self.kind != EventKind::LiveEvent {
+            return None;
+        }
+        Some(LiveEvent::from_tags(&self.tags))
+    }
+
+    /// If this is a NIP-52 calendar event (kind 31922 or 31923), get its details
+    pub fn calendar_event(&self) -> Option<CalendarEvent> {
+        CalendarEvent::from_tags(self.kind, &self.tags, &self.content)
+    }
+
+    /// If this is a NIP-52 calendar event RSVP (kind 31925), get its details
+    pub fn calendar_rsvp(&self) -> Option<CalendarRsvp> {
+        if self.kind != EventKind::CalendarEventRsvp {
+            return None;
+        }
+        CalendarRsvp::from_tags(&self.tags, &self.content)
+    }
+
+    /// If this is a NIP-54 wiki article (kind 30818), get its details
+    pub fn wiki_article(&self) -> Option<WikiArticle> {
+        if self.kind != EventKind::WikiArticle {
+            return None;
+        }
+        Some(WikiArticle::from_tags(&self.tags, &self.content))
+    }
+
+    /// If this is a metadata event (kind 0), the identities it claims on
+    /// other platforms (NIP-39). These are only claims until the proofs are
+    /// checked.
+    pub fn external_identities(&self) -> Vec<ExternalIdentity> {
+        if self.kind != EventKind::Metadata {
+            return vec![];
+        }
+        self.tags
+            .iter()
+            .filter_map(|t| t.parse_external_identity().ok())
+            .collect()
+    }
+
+    /// If this is a NIP-84 highlight (kind 9802), get its details
+    pub fn highlight(&self) -> Option<Highlight> {
+        if self.kind != EventKind::Highlight {
+            return None;
+        }
+        Some(Highlight::from_tags(&self.tags, &self.content))
+    }
+
+    /// If this is a NIP-03 OpenTimestamps attestation (kind 1040) of another
+    /// event, get its details
+    pub fn ots_attestation(&self) -> Option<OtsAttestation> {
+        if self.kind != EventKind::OpenTimestamps {
+            return None;
+        }
+        OtsAttestation::from_tags(&self.tags, &self.content)
+    }
+
+    /// If this is a NIP-35 torrent (kind 2003) with a valid infohash, get its
+    /// details
+    pub fn torrent(&self) -> Option<Torrent> {
+        if self.kind != EventKind::Torrent {
+            return None;
+        }
+        Torrent::from_tags(&self.tags, &self.content)
+    }
+
+    /// If this is a NIP-99 classified listing (kind 30402, or a 30403 draft),
+    /// get its details
+    pub fn classified_listing(&self) -> Option<ClassifiedListing> {
+        if !matches!(
+            self.kind,
+            EventKind::ClassifiedListing | EventKind::DraftClassifiedListing
+        ) {
+            return None;
+        }
+        Some(ClassifiedListing::from_tags(&self.tags, &self.content))
+    }
+
+    /// If this is a NIP-53 live chat message (kind 1311), get the address of
+    /// the live event it belongs to
+    pub fn live_chat_anchor(&self) -> Option<EventAddr> {
+        if self.kind != EventKind::LiveChatMessage {
+            return None;
+        }
+        self.tags
+            .iter()
+            .filter_map(|t| t.parse_address().ok())
+            .map(|(ea, _)| ea)
+            .find(|ea| ea.kind == EventKind::LiveEvent)
+    }
+
+    /// Get the NIP-32 labels on this event, grouped by namespace.
+    ///
+    /// Labels without a namespace are placed in the "ugc" namespace.
+    pub fn labels(&self) -> HashMap<String, Vec<String>> {
+        let mut output: HashMap<String, Vec<String>> = HashMap::new();
+
+        for tag in self.tags.iter() {
+            if let Ok((label, namespace)) = tag.parse_label() {
+                let namespace = match namespace.as_deref() {
+                    Some(ns) if !ns.is_empty() => ns,
+                    _ => "ugc",
+                };
+                output.entry(namespace.to_owned()).or_default().push(label);
+            }
+        }
+
+        output
+    }
+
     /// Return all the hashtags this event refers to
     pub fn hashtags(&self) -> Vec<String> {
         if !self.kind.is_feed_displayable() {
@@ -923,8 +2343,8 @@ impl Event {
         let mut output: Vec<String> = Vec::new();
 
         for tag in self.tags.iter() {
-            if let Tag::Hashtag { hashtag, .. } = tag {
-                output.push(hashtag.clone());
+            if let Ok(hashtag) = tag.parse_hashtag() {
+                output.push(hashtag);
             }
         }
 
@@ -940,8 +2360,8 @@ impl Event {
         let mut output: Vec<RelayUrl> = Vec::new();
 
         for tag in self.tags.iter() {
-            if let Tag::Reference { url, .. } = tag {
-                if let Ok(relay_url) = RelayUrl::try_from_unchecked_url(url) {
+            if let Ok((url, _)) = tag.parse_reference() {
+                if let Ok(relay_url) = RelayUrl::try_from_unchecked_url(&url) {
                     output.push(relay_url);
                 }
             }
@@ -951,41 +2371,70 @@ impl Event {
     }
 
     /// Get the proof-of-work count of leading bits
+    ///
+    /// This is capped at the target committed to in the 'nonce' tag, so that
+    /// work done for a lower target which happened to get lucky is not counted.
     pub fn pow(&self) -> u8 {
         // Count leading bits in the Id field
         let zeroes: u8 = get_leading_zero_bits(&self.id.0);
 
         // Check that they meant it
-        let mut target_zeroes: u8 = 0;
-        for tag in self.tags.iter() {
-            if let Tag::Nonce { target, .. } = tag {
-                if let Some(t) = target {
-                    target_zeroes = t.parse::<u8>().unwrap_or(0);
-                }
-                break;
+        let target_zeroes = self
+            .tags
+            .iter()
+            .find(|t| t.tagname() == "nonce")
+            .and_then(|t| t.parse_nonce().ok())
+            .and_then(|(_, target)| target)
+            .unwrap_or(0);
+
+        zeroes.min(target_zeroes.min(255) as u8)
+    }
+
+    /// Check that this event has at least `min_bits` of proof-of-work (NIP-13),
+    /// returning the number of leading zero bits in the Id.
+    ///
+    /// Both the Id must have enough leading zero bits, and the 'nonce' tag must
+    /// commit to a target of at least `min_bits`, so that spammers cannot get
+    /// through by mining many events at a lower target and sending only the lucky
+    /// ones. The error says which of these failed.
+    pub fn pow_valid(&self, min_bits: u8) -> Result<u8, Error> {
+        let zeroes: u8 = get_leading_zero_bits(&self.id.0);
+        if min_bits == 0 {
+            return Ok(zeroes);
+        }
+        if zeroes < min_bits {
+            return Err(Error::InsufficientPow(zeroes, min_bits));
+        }
+        let nonce_tag = self
+            .tags
+            .iter()
+            .find(|t| t.tagname() == "nonce")
+            .ok_or(Error::PowTargetMissing)?;
+        match nonce_tag.parse_nonce()? {
+            (_, None) => Err(Error::PowTargetMissing),
+            (_, Some(target)) if target < min_bits as u32 => {
+                Err(Error::PowTargetTooLow(target, min_bits))
             }
+            _ => Ok(zeroes),
         }
-
-        zeroes.min(target_zeroes)
     }
 
     /// Was this event delegated, was that valid, and if so what is the pubkey of
     /// the delegator?
     pub fn delegation(&self) -> EventDelegation {
         for tag in self.tags.iter() {
-            if let Tag::Delegation {
-                pubkey,
-                conditions,
-                sig,
-                ..
-            } = tag
-            {
+            if tag.tagname() == "delegation" {
+                let (pubkey, conditions, sig) = match tag.parse_delegation() {
+                    Ok(parts) => parts,
+                    Err(e) => return EventDelegation::InvalidDelegation(format!("{e}")),
+                };
+
                 // Convert hex strings into functional types
-                let signature = match Signature::try_from_hex_string(sig) {
+                let signature = match Signature::try_from_hex_string(&sig) {
                     Ok(sig) => sig,
                     Err(e) => return EventDelegation::InvalidDelegation(format!("{e}")),
                 };
-                let delegator_pubkey = match PublicKey::try_from_hex_string(pubkey) {
+                let delegator_pubkey = match PublicKey::try_from_hex_string(&pubkey) {
                     Ok(pk) => pk,
                     Err(e) => return EventDelegation::InvalidDelegation(format!("{e}")),
                 };
@@ -994,26 +2443,8 @@ impl Event {
                 match conditions.verify_signature(&delegator_pubkey, &self.pubkey, signature) {
                     Ok(_) => {
                         // Check conditions
-                        if let Some(kind) = conditions.kind {
-                            if self.kind != kind {
-                                return EventDelegation::InvalidDelegation(
-                                    "Event Kind not delegated".to_owned(),
-                                );
-                            }
-                        }
-                        if let Some(created_after) = conditions.created_after {
-                            if self.created_at < created_after {
-                                return EventDelegation::InvalidDelegation(
-                                    "Event created before delegation started".to_owned(),
-                                );
-                            }
-                        }
-                        if let Some(created_before) = conditions.created_before {
-                            if self.created_at > created_before {
-                                return EventDelegation::InvalidDelegation(
-                                    "Event created after delegation ended".to_owned(),
-                                );
-                            }
+                        if let Some(reason) = conditions.mismatch(self) {
+                            return EventDelegation::InvalidDelegation(reason.to_owned());
                         }
                         return EventDelegation::DelegatedBy(delegator_pubkey);
                     }
@@ -1029,7 +2460,7 @@ impl Event {
 }
 
 #[inline]
-fn get_leading_zero_bits(bytes: &[u8]) -> u8 {
+pub(crate) fn get_leading_zero_bits(bytes: &[u8]) -> u8 {
     let mut res = 0_u8;
     for b in bytes {
         if *b == 0 {
@@ -1042,9 +2473,167 @@ fn get_leading_zero_bits(bytes: &[u8]) -> u8 {
     res
 }
 
+impl Serialize for Event {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let len = 7 + usize::from(self.ots.is_some()) + self.extra.len();
+        let mut map = serializer.serialize_map(Some(len))?;
+        map.serialize_entry("id", &self.id)?;
+        map.serialize_entry("pubkey", &self.pubkey)?;
+        map.serialize_entry("created_at", &self.created_at)?;
+        map.serialize_entry("kind", &self.kind)?;
+        map.serialize_entry("tags", &self.tags)?;
+        map.serialize_entry("content", &self.content)?;
+        if let Some(ots) = &self.ots {
+            map.serialize_entry("ots", ots)?;
+        }
+        map.serialize_entry("sig", &self.sig)?;
+        for (key, value) in &self.extra {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Event {
+    /// Fields may come in any order. A field given twice is an error, and
+    /// unknown fields are kept in `extra`. This is `from_json_limited()`
+    /// with no limits, so the two cannot differ.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        super::parse_limits::deserialize_event(deserializer, &ParseLimits::UNLIMITED)
+    }
+}
+
+impl Ord for Event {
+    /// Feed order: newest first, then by id. Events with the same id (which
+    /// differ only in their signature or are corrupt) are ordered by their
+    /// fields so that the ordering agrees with `Eq`.
+    fn cmp(&self, other: &Event) -> std::cmp::Ordering {
+        other
+            .created_at
+            .cmp(&self.created_at)
+            .then_with(|| self.id.cmp(&other.id))
+            .then_with(|| self.sig.0.cmp(&other.sig.0))
+            .then_with(|| self.content.cmp(&other.content))
+            .then_with(|| {
+                let fields = other.tags.iter().map(Tag::fields);
+                self.tags.iter().map(Tag::fields).cmp(fields)
+            })
+            .then_with(|| u32::from(self.kind).cmp(&u32::from(other.kind)))
+            .then_with(|| self.pubkey.as_bytes().cmp(&other.pubkey.as_bytes()))
+            .then_with(|| self.ots.cmp(&other.ots))
+            .then_with(|| cmp_map(&self.extra, &other.extra))
+    }
+}
+
+// A total order on JSON values that agrees with their equality. Numbers are
+// compared as numbers where they can be, falling back to their text.
+fn cmp_value(a: &Value, b: &Value) -> std::cmp::Ordering {
+    fn rank(v: &Value) -> u8 {
+        match v {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Number(_) => 2,
+            Value::String(_) => 3,
+            Value::Array(_) => 4,
+            Value::Object(_) => 5,
+        }
+    }
+
+    match (a, b) {
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::Number(a), Value::Number(b)) => match (a.as_f64(), b.as_f64()) {
+            (Some(x), Some(y)) if x != y => x.partial_cmp(&y).unwrap_or(std::cmp::Ordering::Equal),
+            _ => a.to_string().cmp(&b.to_string()),
+        },
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Array(a), Value::Array(b)) => a
+            .iter()
+            .zip(b.iter())
+            .map(|(x, y)| cmp_value(x, y))
+            .find(|o| o.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        (Value::Object(a), Value::Object(b)) => cmp_map(a, b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn cmp_map(a: &Map<String, Value>, b: &Map<String, Value>) -> std::cmp::Ordering {
+    // Sorted by key, as serde_json may be built to keep insertion order instead
+    let mut a: Vec<(&String, &Value)> = a.iter().collect();
+    let mut b: Vec<(&String, &Value)> = b.iter().collect();
+    a.sort_by(|x, y| x.0.cmp(y.0));
+    b.sort_by(|x, y| x.0.cmp(y.0));
+    a.iter()
+        .zip(b.iter())
+        .map(|(x, y)| x.0.cmp(y.0).then_with(|| cmp_value(x.1, y.1)))
+        .find(|o| o.is_ne())
+        .unwrap_or_else(|| a.len().cmp(&b.len()))
+}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Event) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Counts the bytes written to it
+pub(super) struct ByteCounter(pub(super) usize);
+
+impl std::io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// Encrypt to the NIP-04 content form: the base64 ciphertext, then "?iv=" and
+// the base64 IV
+#[cfg(feature = "std")]
+pub(super) fn nip04_content(
+    private_key: &PrivateKey,
+    other: &PublicKey,
+    plaintext: &[u8],
+) -> Result<String, Error> {
+    let (iv, ciphertext) = private_key.nip04_encrypt(other, plaintext)?;
+    Ok(format!(
+        "{}?iv={}",
+        base64::engine::general_purpose::STANDARD.encode(ciphertext),
+        base64::engine::general_purpose::STANDARD.encode(iv)
+    ))
+}
+
+// Decrypt NIP-04 content
+pub(super) fn nip04_plaintext(
+    private_key: &PrivateKey,
+    other: &PublicKey,
+    content: &str,
+) -> Result<Vec<u8>, Error> {
+    let parts: Vec<&str> = content.split("?iv=").collect();
+    if parts.len() != 2 {
+        return Err(Error::BadEncryptedMessage);
+    }
+
+    let ciphertext: Vec<u8> = base64::engine::general_purpose::STANDARD.decode(parts[0])?;
+    let iv_vec: Vec<u8> = base64::engine::general_purpose::STANDARD.decode(parts[1])?;
+    let iv: [u8; 16] = iv_vec.try_into().map_err(|_| Error::BadEncryptedMessage)?;
+
+    private_key.nip04_decrypt(other, &ciphertext, iv)
+}
+
 #[cfg(test)]
 mod test {
     use crate::types::*;
+    use crate::Error;
 
     test_serde! {Event, test_event_serde}
 
@@ -1056,12 +2645,7 @@ mod test {
             pubkey,
             created_at: Unixtime::mock(),
             kind: EventKind::TextNote,
-            tags: vec![Tag::Event {
-                id: Id::mock(),
-                recommended_relay_url: Some(UncheckedUrl::mock()),
-                marker: None,
-                trailing: Vec::new(),
-            }],
+            tags: vec![Tag::new_event(Id::mock(), Some(UncheckedUrl::mock()), None)],
             content: "Hello World!".to_string(),
             ots: None,
         };
@@ -1107,19 +2691,12 @@ mod test {
             created_at,
             kind: EventKind::TextNote,
             tags: vec![
-                Tag::Event {
-                    id: Id::mock(),
-                    recommended_relay_url: Some(UncheckedUrl::mock()),
-                    marker: None,
-                    trailing: Vec::new(),
-                },
-                Tag::Delegation {
-                    pubkey: PublicKeyHex::try_from_string(delegator_pubkey.as_hex_string())
-                        .unwrap(),
+                Tag::new_event(Id::mock(), Some(UncheckedUrl::mock()), None),
+                Tag::new_delegation(
+                    PublicKeyHex::try_from_string(delegator_pubkey.as_hex_string()).unwrap(),
                     conditions,
                     sig,
-                    trailing: Vec::new(),
-                },
+                ),
             ],
             content: "Hello World!".to_string(),
             ots: None,
@@ -1179,9 +2756,1213 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_validate() {
+        let privkey = PrivateKey::mock();
+        let pre = |kind: EventKind, tags: Vec<Tag>, content: &str| PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime(1680000012),
+            kind,
+            tags,
+            content: content.to_owned(),
+            ots: None,
+        };
+        let p_tag = Tag::new_pubkey(PublicKeyHex::mock(), None, None);
+
+        assert!(pre(EventKind::Metadata, vec![], r#"{"name":"x"}"#)
+            .validate()
+            .is_ok());
+        assert!(pre(EventKind::Metadata, vec![], "x").validate().is_err());
+
+        assert!(pre(EventKind::ContactList, vec![p_tag.clone()], "")
+            .validate()
+            .is_ok());
+        let mixed = vec![p_tag.clone(), Tag::new_hashtag("nostr".to_owned())];
+        assert!(pre(EventKind::ContactList, mixed, "").validate().is_err());
+
+        assert!(pre(EventKind::Reaction, vec![p_tag.clone()], "+")
+            .validate()
+            .is_err());
+
+        let relays = Tag::new(&["relays", "wss://relay.example.com"]);
+        let zap = pre(
+            EventKind::ZapRequest,
+            vec![
+                p_tag.clone(),
+                relays.clone(),
+                Tag::new(&["amount", "21000"]),
+            ],
+            "",
+        );
+        assert!(zap.validate().is_ok());
+        let zap = pre(
+            EventKind::ZapRequest,
+            vec![p_tag, relays, Tag::new(&["amount", "lots"])],
+            "",
+        );
+        assert!(zap.validate().is_err());
+
+        let article = pre(EventKind::LongFormContent, vec![], "# Title");
+        assert!(matches!(
+            Event::new_validated(article.clone(), &privkey),
+            Err(Error::InvalidEvent(_))
+        ));
+        let mut article = article;
+        article.tags.push(Tag::new_identifier("title".to_owned()));
+        assert!(Event::new_validated(article, &privkey).is_ok());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_validate_zap_request() {
+        let privkey = PrivateKey::mock();
+        let zap_request = |tags: Vec<Tag>| {
+            let mut pre = PreEvent {
+                pubkey: privkey.public_key(),
+                created_at: Unixtime(1680000012),
+                kind: EventKind::ZapRequest,
+                tags: vec![
+                    Tag::new_pubkey(PublicKeyHex::mock(), None, None),
+                    Tag::new(&["relays", "wss://relay.example.com"]),
+                ],
+                content: "".to_string(),
+                ots: None,
+            };
+            pre.tags.extend(tags);
+            Event::new(pre, &privkey).unwrap()
+        };
+
+        let event = Event::new_zap_request(
+            &privkey,
+            PublicKeyHex::mock(),
+            Some(Id::mock()),
+            21000,
+            vec!["wss://relay.example.com".to_owned()],
+            "Onward!".to_owned(),
+        )
+        .unwrap();
+        assert!(event.validate_zap_request(None).is_ok());
+        assert!(event
+            .validate_zap_request(Some(MilliSatoshi(21000)))
+            .is_ok());
+        assert!(matches!(
+            event.validate_zap_request(Some(MilliSatoshi(1000))),
+            Err(Error::InvalidZapRequest(_))
+        ));
+
+        let mut tampered = event.clone();
+        tampered.content = "Backward!".to_owned();
+        assert!(tampered.validate_zap_request(None).is_err());
+
+        let two_events = zap_request(vec![
+            Tag::new_event(Id::mock(), None, None),
+            Tag::new_event(Id::mock(), None, None),
+        ]);
+        assert!(two_events.validate_zap_request(None).is_err());
+        let two_people = zap_request(vec![Tag::new_pubkey(PublicKeyHex::mock(), None, None)]);
+        assert!(two_people.validate_zap_request(None).is_err());
+        let bad_address = zap_request(vec![Tag::new(&["a", "not:an:address"])]);
+        assert!(bad_address.validate_zap_request(None).is_err());
+        assert!(zap_request(vec![]).validate_zap_request(None).is_ok());
+
+        assert!(matches!(
+            Event::mock().validate_zap_request(None),
+            Err(Error::WrongEventKind)
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_zap_receipt_parts() {
+        let zapper = PrivateKey::mock();
+        let sender = PrivateKey::mock();
+        let address = EventAddr::mock();
+        let request = Event::new_zap_request(
+            &sender,
+            address.author.into(),
+            None,
+            21000,
+            vec!["wss://relay.example.com".to_owned()],
+            "Onward!".to_owned(),
+        )
+        .unwrap();
+        let receipt = |description: &Event| {
+            let pre = PreEvent {
+                pubkey: zapper.public_key(),
+                created_at: Unixtime(1680000012),
+                kind: EventKind::Zap,
+                tags: vec![
+                    Tag::new_pubkey(address.author.into(), None, None),
+                    Tag::new_address(&address, None),
+                    Tag::new(&["P", &PublicKey::mock().as_hex_string()]),
+                    Tag::new(&["description", &serde_json::to_string(description).unwrap()]),
+                ],
+                content: "".to_owned(),
+                ots: None,
+            };
+            Event::new(pre, &zapper).unwrap()
+        };
+
+        // A zap on an address alone is a zap on an event
+        let event = receipt(&request);
+        let parts = event.zap_receipt_parts();
+        assert_eq!(parts.id, None);
+        let zapped = parts.address.unwrap();
+        assert_eq!((zapped.d, zapped.kind), (address.d.clone(), address.kind));
+        assert_eq!(parts.sender, Some(sender.public_key()));
+        assert_eq!(parts.comment.as_deref(), Some("Onward!"));
+        // It has no invoice, so it is an invalid zap, not a zap of no event
+        assert!(matches!(event.zaps(), Err(Error::ZapReceipt(_))));
+
+        // A description that was changed after signing is not believed, not
+        // even as far as the 'P' tag
+        let mut forged = request.clone();
+        forged.content = "Send your sats to me instead".to_owned();
+        let parts = receipt(&forged).zap_receipt_parts();
+        assert_eq!(parts.sender, None);
+        assert_eq!(parts.comment, None);
+
+        let mut person_zap = receipt(&request);
+        person_zap.tags.retain(|t| t.tagname() != "a");
+        assert!(matches!(person_zap.zaps(), Ok(None)));
+    }
+
+    #[test]
+    fn test_relay_hints() {
+        let raw = r##"{"id":"7760408f6459b9546c3a4e70e3e56756421fba34526b7d460db3fcfd2f8817db","pubkey":"460c25e682fda7832b52d1f22d3d22b3176d972f60dcdc3212ed8c92ef85065c","created_at":1687616920,"kind":1,"tags":[["e","7760408f6459b9546c3a4e70e3e56756421fba34526b7d460db3fcfd2f8817db","wss://nostr.example.com/","root"],["p","1bc70a0148b3f316da33fe3c89f23e3e71ac4ff998027ec712b905cd24f6a411","relay.example.com"],["a","30023:1bc70a0148b3f316da33fe3c89f23e3e71ac4ff998027ec712b905cd24f6a411:x","wss://one.example.com","","wss://two.example.com"],["t","wss://not.a.hint"]],"content":"","sig":"dbc5d05a24bfe990a1faaedfcb81a98940d86a105711dbdad9145d05b0ad0f46e3e24eaa3fc283818f27e057fe836a029fd9a68e7f1de06ff477493199d64064"}"##;
+        let event: Event = serde_json::from_str(raw).unwrap();
+
+        let hints = event.relay_hints();
+        let sources: Vec<RelayHintSource> = hints.iter().map(|h| h.source).collect();
+        assert_eq!(
+            sources,
+            vec![
+                RelayHintSource::EventTag,
+                RelayHintSource::PubkeyTag,
+                // Only the usual place; fields past the marker are not hints
+                RelayHintSource::AddressTag,
+            ]
+        );
+        assert!(!hints[1].is_valid());
+
+        // The malformed hint is kept alongside the missing relay
+        let people = event.people_refs();
+        assert_eq!(people[0].relay, None);
+        assert_eq!(
+            people[0].hint.as_ref().unwrap().url.as_str(),
+            "relay.example.com"
+        );
+        let events = event.referred_event_refs();
+        assert!(events[0].relay.is_some());
+        assert_eq!(events[0].hint, Some(hints[0].clone()));
+    }
+
+    #[test]
+    fn test_time_window_tags() {
+        let privkey = PrivateKey::mock();
+        let mut pre = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime(1_700_000_000),
+            kind: EventKind::LongFormContent,
+            tags: vec![Tag::new_identifier("article".to_owned())],
+            content: "# Article".to_owned(),
+            ots: None,
+        };
+        let event = Event::new(pre.clone(), &privkey).unwrap();
+        assert_eq!(event.published_at(), None);
+        assert_eq!(event.expiration(), None);
+        assert!(!event.is_expired(Unixtime(i64::MAX)));
+        assert_eq!(event.display_time(), event.created_at);
+
+        pre.set_published_at(Unixtime(1_600_000_000));
+        pre.set_published_at(Unixtime(1_650_000_000));
+        pre.set_expiration(Unixtime(1_800_000_000));
+        let event = Event::new(pre.clone(), &privkey).unwrap();
+        assert_eq!(event.tags.len(), 3);
+        assert_eq!(event.published_at(), Some(Unixtime(1_650_000_000)));
+        assert_eq!(event.display_time(), Unixtime(1_650_000_000));
+        assert_eq!(event.expiration(), Some(Unixtime(1_800_000_000)));
+        assert!(!event.is_expired(Unixtime(1_799_999_999)));
+        assert!(event.is_expired(Unixtime(1_800_000_000)));
+
+        // A publication time in the future of the event is not believed
+        pre.set_published_at(Unixtime(1_750_000_000));
+        let event = Event::new(pre, &privkey).unwrap();
+        assert_eq!(event.display_time(), event.created_at);
+    }
+
+    #[test]
+    fn test_alt_and_content_warning() {
+        let privkey = PrivateKey::mock();
+        let event = |tags: Vec<Tag>| {
+            let pre = PreEvent {
+                pubkey: privkey.public_key(),
+                created_at: Unixtime(1680000012),
+                kind: EventKind::Other(31999),
+                tags,
+                content: "".to_string(),
+                ots: None,
+            };
+            Event::new(pre, &privkey).unwrap()
+        };
+
+        let plain = event(vec![Tag::new_alt("A poll about lunch".to_owned())]);
+        assert_eq!(
+            plain.alt_description().as_deref(),
+            Some("A poll about lunch")
+        );
+        assert_eq!(plain.content_warning(), None);
+
+        let bare = event(vec![Tag::new_content_warning(None)]);
+        assert_eq!(bare.content_warning().as_deref(), Some(""));
+        assert_eq!(bare.alt_description(), None);
+
+        let labelled = event(vec![
+            Tag::new_label_namespace("content-warning".to_owned()),
+            Tag::new_label("spoilers".to_owned(), Some("content-warning".to_owned())),
+        ]);
+        assert_eq!(labelled.content_warning().as_deref(), Some("spoilers"));
+
+        let other_label = event(vec![Tag::new_label("spoilers".to_owned(), None)]);
+        assert_eq!(other_label.content_warning(), None);
+    }
+
+    #[test]
+    fn test_protected() {
+        let privkey = PrivateKey::mock();
+        let mut pre = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime(1680000012),
+            kind: EventKind::TextNote,
+            tags: vec![],
+            content: "members only".to_string(),
+            ots: None,
+        };
+        let open = Event::new(pre.clone(), &privkey).unwrap();
+        assert!(!open.is_protected());
+        assert!(open.may_be_published_by(None));
+
+        pre.mark_protected();
+        pre.mark_protected();
+        assert_eq!(pre.tags, vec![Tag::new_protected()]);
+        let event = Event::new(pre, &privkey).unwrap();
+        assert!(serde_json::to_string(&event)
+            .unwrap()
+            .contains(r#""tags":[["-"]]"#));
+        assert!(event.is_protected());
+        assert!(!event.may_be_published_by(None));
+        assert!(!event.may_be_published_by(Some(&PrivateKey::generate().public_key())));
+        assert!(event.may_be_published_by(Some(&privkey.public_key())));
+    }
+
+    #[test]
+    fn test_event_with_delegation_multiple_kinds() {
+        let privkey = PrivateKey::mock();
+        let delegator_privkey = PrivateKey::generate();
+        let delegator_pubkey = delegator_privkey.public_key();
+        let conditions = DelegationConditions::new()
+            .kind(EventKind::TextNote)
+            .kind(EventKind::Reaction);
+        let sig = conditions
+            .generate_signature(PublicKeyHex::from(privkey.public_key()), delegator_privkey)
+            .unwrap();
+        let delegated = |kind: EventKind| {
+            let preevent = PreEvent {
+                pubkey: privkey.public_key(),
+                created_at: Unixtime(1680000012),
+                kind,
+                tags: vec![Tag::new_delegation(
+                    PublicKeyHex::from(delegator_pubkey),
+                    conditions.clone(),
+                    sig.clone(),
+                )],
+                content: "+".to_string(),
+                ots: None,
+            };
+            Event::new(preevent, &privkey).unwrap()
+        };
+
+        let reaction = delegated(EventKind::Reaction);
+        assert!(conditions.matches_event(&reaction));
+        assert_eq!(
+            reaction.delegation(),
+            EventDelegation::DelegatedBy(delegator_pubkey)
+        );
+        assert_eq!(
+            delegated(EventKind::Repost).delegation(),
+            EventDelegation::InvalidDelegation("Event Kind not delegated".to_owned())
+        );
+    }
+
     #[test]
     fn test_realworld_event_with_naddr_tag() {
         let raw = r##"{"id":"7760408f6459b9546c3a4e70e3e56756421fba34526b7d460db3fcfd2f8817db","pubkey":"460c25e682fda7832b52d1f22d3d22b3176d972f60dcdc3212ed8c92ef85065c","created_at":1687616920,"kind":1,"tags":[["p","1bc70a0148b3f316da33fe3c89f23e3e71ac4ff998027ec712b905cd24f6a411","","mention"],["a","30311:1bc70a0148b3f316da33fe3c89f23e3e71ac4ff998027ec712b905cd24f6a411:1687612774","","mention"]],"content":"Watching Karnage's stream to see if I learn something about design. \n\nnostr:naddr1qq9rzd3cxumrzv3hxu6qygqmcu9qzj9n7vtd5vl78jyly037wxkyl7vcqflvwy4eqhxjfa4yzypsgqqqwens0qfplk","sig":"dbc5d05a24bfe990a1faaedfcb81a98940d86a105711dbdad9145d05b0ad0f46e3e24eaa3fc283818f27e057fe836a029fd9a68e7f1de06ff477493199d64064"}"##;
-        let _: Event = serde_json::from_str(&raw).unwrap();
+        let event: Event = serde_json::from_str(raw).unwrap();
+        let addresses = event.referred_addresses();
+        assert_eq!(addresses.len(), 1);
+        assert_eq!(addresses[0].addr.kind, EventKind::LiveEvent);
+        assert!(addresses[0].addr.relays.is_empty());
+        assert_eq!(addresses[0].marker.as_deref(), Some("mention"));
+    }
+
+    #[test]
+    fn test_people_verified() {
+        // About half of all x coordinates are not on the curve
+        let garbage = (1u32..)
+            .map(|n| format!("{n:064x}"))
+            .find(|hex| PublicKey::try_from_hex_string(hex).is_err())
+            .unwrap();
+        let valid = PublicKeyHex::mock_deterministic();
+
+        let mut event = Event::mock();
+        event.tags = vec![
+            Tag::new(&["p", &garbage]),
+            Tag::new_pubkey(valid.clone(), None, Some("alice".to_owned())),
+            Tag::new(&["p", "not hex"]),
+        ];
+        assert_eq!(event.people_refs().len(), 2);
+        assert!(event.people_refs()[0].public_key().is_err());
+
+        let verified = event.people_verified();
+        assert_eq!(verified.len(), 1);
+        assert_eq!(verified[0].pubkey, valid);
+        assert_eq!(verified[0].petname.as_deref(), Some("alice"));
+        assert!(verified[0].public_key().is_ok());
+
+        // The curve error is kept as the source
+        let err = event.tags[0].parse_pubkey_strict().unwrap_err();
+        assert!(matches!(err, Error::Context { .. }));
+        assert!(std::error::Error::source(&err).is_some());
+        let (pubkey, _, _) = event.tags[1].parse_pubkey_strict().unwrap();
+        assert_eq!(pubkey, PublicKey::mock_deterministic());
+    }
+
+    #[test]
+    fn test_event_references() {
+        let raw = r##"{"id":"7760408f6459b9546c3a4e70e3e56756421fba34526b7d460db3fcfd2f8817db","pubkey":"460c25e682fda7832b52d1f22d3d22b3176d972f60dcdc3212ed8c92ef85065c","created_at":1687616920,"kind":1,"tags":[["e","7760408f6459b9546c3a4e70e3e56756421fba34526b7d460db3fcfd2f8817db","wss://nostr.example.com/","root"],["e","dbc5d05a24bfe990a1faaedfcb81a98940d86a105711dbdad9145d05b0ad0f46","","mention"],["p","1bc70a0148b3f316da33fe3c89f23e3e71ac4ff998027ec712b905cd24f6a411","","fred"]],"content":"hello #[2]","sig":"dbc5d05a24bfe990a1faaedfcb81a98940d86a105711dbdad9145d05b0ad0f46e3e24eaa3fc283818f27e057fe836a029fd9a68e7f1de06ff477493199d64064"}"##;
+        let event: Event = serde_json::from_str(raw).unwrap();
+
+        let people = event.people_refs();
+        assert_eq!(people.len(), 1);
+        assert_eq!(people[0].relay, None);
+        assert_eq!(people[0].petname.as_deref(), Some("fred"));
+        assert_eq!(event.referenced_people_refs(), people);
+
+        let events = event.referred_event_refs();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].marker.as_deref(), Some("root"));
+        assert_eq!(
+            events[0].relay,
+            Some(RelayUrl::try_from_str("wss://nostr.example.com/").unwrap())
+        );
+
+        let mentions = event.mention_refs();
+        assert_eq!(mentions.len(), 1);
+        assert_eq!(mentions[0], events[1]);
+    }
+
+    #[test]
+    fn test_event_thread() {
+        let privkey = PrivateKey::mock();
+        let pubkey = privkey.public_key();
+        let ids: Vec<Id> = (0..4)
+            .map(|n| {
+                let mut bytes = [0; 32];
+                bytes[0] = n;
+                Id(bytes)
+            })
+            .collect();
+        let etag =
+            |id: Id, marker: Option<&str>| Tag::new_event(id, None, marker.map(|m| m.to_owned()));
+        let make = |tags: Vec<Tag>| {
+            Event::new(
+                PreEvent {
+                    pubkey,
+                    created_at: Unixtime::mock(),
+                    kind: EventKind::TextNote,
+                    tags,
+                    content: "".to_owned(),
+                    ots: None,
+                },
+                &privkey,
+            )
+            .unwrap()
+        };
+
+        // Positional
+        let thread = make(vec![
+            etag(ids[0], None),
+            etag(ids[1], None),
+            etag(ids[2], None),
+        ])
+        .thread();
+        assert_eq!(thread.root.unwrap().id, ids[0]);
+        assert_eq!(thread.reply_to.unwrap().id, ids[2]);
+        assert_eq!(thread.mentions.len(), 1);
+        assert_eq!(thread.mentions[0].id, ids[1]);
+
+        // Positional, single tag is both root and reply
+        let thread = make(vec![etag(ids[0], None)]).thread();
+        assert_eq!(thread.root, thread.reply_to);
+        assert!(thread.mentions.is_empty());
+
+        // Marked, in any order, with a quote
+        let thread = make(vec![
+            etag(ids[1], Some("reply")),
+            etag(ids[2], Some("mention")),
+            etag(ids[0], Some("root")),
+            Tag::new_quote(ids[3], None, Some(pubkey.into())),
+        ])
+        .thread();
+        assert_eq!(thread.root.unwrap().id, ids[0]);
+        assert_eq!(thread.reply_to.unwrap().id, ids[1]);
+        assert_eq!(
+            thread.mentions.iter().map(|m| m.id).collect::<Vec<Id>>(),
+            vec![ids[2], ids[3]]
+        );
+        assert_eq!(thread.mentions[1].author, Some(pubkey.into()));
+
+        // Marked, replying directly to the root
+        let thread = make(vec![etag(ids[0], Some("root"))]).thread();
+        assert_eq!(thread.reply_to.unwrap().id, ids[0]);
+
+        // Not a reply at all
+        assert_eq!(make(vec![]).thread(), Thread::default());
+
+        // Replies within the thread of an addressable event
+        let article = EventAddr {
+            d: "article".to_owned(),
+            relays: vec![UncheckedUrl("wss://relay.example.com/".to_owned())],
+            kind: EventKind::LongFormContent,
+            author: pubkey,
+        };
+        let other = EventAddr {
+            d: "other".to_owned(),
+            relays: vec![],
+            ..article.clone()
+        };
+        let thread = make(vec![
+            Tag::new_address(&article, Some("root".to_owned())),
+            Tag::new_address(&other, Some("mention".to_owned())),
+        ])
+        .thread();
+        assert!(thread.root.is_none());
+        assert_eq!(thread.root_address.as_ref().unwrap().addr, article);
+        assert_eq!(thread.reply_to_address, thread.root_address);
+        assert_eq!(thread.mentioned_addresses.len(), 1);
+        assert_eq!(thread.mentioned_addresses[0].addr, other);
+
+        let thread = make(vec![
+            Tag::new_address(&article, Some("root".to_owned())),
+            etag(ids[0], Some("root")),
+            etag(ids[1], Some("reply")),
+        ])
+        .thread();
+        assert_eq!(thread.root_address.unwrap().addr, article);
+        assert!(thread.reply_to_address.is_none());
+        assert_eq!(thread.reply_to.unwrap().id, ids[1]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_new_comment() {
+        let privkey = PrivateKey::mock();
+        let pubkey = privkey.public_key();
+
+        let article = Event::new(
+            PreEvent {
+                pubkey,
+                created_at: Unixtime::mock(),
+                kind: EventKind::LongFormContent,
+                tags: vec![Tag::new_identifier("my-article".to_owned())],
+                content: "".to_owned(),
+                ots: None,
+            },
+            &privkey,
+        )
+        .unwrap();
+
+        let pre = PreEvent::new_comment(pubkey, &article, "Nice".to_owned());
+        let comment = Event::new(pre, &privkey).unwrap();
+        let root = comment.comment_root().unwrap();
+        let parent = comment.comment_parent().unwrap();
+        assert_eq!(root, parent);
+        assert_eq!(root.event.as_ref().unwrap().id, article.id);
+        assert_eq!(root.address.as_ref().unwrap().d, "my-article");
+        assert_eq!(root.kind, Some(EventKind::LongFormContent));
+        assert_eq!(root.pubkey, Some(pubkey.into()));
+
+        // A reply to the comment keeps the root but changes the parent
+        let pre = PreEvent::new_comment(pubkey, &comment, "Thanks".to_owned());
+        let reply = Event::new(pre, &privkey).unwrap();
+        assert_eq!(reply.comment_root().unwrap(), root);
+        let parent = reply.comment_parent().unwrap();
+        assert_eq!(parent.event.unwrap().id, comment.id);
+        assert_eq!(parent.kind, Some(EventKind::Comment));
+        assert!(parent.address.is_none());
+
+        // Round trips through JSON
+        let json = serde_json::to_string(&reply).unwrap();
+        let reply2: Event = serde_json::from_str(&json).unwrap();
+        assert_eq!(reply, reply2);
+        assert!(reply2.verify(None).is_ok());
+
+        assert!(article.comment_root().is_none());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_labels() {
+        let privkey = PrivateKey::mock();
+        let target = Tag::new_pubkey(PublicKeyHex::mock_deterministic(), None, None);
+        let mut pre = PreEvent::new_label(
+            privkey.public_key(),
+            "com.example.ontology",
+            &["VI-hum", "IL-frd"],
+            vec![target.clone()],
+            "".to_owned(),
+        );
+        pre.tags.push(Tag::new_label("cool".to_owned(), None));
+        let event = Event::new(pre, &privkey).unwrap();
+        assert_eq!(event.kind, EventKind::Label);
+        assert!(event.tags.contains(&target));
+
+        let labels = event.labels();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(
+            labels.get("com.example.ontology").unwrap(),
+            &vec!["VI-hum".to_owned(), "IL-frd".to_owned()]
+        );
+        assert_eq!(labels.get("ugc").unwrap(), &vec!["cool".to_owned()]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_live_event() {
+        let privkey = PrivateKey::mock();
+        let live = LiveEvent::mock();
+        let pre = PreEvent::new_live_event(privkey.public_key(), &live);
+        let event = Event::new(pre, &privkey).unwrap();
+        assert_eq!(event.live_event(), Some(live));
+        assert_eq!(event.live_chat_anchor(), None);
+
+        // The naddr in test_realworld_event_with_naddr_tag
+        let addr = EventAddr::try_from_bech32_string(
+            "naddr1qq9rzd3cxumrzv3hxu6qygqmcu9qzj9n7vtd5vl78jyly037wxkyl7vcqflvwy4eqhxjfa4yzypsgqqqwens0qfplk",
+        )
+        .unwrap();
+        assert_eq!(addr.kind, EventKind::LiveEvent);
+        let pre =
+            PreEvent::new_live_chat_message(privkey.public_key(), &addr, "gm".to_owned()).unwrap();
+        let chat = Event::new(pre, &privkey).unwrap();
+        assert_eq!(chat.live_chat_anchor(), Some(addr));
+        assert_eq!(chat.live_event(), None);
+
+        let addr = EventAddr {
+            kind: EventKind::LongFormContent,
+            ..event.address().unwrap()
+        };
+        assert!(
+            PreEvent::new_live_chat_message(privkey.public_key(), &addr, "gm".to_owned()).is_err()
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_calendar_event() {
+        let privkey = PrivateKey::mock();
+        let calendar_event = CalendarEvent::mock();
+        let pre = PreEvent::new_calendar_event(privkey.public_key(), &calendar_event);
+        let event = Event::new(pre, &privkey).unwrap();
+        assert_eq!(event.kind, EventKind::TimeBasedCalendarEvent);
+        assert_eq!(event.calendar_event(), Some(calendar_event));
+        assert_eq!(event.calendar_rsvp(), None);
+
+        let rsvp = CalendarRsvp {
+            event: event.address().unwrap(),
+            event_id: Some(event.id),
+            ..CalendarRsvp::mock()
+        };
+        let pre = PreEvent::new_calendar_rsvp(privkey.public_key(), &rsvp);
+        let event = Event::new(pre, &privkey).unwrap();
+        assert_eq!(event.calendar_rsvp(), Some(rsvp));
+        assert_eq!(event.calendar_event(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_wiki_article() {
+        let privkey = PrivateKey::mock();
+        let article = WikiArticle::mock();
+        let pre = PreEvent::new_wiki_article(privkey.public_key(), &article);
+        let event = Event::new(pre, &privkey).unwrap();
+        assert_eq!(event.wiki_article(), Some(article.clone()));
+
+        let other = PrivateKey::generate();
+        let mut fork = WikiArticle::fork(&event).unwrap();
+        fork.content.push_str("\nAnd more.");
+        let pre = PreEvent::new_wiki_article(other.public_key(), &fork);
+        let forked = Event::new(pre, &other).unwrap();
+        let fork = forked.wiki_article().unwrap();
+        assert!(fork.same_topic(&article));
+        let (addr, id) = fork.forked_from.unwrap();
+        assert!(event.matches_address(&addr));
+        assert_eq!(id, Some(event.id));
+        assert!(forked
+            .people_refs()
+            .iter()
+            .any(|p| p.pubkey == event.pubkey.into()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_highlight() {
+        let privkey = PrivateKey::mock();
+        let article = Event::mock();
+        let highlight = Highlight {
+            source: Some(HighlightSource::Event(article.id, None)),
+            ..Highlight::mock()
+        };
+        let pre = PreEvent::new_highlight(privkey.public_key(), &highlight);
+        let event = Event::new(pre, &privkey).unwrap();
+        assert_eq!(event.content, highlight.text);
+        assert_eq!(event.highlight(), Some(highlight));
+        assert_eq!(article.highlight(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_ots_attestation() {
+        let privkey = PrivateKey::mock();
+        let note = Event::mock();
+        let attestation = OtsAttestation::new(&note, None, OtsAttestation::mock().proof);
+        let pre = PreEvent::new_ots_attestation(privkey.public_key(), &attestation).unwrap();
+        let event = Event::new(pre, &privkey).unwrap();
+        assert_eq!(event.kind, EventKind::OpenTimestamps);
+        assert_eq!(event.ots_attestation(), Some(attestation.clone()));
+        assert_eq!(note.ots_attestation(), None);
+
+        let bad = OtsAttestation {
+            proof: "%%%".to_owned(),
+            ..attestation
+        };
+        assert!(PreEvent::new_ots_attestation(privkey.public_key(), &bad).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_nip04_multi_and_counterparty() {
+        let alice = PrivateKey::generate();
+        let bob = PrivateKey::generate();
+        let carol = PrivateKey::generate();
+        let recipients = [bob.public_key(), carol.public_key()];
+        let pres = PreEvent::new_nip04_multi(&alice, &recipients, "hi both").unwrap();
+        assert_eq!(pres.len(), 2);
+
+        let to_bob = Event::new(pres[0].clone(), &alice).unwrap();
+        let to_carol = Event::new(pres[1].clone(), &alice).unwrap();
+        assert_eq!(to_bob.decrypted_contents(&bob).unwrap(), "hi both");
+        assert_eq!(to_carol.decrypted_contents(&carol).unwrap(), "hi both");
+
+        let alice_pk = alice.public_key();
+        assert_eq!(to_bob.dm_counterparty(&alice_pk), Some(bob.public_key()));
+        assert_eq!(to_bob.dm_counterparty(&bob.public_key()), Some(alice_pk));
+        assert_eq!(to_bob.dm_counterparty(&carol.public_key()), None);
+
+        let note = Event::mock();
+        assert_eq!(note.dm_counterparty(&note.pubkey), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_reaction() {
+        let privkey = PrivateKey::mock();
+        let target = Event::mock();
+        let relay = UncheckedUrl::from_str("wss://relay.example.com");
+        let pre = PreEvent::new_reaction(
+            privkey.public_key(),
+            &target,
+            Some(relay.clone()),
+            "+".to_owned(),
+        );
+        assert!(pre.validate().is_ok());
+        let event = Event::new(pre, &privkey).unwrap();
+        assert_eq!(event.reaction_target_kind(), Some(target.kind));
+        let (id, content, _) = event.reacts_to().unwrap();
+        assert_eq!(id, target.id);
+        assert_eq!(content, "+");
+        let people = event.people_refs();
+        assert_eq!(people.len(), 1);
+        assert_eq!(people[0].pubkey, target.pubkey.into());
+        assert_eq!(
+            people[0].relay.as_ref().map(|r| r.as_str()),
+            Some("wss://relay.example.com/")
+        );
+        assert_eq!(target.reaction_target_kind(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_repost() {
+        let privkey = PrivateKey::mock();
+        let relay = UncheckedUrl::from_str("wss://relay.example.com");
+        let event = |kind: EventKind, tags: Vec<Tag>| {
+            let pre = PreEvent {
+                pubkey: privkey.public_key(),
+                created_at: Unixtime(1_700_000_000),
+                kind,
+                tags,
+                content: "hello".to_owned(),
+                ots: None,
+            };
+            Event::new(pre, &privkey).unwrap()
+        };
+        let note = event(EventKind::TextNote, vec![]);
+
+        let pre = PreEvent::new_repost(privkey.public_key(), &note, Some(relay.clone())).unwrap();
+        let repost = Event::new(pre, &privkey).unwrap();
+        assert_eq!(repost.kind, EventKind::Repost);
+        assert_eq!(repost.reposted_event(), Some(note.clone()));
+        assert!(repost.tags.iter().all(|t| t.tagname() != "k"));
+        assert_eq!(repost.mention_refs()[0].id, note.id);
+        assert_eq!(repost.people_refs()[0].pubkey, note.pubkey.into());
+
+        let article = event(
+            EventKind::LongFormContent,
+            vec![Tag::new_identifier("article".to_owned())],
+        );
+        let pre = PreEvent::new_repost(privkey.public_key(), &article, Some(relay)).unwrap();
+        let repost = Event::new(pre, &privkey).unwrap();
+        assert_eq!(repost.kind, EventKind::GenericRepost);
+        assert_eq!(
+            repost.tags.iter().find_map(|t| t.parse_kind().ok()),
+            Some(EventKind::LongFormContent)
+        );
+        assert_eq!(
+            repost.referred_addresses()[0].addr,
+            article.address().unwrap()
+        );
+        assert_eq!(repost.reposted_event(), Some(article));
+        assert_eq!(note.reposted_event(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_torrent() {
+        let privkey = PrivateKey::mock();
+        let torrent = Torrent::mock();
+        let pre = PreEvent::new_torrent(privkey.public_key(), &torrent).unwrap();
+        let event = Event::new(pre, &privkey).unwrap();
+        assert_eq!(event.torrent(), Some(torrent.clone()));
+
+        let bad = Torrent {
+            infohash: "abc".to_owned(),
+            ..torrent
+        };
+        assert!(matches!(
+            PreEvent::new_torrent(privkey.public_key(), &bad),
+            Err(Error::InvalidInfohash(_))
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_wallet_connect() {
+        let wallet_key = PrivateKey::mock();
+        let client_key = PrivateKey::generate();
+
+        let info = WalletInfo::mock();
+        let pre = PreEvent::new_wallet_info(wallet_key.public_key(), &info);
+        let event = Event::new(pre, &wallet_key).unwrap();
+        assert_eq!(event.wallet_info(), Some(info));
+
+        let request = WalletRequest::GetBalance {};
+        let pre =
+            PreEvent::new_wallet_request(&client_key, wallet_key.public_key(), &request).unwrap();
+        let request_event = Event::new(pre, &client_key).unwrap();
+        assert!(!request_event.content.contains("get_balance"));
+        assert_eq!(request_event.wallet_request(&wallet_key).unwrap(), request);
+        assert!(matches!(
+            request_event.wallet_response(&client_key),
+            Err(Error::WrongEventKind)
+        ));
+
+        let response = WalletResponse::mock();
+        let pre = PreEvent::new_wallet_response(&wallet_key, &request_event, &response).unwrap();
+        let response_event = Event::new(pre, &wallet_key).unwrap();
+        assert_eq!(
+            response_event.wallet_response(&client_key).unwrap(),
+            response
+        );
+        assert!(response_event
+            .tags
+            .contains(&Tag::new_event(request_event.id, None, None)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_classified_listing() {
+        let privkey = PrivateKey::mock();
+        let listing = ClassifiedListing::mock();
+        let pre = PreEvent::new_classified_listing(privkey.public_key(), &listing, true);
+        let event = Event::new(pre, &privkey).unwrap();
+        assert_eq!(event.kind, EventKind::DraftClassifiedListing);
+        assert_eq!(event.content, listing.description);
+        assert_eq!(event.classified_listing(), Some(listing));
+        assert_eq!(event.live_event(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_report() {
+        let privkey = PrivateKey::mock();
+        let bad_guy = PublicKeyHex::mock_deterministic();
+
+        let pre = PreEvent::new_report(
+            privkey.public_key(),
+            ReportTarget::Pubkey(bad_guy.clone()),
+            ReportType::Impersonation,
+            "Not the real one".to_owned(),
+        );
+        let event = Event::new(pre, &privkey).unwrap();
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.contains(&format!(r#"["p","{bad_guy}","impersonation"]"#)));
+        let event: Event = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            event.report(),
+            Some(Report {
+                target: ReportTarget::Pubkey(bad_guy.clone()),
+                report_type: ReportType::Impersonation,
+            })
+        );
+
+        let target = ReportTarget::Event {
+            id: Id::mock(),
+            author: bad_guy,
+        };
+        let pre = PreEvent::new_report(
+            privkey.public_key(),
+            target.clone(),
+            ReportType::Malware,
+            "".to_owned(),
+        );
+        let event = Event::new(pre, &privkey).unwrap();
+        let report = event.report().unwrap();
+        assert_eq!(report.target, target);
+        assert_eq!(report.report_type, ReportType::Malware);
+    }
+
+    #[test]
+    fn test_pre_event_hash() {
+        let privkey = PrivateKey::mock();
+        let pre = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime::mock(),
+            kind: EventKind::TextNote,
+            tags: vec![Tag::mock(), Tag::new_hashtag("nostr".to_owned())],
+            content: "Hello \"world\"\n".to_owned(),
+            ots: None,
+        };
+        let id = pre.hash();
+        assert!(pre.serialize_inner().starts_with("[0,\""));
+
+        // An external signer given the id produces a valid event
+        let sig = privkey.sign_id(id).unwrap();
+        let event = Event {
+            id,
+            pubkey: pre.pubkey,
+            created_at: pre.created_at,
+            kind: pre.kind,
+            tags: pre.tags.clone(),
+            content: pre.content.clone(),
+            ots: None,
+            sig,
+            extra: serde_json::Map::new(),
+        };
+        assert!(event.verify(None).is_ok());
+
+        let mut hasher = IdHasher::new(&pre.pubkey, pre.created_at, pre.kind);
+        for tag in pre.tags.iter() {
+            hasher.tag(tag);
+        }
+        assert_eq!(hasher.finish(&pre.content), id);
+
+        let hasher = IdHasher::new(&pre.pubkey, pre.created_at, pre.kind);
+        let no_tags = PreEvent {
+            tags: vec![],
+            ..pre.clone()
+        };
+        assert_eq!(hasher.finish(&pre.content), no_tags.hash());
+    }
+
+    #[test]
+    fn test_nip01_string_escaping() {
+        for (input, expected) in [
+            ("plain", "\"plain\""),
+            ("\"quoted\"", "\"\\\"quoted\\\"\""),
+            ("back\\slash", "\"back\\\\slash\""),
+            ("\n\r\t\u{8}\u{c}", "\"\\n\\r\\t\\b\\f\""),
+            // Other control characters are included verbatim
+            (
+                "\u{0}\u{1}\u{b}\u{1b}\u{1f}\u{7f}",
+                "\"\u{0}\u{1}\u{b}\u{1b}\u{1f}\u{7f}\"",
+            ),
+            // As are '/', non-ASCII and characters outside the BMP, which
+            // are never written as \u escapes or surrogate pairs
+            ("</script>", "\"</script>\""),
+            ("\u{2028}\u{2029}ü", "\"\u{2028}\u{2029}ü\""),
+            ("🦀𝄞", "\"🦀𝄞\""),
+        ] {
+            let mut output = String::new();
+            super::push_nip01_string(&mut output, input);
+            assert_eq!(output, expected, "{input:?}");
+        }
+
+        // The incremental hasher agrees
+        let pre = PreEvent {
+            pubkey: PrivateKey::mock().public_key(),
+            created_at: Unixtime(1),
+            kind: EventKind::TextNote,
+            tags: vec![Tag::new(&["t", "\u{1}🦀"])],
+            content: "\u{0}\u{1b}[31m𝄞".to_owned(),
+            ots: None,
+        };
+        let mut hasher = IdHasher::new(&pre.pubkey, pre.created_at, pre.kind);
+        hasher.tag(&pre.tags[0]);
+        assert_eq!(hasher.finish(&pre.content), pre.hash());
+    }
+
+    #[test]
+    fn test_signing_payload_escaping() {
+        let privkey = PrivateKey::mock();
+        let pre = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime(1),
+            kind: EventKind::TextNote,
+            tags: vec![Tag::new(&["t", "a/b"])],
+            content: "\"\\\n\r\t\u{8}\u{c}\u{0}\u{1f}\u{7f}/é🦀".to_owned(),
+            ots: None,
+        };
+        let payload = String::from_utf8(pre.signing_payload()).unwrap();
+        let expected = format!(
+            "[0,\"{}\",1,1,[[\"t\",\"a/b\"]],\"\\\"\\\\\\n\\r\\t\\b\\f\u{0}\u{1f}\u{7f}/é🦀\"]",
+            privkey.public_key().as_hex_string(),
+        );
+        assert_eq!(payload, expected);
+
+        let mut event = Event::new(pre, &privkey).unwrap();
+        let canonical = event.to_canonical_json().unwrap();
+        assert!(canonical.starts_with(r#"{"id":""#));
+        // The wire JSON must still escape every control character
+        assert!(canonical.contains(r#"\u0000\u001f"#));
+        let _ = event
+            .extra
+            .insert("relay".to_owned(), serde_json::Value::Bool(true));
+        assert_eq!(event.to_canonical_json().unwrap(), canonical);
+        assert_ne!(serde_json::to_string(&event).unwrap(), canonical);
+        event.extra.clear();
+        assert_eq!(serde_json::from_str::<Event>(&canonical).unwrap(), event);
+    }
+
+    #[test]
+    fn test_canonical_json_matches_preimage() {
+        let privkey = PrivateKey::mock();
+        let pre = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime(1),
+            kind: EventKind::TextNote,
+            tags: vec![Tag::new(&["t", "a\u{2028}b\u{2029}"])],
+            content: "\"\\\n\r\t\u{8}\u{c}\u{7f}\u{2028}/é🦀".to_owned(),
+            ots: None,
+        };
+        let payload = String::from_utf8(pre.signing_payload()).unwrap();
+        let event = Event::new(pre, &privkey).unwrap();
+        let canonical = event.to_canonical_json().unwrap();
+
+        // The preimage ends with the tags and content, and the canonical JSON
+        // has them byte for byte the same
+        let (_, tail) = payload.split_once(",[[").unwrap();
+        let tail = tail.strip_suffix(']').unwrap();
+        let (tags, content) = tail.split_once("]],").unwrap();
+        assert!(canonical.contains(&format!("\"tags\":[[{tags}]],\"content\":{content},")));
+        assert!(canonical.contains('\u{2028}'));
+        assert_eq!(serde_json::from_str::<Event>(&canonical).unwrap(), event);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_new_with_pow_progress() {
+        let privkey = PrivateKey::mock();
+        let pre = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime::mock(),
+            kind: EventKind::TextNote,
+            tags: vec![],
+            content: "Mining".to_owned(),
+            ots: None,
+        };
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let event = Event::new_with_pow(pre, &privkey, 8, Some(sender)).unwrap();
+        assert!(event.pow() >= 8);
+
+        let reports: Vec<PowProgress> = receiver.iter().collect();
+        let last = reports.last().unwrap();
+        assert!(last.done);
+        assert_eq!(last.target, Difficulty(8));
+        assert!(last.best_work >= 8);
+        assert!(last.attempts > 0);
+        assert!(reports[..reports.len() - 1].iter().all(|p| !p.done));
+    }
+
+    #[test]
+    fn test_event_deserialize_fields() {
+        let event = Event::mock();
+        let value = serde_json::to_value(&event).unwrap();
+        let serde_json::Value::Object(fields) = value else {
+            panic!("not an object");
+        };
+
+        // Any field order
+        let reversed: Vec<String> = fields
+            .iter()
+            .rev()
+            .map(|(k, v)| format!("{}:{v}", serde_json::to_string(k).unwrap()))
+            .collect();
+        let json = format!("{{{}}}", reversed.join(","));
+        assert_eq!(serde_json::from_str::<Event>(&json).unwrap(), event);
+
+        // Duplicate fields, known or not
+        let json = serde_json::to_string(&event).unwrap();
+        let dup = json.replacen("{", r#"{"kind":0,"#, 1);
+        assert!(serde_json::from_str::<Event>(&dup).is_err());
+        let dup = json.replacen("{", r#"{"hint":1,"hint":2,"#, 1);
+        assert!(serde_json::from_str::<Event>(&dup).is_err());
+
+        // Missing sig
+        let mut without_sig = fields.clone();
+        let _ = without_sig.remove("sig");
+        assert!(serde_json::from_value::<Event>(serde_json::Value::Object(without_sig)).is_err());
+
+        // Unknown fields are kept, roundtrip, and do not affect verification
+        let json = json.replacen("{", r#"{"relay":"wss://relay.example.com","#, 1);
+        let parsed: Event = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            parsed.extra.get("relay"),
+            Some(&serde_json::Value::String(
+                "wss://relay.example.com".to_owned()
+            ))
+        );
+        assert!(parsed.verify(None).is_ok());
+        let again: Event = serde_json::from_str(&serde_json::to_string(&parsed).unwrap()).unwrap();
+        assert_eq!(again, parsed);
+    }
+
+    #[test]
+    fn test_from_json_strict() {
+        let event = Event::mock();
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(Event::from_json_strict(&json).unwrap(), event);
+
+        // Duplicate key
+        let dup = json.replacen("{", r#"{"kind":0,"#, 1);
+        assert!(matches!(
+            Event::from_json_strict(&dup),
+            Err(Error::NonCanonicalJson(_))
+        ));
+
+        // Non-canonical escape of otherwise identical content
+        let mut event = Event::mock();
+        event.content = "a/b".to_owned();
+        let json = serde_json::to_string(&event).unwrap();
+        let escaped = json.replace("a/b", r#"a\/b"#);
+        assert!(matches!(
+            Event::from_json_strict(&escaped),
+            Err(Error::NonCanonicalJson(_))
+        ));
+
+        // Tampered id
+        let mut event = Event::mock();
+        event.id.0[0] ^= 1;
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(matches!(
+            Event::from_json_strict(&json),
+            Err(Error::HashMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_event_address() {
+        let privkey = PrivateKey::mock();
+        let make = |kind: EventKind, tags: Vec<Tag>| {
+            Event::new(
+                PreEvent {
+                    pubkey: privkey.public_key(),
+                    created_at: Unixtime::mock(),
+                    kind,
+                    tags,
+                    content: "".to_owned(),
+                    ots: None,
+                },
+                &privkey,
+            )
+            .unwrap()
+        };
+
+        let event = make(
+            EventKind::LongFormContent,
+            vec![Tag::new_identifier("abc".to_owned())],
+        );
+        let addr = event.address().unwrap();
+        assert_eq!(addr.kind, EventKind::LongFormContent);
+        assert_eq!(addr.author, privkey.public_key());
+        assert_eq!(addr.d, "abc");
+        assert!(event.matches_address(&addr));
+
+        // Relays do not matter
+        let mut with_relays = addr.clone();
+        with_relays.relays = vec![UncheckedUrl::mock()];
+        assert!(event.matches_address(&with_relays));
+
+        // Implicit empty d-tag
+        let event = make(EventKind::LongFormContent, vec![]);
+        assert_eq!(event.address().unwrap().d, "");
+        assert!(!event.matches_address(&addr));
+
+        // Not parameterized replaceable
+        let event = make(EventKind::TextNote, vec![]);
+        assert!(event.address().is_none());
+        assert!(!event.matches_address(&addr));
+    }
+
+    #[test]
+    fn test_pow_valid() {
+        // The id is not checked, so set its leading zeroes directly
+        let mut event = Event::mock();
+        event.id.0 = [0xff; 32];
+        event.id.0[0] = 0;
+        event.id.0[1] = 0x0f; // 12 leading zero bits
+        event.tags = vec![Tag::new_nonce(12345, Some(10))];
+        assert_eq!(event.pow(), 10);
+        assert_eq!(event.pow_valid(10).unwrap(), 12);
+        assert!(matches!(
+            event.pow_valid(11),
+            Err(Error::PowTargetTooLow(10, 11))
+        ));
+        assert!(matches!(
+            event.pow_valid(13),
+            Err(Error::InsufficientPow(12, 13))
+        ));
+        assert_eq!(event.pow_valid(0).unwrap(), 12);
+
+        event.tags = vec![Tag::new_nonce(12345, None)];
+        assert_eq!(event.pow(), 0);
+        assert!(matches!(event.pow_valid(8), Err(Error::PowTargetMissing)));
+        event.tags = vec![];
+        assert!(matches!(event.pow_valid(8), Err(Error::PowTargetMissing)));
+
+        // Tolerate the target in a trailing position
+        event.tags = vec![Tag::new(&["nonce", "12345", "", "12"])];
+        assert_eq!(event.pow(), 12);
+        assert_eq!(event.pow_valid(12).unwrap(), 12);
     }
 }
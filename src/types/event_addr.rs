@@ -1,4 +1,4 @@
-use super::{EventKind, PublicKey, UncheckedUrl};
+use super::{EventKind, PublicKey, RelayHint, RelayHintSource, Tag, UncheckedUrl};
 use crate::Error;
 use bech32::{FromBase32, ToBase32};
 use serde::{Deserialize, Serialize};
@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use speedy::{Readable, Writable};
 
 /// An 'naddr': data to address a parameterized replaceable event (d-tag, kind, author, and relays)
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 #[cfg_attr(feature = "speedy", derive(Readable, Writable))]
 pub struct EventAddr {
     /// the 'd' tag of the Event
@@ -23,6 +23,11 @@ pub struct EventAddr {
 }
 
 impl EventAddr {
+    /// The relays as hints, including any that are not valid relay URLs
+    pub fn relay_hints(&self) -> Vec<RelayHint> {
+        RelayHint::from_urls(&self.relays, RelayHintSource::EventAddr)
+    }
+
     /// Export as a bech32 encoded string ("naddr")
     pub fn as_bech32_string(&self) -> String {
         // Compose
@@ -70,14 +75,14 @@ impl EventAddr {
             let mut pos = 0;
             loop {
                 // we need at least 2 more characters for anything meaningful
-                if pos > tlv.len() - 2 {
+                if pos + 2 > tlv.len() {
                     break;
                 }
                 let ty = tlv[pos];
                 let len = tlv[pos + 1] as usize;
                 pos += 2;
                 if pos + len > tlv.len() {
-                    return Err(Error::InvalidProfile);
+                    return Err(Error::InvalidEventAddr);
                 }
                 let raw = &tlv[pos..pos + len];
                 match ty {
@@ -136,6 +141,12 @@ impl EventAddr {
     }
 }
 
+impl From<EventAddr> for Tag {
+    fn from(ea: EventAddr) -> Tag {
+        Tag::new_address(&ea, None)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -151,4 +162,24 @@ mod test {
             EventAddr::try_from_bech32_string(&bech32).unwrap()
         );
     }
+
+    #[test]
+    fn test_event_addr_to_tag() {
+        let ea = EventAddr::mock();
+        let tag: Tag = ea.clone().into();
+        let (ea2, marker) = tag.parse_address().unwrap();
+        assert_eq!(ea2.kind, ea.kind);
+        assert_eq!(ea2.author, ea.author);
+        assert_eq!(ea2.d, ea.d);
+        assert_eq!(ea2.relays.first(), ea.relays.first());
+        assert_eq!(marker, None);
+    }
+
+    #[test]
+    fn test_truncated_tlv() {
+        for tlv in [vec![], vec![0], vec![0, 5, b'a']] {
+            let bech32 = bech32::encode("naddr", tlv.to_base32(), bech32::Variant::Bech32).unwrap();
+            assert!(EventAddr::try_from_bech32_string(&bech32).is_err());
+        }
+    }
 }
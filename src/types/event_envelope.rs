@@ -0,0 +1,119 @@
+use super::{Event, RelayUrl, Unixtime};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+
+/// An event along with where and when it was seen, for passing events
+/// between clients and for deciding which relays to forward them to
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct EventEnvelope {
+    /// The event
+    pub event: Event,
+
+    /// The relays the event has been seen on, in the order first seen and
+    /// each only once
+    pub seen_on: Vec<RelayUrl>,
+
+    /// When the event was first seen, on any relay
+    pub first_seen: Unixtime,
+}
+
+impl EventEnvelope {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> EventEnvelope {
+        EventEnvelope {
+            event: Event::mock(),
+            seen_on: vec![RelayUrl::try_from_str("wss://relay.example.com").unwrap()],
+            first_seen: Unixtime(1_700_000_000),
+        }
+    }
+
+    /// Wrap an event first seen at `first_seen`, on no relay so far
+    pub fn new(event: Event, first_seen: Unixtime) -> EventEnvelope {
+        EventEnvelope {
+            event,
+            seen_on: vec![],
+            first_seen,
+        }
+    }
+
+    /// Record that the event was seen on `relay` at `when`
+    pub fn add_seen(&mut self, relay: RelayUrl, when: Unixtime) {
+        if !self.seen_on.contains(&relay) {
+            self.seen_on.push(relay);
+        }
+        self.first_seen = self.first_seen.min(when);
+    }
+
+    /// Whether the event has been seen on `relay`
+    pub fn was_seen_on(&self, relay: &RelayUrl) -> bool {
+        self.seen_on.contains(relay)
+    }
+
+    /// Those of `relays` the event has not been seen on, which are the ones
+    /// it still needs to be sent to
+    pub fn missing_from<'a>(&self, relays: &'a [RelayUrl]) -> Vec<&'a RelayUrl> {
+        relays.iter().filter(|r| !self.was_seen_on(r)).collect()
+    }
+
+    /// Take in what `other` knows about the same event: the relays it was
+    /// seen on, and the earlier first seen time. Returns false, changing
+    /// nothing, if `other` is for a different event.
+    pub fn merge(&mut self, other: EventEnvelope) -> bool {
+        if other.event.id != self.event.id {
+            return false;
+        }
+        for relay in other.seen_on {
+            self.add_seen(relay, other.first_seen);
+        }
+        self.first_seen = self.first_seen.min(other.first_seen);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {EventEnvelope, test_event_envelope_serde}
+
+    #[test]
+    fn test_event_envelope_merge() {
+        let relay = |s: &str| RelayUrl::try_from_str(s).unwrap();
+        let event = Event::mock();
+
+        let mut ours = EventEnvelope::new(event.clone(), Unixtime(200));
+        ours.add_seen(relay("wss://a.example.com"), Unixtime(200));
+        ours.add_seen(relay("wss://b.example.com"), Unixtime(250));
+        ours.add_seen(relay("wss://a.example.com"), Unixtime(300));
+        assert_eq!(ours.seen_on.len(), 2);
+        assert_eq!(ours.first_seen, Unixtime(200));
+
+        let mut theirs = EventEnvelope::new(event, Unixtime(100));
+        theirs.add_seen(relay("wss://c.example.com"), Unixtime(100));
+        theirs.add_seen(relay("wss://b.example.com"), Unixtime(120));
+        assert!(ours.merge(theirs));
+        assert_eq!(
+            ours.seen_on,
+            vec![
+                relay("wss://a.example.com"),
+                relay("wss://b.example.com"),
+                relay("wss://c.example.com"),
+            ]
+        );
+        assert_eq!(ours.first_seen, Unixtime(100));
+
+        let outbox = vec![relay("wss://c.example.com"), relay("wss://d.example.com")];
+        assert_eq!(
+            ours.missing_from(&outbox),
+            vec![&relay("wss://d.example.com")]
+        );
+
+        let other = EventEnvelope::mock();
+        let before = ours.clone();
+        assert!(!ours.merge(other));
+        assert_eq!(ours, before);
+    }
+}
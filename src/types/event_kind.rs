@@ -28,6 +28,16 @@ pub enum EventKind {
     Repost = 6,
     /// Event is a reaction to a `TextNote` event
     Reaction = 7,
+    /// Badge Award
+    BadgeAward = 8,
+    /// Seal, wrapping a signed but unpublished event (NIP-59)
+    Seal = 13,
+    /// Private direct message (NIP-17)
+    DirectMessage = 14,
+    /// Private file message (NIP-17)
+    FileMessage = 15,
+    /// Repost of an event other than a text note
+    GenericRepost = 16,
     /// Event creates a public channel
     ChannelCreation = 40,
     /// Event sets metadata on a public channel
@@ -48,20 +58,129 @@ pub enum EventKind {
     PublicChatReserved48 = 48,
     /// Reserved for future public channel usage
     PublicChatReserved49 = 49,
+    /// Poll Response (NIP-88)
+    PollResponse = 1018,
+    /// OpenTimestamps attestation for an event (NIP-03)
+    OpenTimestamps = 1040,
+    /// Gift Wrap (NIP-59)
+    GiftWrap = 1059,
+    /// File Metadata (NIP-94)
+    FileMetadata = 1063,
+    /// Poll (NIP-88)
+    Poll = 1068,
+    /// Comment on any event (NIP-22)
+    Comment = 1111,
+    /// Live Chat Message (NIP-53)
+    LiveChatMessage = 1311,
+    /// Key Migration, naming the key an account moves to (NIP-41 draft)
+    KeyMigration = 1776,
+    /// Reporting (NIP-56)
+    Reporting = 1984,
+    /// Label (NIP-32)
+    Label = 1985,
+    /// Torrent (NIP-35)
+    Torrent = 2003,
+    /// Torrent Comment (NIP-35)
+    TorrentComment = 2004,
+    /// Community Post Approval (NIP-72)
+    CommunityPostApproval = 4550,
+    /// Job Request to a Data Vending Machine (NIP-90), kinds 5000-5999
+    JobRequest(u32),
+    /// Job Result from a Data Vending Machine (NIP-90), kinds 6000-6999, being
+    /// 1000 more than the kind of the request
+    JobResult(u32),
+    /// Job Feedback from a Data Vending Machine (NIP-90)
+    JobFeedback = 7000,
+    /// Zap Goal (NIP-75)
+    ZapGoal = 9041,
     /// Zap Request
     ZapRequest = 9734,
     /// Zap
     Zap = 9735,
+    /// Highlight (NIP-84)
+    Highlight = 9802,
+    /// Mute List (NIP-51)
+    MuteList = 10000,
     /// Relays List (NIP-23)
     RelaysListNip23 = 10001,
     /// Relays List (NIP-65)
     RelayList = 10002,
+    /// Bookmark List (NIP-51)
+    BookmarkList = 10003,
+    /// Communities List (NIP-51)
+    CommunitiesList = 10004,
+    /// Public Chats List (NIP-51)
+    PublicChatsList = 10005,
+    /// Blocked Relays List (NIP-51)
+    BlockedRelaysList = 10006,
+    /// Search Relays List (NIP-51)
+    SearchRelaysList = 10007,
+    /// Interests List (NIP-51)
+    InterestsList = 10015,
+    /// Emoji List (NIP-51)
+    EmojiList = 10030,
+    /// Relays to receive private direct messages at (NIP-17)
+    DmRelayList = 10050,
+    /// Wallet Info (NIP-47)
+    WalletInfo = 13194,
     /// Authentication
     Auth = 22242,
+    /// Wallet Request (NIP-47)
+    WalletRequest = 23194,
+    /// Wallet Response (NIP-47)
+    WalletResponse = 23195,
+    /// Nostr Connect remote signing (NIP-46)
+    NostrConnect = 24133,
+    /// HTTP Auth (NIP-98)
+    HttpAuth = 27235,
+    /// Follow Sets (NIP-51)
+    FollowSets = 30000,
+    /// Generic Lists (NIP-51)
+    GenericLists = 30001,
+    /// Relay Sets (NIP-51)
+    RelaySets = 30002,
+    /// Bookmark Sets (NIP-51)
+    BookmarkSets = 30003,
+    /// Curation Sets (NIP-51)
+    CurationSets = 30004,
+    /// Profile Badges (NIP-58)
+    ProfileBadges = 30008,
+    /// Badge Definition (NIP-58)
+    BadgeDefinition = 30009,
+    /// Interest Sets (NIP-51)
+    InterestSets = 30015,
     /// Long-form Content
     LongFormContent = 30023,
+    /// Draft Long-form Content (NIP-23)
+    DraftLongFormContent = 30024,
+    /// Emoji Sets (NIP-51)
+    EmojiSets = 30030,
+    /// Application-specific Data (NIP-78)
+    AppSpecificData = 30078,
+    /// Live Event (NIP-53)
+    LiveEvent = 30311,
+    /// Classified Listing (NIP-99)
+    ClassifiedListing = 30402,
+    /// Draft Classified Listing (NIP-99)
+    DraftClassifiedListing = 30403,
+    /// Wiki Article (NIP-54)
+    WikiArticle = 30818,
     /// Client Settings
     ClientSettings = 31111,
+    /// Date-based Calendar Event (NIP-52)
+    DateBasedCalendarEvent = 31922,
+    /// Time-based Calendar Event (NIP-52)
+    TimeBasedCalendarEvent = 31923,
+    /// Calendar (NIP-52)
+    Calendar = 31924,
+    /// Calendar Event RSVP (NIP-52)
+    CalendarEventRsvp = 31925,
+    /// Handler Recommendation (NIP-89)
+    HandlerRecommendation = 31989,
+    /// Handler Information (NIP-89)
+    HandlerInformation = 31990,
+    /// Community Definition (NIP-72)
+    CommunityDefinition = 34550,
     /// Relay-specific replaceable event
     Replaceable(u32),
     /// Ephemeral event, sent to all clients with matching filters and should not be stored
@@ -72,6 +191,29 @@ pub enum EventKind {
 
 use EventKind::*;
 
+/// Who can read the content of events of a kind. Anything not public should
+/// not be logged or displayed as-is.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ContentVisibility {
+    /// Anyone can read the content
+    Public,
+
+    /// The content is (or may contain) data the author encrypted to
+    /// themselves, such as the private items of a NIP-51 list
+    AuthorEncrypted,
+
+    /// The content is for the recipients only: encrypted to them, or a
+    /// private message only ever seen after it is unwrapped
+    RecipientEncrypted,
+}
+
+impl ContentVisibility {
+    /// Whether the content is not for everyone
+    pub fn is_private(&self) -> bool {
+        *self != ContentVisibility::Public
+    }
+}
+
 impl EventKind {
     // Mock data for testing
     #[allow(dead_code)]
@@ -104,6 +246,36 @@ impl EventKind {
         (30000..=39999).contains(&u)
     }
 
+    /// If this event kind is a NIP-90 job request
+    pub fn is_job_request(&self) -> bool {
+        let u: u32 = From::from(*self);
+        (5000..=5999).contains(&u)
+    }
+
+    /// If this event kind is a NIP-90 job result
+    pub fn is_job_result(&self) -> bool {
+        let u: u32 = From::from(*self);
+        (6000..=6999).contains(&u)
+    }
+
+    /// The kind of the result to a NIP-90 job request of this kind
+    pub fn job_result_kind(&self) -> Option<EventKind> {
+        if self.is_job_request() {
+            Some(EventKind::from(u32::from(*self) + 1000))
+        } else {
+            None
+        }
+    }
+
+    /// The kind of the NIP-90 job request that a result of this kind answers
+    pub fn job_request_kind(&self) -> Option<EventKind> {
+        if self.is_job_result() {
+            Some(EventKind::from(u32::from(*self) - 1000))
+        } else {
+            None
+        }
+    }
+
     /// If this event kind is feed related.
     pub fn is_feed_related(&self) -> bool {
         self.is_feed_displayable() || self.augments_feed_related()
@@ -122,6 +294,75 @@ impl EventKind {
         matches!(*self, EventDeletion | Reaction | Zap)
     }
 
+    /// The NIP that defines this event kind, such as `"01"`, if it is well known
+    pub fn nip(&self) -> Option<&'static str> {
+        match *self {
+            Metadata | TextNote | RecommendRelay => Some("01"),
+            ContactList => Some("02"),
+            OpenTimestamps => Some("03"),
+            EncryptedDirectMessage => Some("04"),
+            EventDeletion => Some("09"),
+            DirectMessage | FileMessage | DmRelayList => Some("17"),
+            Repost | GenericRepost => Some("18"),
+            Comment => Some("22"),
+            LongFormContent | DraftLongFormContent => Some("23"),
+            Reaction => Some("25"),
+            ChannelCreation | ChannelMetadata | ChannelMessage | ChannelHideMessage
+            | ChannelMuteUser | PublicChatReserved45 | PublicChatReserved46
+            | PublicChatReserved47 | PublicChatReserved48 | PublicChatReserved49 => Some("28"),
+            Label => Some("32"),
+            Torrent | TorrentComment => Some("35"),
+            KeyMigration => Some("41"),
+            Auth => Some("42"),
+            NostrConnect => Some("46"),
+            WalletInfo | WalletRequest | WalletResponse => Some("47"),
+            MuteList | BookmarkList | CommunitiesList | PublicChatsList | BlockedRelaysList
+            | SearchRelaysList | InterestsList | EmojiList | FollowSets | GenericLists
+            | RelaySets | BookmarkSets | CurationSets | InterestSets | EmojiSets => Some("51"),
+            DateBasedCalendarEvent | TimeBasedCalendarEvent | Calendar | CalendarEventRsvp => {
+                Some("52")
+            }
+            LiveChatMessage | LiveEvent => Some("53"),
+            WikiArticle => Some("54"),
+            Reporting => Some("56"),
+            ZapRequest | Zap => Some("57"),
+            BadgeAward | ProfileBadges | BadgeDefinition => Some("58"),
+            Seal | GiftWrap => Some("59"),
+            RelayList => Some("65"),
+            CommunityPostApproval | CommunityDefinition => Some("72"),
+            ZapGoal => Some("75"),
+            AppSpecificData => Some("78"),
+            Highlight => Some("84"),
+            HandlerRecommendation | HandlerInformation => Some("89"),
+            JobRequest(_) | JobResult(_) | JobFeedback => Some("90"),
+            Poll | PollResponse => Some("88"),
+            FileMetadata => Some("94"),
+            HttpAuth => Some("98"),
+            ClassifiedListing | DraftClassifiedListing => Some("99"),
+            _ => None,
+        }
+    }
+
+    /// Who can read the content of events of this kind
+    pub fn visibility(&self) -> ContentVisibility {
+        match *self {
+            EncryptedDirectMessage
+            | Seal
+            | DirectMessage
+            | FileMessage
+            | GiftWrap
+            | WalletRequest
+            | WalletResponse
+            | NostrConnect => ContentVisibility::RecipientEncrypted,
+            MuteList | BookmarkList | CommunitiesList | PublicChatsList | BlockedRelaysList
+            | SearchRelaysList | InterestsList | EmojiList | FollowSets | GenericLists
+            | RelaySets | BookmarkSets | CurationSets | InterestSets | EmojiSets => {
+                ContentVisibility::AuthorEncrypted
+            }
+            _ => ContentVisibility::Public,
+        }
+    }
+
     /// This iterates through every well-known EventKind
     pub fn iter() -> EventKindIterator {
         EventKindIterator::new()
@@ -134,7 +375,7 @@ pub struct EventKindIterator {
     pos: usize,
 }
 
-static WELL_KNOWN_KINDS: &[EventKind] = &[
+pub(super) static WELL_KNOWN_KINDS: &[EventKind] = &[
     Metadata,
     TextNote,
     RecommendRelay,
@@ -143,6 +384,11 @@ static WELL_KNOWN_KINDS: &[EventKind] = &[
     EventDeletion,
     Repost,
     Reaction,
+    BadgeAward,
+    Seal,
+    DirectMessage,
+    FileMessage,
+    GenericRepost,
     ChannelCreation,
     ChannelMetadata,
     ChannelMessage,
@@ -153,13 +399,65 @@ static WELL_KNOWN_KINDS: &[EventKind] = &[
     PublicChatReserved47,
     PublicChatReserved48,
     PublicChatReserved49,
+    PollResponse,
+    OpenTimestamps,
+    GiftWrap,
+    FileMetadata,
+    Poll,
+    Comment,
+    LiveChatMessage,
+    KeyMigration,
+    Reporting,
+    Label,
+    Torrent,
+    TorrentComment,
+    CommunityPostApproval,
+    JobFeedback,
+    ZapGoal,
     ZapRequest,
     Zap,
+    Highlight,
+    MuteList,
     RelaysListNip23,
     RelayList,
+    BookmarkList,
+    CommunitiesList,
+    PublicChatsList,
+    BlockedRelaysList,
+    SearchRelaysList,
+    InterestsList,
+    EmojiList,
+    DmRelayList,
+    WalletInfo,
     Auth,
+    WalletRequest,
+    WalletResponse,
+    NostrConnect,
+    HttpAuth,
+    FollowSets,
+    GenericLists,
+    RelaySets,
+    BookmarkSets,
+    CurationSets,
+    ProfileBadges,
+    BadgeDefinition,
+    InterestSets,
     LongFormContent,
+    DraftLongFormContent,
+    EmojiSets,
+    AppSpecificData,
+    LiveEvent,
+    ClassifiedListing,
+    DraftClassifiedListing,
+    WikiArticle,
     ClientSettings,
+    DateBasedCalendarEvent,
+    TimeBasedCalendarEvent,
+    Calendar,
+    CalendarEventRsvp,
+    HandlerRecommendation,
+    HandlerInformation,
+    CommunityDefinition,
 ];
 
 impl EventKindIterator {
@@ -197,6 +495,11 @@ impl From<u32> for EventKind {
             5 => EventDeletion,
             6 => Repost,
             7 => Reaction,
+            8 => BadgeAward,
+            13 => Seal,
+            14 => DirectMessage,
+            15 => FileMessage,
+            16 => GenericRepost,
             40 => ChannelCreation,
             41 => ChannelMetadata,
             42 => ChannelMessage,
@@ -207,13 +510,67 @@ impl From<u32> for EventKind {
             47 => PublicChatReserved47,
             48 => PublicChatReserved48,
             49 => PublicChatReserved49,
+            1018 => PollResponse,
+            1040 => OpenTimestamps,
+            1059 => GiftWrap,
+            1063 => FileMetadata,
+            1068 => Poll,
+            1111 => Comment,
+            1311 => LiveChatMessage,
+            1776 => KeyMigration,
+            1984 => Reporting,
+            1985 => Label,
+            2003 => Torrent,
+            2004 => TorrentComment,
+            4550 => CommunityPostApproval,
+            7000 => JobFeedback,
+            9041 => ZapGoal,
             9734 => ZapRequest,
             9735 => Zap,
+            9802 => Highlight,
+            10000 => MuteList,
             10001 => RelaysListNip23,
             10002 => RelayList,
+            10003 => BookmarkList,
+            10004 => CommunitiesList,
+            10005 => PublicChatsList,
+            10006 => BlockedRelaysList,
+            10007 => SearchRelaysList,
+            10015 => InterestsList,
+            10030 => EmojiList,
+            10050 => DmRelayList,
+            13194 => WalletInfo,
             22242 => Auth,
+            23194 => WalletRequest,
+            23195 => WalletResponse,
+            24133 => NostrConnect,
+            27235 => HttpAuth,
+            30000 => FollowSets,
+            30001 => GenericLists,
+            30002 => RelaySets,
+            30003 => BookmarkSets,
+            30004 => CurationSets,
+            30008 => ProfileBadges,
+            30009 => BadgeDefinition,
+            30015 => InterestSets,
             30023 => LongFormContent,
+            30024 => DraftLongFormContent,
+            30030 => EmojiSets,
+            30078 => AppSpecificData,
+            30311 => LiveEvent,
+            30402 => ClassifiedListing,
+            30403 => DraftClassifiedListing,
+            30818 => WikiArticle,
             31111 => ClientSettings,
+            31922 => DateBasedCalendarEvent,
+            31923 => TimeBasedCalendarEvent,
+            31924 => Calendar,
+            31925 => CalendarEventRsvp,
+            31989 => HandlerRecommendation,
+            31990 => HandlerInformation,
+            34550 => CommunityDefinition,
+            x if (5_000..6_000).contains(&x) => JobRequest(x),
+            x if (6_000..7_000).contains(&x) => JobResult(x),
             x if (10_000..20_000).contains(&x) => Replaceable(x),
             x if (20_000..30_000).contains(&x) => Ephemeral(x),
             x => Other(x),
@@ -232,6 +589,11 @@ impl From<EventKind> for u32 {
             EventDeletion => 5,
             Repost => 6,
             Reaction => 7,
+            BadgeAward => 8,
+            Seal => 13,
+            DirectMessage => 14,
+            FileMessage => 15,
+            GenericRepost => 16,
             ChannelCreation => 40,
             ChannelMetadata => 41,
             ChannelMessage => 42,
@@ -242,13 +604,67 @@ impl From<EventKind> for u32 {
             PublicChatReserved47 => 47,
             PublicChatReserved48 => 48,
             PublicChatReserved49 => 49,
+            PollResponse => 1018,
+            OpenTimestamps => 1040,
+            GiftWrap => 1059,
+            FileMetadata => 1063,
+            Poll => 1068,
+            Comment => 1111,
+            LiveChatMessage => 1311,
+            KeyMigration => 1776,
+            Reporting => 1984,
+            Label => 1985,
+            Torrent => 2003,
+            TorrentComment => 2004,
+            CommunityPostApproval => 4550,
+            JobRequest(u) => u,
+            JobResult(u) => u,
+            JobFeedback => 7000,
+            ZapGoal => 9041,
             ZapRequest => 9734,
             Zap => 9735,
+            Highlight => 9802,
+            MuteList => 10000,
             RelaysListNip23 => 10001,
             RelayList => 10002,
+            BookmarkList => 10003,
+            CommunitiesList => 10004,
+            PublicChatsList => 10005,
+            BlockedRelaysList => 10006,
+            SearchRelaysList => 10007,
+            InterestsList => 10015,
+            EmojiList => 10030,
+            DmRelayList => 10050,
+            WalletInfo => 13194,
             Auth => 22242,
+            WalletRequest => 23194,
+            WalletResponse => 23195,
+            NostrConnect => 24133,
+            HttpAuth => 27235,
+            FollowSets => 30000,
+            GenericLists => 30001,
+            RelaySets => 30002,
+            BookmarkSets => 30003,
+            CurationSets => 30004,
+            ProfileBadges => 30008,
+            BadgeDefinition => 30009,
+            InterestSets => 30015,
             LongFormContent => 30023,
+            DraftLongFormContent => 30024,
+            EmojiSets => 30030,
+            AppSpecificData => 30078,
+            LiveEvent => 30311,
+            ClassifiedListing => 30402,
+            DraftClassifiedListing => 30403,
+            WikiArticle => 30818,
             ClientSettings => 31111,
+            DateBasedCalendarEvent => 31922,
+            TimeBasedCalendarEvent => 31923,
+            Calendar => 31924,
+            CalendarEventRsvp => 31925,
+            HandlerRecommendation => 31989,
+            HandlerInformation => 31990,
+            CommunityDefinition => 34550,
             Replaceable(u) => u,
             Ephemeral(u) => u,
             Other(u) => u,
@@ -341,6 +757,20 @@ pub enum EventKindOrRange {
     Range(Vec<EventKind>),
 }
 
+impl EventKindOrRange {
+    /// Whether this is the kind, or an inclusive range containing it. A
+    /// malformed range contains nothing.
+    pub fn contains(&self, kind: EventKind) -> bool {
+        match self {
+            EventKindOrRange::EventKind(k) => *k == kind,
+            EventKindOrRange::Range(range) => match range.as_slice() {
+                [start, end] => (u32::from(*start)..=u32::from(*end)).contains(&u32::from(kind)),
+                _ => false,
+            },
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -360,4 +790,54 @@ mod test {
         assert!(!TextNote.is_parameterized_replaceable());
         assert!(LongFormContent.is_parameterized_replaceable());
     }
+
+    #[test]
+    fn test_visibility() {
+        assert_eq!(TextNote.visibility(), ContentVisibility::Public);
+        assert!(!WalletInfo.visibility().is_private());
+        assert_eq!(
+            EncryptedDirectMessage.visibility(),
+            ContentVisibility::RecipientEncrypted
+        );
+        assert_eq!(GiftWrap.visibility(), ContentVisibility::RecipientEncrypted);
+        assert_eq!(MuteList.visibility(), ContentVisibility::AuthorEncrypted);
+        assert!(WalletResponse.visibility().is_private());
+        assert_eq!(Other(12345).visibility(), ContentVisibility::Public);
+    }
+
+    #[test]
+    fn test_well_known_kinds() {
+        for kind in EventKind::iter() {
+            let u: u32 = kind.into();
+            assert_eq!(EventKind::from(u), kind);
+        }
+        let mut numbers: Vec<u32> = EventKind::iter().map(|k| k.into()).collect();
+        let len = numbers.len();
+        numbers.dedup();
+        assert_eq!(numbers.len(), len);
+
+        assert_eq!(EventKind::from(10050), DmRelayList);
+        assert!(DmRelayList.is_replaceable());
+        assert!(WikiArticle.is_parameterized_replaceable());
+        assert_eq!(EventKind::from(19999), Replaceable(19999));
+        assert_eq!(EventKind::from(39999), Other(39999));
+        assert_eq!(EventKind::from(5050), JobRequest(5050));
+        assert_eq!(EventKind::from(6999), JobResult(6999));
+        assert_eq!(EventKind::from(7000), JobFeedback);
+        assert_eq!(JobRequest(5050).job_result_kind(), Some(JobResult(6050)));
+        assert_eq!(JobResult(6050).job_request_kind(), Some(JobRequest(5050)));
+        assert_eq!(JobFeedback.job_result_kind(), None);
+        assert_eq!(JobFeedback.nip(), Some("90"));
+    }
+
+    #[test]
+    fn test_event_kind_nip() {
+        assert_eq!(TextNote.nip(), Some("01"));
+        assert_eq!(GiftWrap.nip(), Some("59"));
+        assert_eq!(Seal.nip(), Some("59"));
+        assert_eq!(LiveEvent.nip(), Some("53"));
+        assert_eq!(ClassifiedListing.nip(), Some("99"));
+        assert_eq!(ClientSettings.nip(), None);
+        assert_eq!(Other(12345).nip(), None);
+    }
 }
@@ -1,4 +1,4 @@
-use super::{EventKind, Id, PublicKey, UncheckedUrl};
+use super::{EventKind, Id, PublicKey, RelayHint, RelayHintSource, Tag, UncheckedUrl};
 use crate::Error;
 use bech32::{FromBase32, ToBase32};
 use serde::{Deserialize, Serialize};
@@ -27,6 +27,11 @@ pub struct EventPointer {
 }
 
 impl EventPointer {
+    /// The relays as hints, including any that are not valid relay URLs
+    pub fn relay_hints(&self) -> Vec<RelayHint> {
+        RelayHint::from_urls(&self.relays, RelayHintSource::EventPointer)
+    }
+
     /// Export as a bech32 encoded string ("nevent")
     pub fn as_bech32_string(&self) -> String {
         // Compose
@@ -78,14 +83,14 @@ impl EventPointer {
             let mut pos = 0;
             loop {
                 // we need at least 2 more characters for anything meaningful
-                if pos > tlv.len() - 2 {
+                if pos + 2 > tlv.len() {
                     break;
                 }
                 let ty = tlv[pos];
                 let len = tlv[pos + 1] as usize;
                 pos += 2;
                 if pos + len > tlv.len() {
-                    return Err(Error::InvalidProfile);
+                    return Err(Error::InvalidEventPointer);
                 }
                 let raw = &tlv[pos..pos + len];
                 match ty {
@@ -152,12 +157,44 @@ impl EventPointer {
     }
 }
 
+impl From<EventPointer> for Tag {
+    /// An 'e' tag with the first relay and the author. An 'e' tag has no
+    /// place for the kind, so that is left out.
+    fn from(ep: EventPointer) -> Tag {
+        Tag::new_event_with_author(
+            ep.id,
+            ep.relays.into_iter().next(),
+            None,
+            ep.author.map(|pk| pk.into()),
+        )
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     test_serde! {EventPointer, test_event_pointer_serde}
 
+    #[test]
+    fn test_event_pointer_tag() {
+        let mut ep = EventPointer::mock();
+        let tag: Tag = ep.clone().into();
+        assert_eq!(tag.fields().len(), 3);
+
+        ep.author = Some(PublicKey::mock_deterministic());
+        ep.kind = Some(EventKind::TextNote);
+        let tag: Tag = ep.clone().into();
+        let (id, relay, marker) = tag.parse_event().unwrap();
+        assert_eq!(id, ep.id);
+        assert_eq!(relay.as_ref(), ep.relays.first());
+        assert_eq!(marker, None);
+        assert_eq!(
+            PublicKey::try_from_hex_string(tag.get_index(4)).ok(),
+            ep.author
+        );
+    }
+
     #[test]
     fn test_profile_bech32() {
         let bech32 = EventPointer::mock().as_bech32_string();
@@ -234,4 +271,14 @@ mod test {
             EventPointer::try_from_bech32_string(bech32).unwrap()
         );
     }
+
+    #[test]
+    fn test_event_pointer_to_tag() {
+        let ep = EventPointer::mock();
+        let tag: Tag = ep.clone().into();
+        let (id, recommended_relay_url, marker) = tag.parse_event().unwrap();
+        assert_eq!(id, ep.id);
+        assert_eq!(recommended_relay_url, ep.relays.first().cloned());
+        assert_eq!(marker, None);
+    }
 }
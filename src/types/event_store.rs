@@ -0,0 +1,261 @@
+use super::{Event, EventAddr, EventKind, Id, PublicKey, RetentionPolicy, Unixtime};
+use std::collections::HashMap;
+
+/// A minimal in-memory store of events with relay semantics (NIP-01)
+///
+/// Ephemeral events are never stored. A replaceable event replaces the one
+/// before it with the same kind and author, and a parameterized replaceable
+/// event the one before it at the same address. Of two such events the newer
+/// one wins, or on equal `created_at` the one with the lower id. Events the
+/// retention policy does not keep are refused, and `prune()` drops those
+/// that have aged out or exceed a count limit.
+///
+/// Events are not verified here; check them before inserting.
+#[derive(Clone, Debug, Default)]
+pub struct EventStore {
+    events: HashMap<Id, Event>,
+    replaceable: HashMap<(u32, PublicKey), Id>,
+    addressable: HashMap<(u32, PublicKey, String), Id>,
+    retention: RetentionPolicy,
+}
+
+impl EventStore {
+    /// An empty store keeping events as `retention` allows
+    pub fn new(retention: RetentionPolicy) -> EventStore {
+        EventStore {
+            retention,
+            ..Default::default()
+        }
+    }
+
+    /// Store an event received at `now`. Returns false, storing nothing, if
+    /// it is ephemeral, already stored, not kept by the retention policy, or
+    /// older than the event it would replace.
+    pub fn insert(&mut self, event: Event, now: Unixtime) -> bool {
+        if event.kind.is_ephemeral()
+            || self.events.contains_key(&event.id)
+            || !self.retention.retains(&event, now)
+        {
+            return false;
+        }
+        if let Some(previous) = self.current(&event) {
+            if (previous.created_at, std::cmp::Reverse(previous.id))
+                >= (event.created_at, std::cmp::Reverse(event.id))
+            {
+                return false;
+            }
+            let previous = previous.id;
+            let _ = self.remove(&previous);
+        }
+        let id = event.id;
+        let u: u32 = From::from(event.kind);
+        if event.kind.is_replaceable() {
+            let _ = self.replaceable.insert((u, event.pubkey), id);
+        } else if let Some(addr) = event.address() {
+            let _ = self.addressable.insert((u, event.pubkey, addr.d), id);
+        }
+        let _ = self.events.insert(id, event);
+        true
+    }
+
+    /// Remove an event, returning it if it was stored
+    pub fn remove(&mut self, id: &Id) -> Option<Event> {
+        let event = self.events.remove(id)?;
+        let u: u32 = From::from(event.kind);
+        if event.kind.is_replaceable() {
+            let _ = self.replaceable.remove(&(u, event.pubkey));
+        } else if let Some(addr) = event.address() {
+            let _ = self.addressable.remove(&(u, event.pubkey, addr.d));
+        }
+        Some(event)
+    }
+
+    /// The event with this id
+    pub fn get(&self, id: &Id) -> Option<&Event> {
+        self.events.get(id)
+    }
+
+    /// The current replaceable event of this kind by this author
+    pub fn get_replaceable(&self, kind: EventKind, author: PublicKey) -> Option<&Event> {
+        let id = self.replaceable.get(&(From::from(kind), author))?;
+        self.events.get(id)
+    }
+
+    /// The current parameterized replaceable event at `addr`. Relays in
+    /// `addr` are ignored.
+    pub fn get_addressable(&self, addr: &EventAddr) -> Option<&Event> {
+        let key = (From::from(addr.kind), addr.author, addr.d.clone());
+        let id = self.addressable.get(&key)?;
+        self.events.get(id)
+    }
+
+    /// How many events are stored
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Whether no events are stored
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The stored events, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = &Event> {
+        self.events.values()
+    }
+
+    /// Drop the events the retention policy no longer keeps at `now`: those
+    /// that have aged out, and beyond the most recent `max_count` of the
+    /// kinds sharing a retention entry. Returns how many were dropped.
+    pub fn prune(&mut self, now: Unixtime) -> usize {
+        let mut drop: Vec<Id> = vec![];
+        let mut by_entry: HashMap<usize, Vec<&Event>> = HashMap::new();
+        for event in self.events.values() {
+            if !self.retention.retains(event, now) {
+                drop.push(event.id);
+            } else if let Some(index) = self.retention.entry_index(event.kind) {
+                by_entry.entry(index).or_default().push(event);
+            }
+        }
+        for mut events in by_entry.into_values() {
+            let Some(max) = self.retention.max_count(events[0].kind) else {
+                continue;
+            };
+            if events.len() > max {
+                // Feed order, newest first
+                events.sort();
+                drop.extend(events[max..].iter().map(|e| e.id));
+            }
+        }
+        for id in &drop {
+            let _ = self.remove(id);
+        }
+        drop.len()
+    }
+
+    // The stored event this one would replace, if any
+    fn current(&self, event: &Event) -> Option<&Event> {
+        if event.kind.is_replaceable() {
+            self.get_replaceable(event.kind, event.pubkey)
+        } else {
+            let addr = event.address()?;
+            self.get_addressable(&addr)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{EventKindOrRange, PrivateKey, RelayRetention, Tag};
+
+    fn event(
+        privkey: &PrivateKey,
+        kind: EventKind,
+        created_at: i64,
+        tags: Vec<Tag>,
+        content: &str,
+    ) -> Event {
+        let pre = crate::types::PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime(created_at),
+            kind,
+            tags,
+            content: content.to_owned(),
+            ots: None,
+        };
+        Event::new(pre, privkey).unwrap()
+    }
+
+    #[test]
+    fn test_event_store() {
+        let privkey = PrivateKey::mock();
+        let now = Unixtime(2_000);
+        let mut store = EventStore::default();
+
+        // Ephemeral events are never stored
+        let ephemeral = event(&privkey, EventKind::Other(20001), 1_000, vec![], "");
+        assert!(!store.insert(ephemeral, now));
+        assert!(store.is_empty());
+
+        // Regular events are all kept, once each
+        let note = event(&privkey, EventKind::TextNote, 1_000, vec![], "a");
+        assert!(store.insert(note.clone(), now));
+        assert!(!store.insert(note.clone(), now));
+        assert!(store.insert(event(&privkey, EventKind::TextNote, 900, vec![], "b"), now));
+        assert_eq!(store.len(), 2);
+
+        // A newer replaceable event replaces the older, but not the reverse
+        let old = event(&privkey, EventKind::Metadata, 1_000, vec![], "old");
+        let new = event(&privkey, EventKind::Metadata, 1_100, vec![], "new");
+        assert!(store.insert(old.clone(), now));
+        assert!(store.insert(new.clone(), now));
+        assert!(!store.insert(old.clone(), now));
+        assert_eq!(
+            store.get_replaceable(EventKind::Metadata, privkey.public_key()),
+            Some(&new)
+        );
+        assert!(store.get(&old.id).is_none());
+        assert_eq!(store.len(), 3);
+
+        // With equal times, the lower id wins
+        let a = event(&privkey, EventKind::ContactList, 1_000, vec![], "a");
+        let b = event(&privkey, EventKind::ContactList, 1_000, vec![], "b");
+        let (low, high) = if a.id < b.id { (a, b) } else { (b, a) };
+        assert!(store.insert(high, now));
+        assert!(store.insert(low.clone(), now));
+        assert_eq!(
+            store.get_replaceable(EventKind::ContactList, privkey.public_key()),
+            Some(&low)
+        );
+
+        // Parameterized replaceable events replace by address only
+        let kind = EventKind::LongFormContent;
+        let d = |s: &str| vec![Tag::new_identifier(s.to_owned())];
+        let x1 = event(&privkey, kind, 1_000, d("x"), "1");
+        let x2 = event(&privkey, kind, 1_100, d("x"), "2");
+        let y = event(&privkey, kind, 1_000, d("y"), "");
+        assert!(store.insert(x1.clone(), now));
+        assert!(store.insert(y.clone(), now));
+        assert!(store.insert(x2.clone(), now));
+        assert!(store.get(&x1.id).is_none());
+        assert_eq!(store.get_addressable(&x2.address().unwrap()), Some(&x2));
+        assert_eq!(store.get_addressable(&y.address().unwrap()), Some(&y));
+        assert_eq!(store.len(), 6);
+
+        assert_eq!(store.remove(&x2.id), Some(x2.clone()));
+        assert!(store.get_addressable(&x2.address().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_event_store_retention() {
+        let privkey = PrivateKey::mock();
+        let mut store = EventStore::new(RetentionPolicy::new(vec![
+            RelayRetention {
+                kinds: vec![EventKindOrRange::EventKind(EventKind::TextNote)],
+                time: Some(100),
+                count: None,
+            },
+            RelayRetention {
+                kinds: vec![],
+                time: None,
+                count: Some(2),
+            },
+        ]));
+
+        let note = event(&privkey, EventKind::TextNote, 1_000, vec![], "");
+        assert!(!store.insert(note.clone(), Unixtime(1_101)));
+        assert!(store.insert(note.clone(), Unixtime(1_050)));
+        for i in 0..3 {
+            let reaction = event(&privkey, EventKind::Reaction, 1_000 + i, vec![], "+");
+            assert!(store.insert(reaction, Unixtime(1_050)));
+        }
+        assert_eq!(store.len(), 4);
+
+        // The note ages out, and only the two newest reactions are kept
+        assert_eq!(store.prune(Unixtime(1_101)), 2);
+        let mut kept: Vec<i64> = store.iter().map(|e| e.created_at.0).collect();
+        kept.sort();
+        assert_eq!(kept, vec![1_001, 1_002]);
+    }
+}
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+use std::fmt;
+
+/// A platform on which someone claims an identity (NIP-39)
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub enum IdentityPlatform {
+    /// GitHub. The proof is the id of a gist.
+    GitHub,
+
+    /// Twitter, now X. The proof is the id of a tweet.
+    Twitter,
+
+    /// Mastodon, with identities like `bitcoinhackers.org/@semisol`. The proof
+    /// is the id of a post.
+    Mastodon,
+
+    /// Telegram. The proof is a message in a public channel, as
+    /// `channel/message id`.
+    Telegram,
+
+    /// A platform without a specified proof
+    Other(String),
+}
+
+impl IdentityPlatform {
+    /// The name used on the wire
+    pub fn as_str(&self) -> &str {
+        match self {
+            IdentityPlatform::GitHub => "github",
+            IdentityPlatform::Twitter => "twitter",
+            IdentityPlatform::Mastodon => "mastodon",
+            IdentityPlatform::Telegram => "telegram",
+            IdentityPlatform::Other(name) => name,
+        }
+    }
+
+    /// From the name used on the wire
+    pub fn parse(s: &str) -> IdentityPlatform {
+        match s {
+            "github" => IdentityPlatform::GitHub,
+            "twitter" => IdentityPlatform::Twitter,
+            "mastodon" => IdentityPlatform::Mastodon,
+            "telegram" => IdentityPlatform::Telegram,
+            other => IdentityPlatform::Other(other.to_owned()),
+        }
+    }
+}
+
+impl fmt::Display for IdentityPlatform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// An identity someone claims on another platform, with a proof there that
+/// links back to their nostr key (NIP-39 'i' tag, on kind 0 events)
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct ExternalIdentity {
+    /// The platform
+    pub platform: IdentityPlatform,
+
+    /// The user name or other identity on the platform
+    pub identity: String,
+
+    /// Where on the platform to find the proof, which depends on the platform
+    pub proof: String,
+}
+
+impl ExternalIdentity {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> ExternalIdentity {
+        ExternalIdentity {
+            platform: IdentityPlatform::GitHub,
+            identity: "semisol".to_owned(),
+            proof: "9721ce4ee4fceb91c9711ca2a6c9a5ab".to_owned(),
+        }
+    }
+
+    /// Where to find the proof, for platforms where that is known. The proof
+    /// should contain the text `Verifying that I control the following Nostr
+    /// public key: ` followed by the npub.
+    pub fn proof_url(&self) -> Option<String> {
+        match self.platform {
+            IdentityPlatform::GitHub => Some(format!(
+                "https://gist.github.com/{}/{}",
+                self.identity, self.proof
+            )),
+            IdentityPlatform::Twitter => Some(format!(
+                "https://twitter.com/{}/status/{}",
+                self.identity, self.proof
+            )),
+            IdentityPlatform::Mastodon => Some(format!("https://{}/{}", self.identity, self.proof)),
+            IdentityPlatform::Telegram => Some(format!("https://t.me/{}", self.proof)),
+            IdentityPlatform::Other(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{Event, EventKind, Metadata, PreEvent, PrivateKey, Tag, Unixtime};
+
+    test_serde! {ExternalIdentity, test_external_identity_serde}
+
+    #[test]
+    fn test_external_identity_tags() {
+        let identity = ExternalIdentity::mock();
+        let tag = Tag::new_external_identity(&identity);
+        assert_eq!(
+            tag.clone().into_inner(),
+            vec!["i", "github:semisol", "9721ce4ee4fceb91c9711ca2a6c9a5ab"]
+        );
+        assert_eq!(tag.parse_external_identity().unwrap(), identity);
+        assert_eq!(
+            identity.proof_url().as_deref(),
+            Some("https://gist.github.com/semisol/9721ce4ee4fceb91c9711ca2a6c9a5ab")
+        );
+
+        let tag = Tag::new(&[
+            "i",
+            "mastodon:bitcoinhackers.org/@semisol",
+            "109775066355589974",
+        ]);
+        let mastodon = tag.parse_external_identity().unwrap();
+        assert_eq!(mastodon.platform, IdentityPlatform::Mastodon);
+        assert_eq!(
+            mastodon.proof_url().as_deref(),
+            Some("https://bitcoinhackers.org/@semisol/109775066355589974")
+        );
+
+        let tag = Tag::new(&["i", "keybase:semisol", "proof"]);
+        let other = tag.parse_external_identity().unwrap();
+        assert_eq!(
+            other.platform,
+            IdentityPlatform::Other("keybase".to_owned())
+        );
+        assert_eq!(Tag::new_external_identity(&other), tag);
+        assert_eq!(other.proof_url(), None);
+
+        assert!(Tag::new(&["i", "github", "x"])
+            .parse_external_identity()
+            .is_err());
+        assert!(Tag::new(&["i", "github:semisol"])
+            .parse_external_identity()
+            .is_err());
+        assert!(Tag::new(&["t", "github:semisol", "x"])
+            .parse_external_identity()
+            .is_err());
+    }
+
+    #[test]
+    fn test_event_external_identities() {
+        let privkey = PrivateKey::mock();
+        let pre = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime(1_700_000_000),
+            kind: EventKind::TextNote,
+            tags: vec![
+                Tag::new_external_identity(&ExternalIdentity::mock()),
+                Tag::new_hashtag("nostr".to_owned()),
+            ],
+            content: String::new(),
+            ots: None,
+        };
+        let event = Event::new(pre.clone(), &privkey).unwrap();
+        assert!(event.external_identities().is_empty());
+        let event = Event::new_set_metadata(pre, &privkey, Metadata::mock()).unwrap();
+        assert_eq!(event.external_identities(), vec![ExternalIdentity::mock()]);
+    }
+}
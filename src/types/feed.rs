@@ -0,0 +1,149 @@
+#[cfg(feature = "rayon")]
+use super::Unixtime;
+use super::{Event, Id};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Sort events into feed order (newest first, see `Event`'s `Ord`) and drop
+/// any that repeat an earlier event's id, as happens when the same event
+/// arrives from several relays
+pub fn dedup_events(events: &mut Vec<Event>) {
+    events.sort();
+    events.dedup_by(|a, b| a.id == b.id);
+}
+
+/// Merge streams of events that are each already in feed order, such as the
+/// results from several relays, into one stream in feed order without
+/// duplicates
+pub fn merge_sorted_event_streams<I>(streams: Vec<I>) -> MergedEvents<I::IntoIter>
+where
+    I: IntoIterator<Item = Event>,
+{
+    let mut streams: Vec<I::IntoIter> = streams.into_iter().map(|s| s.into_iter()).collect();
+    let mut heap = BinaryHeap::with_capacity(streams.len());
+    for (index, stream) in streams.iter_mut().enumerate() {
+        if let Some(event) = stream.next() {
+            heap.push(Reverse((event, index)));
+        }
+    }
+    MergedEvents {
+        streams,
+        heap,
+        last_id: None,
+    }
+}
+
+/// The iterator returned by `merge_sorted_event_streams`
+#[derive(Debug)]
+pub struct MergedEvents<I> {
+    streams: Vec<I>,
+    heap: BinaryHeap<Reverse<(Event, usize)>>,
+    last_id: Option<Id>,
+}
+
+impl<I: Iterator<Item = Event>> Iterator for MergedEvents<I> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        // Copies of an event sort next to each other, so comparing against
+        // the last one returned is enough to skip them
+        while let Some(Reverse((event, index))) = self.heap.pop() {
+            if let Some(next) = self.streams[index].next() {
+                self.heap.push(Reverse((next, index)));
+            }
+            if self.last_id != Some(event.id) {
+                self.last_id = Some(event.id);
+                return Some(event);
+            }
+        }
+        None
+    }
+}
+
+/// Verify events on all cores (see `Event::verify`), returning the indices
+/// of those that fail, in order
+#[cfg(feature = "rayon")]
+pub fn par_verify(events: &[Event], maxtime: Option<Unixtime>) -> Vec<usize> {
+    events
+        .par_iter()
+        .enumerate()
+        .filter(|(_, event)| event.verify(maxtime).is_err())
+        .map(|(index, _)| index)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{EventKind, PreEvent, PrivateKey, Unixtime};
+
+    fn event(privkey: &PrivateKey, created_at: i64) -> Event {
+        let pre = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime(created_at),
+            kind: EventKind::TextNote,
+            tags: vec![],
+            content: format!("note at {created_at}"),
+            ots: None,
+        };
+        Event::new(pre, privkey).unwrap()
+    }
+
+    #[test]
+    fn test_event_order() {
+        let privkey = PrivateKey::mock();
+        let old = event(&privkey, 100);
+        let new = event(&privkey, 200);
+        assert!(new < old);
+
+        let other = event(&PrivateKey::generate(), 200);
+        assert_eq!(new.cmp(&other), new.id.cmp(&other.id));
+        assert_eq!(new.cmp(&new.clone()), std::cmp::Ordering::Equal);
+
+        // Events with the same id are told apart by their other fields
+        let mut resigned = new.clone();
+        resigned.sig.0[0] ^= 1;
+        assert_eq!(new.cmp(&resigned), new.sig.0.cmp(&resigned.sig.0));
+        let mut a = new.clone();
+        let mut b = new.clone();
+        let _ = a.extra.insert("x".to_owned(), serde_json::json!([1, "a"]));
+        let _ = b.extra.insert("x".to_owned(), serde_json::json!([1.5]));
+        assert!(a < b);
+        assert_eq!(b.cmp(&a), std::cmp::Ordering::Greater);
+        assert_eq!(a.cmp(&a.clone()), std::cmp::Ordering::Equal);
+    }
+
+    #[test]
+    fn test_dedup_and_merge() {
+        let privkey = PrivateKey::mock();
+        let a = event(&privkey, 300);
+        let b = event(&privkey, 200);
+        let c = event(&privkey, 100);
+
+        let mut events = vec![c.clone(), a.clone(), b.clone(), a.clone(), c.clone()];
+        dedup_events(&mut events);
+        assert_eq!(events, vec![a.clone(), b.clone(), c.clone()]);
+
+        let relay1 = vec![a.clone(), c.clone()];
+        let relay2 = vec![a.clone(), b.clone(), c.clone()];
+        let relay3: Vec<Event> = vec![];
+        let merged: Vec<Event> = merge_sorted_event_streams(vec![relay1, relay2, relay3]).collect();
+        assert_eq!(merged, vec![a, b, c]);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_verify() {
+        let privkey = PrivateKey::mock();
+        let mut events: Vec<Event> = (0..10).map(|i| event(&privkey, 100 + i)).collect();
+        events[3].content = "tampered".to_owned();
+        events[7].created_at = Unixtime(0);
+        assert_eq!(par_verify(&events, None), vec![3, 7]);
+        assert_eq!(
+            par_verify(&events, Some(Unixtime(105))),
+            vec![3, 6, 7, 8, 9]
+        );
+    }
+}
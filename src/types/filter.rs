@@ -1,87 +1,130 @@
 use super::{EventKind, IdHex, IdHexPrefix, PublicKeyHex, PublicKeyHexPrefix, Unixtime};
-use serde::{Deserialize, Serialize};
+use serde::de::{Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
 #[cfg(feature = "speedy")]
 use speedy::{Readable, Writable};
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::ops::Deref;
 
 /// Filter which specify what events a client is looking for
-#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(feature = "speedy", derive(Readable, Writable))]
 pub struct Filter {
     /// Events which match these ids
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(default)]
     pub ids: Vec<IdHexPrefix>, // ID as hex, or prefix thereof
 
     /// Events which match these authors
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(default)]
     pub authors: Vec<PublicKeyHexPrefix>, // PublicKey as hex, or prefix thereof
 
     /// Events which match these kinds
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(default)]
     pub kinds: Vec<EventKind>,
 
-    /// Events which refer to this naddr in an 'a' tag
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(rename = "#a")]
-    #[serde(default)]
-    pub a: Vec<String>,
-
-    /// Events which refer to this parameter in a 'd' tag
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(rename = "#d")]
-    #[serde(default)]
-    pub d: Vec<String>,
-
-    /// Events which refer to these other events in an 'e' tag
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(rename = "#e")]
-    #[serde(default)]
-    pub e: Vec<IdHex>,
-
-    /// Events which refer to this geohash in a 'g' tag
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(rename = "#g")]
-    #[serde(default)]
-    pub g: Vec<String>,
-
-    /// Events which refer to these public keys in a 'p' tag
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(rename = "#p")]
-    #[serde(default)]
-    pub p: Vec<PublicKeyHex>,
-
-    /// Events which refer to this URL reference in an 'r' tag
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(rename = "#r")]
-    #[serde(default)]
-    pub r: Vec<String>,
-
-    /// Events which refer to this hashtag in a 't' tag
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    #[serde(rename = "#t")]
-    #[serde(default)]
-    pub t: Vec<String>,
+    /// Events which have a tag named by the letter with one of the values.
+    /// These are written as `"#<letter>"` fields, e.g. `"#e"` for events
+    /// referring to other events and `"#t"` for hashtags.
+    pub tags: BTreeMap<char, Vec<String>>,
 
     /// Events occuring after this date
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(default)]
     pub since: Option<Unixtime>,
 
     /// Events occuring before this date
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(default)]
     pub until: Option<Unixtime>,
 
     /// A limit on the number of events to return in the initial query
-    #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(default)]
     pub limit: Option<usize>,
 }
 
+impl Serialize for Filter {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        if !self.ids.is_empty() {
+            map.serialize_entry("ids", &self.ids)?;
+        }
+        if !self.authors.is_empty() {
+            map.serialize_entry("authors", &self.authors)?;
+        }
+        if !self.kinds.is_empty() {
+            map.serialize_entry("kinds", &self.kinds)?;
+        }
+        for (letter, values) in &self.tags {
+            if !values.is_empty() {
+                map.serialize_entry(&format!("#{letter}"), values)?;
+            }
+        }
+        if let Some(since) = &self.since {
+            map.serialize_entry("since", since)?;
+        }
+        if let Some(until) = &self.until {
+            map.serialize_entry("until", until)?;
+        }
+        if let Some(limit) = &self.limit {
+            map.serialize_entry("limit", limit)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Filter {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(FilterVisitor)
+    }
+}
+
+struct FilterVisitor;
+
+impl<'de> Visitor<'de> for FilterVisitor {
+    type Value = Filter;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a filter object")
+    }
+
+    fn visit_map<M>(self, mut map: M) -> Result<Filter, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut filter = Filter::new();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "ids" => filter.ids = map.next_value()?,
+                "authors" => filter.authors = map.next_value()?,
+                "kinds" => filter.kinds = map.next_value()?,
+                "since" => filter.since = map.next_value()?,
+                "until" => filter.until = map.next_value()?,
+                "limit" => filter.limit = map.next_value()?,
+                _ => match tag_letter(&key) {
+                    Some(letter) => {
+                        let values: Vec<String> = map.next_value()?;
+                        filter.tags.entry(letter).or_default().extend(values);
+                    }
+                    // Ignore fields from extensions we do not understand
+                    None => {
+                        let _ = map.next_value::<IgnoredAny>()?;
+                    }
+                },
+            }
+        }
+        Ok(filter)
+    }
+}
+
+// The letter of a "#<letter>" filter field
+fn tag_letter(key: &str) -> Option<char> {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('#'), Some(letter), None) if letter.is_ascii_alphabetic() => Some(letter),
+        _ => None,
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 enum PrefixMatch {
     Longer,
@@ -144,6 +187,39 @@ fn del_substr<T: Deref<Target = String>>(vec: &mut Vec<T>, del: T) {
     }
 }
 
+// Is every prefix in `narrow` at least as specific as some prefix in `wide`?
+// An empty list matches anything.
+fn prefixes_narrow<T: Deref<Target = String>>(narrow: &[T], wide: &[T]) -> bool {
+    wide.is_empty()
+        || (!narrow.is_empty()
+            && narrow
+                .iter()
+                .all(|n| wide.iter().any(|w| n.starts_with(w.as_str()))))
+}
+
+// Is every value in `narrow` also in `wide`? An empty list matches anything.
+fn values_narrow<T: PartialEq>(narrow: &[T], wide: &[T]) -> bool {
+    wide.is_empty() || (!narrow.is_empty() && narrow.iter().all(|n| wide.contains(n)))
+}
+
+fn same_values<T: PartialEq>(a: &[T], b: &[T]) -> bool {
+    a.iter().all(|x| b.contains(x)) && b.iter().all(|x| a.contains(x))
+}
+
+// The union of two lists, where an empty list matches anything
+fn union_values<T: PartialEq + Clone>(a: &[T], b: &[T]) -> Vec<T> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+    let mut output = a.to_vec();
+    for x in b {
+        if !output.contains(x) {
+            output.push(x.clone());
+        }
+    }
+    output
+}
+
 impl Filter {
     /// Create a new Filter object
     pub fn new() -> Filter {
@@ -191,31 +267,185 @@ impl Filter {
 
     /// Add an e-tag Id to the filter
     pub fn add_e_tag_ids(&mut self, id_hex: IdHex) {
-        if self.e.contains(&id_hex) {
-            return;
-        }
-        self.e.push(id_hex);
+        self.add_tag_value('e', id_hex.into_string());
     }
 
     /// Delete an e-tag Id from the filter
     pub fn del_e_tag_ids(&mut self, id_hex: &IdHex) {
-        if let Some(position) = self.e.iter().position(|x| x == id_hex) {
-            let _ = self.e.swap_remove(position);
-        }
+        self.del_tag_value('e', id_hex.as_str());
     }
 
     /// Add a PublicKey to the filter
     pub fn add_p_tag_public_key(&mut self, public_key_hex: PublicKeyHex) {
-        if self.p.contains(&public_key_hex) {
-            return;
-        }
-        self.p.push(public_key_hex);
+        self.add_tag_value('p', public_key_hex.into_string());
     }
 
     /// Delete a PublicKey from the filter
     pub fn del_p_tag_public_key(&mut self, public_key_hex: &PublicKeyHex) {
-        if let Some(position) = self.p.iter().position(|x| x == public_key_hex) {
-            let _ = self.p.swap_remove(position);
+        self.del_tag_value('p', public_key_hex.as_str());
+    }
+
+    /// Add a value for the tag named by `letter` to the filter.
+    /// `letter` must be an ASCII letter; values for anything else are ignored
+    /// as relays could not index them.
+    pub fn add_tag_value(&mut self, letter: char, value: String) {
+        if !letter.is_ascii_alphabetic() {
+            return;
+        }
+        let values = self.tags.entry(letter).or_default();
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    }
+
+    /// Delete a value for the tag named by `letter` from the filter
+    pub fn del_tag_value(&mut self, letter: char, value: &str) {
+        if let Some(values) = self.tags.get_mut(&letter) {
+            values.retain(|v| v != value);
+            if values.is_empty() {
+                let _ = self.tags.remove(&letter);
+            }
+        }
+    }
+
+    /// The values for the tag named by `letter`
+    pub fn tag_values(&self, letter: char) -> &[String] {
+        self.tags.get(&letter).map(|v| v.as_slice()).unwrap_or(&[])
+    }
+
+    /// Builder: match events with this Id (or prefix)
+    pub fn id<T: Into<IdHexPrefix>>(mut self, id: T) -> Filter {
+        self.add_id(id);
+        self
+    }
+
+    /// Builder: match events by this author (or prefix)
+    pub fn author<T: Into<PublicKeyHexPrefix>>(mut self, author: T) -> Filter {
+        self.add_author(author);
+        self
+    }
+
+    /// Builder: match events of this kind
+    pub fn kind(mut self, kind: EventKind) -> Filter {
+        self.add_event_kind(kind);
+        self
+    }
+
+    /// Builder: match events with a tag named by `letter` having this value,
+    /// e.g. `.tag('t', "nostr")`. See `add_tag_value()`.
+    pub fn tag(mut self, letter: char, value: &str) -> Filter {
+        self.add_tag_value(letter, value.to_owned());
+        self
+    }
+
+    /// Builder: match events created at or after this time
+    pub fn since(mut self, since: Unixtime) -> Filter {
+        self.since = Some(since);
+        self
+    }
+
+    /// Builder: match events created at or before this time
+    pub fn until(mut self, until: Unixtime) -> Filter {
+        self.until = Some(until);
+        self
+    }
+
+    /// Builder: ask for at most this many events in the initial query
+    pub fn limit(mut self, limit: usize) -> Filter {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Does this filter only match events which `other` also matches?
+    ///
+    /// This is true if every condition of `other` is met by the conditions of
+    /// this filter, so a subscription with this filter adds nothing to one with
+    /// `other`. The limit is not considered, as it does not change which events
+    /// match.
+    pub fn narrows(&self, other: &Filter) -> bool {
+        prefixes_narrow(&self.ids, &other.ids)
+            && prefixes_narrow(&self.authors, &other.authors)
+            && values_narrow(&self.kinds, &other.kinds)
+            && other
+                .tags
+                .iter()
+                .all(|(letter, values)| values_narrow(self.tag_values(*letter), values))
+            && other
+                .since
+                .is_none_or(|o| self.since.is_some_and(|s| s >= o))
+            && other
+                .until
+                .is_none_or(|o| self.until.is_some_and(|u| u <= o))
+    }
+
+    /// Combine this filter and `other` into a single filter which matches
+    /// exactly the events that either of them match, if that is possible.
+    ///
+    /// This works when one filter narrows the other, or when they differ in
+    /// only one condition (such as having different authors, or overlapping
+    /// time ranges). Filters with limits are only merged into a broader filter
+    /// without one, as the limits could not otherwise be honored.
+    pub fn merge(&self, other: &Filter) -> Option<Filter> {
+        if self == other {
+            return Some(self.clone());
+        }
+        if self.narrows(other) && other.limit.is_none() {
+            return Some(other.clone());
+        }
+        if other.narrows(self) && self.limit.is_none() {
+            return Some(self.clone());
+        }
+        if self.limit.is_some() || other.limit.is_some() {
+            return None;
+        }
+
+        let mut merged = self.clone();
+        let mut differences = 0;
+        if !same_values(&self.ids, &other.ids) {
+            differences += 1;
+            merged.ids = union_values(&self.ids, &other.ids);
+        }
+        if !same_values(&self.authors, &other.authors) {
+            differences += 1;
+            merged.authors = union_values(&self.authors, &other.authors);
+        }
+        if !same_values(&self.kinds, &other.kinds) {
+            differences += 1;
+            merged.kinds = union_values(&self.kinds, &other.kinds);
+        }
+        let letters: BTreeSet<char> = self.tags.keys().chain(other.tags.keys()).copied().collect();
+        for letter in letters {
+            let (a, b) = (self.tag_values(letter), other.tag_values(letter));
+            if !same_values(a, b) {
+                differences += 1;
+                let _ = merged.tags.insert(letter, union_values(a, b));
+            }
+        }
+        if self.since != other.since || self.until != other.until {
+            differences += 1;
+            // The time ranges must overlap or touch for their union to be a range
+            let latest_since = self.since.max(other.since);
+            let earliest_until = match (self.until, other.until) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            if let (Some(since), Some(until)) = (latest_since, earliest_until) {
+                if since.0 > until.0.saturating_add(1) {
+                    return None;
+                }
+            }
+            merged.since = self.since.min(other.since);
+            merged.until = self
+                .until
+                .max(other.until)
+                .filter(|_| self.until.is_some() && other.until.is_some());
+        }
+        merged.tags.retain(|_, values| !values.is_empty());
+
+        if differences == 1 {
+            Some(merged)
+        } else {
+            None
         }
     }
 
@@ -226,11 +456,16 @@ impl Filter {
             ids: vec![IdHexPrefix::try_from_str("21345b").unwrap()],
             authors: vec![],
             kinds: vec![EventKind::TextNote, EventKind::Metadata],
-            e: vec![IdHex::mock()],
-            p: vec![PublicKeyHex::try_from_str(
-                "221115830ced1ca94352002485fcc7a75dcfe30d1b07f5f6fbe9c0407cfa59a1",
-            )
-            .unwrap()],
+            tags: BTreeMap::from([
+                ('e', vec![IdHex::mock().into_string()]),
+                (
+                    'p',
+                    vec![
+                        "221115830ced1ca94352002485fcc7a75dcfe30d1b07f5f6fbe9c0407cfa59a1"
+                            .to_owned(),
+                    ],
+                ),
+            ]),
             since: Some(Unixtime(1668572286)),
             ..Default::default()
         }
@@ -240,6 +475,7 @@ impl Filter {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::types::{Id, PublicKey};
 
     test_serde! {Filter, test_filters_serde}
 
@@ -302,4 +538,124 @@ mod test {
     }
 
     // add_remove_author would be very similar to the above
+
+    #[test]
+    fn test_filter_builder() {
+        let pubkey = PublicKey::mock_deterministic();
+        let filter = Filter::new()
+            .author(pubkey)
+            .kind(EventKind::TextNote)
+            .kind(EventKind::TextNote)
+            .id(Id::mock())
+            .tag('t', "nostr")
+            .tag('I', "isbn:9780765382030")
+            .tag('1', "ignored")
+            .since(Unixtime(1))
+            .limit(10);
+        assert_eq!(filter.authors, vec![PublicKeyHexPrefix::from(pubkey)]);
+        assert_eq!(filter.kinds, vec![EventKind::TextNote]);
+        assert_eq!(filter.ids, vec![IdHexPrefix::from(Id::mock())]);
+        assert_eq!(filter.tag_values('t'), &["nostr".to_owned()]);
+        assert!(filter.tag_values('1').is_empty());
+        let json = serde_json::to_string(&filter).unwrap();
+        assert!(
+            json.contains(r##""#I":["isbn:9780765382030"],"#t":["nostr"],"since":1,"limit":10"##)
+        );
+        assert_eq!(serde_json::from_str::<Filter>(&json).unwrap(), filter);
+    }
+
+    #[test]
+    fn test_filter_narrows() {
+        let alice = PublicKeyHex::mock_deterministic();
+        let bob = PublicKeyHex::mock();
+        let wide = Filter::new()
+            .author(alice.prefix(8))
+            .kind(EventKind::TextNote)
+            .kind(EventKind::Repost)
+            .since(Unixtime(100));
+        let narrow = Filter::new()
+            .author(alice.clone())
+            .kind(EventKind::TextNote)
+            .tag('t', "nostr")
+            .since(Unixtime(200))
+            .until(Unixtime(300))
+            .limit(5);
+        assert!(narrow.narrows(&wide));
+        assert!(!wide.narrows(&narrow));
+        assert!(wide.narrows(&wide));
+        assert!(Filter::new().narrows(&Filter::new()));
+        assert!(!Filter::new().narrows(&wide));
+        assert!(!Filter::new().author(bob).narrows(&wide));
+        assert!(!Filter::new()
+            .author(alice)
+            .kind(EventKind::TextNote)
+            .since(Unixtime(50))
+            .narrows(&wide));
+    }
+
+    #[test]
+    fn test_filter_merge() {
+        let alice = PublicKeyHex::mock_deterministic();
+        let bob = PublicKeyHex::mock();
+        let notes = Filter::new().kind(EventKind::TextNote);
+
+        // One narrows the other
+        let a = notes.clone().author(alice.clone());
+        assert_eq!(a.merge(&notes), Some(notes.clone()));
+        assert_eq!(notes.merge(&a), Some(notes.clone()));
+        // but not when the broader one has a limit
+        assert_eq!(a.merge(&notes.clone().limit(10)), None);
+
+        // Differing in one condition
+        let b = notes.clone().author(bob.clone());
+        let merged = a.merge(&b).unwrap();
+        assert_eq!(merged.kinds, vec![EventKind::TextNote]);
+        assert_eq!(merged.authors.len(), 2);
+        assert!(a.narrows(&merged) && b.narrows(&merged));
+
+        let t1 = notes.clone().tag('t', "nostr");
+        let t2 = notes.clone().tag('t', "bitcoin");
+        assert_eq!(t1.merge(&t2).unwrap().tag_values('t').len(), 2);
+
+        // Differing in two conditions
+        let c = Filter::new().kind(EventKind::Reaction).author(bob);
+        assert_eq!(a.merge(&c), None);
+        assert_eq!(a.clone().limit(1).merge(&b), None);
+
+        // Time ranges must overlap or touch
+        let early = notes.clone().since(Unixtime(0)).until(Unixtime(100));
+        let late = notes.clone().since(Unixtime(101)).until(Unixtime(200));
+        let merged = early.merge(&late).unwrap();
+        assert_eq!(
+            (merged.since, merged.until),
+            (Some(Unixtime(0)), Some(Unixtime(200)))
+        );
+        let later = notes.clone().since(Unixtime(150));
+        assert_eq!(early.merge(&later), None);
+        let merged = late.merge(&later).unwrap();
+        assert_eq!((merged.since, merged.until), (Some(Unixtime(101)), None));
+
+        // Even at the very end of time
+        let to_end = notes.clone().since(Unixtime(0)).until(Unixtime(i64::MAX));
+        let merged = to_end.merge(&later).unwrap();
+        assert_eq!((merged.since, merged.until), (Some(Unixtime(0)), None));
+    }
+
+    #[test]
+    fn test_filter_dynamic_tags() {
+        let json = r##"{"kinds":[1],"#e":["abc"],"#x":["1","2"],"search":"hello","#":["no"],"#ab":["no"],"limit":5}"##;
+        let filter: Filter = serde_json::from_str(json).unwrap();
+        assert_eq!(filter.tags.len(), 2);
+        assert_eq!(filter.tag_values('e'), &["abc".to_owned()]);
+        assert_eq!(filter.tag_values('x'), &["1".to_owned(), "2".to_owned()]);
+        assert_eq!(filter.limit, Some(5));
+
+        let mut filter = filter;
+        filter.del_tag_value('e', "abc");
+        assert!(!filter.tags.contains_key(&'e'));
+        assert_eq!(
+            serde_json::to_string(&filter).unwrap(),
+            r##"{"kinds":[1],"#x":["1","2"],"limit":5}"##
+        );
+    }
 }
@@ -0,0 +1,320 @@
+//! `arbitrary::Arbitrary` implementations for fuzzing and property testing
+//!
+//! Values are structurally valid rather than uniformly random: public keys are
+//! real curve points, events have correct ids and real signatures, and tags
+//! favor the common names with well-formed values. Content and other free text
+//! is arbitrary.
+
+use super::event_kind::WELL_KNOWN_KINDS;
+use super::{
+    ClientMessage, CountResult, Event, EventKind, Filter, Id, IdHex, Metadata, PreEvent,
+    PrivateKey, PublicKey, PublicKeyHexPrefix, RelayMessage, RelayReason, SubscriptionId, Tag,
+    Unixtime,
+};
+use arbitrary::{Arbitrary, Result, Unstructured};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+// Up to the year 2100
+const MAX_TIME: i64 = 4_102_444_800;
+
+const COMMON_TAG_NAMES: &[&str] = &[
+    "e",
+    "p",
+    "a",
+    "t",
+    "d",
+    "r",
+    "k",
+    "subject",
+    "expiration",
+    "nonce",
+];
+
+const METADATA_FIELDS: &[&str] = &["display_name", "website", "banner", "lud16", "bot"];
+
+// A private key from 32 arbitrary bytes. Fails only if they are zero or not
+// below the curve order, which is vanishingly unlikely.
+fn private_key(u: &mut Unstructured<'_>) -> Result<PrivateKey> {
+    let bytes: [u8; 32] = u.arbitrary()?;
+    PrivateKey::try_from_hex_string(&hex::encode(bytes))
+        .map_err(|_| arbitrary::Error::IncorrectFormat)
+}
+
+fn pre_event(u: &mut Unstructured<'_>, pubkey: PublicKey) -> Result<PreEvent> {
+    let num_tags = u.int_in_range(0..=6)?;
+    let tags = (0..num_tags)
+        .map(|_| u.arbitrary())
+        .collect::<Result<Vec<Tag>>>()?;
+    Ok(PreEvent {
+        pubkey,
+        created_at: u.arbitrary()?,
+        kind: u.arbitrary()?,
+        tags,
+        content: u.arbitrary()?,
+        ots: None,
+    })
+}
+
+impl<'a> Arbitrary<'a> for Unixtime {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Unixtime(u.int_in_range(0..=MAX_TIME)?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for EventKind {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        // Mostly kinds we know, sometimes any kind in the regular, replaceable,
+        // ephemeral and parameterized replaceable ranges
+        if u.ratio(3, 4)? {
+            Ok(*u.choose(WELL_KNOWN_KINDS)?)
+        } else {
+            Ok(EventKind::from(u.int_in_range(0..=39999u32)?))
+        }
+    }
+}
+
+impl<'a> Arbitrary<'a> for Id {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Id(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for PublicKey {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(private_key(u)?.public_key())
+    }
+}
+
+impl<'a> Arbitrary<'a> for Tag {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let name: String = if u.ratio(7, 8)? {
+            (*u.choose(COMMON_TAG_NAMES)?).to_owned()
+        } else {
+            u.arbitrary()?
+        };
+        let value: String = match name.as_str() {
+            "e" => u.arbitrary::<Id>()?.as_hex_string(),
+            "p" => u.arbitrary::<PublicKey>()?.as_hex_string(),
+            "expiration" => u.arbitrary::<Unixtime>()?.0.to_string(),
+            _ => u.arbitrary()?,
+        };
+        let mut fields = vec![name, value];
+        for _ in 0..u.int_in_range(0..=2)? {
+            fields.push(u.arbitrary()?);
+        }
+        Ok(Tag::from_strings(fields))
+    }
+}
+
+impl<'a> Arbitrary<'a> for PreEvent {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let pubkey = u.arbitrary()?;
+        pre_event(u, pubkey)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Event {
+    /// A signed event with a correct id
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let privkey = private_key(u)?;
+        let pre = pre_event(u, privkey.public_key())?;
+        Event::new(pre, &privkey).map_err(|_| arbitrary::Error::IncorrectFormat)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Filter {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut filter = Filter::new();
+        for _ in 0..u.int_in_range(0..=3)? {
+            let id: IdHex = u.arbitrary::<Id>()?.into();
+            filter.ids.push(id.prefix(u.int_in_range(4..=64)?));
+        }
+        for _ in 0..u.int_in_range(0..=3)? {
+            // Any hex will do for a prefix, so skip deriving a real key
+            let bytes: [u8; 32] = u.arbitrary()?;
+            let chars = u.int_in_range(4..=64)?;
+            let prefix: String = hex::encode(bytes).chars().take(chars).collect();
+            filter
+                .authors
+                .push(PublicKeyHexPrefix::try_from_string(prefix).unwrap());
+        }
+        for _ in 0..u.int_in_range(0..=3)? {
+            filter.kinds.push(u.arbitrary()?);
+        }
+        let mut tags: BTreeMap<char, Vec<String>> = BTreeMap::new();
+        for _ in 0..u.int_in_range(0..=2)? {
+            let letter = char::from(u.int_in_range(b'a'..=b'z')?);
+            let values = tags.entry(letter).or_default();
+            for _ in 0..u.int_in_range(1..=3)? {
+                values.push(u.arbitrary()?);
+            }
+        }
+        filter.tags = tags;
+        filter.since = u.arbitrary()?;
+        filter.until = u.arbitrary()?;
+        if u.arbitrary()? {
+            filter.limit = Some(u.int_in_range(0..=500)?);
+        }
+        Ok(filter)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Metadata {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut other: Map<String, Value> = Map::new();
+        for _ in 0..u.int_in_range(0..=2)? {
+            let key = (*u.choose(METADATA_FIELDS)?).to_owned();
+            let _ = other.insert(key, Value::String(u.arbitrary()?));
+        }
+        Ok(Metadata {
+            name: u.arbitrary()?,
+            about: u.arbitrary()?,
+            picture: u.arbitrary()?,
+            nip05: u.arbitrary()?,
+            other,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for SubscriptionId {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(SubscriptionId(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for RelayReason {
+    /// Parsed from an arbitrary message, so it has the form relays send
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let message: String = if u.arbitrary()? {
+            let prefix = *u.choose(&["duplicate", "pow", "blocked", "error", "invalid"])?;
+            format!("{prefix}: {}", u.arbitrary::<String>()?)
+        } else {
+            u.arbitrary()?
+        };
+        Ok(RelayReason::parse(&message))
+    }
+}
+
+impl<'a> Arbitrary<'a> for CountResult {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(CountResult {
+            count: u.arbitrary()?,
+            approximate: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for ClientMessage {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let filters = |u: &mut Unstructured<'a>| {
+            (0..u.int_in_range(1..=3)?)
+                .map(|_| u.arbitrary())
+                .collect::<Result<Vec<Filter>>>()
+        };
+        Ok(match u.int_in_range(0..=4)? {
+            0 => ClientMessage::Event(Box::new(u.arbitrary()?)),
+            1 => ClientMessage::Req(u.arbitrary()?, filters(u)?),
+            2 => ClientMessage::Close(u.arbitrary()?),
+            3 => ClientMessage::Auth(Box::new(u.arbitrary()?)),
+            _ => ClientMessage::Count(u.arbitrary()?, filters(u)?),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for RelayMessage {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=6)? {
+            0 => RelayMessage::Event(u.arbitrary()?, Box::new(u.arbitrary()?)),
+            1 => RelayMessage::Notice(u.arbitrary()?),
+            2 => RelayMessage::Eose(u.arbitrary()?),
+            3 => RelayMessage::Ok(u.arbitrary()?, u.arbitrary()?, u.arbitrary()?),
+            4 => RelayMessage::Auth(u.arbitrary()?),
+            5 => RelayMessage::Closed {
+                subscription: u.arbitrary()?,
+                reason: u.arbitrary()?,
+            },
+            _ => RelayMessage::Count(u.arbitrary()?, u.arbitrary()?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    // Deterministic pseudo-random input
+    fn data(len: usize) -> Vec<u8> {
+        let mut output: Vec<u8> = Vec::with_capacity(len + 32);
+        let mut block: [u8; 32] = [0; 32];
+        while output.len() < len {
+            block = Sha256::digest(block).into();
+            output.extend(block);
+        }
+        output
+    }
+
+    #[test]
+    fn test_arbitrary_events() {
+        let data = data(64 * 1024);
+        let mut u = Unstructured::new(&data);
+        for _ in 0..20 {
+            let event: Event = u.arbitrary().unwrap();
+            assert!(event.verify(None).is_ok());
+            let json = serde_json::to_string(&event).unwrap();
+            assert_eq!(serde_json::from_str::<Event>(&json).unwrap(), event);
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_filters_and_metadata() {
+        let data = data(64 * 1024);
+        let mut u = Unstructured::new(&data);
+        for _ in 0..20 {
+            let filter: Filter = u.arbitrary().unwrap();
+            let json = serde_json::to_string(&filter).unwrap();
+            assert_eq!(serde_json::from_str::<Filter>(&json).unwrap(), filter);
+
+            let metadata: Metadata = u.arbitrary().unwrap();
+            let json = serde_json::to_string(&metadata).unwrap();
+            assert_eq!(serde_json::from_str::<Metadata>(&json).unwrap(), metadata);
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_messages() {
+        // Both directions, as a client and as a relay would use them
+        let data = data(256 * 1024);
+        let mut u = Unstructured::new(&data);
+        for _ in 0..50 {
+            let message: ClientMessage = u.arbitrary().unwrap();
+            let json = serde_json::to_string(&message).unwrap();
+            assert_eq!(
+                serde_json::from_str::<ClientMessage>(&json).unwrap(),
+                message
+            );
+
+            let message: RelayMessage = u.arbitrary().unwrap();
+            let json = serde_json::to_string(&message).unwrap();
+            assert_eq!(
+                serde_json::from_str::<RelayMessage>(&json).unwrap(),
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_garbage_messages() {
+        // Neither side may panic on arbitrary input
+        let data = data(64 * 1024);
+        for chunk in data.chunks(97) {
+            let text = String::from_utf8_lossy(chunk);
+            let _ = serde_json::from_str::<ClientMessage>(&text);
+            let _ = serde_json::from_str::<RelayMessage>(&text);
+            let framed = format!(r#"["EVENT",{text}]"#);
+            let _ = serde_json::from_str::<ClientMessage>(&framed);
+            let _ = serde_json::from_str::<RelayMessage>(&framed);
+        }
+    }
+}
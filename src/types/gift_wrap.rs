@@ -0,0 +1,143 @@
+#[cfg(feature = "std")]
+use super::{Event, PrivateKey};
+use super::{EventKind, Id, PreEvent, PublicKey, Tag, Unixtime};
+use crate::Error;
+#[cfg(feature = "std")]
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+// Seals and gift wraps are backdated by up to this long so their times do not
+// reveal when the message was sent
+#[cfg(feature = "std")]
+const MAX_BACKDATE_SECS: i64 = 2 * 24 * 60 * 60;
+
+/// An unsigned event (NIP-59). It cannot be published on its own, so if it
+/// leaks it cannot be proven to come from its author.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Rumor {
+    /// The Id of the event, generated as a SHA256 of the inner event data
+    pub id: Id,
+
+    /// The public key of the actor who created the event
+    pub pubkey: PublicKey,
+
+    /// The time at which the event was created
+    pub created_at: Unixtime,
+
+    /// The kind of event
+    pub kind: EventKind,
+
+    /// A set of tags that apply to the event
+    pub tags: Vec<Tag>,
+
+    /// The content of the event
+    pub content: String,
+}
+
+impl Rumor {
+    /// Create a rumor from a PreEvent, computing its Id
+    pub fn new(input: PreEvent) -> Result<Rumor, Error> {
+        let id = input.hash();
+        Ok(Rumor {
+            id,
+            pubkey: input.pubkey,
+            created_at: input.created_at,
+            kind: input.kind,
+            tags: input.tags,
+            content: input.content,
+        })
+    }
+
+    /// The PreEvent this rumor was made from
+    pub fn to_pre_event(&self) -> PreEvent {
+        PreEvent {
+            pubkey: self.pubkey,
+            created_at: self.created_at,
+            kind: self.kind,
+            tags: self.tags.clone(),
+            content: self.content.clone(),
+            ots: None,
+        }
+    }
+
+    /// Check that the Id matches the rest of the rumor
+    pub fn verify_id(&self) -> Result<(), Error> {
+        if self.to_pre_event().hash() == self.id {
+            Ok(())
+        } else {
+            Err(Error::HashMismatch)
+        }
+    }
+
+    /// Seal this rumor for `recipient` (kind 13), encrypting it with the
+    /// author's key. `author` must be the rumor's author.
+    #[cfg(feature = "std")]
+    pub fn seal(&self, author: &PrivateKey, recipient: &PublicKey) -> Result<Event, Error> {
+        if author.public_key() != self.pubkey {
+            return Err(Error::SealMismatch);
+        }
+        let json = serde_json::to_string(self)?;
+        let pre = PreEvent {
+            pubkey: self.pubkey,
+            created_at: backdated_now(),
+            kind: EventKind::Seal,
+            tags: vec![],
+            content: author.nip44_encrypt(recipient, &json)?,
+            ots: None,
+        };
+        Event::new(pre, author)
+    }
+
+    /// Seal this rumor and gift wrap it (kind 1059) for `recipient`. The gift
+    /// wrap is signed by a new random key, so only the recipient can tell who
+    /// sent it.
+    #[cfg(feature = "std")]
+    pub fn gift_wrap(&self, author: &PrivateKey, recipient: &PublicKey) -> Result<Event, Error> {
+        let seal = self.seal(author, recipient)?;
+        let json = serde_json::to_string(&seal)?;
+        let ephemeral = PrivateKey::generate();
+        let pre = PreEvent {
+            pubkey: ephemeral.public_key(),
+            created_at: backdated_now(),
+            kind: EventKind::GiftWrap,
+            tags: vec![Tag::new_pubkey((*recipient).into(), None, None)],
+            content: ephemeral.nip44_encrypt(recipient, &json)?,
+            ots: None,
+        };
+        Event::new(pre, &ephemeral)
+    }
+}
+
+// The current time, randomly backdated
+#[cfg(feature = "std")]
+fn backdated_now() -> Unixtime {
+    let backdate = OsRng.next_u64() % MAX_BACKDATE_SECS as u64;
+    Unixtime(Unixtime::now().0 - backdate as i64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::PrivateKey;
+
+    #[test]
+    fn test_rumor() {
+        let privkey = PrivateKey::mock();
+        let pre = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime::mock(),
+            kind: EventKind::DirectMessage,
+            tags: vec![],
+            content: "psst".to_owned(),
+            ots: None,
+        };
+        let mut rumor = Rumor::new(pre).unwrap();
+        assert!(rumor.verify_id().is_ok());
+        let json = serde_json::to_string(&rumor).unwrap();
+        assert!(!json.contains("sig"));
+        assert_eq!(serde_json::from_str::<Rumor>(&json).unwrap(), rumor);
+
+        rumor.content = "changed".to_owned();
+        assert!(matches!(rumor.verify_id(), Err(Error::HashMismatch)));
+    }
+}
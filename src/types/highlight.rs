@@ -0,0 +1,186 @@
+use super::{EventAddr, Id, PublicKeyHex, Tag, UncheckedUrl};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+
+/// Where a highlight (NIP-84) was taken from
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub enum HighlightSource {
+    /// A nostr event, from an 'e' tag
+    Event(Id, Option<UncheckedUrl>),
+
+    /// A replaceable nostr event such as a long-form article, from an 'a' tag
+    Address(EventAddr),
+
+    /// A web page or other URL, from an 'r' tag
+    Url(UncheckedUrl),
+}
+
+/// Someone credited in a highlight (NIP-84), from a 'p' tag
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct HighlightAttribution {
+    /// The person
+    pub pubkey: PublicKeyHex,
+
+    /// A relay where the person can be found
+    pub relay: Option<UncheckedUrl>,
+
+    /// Their role, e.g. "author", "editor" or "mention"
+    pub role: Option<String>,
+}
+
+/// A highlight (NIP-84, kind 9802)
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct Highlight {
+    /// The highlighted text. This is the event content.
+    pub text: String,
+
+    /// Where the text was highlighted from
+    pub source: Option<HighlightSource>,
+
+    /// The surrounding text, when the highlight is only part of a paragraph
+    pub context: Option<String>,
+
+    /// A comment on the highlight, making it a quote highlight
+    pub comment: Option<String>,
+
+    /// The authors and editors of the source
+    pub attributions: Vec<HighlightAttribution>,
+}
+
+impl Highlight {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> Highlight {
+        Highlight {
+            text: "the quick brown fox".to_owned(),
+            source: Some(HighlightSource::Url(UncheckedUrl::mock())),
+            context: Some("Once, the quick brown fox jumped.".to_owned()),
+            comment: None,
+            attributions: vec![HighlightAttribution {
+                pubkey: PublicKeyHex::mock_deterministic(),
+                relay: None,
+                role: Some("author".to_owned()),
+            }],
+        }
+    }
+
+    /// Read a highlight from the tags and content of a kind 9802 event
+    pub fn from_tags(tags: &[Tag], content: &str) -> Highlight {
+        let mut highlight = Highlight {
+            text: content.to_owned(),
+            ..Default::default()
+        };
+        // Prefer a nostr source to a URL, as the URL may only be where the
+        // nostr event was being viewed
+        let mut url_source: Option<HighlightSource> = None;
+        for tag in tags {
+            match tag.tagname() {
+                "e" if highlight.source.is_none() => {
+                    if let Ok((id, relay, marker)) = tag.parse_event() {
+                        if marker.as_deref() != Some("mention") {
+                            highlight.source = Some(HighlightSource::Event(id, relay));
+                        }
+                    }
+                }
+                "a" => {
+                    if let Ok((ea, marker)) = tag.parse_address() {
+                        if marker.as_deref() != Some("mention") {
+                            highlight.source = Some(HighlightSource::Address(ea));
+                        }
+                    }
+                }
+                "r" if url_source.is_none()
+                    && !tag.get_index(1).is_empty()
+                    && matches!(tag.get_index(2), "" | "source") =>
+                {
+                    url_source = Some(HighlightSource::Url(UncheckedUrl::from_str(
+                        tag.get_index(1),
+                    )));
+                }
+                "context" => highlight.context = Some(tag.get_index(1).to_owned()),
+                "comment" => highlight.comment = Some(tag.get_index(1).to_owned()),
+                "p" => {
+                    if let Ok((pubkey, relay, role)) = tag.parse_pubkey() {
+                        highlight.attributions.push(HighlightAttribution {
+                            pubkey,
+                            relay,
+                            role,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        if highlight.source.is_none() {
+            highlight.source = url_source;
+        }
+        highlight
+    }
+
+    /// Write this highlight out as tags for a kind 9802 event. The
+    /// highlighted text goes in the content.
+    pub fn to_tags(&self) -> Vec<Tag> {
+        let mut tags: Vec<Tag> = Vec::new();
+        match &self.source {
+            Some(HighlightSource::Event(id, relay)) => {
+                tags.push(Tag::new_event(*id, relay.clone(), None))
+            }
+            Some(HighlightSource::Address(ea)) => tags.push(Tag::new_address(ea, None)),
+            Some(HighlightSource::Url(url)) => tags.push(Tag::new(&["r", url.as_str(), "source"])),
+            None => {}
+        }
+        if let Some(context) = &self.context {
+            tags.push(Tag::new(&["context", context]));
+        }
+        if let Some(comment) = &self.comment {
+            tags.push(Tag::new(&["comment", comment]));
+        }
+        for a in &self.attributions {
+            tags.push(Tag::new_pubkey(
+                a.pubkey.clone(),
+                a.relay.clone(),
+                a.role.clone(),
+            ));
+        }
+        tags
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {Highlight, test_highlight_serde}
+
+    #[test]
+    fn test_highlight_tags() {
+        let highlight = Highlight::mock();
+        let tags = highlight.to_tags();
+        assert_eq!(Highlight::from_tags(&tags, &highlight.text), highlight);
+
+        let highlight = Highlight {
+            source: Some(HighlightSource::Event(Id::mock(), None)),
+            comment: Some("So true".to_owned()),
+            ..Highlight::mock()
+        };
+        let tags = highlight.to_tags();
+        assert_eq!(Highlight::from_tags(&tags, &highlight.text), highlight);
+
+        // A nostr source wins over a URL, and mentions are not sources
+        let tags = vec![
+            Tag::new(&["r", "https://example.com/viewer", "source"]),
+            Tag::new(&["r", "https://example.com/other", "mention"]),
+            Tag::new_event(Id::mock(), None, None),
+        ];
+        assert_eq!(
+            Highlight::from_tags(&tags, "").source,
+            Some(HighlightSource::Event(Id::mock(), None))
+        );
+        let tags = vec![Tag::new(&["r", "https://example.com/other", "mention"])];
+        assert_eq!(Highlight::from_tags(&tags, "").source, None);
+    }
+}
@@ -1,12 +1,17 @@
+use super::{PublicKey, Signature};
 use crate::Error;
 use bech32::{FromBase32, ToBase32};
-use derive_more::{AsMut, AsRef, Deref, Display, From, FromStr, Into};
+use derive_more::{AsMut, AsRef, Deref, Display, From, Into};
 use serde::de::{Deserializer, Visitor};
 use serde::ser::Serializer;
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "speedy")]
-use speedy::{Readable, Writable};
+use speedy::{Context, Readable, Reader, Writable};
+use std::borrow::Borrow;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use subtle::{Choice, ConstantTimeEq};
 
 /// An event identifier, constructed as a SHA256 hash of the event fields according to NIP-01
 #[derive(
@@ -31,6 +36,22 @@ impl Id {
             .map_err(|_| Error::WrongLengthHexString)?))
     }
 
+    /// The 32 bytes of the id
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// From the 32 bytes of an id
+    pub fn from_bytes(bytes: [u8; 32]) -> Id {
+        Id(bytes)
+    }
+
+    /// Whether the hex form of this id starts with `prefix`, as in a NIP-01
+    /// filter. The prefix may have an odd number of hex digits, in either case.
+    pub fn starts_with_hex(&self, prefix: &str) -> bool {
+        bytes_start_with_hex(&self.0, prefix)
+    }
+
     /// Export as a bech32 encoded string ("note")
     pub fn as_bech32_string(&self) -> String {
         bech32::encode("note", self.0.to_vec().to_base32(), bech32::Variant::Bech32).unwrap()
@@ -54,6 +75,12 @@ impl Id {
         }
     }
 
+    /// Verify that `signature` is `pubkey`'s signature over this id, which
+    /// may be any 32-byte hash, not only an event id
+    pub fn verify_signature(&self, pubkey: &PublicKey, signature: &Signature) -> Result<(), Error> {
+        pubkey.verify_id(*self, signature)
+    }
+
     // Mock data for testing
     #[allow(dead_code)]
     pub(crate) fn mock() -> Id {
@@ -62,6 +89,34 @@ impl Id {
     }
 }
 
+/// Displays as a note. Use `as_hex_string()` for hex.
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_bech32_string())
+    }
+}
+
+/// Parses either a note or hex
+impl FromStr for Id {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Id, Error> {
+        if s.starts_with("note1") {
+            Id::try_from_bech32_string(s)
+        } else {
+            Id::try_from_hex_string(s)
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for Id {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Id, Error> {
+        Ok(Id(<[u8; 32]>::try_from(bytes)?))
+    }
+}
+
 impl Serialize for Id {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -106,9 +161,12 @@ impl Visitor<'_> for IdVisitor {
 
 /// An event identifier, constructed as a SHA256 hash of the event fields according to NIP-01, as a hex string
 ///
-/// You can convert from an `Id` into this with `From`/`Into`.  You can convert this back to an `Id` with `TryFrom`/`TryInto`.
-#[derive(AsMut, AsRef, Clone, Debug, Deref, Display, Eq, From, FromStr, Hash, Into, PartialEq)]
-#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+/// You can convert from an `Id` into this with `From`/`Into`, and back the same way.
+///
+/// It is always 64 lowercase hex digits; construction fails otherwise. Equality
+/// is checked in constant time.
+#[derive(AsRef, Clone, Debug, Deref, Display, Eq, Into)]
+#[cfg_attr(feature = "speedy", derive(Writable))]
 pub struct IdHex(String);
 
 impl IdHex {
@@ -125,11 +183,7 @@ impl IdHex {
 
     /// Try from String
     pub fn try_from_string(s: String) -> Result<IdHex, Error> {
-        if s.len() != 64 {
-            return Err(Error::InvalidId);
-        }
-        let vec: Vec<u8> = hex::decode(&s)?;
-        if vec.len() != 32 {
+        if s.len() != 64 || !is_lowercase_hex(&s) {
             return Err(Error::InvalidId);
         }
         Ok(IdHex(s))
@@ -145,6 +199,16 @@ impl IdHex {
         self.0
     }
 
+    /// Export as a bech32 encoded string ("note")
+    pub fn as_bech32_string(&self) -> String {
+        Id::from(self).as_bech32_string()
+    }
+
+    /// Import from a bech32 encoded string ("note")
+    pub fn try_from_bech32_string(s: &str) -> Result<IdHex, Error> {
+        Ok(Id::try_from_bech32_string(s)?.into())
+    }
+
     /// Prefix of
     pub fn prefix(&self, mut chars: usize) -> IdHexPrefix {
         if chars > 64 {
@@ -154,6 +218,38 @@ impl IdHex {
     }
 }
 
+impl ConstantTimeEq for IdHex {
+    fn ct_eq(&self, other: &IdHex) -> Choice {
+        self.0.as_bytes().ct_eq(other.0.as_bytes())
+    }
+}
+
+impl PartialEq for IdHex {
+    fn eq(&self, other: &IdHex) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Hash for IdHex {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl Borrow<str> for IdHex {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for IdHex {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<IdHex, Error> {
+        IdHex::try_from_str(s)
+    }
+}
+
 impl TryFrom<&str> for IdHex {
     type Error = Error;
 
@@ -162,16 +258,42 @@ impl TryFrom<&str> for IdHex {
     }
 }
 
+impl TryFrom<String> for IdHex {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<IdHex, Error> {
+        IdHex::try_from_string(s)
+    }
+}
+
 impl From<Id> for IdHex {
     fn from(i: Id) -> IdHex {
         IdHex(i.as_hex_string())
     }
 }
 
+impl From<&IdHex> for Id {
+    fn from(h: &IdHex) -> Id {
+        let mut bytes = [0u8; 32];
+        // Cannot fail: an IdHex is always 64 hex digits
+        let _ = hex::decode_to_slice(&h.0, &mut bytes);
+        Id(bytes)
+    }
+}
+
 impl From<IdHex> for Id {
     fn from(h: IdHex) -> Id {
-        // could only fail if IdHex is invalid
-        Id::try_from_hex_string(&h.0).unwrap()
+        Id::from(&h)
+    }
+}
+
+#[cfg(feature = "speedy")]
+impl<'a, C: Context> Readable<'a, C> for IdHex {
+    #[inline]
+    fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
+        let s = String::read_from(reader)?;
+        let idhex = IdHex::try_from_string(s).map_err(|e| speedy::Error::custom(e))?;
+        Ok(idhex)
     }
 }
 
@@ -209,10 +331,8 @@ impl Visitor<'_> for IdHexVisitor {
         if v.len() != 64 {
             return Err(serde::de::Error::custom("IdHex is not 64 characters long"));
         }
-
-        let vec: Vec<u8> = hex::decode(v).map_err(|e| serde::de::Error::custom(format!("{e}")))?;
-        if vec.len() != 32 {
-            return Err(serde::de::Error::custom("Invalid IdHex"));
+        if !is_lowercase_hex(v) {
+            return Err(serde::de::Error::custom("IdHex is not lowercase hex"));
         }
 
         Ok(IdHex(v.to_owned()))
@@ -220,23 +340,13 @@ impl Visitor<'_> for IdHexVisitor {
 }
 
 /// An event identifier prefix, constructed as a SHA256 hash of the event fields according to NIP-01, as a hex string
+///
+/// It is always at most 64 lowercase hex digits.
 #[derive(
-    AsMut,
-    AsRef,
-    Clone,
-    Debug,
-    Deref,
-    Deserialize,
-    Display,
-    Eq,
-    From,
-    FromStr,
-    Hash,
-    Into,
-    PartialEq,
-    Serialize,
+    AsRef, Clone, Debug, Deref, Deserialize, Display, Eq, Hash, Into, PartialEq, Serialize,
 )]
 #[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+#[serde(try_from = "String")]
 pub struct IdHexPrefix(String);
 
 impl IdHexPrefix {
@@ -253,16 +363,9 @@ impl IdHexPrefix {
 
     /// Try from String
     pub fn try_from_string(s: String) -> Result<IdHexPrefix, Error> {
-        if s.len() > 64 {
+        if s.len() > 64 || !is_lowercase_hex(&s) {
             return Err(Error::InvalidIdPrefix);
         }
-        if s.chars().any(|c| !c.is_ascii_hexdigit()) {
-            return Err(Error::InvalidPublicKeyPrefix);
-        }
-        // let vec: Vec<u8> = hex::decode(&s)?;
-        // if vec.len() > 32 {
-        //     return Err(Error::InvalidIdPrefix);
-        // }
         Ok(IdHexPrefix(s))
     }
 
@@ -288,6 +391,20 @@ impl From<IdHex> for IdHexPrefix {
     }
 }
 
+impl From<Id> for IdHexPrefix {
+    fn from(id: Id) -> IdHexPrefix {
+        IdHexPrefix(id.as_hex_string())
+    }
+}
+
+impl FromStr for IdHexPrefix {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<IdHexPrefix, Error> {
+        IdHexPrefix::try_from_str(s)
+    }
+}
+
 impl TryFrom<&str> for IdHexPrefix {
     type Error = Error;
 
@@ -296,6 +413,32 @@ impl TryFrom<&str> for IdHexPrefix {
     }
 }
 
+impl TryFrom<String> for IdHexPrefix {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<IdHexPrefix, Error> {
+        IdHexPrefix::try_from_string(s)
+    }
+}
+
+// Hex as NIP-01 writes it: digits and lowercase letters only
+pub(crate) fn is_lowercase_hex(s: &str) -> bool {
+    s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+// Whether the hex form of `bytes` starts with `prefix`, compared a nibble at a
+// time so neither side needs converting
+pub(crate) fn bytes_start_with_hex(bytes: &[u8], prefix: &str) -> bool {
+    if prefix.len() > bytes.len() * 2 {
+        return false;
+    }
+    prefix.chars().enumerate().all(|(i, c)| {
+        let byte = bytes[i / 2];
+        let nibble = if i % 2 == 0 { byte >> 4 } else { byte & 0x0f };
+        c.to_digit(16) == Some(nibble as u32)
+    })
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -310,4 +453,68 @@ mod test {
         println!("{bech32}");
         assert_eq!(Id::mock(), Id::try_from_bech32_string(&bech32).unwrap());
     }
+
+    #[test]
+    fn test_id_from_str() {
+        let id = Id::mock();
+        assert_eq!(id.to_string(), id.as_bech32_string());
+        assert_eq!(id.to_string().parse::<Id>().unwrap(), id);
+        assert_eq!(id.as_hex_string().parse::<Id>().unwrap(), id);
+        assert!("abcd".parse::<Id>().is_err());
+    }
+
+    #[test]
+    fn test_id_hex_bech32() {
+        let idhex = IdHex::mock();
+        let bech32 = idhex.as_bech32_string();
+        assert_eq!(bech32, Id::mock().as_bech32_string());
+        assert_eq!(idhex, IdHex::try_from_bech32_string(&bech32).unwrap());
+
+        // Wrong human readable part
+        let npub = "npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg";
+        assert!(IdHex::try_from_bech32_string(npub).is_err());
+    }
+
+    #[test]
+    fn test_id_bytes() {
+        let id = Id::mock();
+        assert_eq!(Id::from_bytes(*id.as_bytes()), id);
+        assert_eq!(Id::try_from(&id.as_bytes()[..]).unwrap(), id);
+        assert!(Id::try_from(&id.as_bytes()[1..]).is_err());
+    }
+
+    #[test]
+    fn test_id_starts_with_hex() {
+        let id = Id::mock();
+        assert!(id.starts_with_hex(""));
+        assert!(id.starts_with_hex("5df6"));
+        assert!(id.starts_with_hex("5DF64"));
+        assert!(id.starts_with_hex(&id.as_hex_string()));
+        assert!(!id.starts_with_hex("5df7"));
+        assert!(!id.starts_with_hex("5dx"));
+        assert!(!id.starts_with_hex(&format!("{}0", id.as_hex_string())));
+    }
+
+    #[test]
+    fn test_id_hex_validation() {
+        let lower = Id::mock().as_hex_string();
+        let upper = lower.to_uppercase();
+        assert!(IdHex::try_from_str(&upper).is_err());
+        assert!(upper.parse::<IdHex>().is_err());
+        assert!(serde_json::from_str::<IdHex>(&format!("\"{upper}\"")).is_err());
+        assert!(IdHexPrefix::try_from_str("5DF6").is_err());
+        assert!(serde_json::from_str::<IdHexPrefix>("\"5DF6\"").is_err());
+
+        let idhex: IdHex = lower.parse().unwrap();
+        assert_eq!(Id::from(&idhex), Id::mock());
+        let set: std::collections::HashSet<IdHex> = [idhex].into();
+        assert!(set.contains(lower.as_str()));
+    }
+
+    #[test]
+    fn test_id_hex_ct_eq() {
+        let a = IdHex::mock();
+        assert!(bool::from(a.ct_eq(&IdHex::mock())));
+        assert_ne!(a, IdHex::from(Id([0; 32])));
+    }
 }
@@ -0,0 +1,285 @@
+use super::{EncryptedPrivateKey, Event, PreEvent, PrivateKey, PublicKey, RelayUrl};
+use crate::Error;
+use std::fmt;
+
+/// How an identity signs
+pub enum KeySource {
+    /// A private key held in memory
+    Local(PrivateKey),
+
+    /// A private key encrypted with a password (NIP-49), decrypted only
+    /// while signing
+    Encrypted(EncryptedPrivateKey),
+
+    /// A remote signer (NIP-46) that holds the key. Signing is a request to
+    /// it over these relays, which is up to the caller.
+    Remote {
+        /// The remote signer's own public key
+        signer: PublicKey,
+
+        /// The relays the remote signer listens on
+        relays: Vec<RelayUrl>,
+    },
+}
+
+impl fmt::Debug for KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeySource::Local(_) => f.write_str("Local(..)"),
+            KeySource::Encrypted(_) => f.write_str("Encrypted(..)"),
+            KeySource::Remote { signer, relays } => f
+                .debug_struct("Remote")
+                .field("signer", signer)
+                .field("relays", relays)
+                .finish(),
+        }
+    }
+}
+
+/// A nostr identity: a public key, and how to sign for it if we can
+#[derive(Debug)]
+pub struct Identity {
+    /// The public key
+    pub public_key: PublicKey,
+
+    /// How to sign, or None if we can only watch this identity
+    pub key: Option<KeySource>,
+}
+
+impl Identity {
+    /// An identity we hold the private key for
+    pub fn new_local(private_key: PrivateKey) -> Identity {
+        Identity {
+            public_key: private_key.public_key(),
+            key: Some(KeySource::Local(private_key)),
+        }
+    }
+
+    /// An identity whose private key we hold encrypted. The public key is
+    /// checked against the key each time it is decrypted.
+    pub fn new_encrypted(public_key: PublicKey, encrypted: EncryptedPrivateKey) -> Identity {
+        Identity {
+            public_key,
+            key: Some(KeySource::Encrypted(encrypted)),
+        }
+    }
+
+    /// An identity whose key is held by a remote signer (NIP-46)
+    pub fn new_remote(public_key: PublicKey, signer: PublicKey, relays: Vec<RelayUrl>) -> Identity {
+        Identity {
+            public_key,
+            key: Some(KeySource::Remote { signer, relays }),
+        }
+    }
+
+    /// An identity we cannot sign for, e.g. to browse as someone else
+    pub fn new_watch_only(public_key: PublicKey) -> Identity {
+        Identity {
+            public_key,
+            key: None,
+        }
+    }
+
+    /// Whether `sign_event` can sign without a password or a remote signer
+    pub fn is_unlocked(&self) -> bool {
+        matches!(self.key, Some(KeySource::Local(_)))
+    }
+
+    /// Whether we can sign for this identity at all, in some way
+    pub fn can_sign(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Decrypt an encrypted key and keep it in memory, so signing no longer
+    /// needs the password. Does nothing if the key is already in memory.
+    pub fn unlock(&mut self, password: &str) -> Result<(), Error> {
+        match &self.key {
+            Some(KeySource::Local(_)) => Ok(()),
+            Some(KeySource::Encrypted(encrypted)) => {
+                let private_key = encrypted.decrypt(password)?;
+                if private_key.public_key() != self.public_key {
+                    return Err(Error::WrongPublicKey);
+                }
+                self.key = Some(KeySource::Local(private_key));
+                Ok(())
+            }
+            Some(KeySource::Remote { .. }) => Err(Error::CannotSign(
+                "the key is held by a remote signer".to_owned(),
+            )),
+            None => Err(Error::CannotSign("watch-only identity".to_owned())),
+        }
+    }
+
+    /// Sign an event. `password` is needed if the key is encrypted and not
+    /// unlocked. Events for identities with a remote signer must be sent to
+    /// it to sign.
+    pub fn sign_event(&self, input: PreEvent, password: Option<&str>) -> Result<Event, Error> {
+        if input.pubkey != self.public_key {
+            return Err(Error::WrongPublicKey);
+        }
+        match &self.key {
+            Some(KeySource::Local(private_key)) => Event::new(input, private_key),
+            Some(KeySource::Encrypted(encrypted)) => {
+                let password =
+                    password.ok_or_else(|| Error::CannotSign("the key is locked".to_owned()))?;
+                let private_key = encrypted.decrypt(password)?;
+                if private_key.public_key() != self.public_key {
+                    return Err(Error::WrongPublicKey);
+                }
+                Event::new(input, &private_key)
+            }
+            Some(KeySource::Remote { .. }) => Err(Error::CannotSign(
+                "the key is held by a remote signer".to_owned(),
+            )),
+            None => Err(Error::CannotSign("watch-only identity".to_owned())),
+        }
+    }
+}
+
+/// The identities of a multi-account client, one of which is selected
+#[derive(Debug, Default)]
+pub struct Keyring {
+    identities: Vec<Identity>,
+    selected: Option<PublicKey>,
+}
+
+impl Keyring {
+    /// An empty keyring
+    pub fn new() -> Keyring {
+        Keyring::default()
+    }
+
+    /// Add an identity, replacing any with the same public key. The first
+    /// identity added is selected.
+    pub fn add(&mut self, identity: Identity) {
+        if self.selected.is_none() {
+            self.selected = Some(identity.public_key);
+        }
+        match self
+            .identities
+            .iter_mut()
+            .find(|i| i.public_key == identity.public_key)
+        {
+            Some(existing) => *existing = identity,
+            None => self.identities.push(identity),
+        }
+    }
+
+    /// Remove an identity. If it was selected, nothing is selected.
+    pub fn remove(&mut self, public_key: &PublicKey) -> Option<Identity> {
+        let index = self
+            .identities
+            .iter()
+            .position(|i| &i.public_key == public_key)?;
+        if self.selected.as_ref() == Some(public_key) {
+            self.selected = None;
+        }
+        Some(self.identities.remove(index))
+    }
+
+    /// The identity with this public key
+    pub fn get(&self, public_key: &PublicKey) -> Option<&Identity> {
+        self.identities.iter().find(|i| &i.public_key == public_key)
+    }
+
+    /// The identity with this public key, to unlock it
+    pub fn get_mut(&mut self, public_key: &PublicKey) -> Option<&mut Identity> {
+        self.identities
+            .iter_mut()
+            .find(|i| &i.public_key == public_key)
+    }
+
+    /// All identities, in the order they were added
+    pub fn iter(&self) -> impl Iterator<Item = &Identity> {
+        self.identities.iter()
+    }
+
+    /// Select the identity with this public key. Returns false, changing
+    /// nothing, if there is none.
+    pub fn select(&mut self, public_key: &PublicKey) -> bool {
+        if self.get(public_key).is_none() {
+            return false;
+        }
+        self.selected = Some(*public_key);
+        true
+    }
+
+    /// The selected identity
+    pub fn selected(&self) -> Option<&Identity> {
+        self.get(self.selected.as_ref()?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{EventKind, Unixtime};
+
+    fn note(public_key: PublicKey) -> PreEvent {
+        PreEvent {
+            pubkey: public_key,
+            created_at: Unixtime(1_700_000_000),
+            kind: EventKind::TextNote,
+            tags: vec![],
+            content: "hello".to_owned(),
+            ots: None,
+        }
+    }
+
+    #[test]
+    fn test_identity_signing() {
+        let local = Identity::new_local(PrivateKey::generate());
+        let event = local.sign_event(note(local.public_key), None).unwrap();
+        assert!(event.verify(None).is_ok());
+        assert!(matches!(
+            local.sign_event(note(PrivateKey::generate().public_key()), None),
+            Err(Error::WrongPublicKey)
+        ));
+
+        let private_key = PrivateKey::generate();
+        let public_key = private_key.public_key();
+        let encrypted = private_key.export_encrypted("secret", 8).unwrap();
+        let mut identity = Identity::new_encrypted(public_key, encrypted);
+        assert!(!identity.is_unlocked());
+        assert!(identity.sign_event(note(public_key), None).is_err());
+        assert!(identity
+            .sign_event(note(public_key), Some("secret"))
+            .is_ok());
+        assert!(identity.unlock("wrong").is_err());
+        identity.unlock("secret").unwrap();
+        assert!(identity.is_unlocked());
+        assert!(identity.sign_event(note(public_key), None).is_ok());
+
+        let watched = Identity::new_watch_only(public_key);
+        assert!(!watched.can_sign());
+        assert!(matches!(
+            watched.sign_event(note(public_key), None),
+            Err(Error::CannotSign(_))
+        ));
+    }
+
+    #[test]
+    fn test_keyring() {
+        let alice = Identity::new_local(PrivateKey::generate());
+        let alice_pk = alice.public_key;
+        let bob_pk = PrivateKey::generate().public_key();
+        let relays = vec![RelayUrl::try_from_str("wss://relay.example.com").unwrap()];
+        let bob = Identity::new_remote(bob_pk, PrivateKey::generate().public_key(), relays);
+
+        let mut keyring = Keyring::new();
+        keyring.add(alice);
+        keyring.add(bob);
+        assert_eq!(keyring.selected().unwrap().public_key, alice_pk);
+        assert!(keyring.select(&bob_pk));
+        assert!(!keyring.selected().unwrap().is_unlocked());
+        assert!(!keyring.select(&PrivateKey::generate().public_key()));
+        assert_eq!(keyring.selected().unwrap().public_key, bob_pk);
+
+        keyring.add(Identity::new_watch_only(bob_pk));
+        assert_eq!(keyring.iter().count(), 2);
+        assert!(!keyring.get(&bob_pk).unwrap().can_sign());
+        assert!(keyring.remove(&bob_pk).is_some());
+        assert!(keyring.selected().is_none());
+        assert!(format!("{keyring:?}").contains("Local(..)"));
+    }
+}
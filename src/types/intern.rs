@@ -0,0 +1,174 @@
+//! Sharing one copy of the strings that recur across many events
+//!
+//! Tag names, markers and relay URLs make up much of the memory of a large
+//! set of events, as each tag would otherwise hold its own copy of "p" or
+//! "wss://relay.damus.io". Tag fields are `Arc<str>`s, and these are shared
+//! through here as tags are built and parsed.
+//!
+//! Relay URLs are pooled per thread, so parsing takes no lock, and the pool
+//! only holds weak references, so a URL is forgotten once no tag uses it.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+
+// Tag names and markers common enough to always share
+const COMMON: &[&str] = &[
+    "",
+    "-",
+    "a",
+    "A",
+    "client",
+    "d",
+    "e",
+    "E",
+    "emoji",
+    "expiration",
+    "g",
+    "i",
+    "imeta",
+    "k",
+    "K",
+    "l",
+    "L",
+    "mention",
+    "nonce",
+    "p",
+    "P",
+    "q",
+    "r",
+    "read",
+    "relay",
+    "relays",
+    "reply",
+    "root",
+    "subject",
+    "t",
+    "title",
+    "write",
+];
+
+// The longest of COMMON, so longer strings can skip looking there
+const MAX_COMMON_LEN: usize = 10;
+
+// Relay URLs in use are shared up to this many per thread, so that a flood
+// of junk URLs that are all still held cannot grow the pool without bound
+const MAX_RELAY_URLS: usize = 65536;
+
+// The pool is first swept of URLs no longer in use at this size
+const FIRST_SWEEP: usize = 1024;
+
+// Longer relay URLs are too unusual to be worth sharing
+const MAX_RELAY_URL_LEN: usize = 256;
+
+lazy_static::lazy_static! {
+    static ref COMMON_STRINGS: HashMap<&'static str, Arc<str>> =
+        COMMON.iter().map(|s| (*s, Arc::from(*s))).collect();
+}
+
+// Relay URLs seen by this thread, while any tag still holds them
+struct RelayUrlPool {
+    urls: HashMap<Box<str>, Weak<str>>,
+    sweep_at: usize,
+    refused: usize,
+}
+
+impl RelayUrlPool {
+    fn get_or_insert(&mut self, s: &str) -> Option<Arc<str>> {
+        if let Some(url) = self.urls.get(s).and_then(Weak::upgrade) {
+            return Some(url);
+        }
+        if self.urls.len() >= self.sweep_at && !self.urls.contains_key(s) {
+            // When full, sweep again only after refusing as many URLs as the
+            // first sweep size, so a full pool is not swept for every one
+            if self.urls.len() >= MAX_RELAY_URLS && self.refused < FIRST_SWEEP {
+                self.refused += 1;
+                return None;
+            }
+            self.refused = 0;
+            self.urls.retain(|_, url| url.strong_count() > 0);
+            // Sweep again only once the pool has doubled, so that sweeping
+            // costs little for each URL added
+            self.sweep_at = (self.urls.len() * 2).clamp(FIRST_SWEEP, MAX_RELAY_URLS);
+            if self.urls.len() >= MAX_RELAY_URLS {
+                return None;
+            }
+        }
+        let url: Arc<str> = Arc::from(s);
+        let _ = self.urls.insert(Box::from(s), Arc::downgrade(&url));
+        Some(url)
+    }
+}
+
+thread_local! {
+    static RELAY_URLS: RefCell<RelayUrlPool> = RefCell::new(RelayUrlPool {
+        urls: HashMap::new(),
+        sweep_at: FIRST_SWEEP,
+        refused: 0,
+    });
+}
+
+// A shared copy of `s` if it is a common string or relay URL
+fn shared(s: &str) -> Option<Arc<str>> {
+    if s.len() <= MAX_COMMON_LEN {
+        if let Some(common) = COMMON_STRINGS.get(s) {
+            return Some(common.clone());
+        }
+    }
+    if s.len() <= MAX_RELAY_URL_LEN && (s.starts_with("wss://") || s.starts_with("ws://")) {
+        return RELAY_URLS.with(|pool| pool.borrow_mut().get_or_insert(s));
+    }
+    None
+}
+
+/// A tag field, shared with other tags if it is a common one
+pub(crate) fn intern(s: &str) -> Arc<str> {
+    shared(s).unwrap_or_else(|| Arc::from(s))
+}
+
+/// A tag field from an owned string, shared with other tags if it is a
+/// common one
+pub(crate) fn intern_owned(s: String) -> Arc<str> {
+    shared(&s).unwrap_or_else(|| Arc::from(s))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_intern() {
+        assert!(COMMON.iter().all(|s| s.len() <= MAX_COMMON_LEN));
+        assert!(Arc::ptr_eq(&intern("p"), &intern_owned("p".to_owned())));
+        assert!(Arc::ptr_eq(
+            &intern("wss://relay.example.com"),
+            &intern("wss://relay.example.com")
+        ));
+        assert!(!Arc::ptr_eq(&intern("hello"), &intern("hello")));
+        assert_eq!(&*intern("hello"), "hello");
+    }
+
+    #[test]
+    fn test_relay_urls_forgotten() {
+        // URLs no longer held are swept rather than filling the pool
+        let held = intern("wss://held.example.com");
+        for i in 0..MAX_RELAY_URLS * 2 {
+            let _ = intern(&format!("wss://junk{i}.example.com"));
+        }
+        let count = RELAY_URLS.with(|pool| pool.borrow().urls.len());
+        assert!(count < MAX_RELAY_URLS);
+        let fresh = intern("wss://fresh.example.com");
+        assert!(Arc::ptr_eq(&fresh, &intern("wss://fresh.example.com")));
+        assert!(Arc::ptr_eq(&held, &intern("wss://held.example.com")));
+
+        // But while they are held, the pool stops growing at its limit
+        let kept: Vec<Arc<str>> = (0..MAX_RELAY_URLS + 10)
+            .map(|i| intern(&format!("wss://kept{i}.example.com")))
+            .collect();
+        let count = RELAY_URLS.with(|pool| pool.borrow().urls.len());
+        assert!(count <= MAX_RELAY_URLS);
+        let extra = intern("wss://one-more.example.com");
+        assert!(!Arc::ptr_eq(&extra, &intern("wss://one-more.example.com")));
+        drop(kept);
+    }
+}
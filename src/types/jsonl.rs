@@ -0,0 +1,271 @@
+use super::{Event, ParseLimits};
+use crate::Error;
+use std::fmt;
+use std::io::{BufRead, Read, Write};
+
+/// What `read_jsonl()` does with a line that is not a valid, correctly
+/// signed event
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum JsonlErrorMode {
+    /// Skip it, only counting it (see `JsonlReader::skipped()`)
+    #[default]
+    Skip,
+
+    /// Yield the error and end there
+    Stop,
+
+    /// Skip it, keeping the error (see `JsonlReader::errors()`)
+    Collect,
+}
+
+/// A line of a JSON Lines archive that could not be read as an event
+#[derive(Debug)]
+pub struct JsonlError {
+    /// The line number, counting from 1
+    pub line: usize,
+
+    /// What was wrong with it
+    pub error: Error,
+}
+
+impl fmt::Display for JsonlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+impl std::error::Error for JsonlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Events read one at a time from a JSON Lines archive, made by
+/// `read_jsonl()`
+#[derive(Debug)]
+pub struct JsonlReader<R> {
+    reader: R,
+    mode: JsonlErrorMode,
+    limits: ParseLimits,
+    line: usize,
+    skipped: usize,
+    errors: Vec<JsonlError>,
+    stopped: bool,
+}
+
+impl<R: BufRead> JsonlReader<R> {
+    /// Parse events within `limits` rather than the default limits. Lines
+    /// longer than `max_json_len` are not read into memory, but skipped as
+    /// errors.
+    pub fn with_limits(mut self, limits: ParseLimits) -> JsonlReader<R> {
+        self.limits = limits;
+        self
+    }
+
+    /// How many lines have been skipped for not being valid events, in the
+    /// `Skip` and `Collect` modes
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// The lines that were skipped, with why, in the `Collect` mode
+    pub fn errors(&self) -> &[JsonlError] {
+        &self.errors
+    }
+
+    /// Take the errors collected so far
+    pub fn take_errors(&mut self) -> Vec<JsonlError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    // Read the next line into `buffer`, but no more of it than the JSON
+    // length limit allows (with room for a line ending). Returns whether the
+    // whole line was read.
+    fn read_line(&mut self, buffer: &mut Vec<u8>) -> std::io::Result<bool> {
+        let cap = (self.limits.max_json_len as u64).saturating_add(2);
+        let read = Read::take(&mut self.reader, cap).read_until(b'\n', buffer)?;
+        if (read as u64) < cap || buffer.last() == Some(&b'\n') {
+            return Ok(true);
+        }
+
+        // Skip the rest of the line
+        loop {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                return Ok(false);
+            }
+            match available.iter().position(|b| *b == b'\n') {
+                Some(i) => {
+                    self.reader.consume(i + 1);
+                    return Ok(false);
+                }
+                None => {
+                    let n = available.len();
+                    self.reader.consume(n);
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for JsonlReader<R> {
+    type Item = Result<Event, JsonlError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stopped {
+            return None;
+        }
+        let mut buffer: Vec<u8> = Vec::new();
+        loop {
+            buffer.clear();
+            self.line += 1;
+            let result = match self.read_line(&mut buffer) {
+                Ok(_) if buffer.is_empty() => return None,
+                Ok(true) => std::str::from_utf8(&buffer)
+                    .map_err(Error::from)
+                    .and_then(|line| {
+                        let line = line.trim();
+                        if line.is_empty() {
+                            return Ok(None);
+                        }
+                        let event = Event::from_json_limited(line, &self.limits)?;
+                        event.verify(None)?;
+                        Ok(Some(event))
+                    }),
+                Ok(false) => Err(Error::JsonTooLong(self.limits.max_json_len)),
+                Err(e) => {
+                    // A failing reader will not recover, whatever the mode
+                    self.stopped = true;
+                    return Some(Err(JsonlError {
+                        line: self.line,
+                        error: e.into(),
+                    }));
+                }
+            };
+            let error = match result {
+                Ok(None) => continue,
+                Ok(Some(event)) => return Some(Ok(event)),
+                Err(error) => JsonlError {
+                    line: self.line,
+                    error,
+                },
+            };
+            match self.mode {
+                JsonlErrorMode::Skip => self.skipped += 1,
+                JsonlErrorMode::Collect => {
+                    self.skipped += 1;
+                    self.errors.push(error);
+                }
+                JsonlErrorMode::Stop => {
+                    self.stopped = true;
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+/// Read events from a JSON Lines archive, one event per line, checking
+/// each one's Id and signature as it is read. Blank lines are ignored.
+/// Events are parsed within the default `ParseLimits`; see
+/// `JsonlReader::with_limits()`.
+/// Lines that are not valid events are handled according to `mode`, but an
+/// error from the reader itself always ends the stream.
+pub fn read_jsonl<R: BufRead>(reader: R, mode: JsonlErrorMode) -> JsonlReader<R> {
+    JsonlReader {
+        reader,
+        mode,
+        limits: ParseLimits::default(),
+        line: 0,
+        skipped: 0,
+        errors: vec![],
+        stopped: false,
+    }
+}
+
+/// Write events as JSON Lines, one event per line, returning how many were
+/// written. The writer is not flushed.
+pub fn write_jsonl<'a, W, I>(mut writer: W, events: I) -> Result<usize, Error>
+where
+    W: Write,
+    I: IntoIterator<Item = &'a Event>,
+{
+    let mut count = 0;
+    for event in events {
+        serde_json::to_writer(&mut writer, event)?;
+        writer.write_all(b"\n")?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn archive() -> (Vec<Event>, Vec<u8>) {
+        let events: Vec<Event> = (0..3).map(|_| Event::mock()).collect();
+        let mut tampered = events[1].clone();
+        tampered.content = "Something else".to_owned();
+
+        let mut bytes: Vec<u8> = Vec::new();
+        assert_eq!(write_jsonl(&mut bytes, &events[..1]).unwrap(), 1);
+        bytes.extend(b"not an event\n\n");
+        assert_eq!(write_jsonl(&mut bytes, [&tampered, &events[1]]).unwrap(), 2);
+        bytes.extend(b"\xff\xfe\n");
+        assert_eq!(write_jsonl(&mut bytes, &events[2..]).unwrap(), 1);
+        (events, bytes)
+    }
+
+    #[test]
+    fn test_jsonl() {
+        let (events, bytes) = archive();
+
+        let mut reader = read_jsonl(bytes.as_slice(), JsonlErrorMode::Skip);
+        let read: Vec<Event> = reader.by_ref().map(|r| r.unwrap()).collect();
+        assert_eq!(read, events);
+        assert_eq!(reader.skipped(), 3);
+        assert!(reader.errors().is_empty());
+
+        let mut reader = read_jsonl(bytes.as_slice(), JsonlErrorMode::Collect);
+        assert_eq!(reader.by_ref().filter(|r| r.is_ok()).count(), 3);
+        let lines: Vec<usize> = reader.errors().iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![2, 4, 6]);
+
+        let mut reader = read_jsonl(bytes.as_slice(), JsonlErrorMode::Stop);
+        assert_eq!(reader.next().unwrap().unwrap(), events[0]);
+        let error = reader.next().unwrap().unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_jsonl_limits() {
+        let (events, mut bytes) = archive();
+        let len = serde_json::to_string(&events[0]).unwrap().len();
+        let limits = ParseLimits {
+            max_json_len: len,
+            ..Default::default()
+        };
+
+        // A huge line is skipped without being kept, and reading carries on
+        bytes.extend(vec![b'x'; 100 * len]);
+        bytes.extend(b"\r\n");
+        let _ = write_jsonl(&mut bytes, &events[..1]).unwrap();
+        let mut reader = read_jsonl(bytes.as_slice(), JsonlErrorMode::Collect).with_limits(limits);
+        let read: Vec<Event> = reader.by_ref().map(|r| r.unwrap()).collect();
+        assert_eq!(read.len(), 4);
+        let error = reader.errors().last().unwrap();
+        assert_eq!(error.line, 8);
+        assert!(matches!(error.error, Error::JsonTooLong(l) if l == len));
+        assert!(std::error::Error::source(error).is_some());
+
+        let limits = ParseLimits {
+            max_json_len: len - 1,
+            ..Default::default()
+        };
+        let mut reader = read_jsonl(bytes.as_slice(), JsonlErrorMode::Skip).with_limits(limits);
+        assert_eq!(reader.by_ref().count(), 0);
+        assert_eq!(reader.skipped(), 8);
+    }
+}
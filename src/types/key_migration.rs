@@ -0,0 +1,183 @@
+use super::{Event, EventKind, PublicKey};
+#[cfg(feature = "std")]
+use super::{PreEvent, PublicKeyHex, Tag, Unixtime};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+
+/// An announcement, signed by an account's old key, that the account has
+/// moved to a new key (NIP-41 draft, kind 1776)
+///
+/// Anyone holding the old key can sign one of these, including someone who
+/// stole it, so clients should treat the first announcement from a key as
+/// the one that counts. See `KeyMigration::follow()`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct KeyMigration {
+    /// The key the account is moving from, which signs the announcement
+    pub old: PublicKey,
+
+    /// The key the account is moving to, from the 'p' tag
+    pub new: PublicKey,
+
+    /// Why the account moved, e.g. that the old key was compromised. This is
+    /// the event content.
+    pub reason: String,
+}
+
+impl KeyMigration {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> KeyMigration {
+        KeyMigration {
+            old: PublicKey::mock(),
+            new: PublicKey::mock_deterministic(),
+            reason: "Old key leaked".to_owned(),
+        }
+    }
+
+    /// Check that `event` is a valid announcement, signed by `old`, that the
+    /// account moved from `old` to `new`
+    pub fn verify_transition(
+        old: &PublicKey,
+        new: &PublicKey,
+        event: &Event,
+    ) -> Result<KeyMigration, Error> {
+        if event.kind != EventKind::KeyMigration {
+            return Err(Error::WrongEventKind);
+        }
+        if event.pubkey != *old {
+            return Err(Error::WrongPublicKey);
+        }
+        event.verify(None)?;
+        let migration = event.key_migration().ok_or(Error::WrongPublicKey)?;
+        if migration.new != *new {
+            return Err(Error::WrongPublicKey);
+        }
+        Ok(migration)
+    }
+
+    /// The key the account that started out as `start` has ended up at, by
+    /// following valid migrations among `events`. Where a key announced
+    /// more than one move, the earliest is followed, as a later one may be
+    /// by someone who stole the key. A move back to a key already passed
+    /// through ends the search.
+    pub fn follow(start: PublicKey, events: &[Event]) -> PublicKey {
+        let mut path: Vec<PublicKey> = vec![start];
+        let mut current = start;
+        loop {
+            let next = events
+                .iter()
+                .filter(|e| e.kind == EventKind::KeyMigration && e.pubkey == current)
+                .filter(|e| e.verify(None).is_ok())
+                .filter_map(|e| Some((e.created_at, e.key_migration()?.new)))
+                .min_by_key(|(created_at, _)| *created_at);
+            match next {
+                Some((_, new)) if !path.contains(&new) => {
+                    path.push(new);
+                    current = new;
+                }
+                _ => return current,
+            }
+        }
+    }
+}
+
+impl Event {
+    /// If this is a NIP-41 key migration (kind 1776), get its details. The
+    /// signature is not checked here; see `KeyMigration::verify_transition()`.
+    pub fn key_migration(&self) -> Option<KeyMigration> {
+        if self.kind != EventKind::KeyMigration {
+            return None;
+        }
+        let new = self
+            .tags
+            .iter()
+            .filter_map(|t| t.parse_pubkey().ok())
+            .find_map(|(pubkey, _, _)| PublicKey::try_from(pubkey).ok())?;
+        if new == self.pubkey {
+            return None;
+        }
+        Some(KeyMigration {
+            old: self.pubkey,
+            new,
+            reason: self.content.clone(),
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl PreEvent {
+    /// Create a NIP-41 key migration PreEvent (kind 1776), to be signed by
+    /// the `old` key, announcing that the account has moved to `new`
+    pub fn new_key_migration(
+        old: PublicKey,
+        new: PublicKey,
+        reason: String,
+    ) -> Result<PreEvent, Error> {
+        if old == new {
+            return Err(Error::WrongPublicKey);
+        }
+        let new: PublicKeyHex = new.into();
+        Ok(PreEvent {
+            pubkey: old,
+            created_at: Unixtime::now(),
+            kind: EventKind::KeyMigration,
+            tags: vec![Tag::new_pubkey(new, None, None)],
+            content: reason,
+            ots: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {KeyMigration, test_key_migration_serde}
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_key_migration() {
+        use crate::types::PrivateKey;
+
+        let keys: Vec<PrivateKey> = (0..4).map(|_| PrivateKey::generate()).collect();
+        let public = |i: usize| keys[i].public_key();
+        let migrate = |from: usize, to: usize, at: i64| {
+            let mut pre =
+                PreEvent::new_key_migration(public(from), public(to), "".to_owned()).unwrap();
+            pre.created_at = Unixtime(at);
+            Event::new(pre, &keys[from]).unwrap()
+        };
+
+        let event = migrate(0, 1, 100);
+        let migration = KeyMigration::verify_transition(&public(0), &public(1), &event).unwrap();
+        assert_eq!(migration.old, public(0));
+        assert_eq!(migration.new, public(1));
+        assert!(matches!(
+            KeyMigration::verify_transition(&public(0), &public(2), &event),
+            Err(Error::WrongPublicKey)
+        ));
+        assert!(matches!(
+            KeyMigration::verify_transition(&public(1), &public(0), &event),
+            Err(Error::WrongPublicKey)
+        ));
+        let mut forged = event.clone();
+        forged.tags = vec![Tag::new_pubkey(public(2).into(), None, None)];
+        assert!(KeyMigration::verify_transition(&public(0), &public(2), &forged).is_err());
+
+        assert!(PreEvent::new_key_migration(public(0), public(0), "".to_owned()).is_err());
+
+        // 0 -> 1 -> 2, where a later move from 0 to 3 does not count, and a
+        // move from 2 back to 0 ends the chain
+        let events = vec![
+            migrate(1, 2, 200),
+            migrate(0, 3, 300),
+            event,
+            migrate(2, 0, 400),
+        ];
+        assert_eq!(KeyMigration::follow(public(0), &events), public(2));
+        assert_eq!(KeyMigration::follow(public(3), &events), public(3));
+    }
+}
@@ -0,0 +1,272 @@
+use super::{PublicKeyHex, Tag, UncheckedUrl, Unixtime};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+use std::fmt;
+
+/// The status of a live event (NIP-53)
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+#[serde(rename_all = "lowercase")]
+pub enum LiveStatus {
+    /// Scheduled for the future
+    Planned,
+
+    /// Currently streaming
+    Live,
+
+    /// Finished
+    Ended,
+}
+
+impl LiveStatus {
+    /// The string used on the wire for this status
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            LiveStatus::Planned => "planned",
+            LiveStatus::Live => "live",
+            LiveStatus::Ended => "ended",
+        }
+    }
+
+    /// Parse a status from the wire, or None if it is not recognized
+    pub fn parse(s: &str) -> Option<LiveStatus> {
+        match s {
+            "planned" => Some(LiveStatus::Planned),
+            "live" => Some(LiveStatus::Live),
+            "ended" => Some(LiveStatus::Ended),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LiveStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A participant in a live event (NIP-53), from a 'p' tag
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct LiveParticipant {
+    /// The participant
+    pub pubkey: PublicKeyHex,
+
+    /// A relay where the participant can be found
+    pub relay: Option<UncheckedUrl>,
+
+    /// Their role, e.g. "Host", "Speaker" or "Participant"
+    pub role: Option<String>,
+
+    /// Hex signature by the participant of the event address, proving
+    /// they agreed to take part
+    pub proof: Option<String>,
+}
+
+/// A live streaming event (NIP-53, kind 30311)
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct LiveEvent {
+    /// The 'd' identifier
+    pub d: String,
+
+    /// Title
+    pub title: Option<String>,
+
+    /// Summary
+    pub summary: Option<String>,
+
+    /// Preview image
+    pub image: Option<UncheckedUrl>,
+
+    /// Where the stream can be watched
+    pub streaming: Option<UncheckedUrl>,
+
+    /// Where a recording can be watched, after the event ends
+    pub recording: Option<UncheckedUrl>,
+
+    /// When the event starts
+    pub starts: Option<Unixtime>,
+
+    /// When the event ends
+    pub ends: Option<Unixtime>,
+
+    /// Status, if given and recognized
+    pub status: Option<LiveStatus>,
+
+    /// The number of people currently watching
+    pub current_participants: Option<u64>,
+
+    /// The number of people who have watched in total
+    pub total_participants: Option<u64>,
+
+    /// The people taking part
+    pub participants: Vec<LiveParticipant>,
+
+    /// Hashtags
+    pub hashtags: Vec<String>,
+
+    /// Relays where the chat and other related events are published
+    pub relays: Vec<UncheckedUrl>,
+}
+
+impl LiveEvent {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> LiveEvent {
+        LiveEvent {
+            d: "1687612774".to_owned(),
+            title: Some("Designing in public".to_owned()),
+            summary: None,
+            image: Some(UncheckedUrl::mock()),
+            streaming: Some(UncheckedUrl("https://example.com/live.m3u8".to_owned())),
+            recording: None,
+            starts: Some(Unixtime(1687612774)),
+            ends: None,
+            status: Some(LiveStatus::Live),
+            current_participants: Some(12),
+            total_participants: None,
+            participants: vec![LiveParticipant {
+                pubkey: PublicKeyHex::mock_deterministic(),
+                relay: None,
+                role: Some("Host".to_owned()),
+                proof: None,
+            }],
+            hashtags: vec!["design".to_owned()],
+            relays: vec![UncheckedUrl("wss://relay.example.com".to_owned())],
+        }
+    }
+
+    /// Read a live event from the tags of a kind 30311 event
+    pub fn from_tags(tags: &[Tag]) -> LiveEvent {
+        let mut live = LiveEvent::default();
+        let url = |tag: &Tag| match tag.get_index(1) {
+            "" => None,
+            s => Some(UncheckedUrl::from_str(s)),
+        };
+        let time = |tag: &Tag| tag.get_index(1).parse::<i64>().ok().map(Unixtime);
+        for tag in tags {
+            match tag.tagname() {
+                "d" => live.d = tag.get_index(1).to_owned(),
+                "title" => live.title = Some(tag.get_index(1).to_owned()),
+                "summary" => live.summary = Some(tag.get_index(1).to_owned()),
+                "image" => live.image = url(tag),
+                "streaming" => live.streaming = url(tag),
+                "recording" => live.recording = url(tag),
+                "starts" => live.starts = time(tag),
+                "ends" => live.ends = time(tag),
+                "status" => live.status = LiveStatus::parse(tag.get_index(1)),
+                "current_participants" => live.current_participants = tag.get_index(1).parse().ok(),
+                "total_participants" => live.total_participants = tag.get_index(1).parse().ok(),
+                "t" => live.hashtags.push(tag.get_index(1).to_owned()),
+                "relays" => live.relays.extend(
+                    tag.fields()
+                        .iter()
+                        .skip(1)
+                        .map(|s| UncheckedUrl::from_str(s)),
+                ),
+                "p" => {
+                    let Ok(pubkey) = PublicKeyHex::try_from_str(tag.get_index(1)) else {
+                        continue;
+                    };
+                    let optional = |i| match tag.get_index(i) {
+                        "" => None,
+                        s => Some(s.to_owned()),
+                    };
+                    live.participants.push(LiveParticipant {
+                        pubkey,
+                        relay: optional(2).map(UncheckedUrl),
+                        role: optional(3),
+                        proof: optional(4),
+                    });
+                }
+                _ => {}
+            }
+        }
+        live
+    }
+
+    /// Write this live event out as tags for a kind 30311 event
+    pub fn to_tags(&self) -> Vec<Tag> {
+        let mut tags = vec![Tag::new_identifier(self.d.clone())];
+        let mut push = |name: &str, value: Option<&str>| {
+            if let Some(value) = value {
+                tags.push(Tag::new(&[name, value]));
+            }
+        };
+        push("title", self.title.as_deref());
+        push("summary", self.summary.as_deref());
+        push("image", self.image.as_ref().map(|u| u.as_str()));
+        push("streaming", self.streaming.as_ref().map(|u| u.as_str()));
+        push("recording", self.recording.as_ref().map(|u| u.as_str()));
+        push("starts", self.starts.map(|t| t.0.to_string()).as_deref());
+        push("ends", self.ends.map(|t| t.0.to_string()).as_deref());
+        push("status", self.status.map(|s| s.as_str()));
+        push(
+            "current_participants",
+            self.current_participants.map(|n| n.to_string()).as_deref(),
+        );
+        push(
+            "total_participants",
+            self.total_participants.map(|n| n.to_string()).as_deref(),
+        );
+        for p in &self.participants {
+            let mut tag = Tag::new(&[
+                "p",
+                p.pubkey.as_str(),
+                p.relay.as_ref().map(|u| u.as_str()).unwrap_or(""),
+                p.role.as_deref().unwrap_or(""),
+            ]);
+            if let Some(proof) = &p.proof {
+                tag.push_value(proof.clone());
+            }
+            tags.push(tag);
+        }
+        for hashtag in &self.hashtags {
+            tags.push(Tag::new_hashtag(hashtag.clone()));
+        }
+        if !self.relays.is_empty() {
+            let mut tag = Tag::new(&["relays"]);
+            for relay in &self.relays {
+                tag.push_value(relay.0.clone());
+            }
+            tags.push(tag);
+        }
+        tags
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {LiveEvent, test_live_event_serde}
+
+    #[test]
+    fn test_live_event_tags() {
+        let live = LiveEvent::mock();
+        let tags = live.to_tags();
+        assert_eq!(LiveEvent::from_tags(&tags), live);
+
+        let tags = vec![
+            Tag::new(&["d", "x"]),
+            Tag::new(&["status", "paused"]),
+            Tag::new(&["starts", "soon"]),
+            Tag::new(&["p", "not a key", "", "Host"]),
+            Tag::new(&[
+                "p",
+                "ee11a5dff40c19a555f41fe42b48f00e618c91225622ae37b6c2bb67b76c4e49",
+                "",
+                "Speaker",
+                "abcd",
+            ]),
+        ];
+        let live = LiveEvent::from_tags(&tags);
+        assert_eq!(live.status, None);
+        assert_eq!(live.starts, None);
+        assert_eq!(live.participants.len(), 1);
+        assert_eq!(live.participants[0].role.as_deref(), Some("Speaker"));
+        assert_eq!(live.participants[0].proof.as_deref(), Some("abcd"));
+    }
+}
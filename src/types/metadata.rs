@@ -1,3 +1,4 @@
+use super::Url;
 use bech32::FromBase32;
 use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
 use serde::ser::{Serialize, SerializeMap, Serializer};
@@ -85,6 +86,280 @@ impl Metadata {
 
         None
     }
+
+    /// Update from a newer version of the metadata. Fields set in `newer`
+    /// replace ours, and fields it leaves out (or sets to null) keep their
+    /// values, including fields in `other` that only some clients know about.
+    pub fn merge(&mut self, newer: &Metadata) {
+        for (field, value) in newer.fields() {
+            self.set_field(field, value);
+        }
+    }
+
+    /// The fields that differ between this and `other`, in field name order
+    pub fn diff(&self, other: &Metadata) -> Vec<MetadataChange> {
+        let mut ours = self.fields();
+        let mut changes: Vec<MetadataChange> = Vec::new();
+        for (field, new) in other.fields() {
+            let old = ours.remove(&field);
+            if old.as_ref() != Some(&new) {
+                changes.push(MetadataChange {
+                    field,
+                    old,
+                    new: Some(new),
+                });
+            }
+        }
+        for (field, old) in ours {
+            changes.push(MetadataChange {
+                field,
+                old: Some(old),
+                new: None,
+            });
+        }
+        changes.sort_by(|a, b| a.field.cmp(&b.field));
+        changes
+    }
+
+    // All the set fields, skipping nulls
+    fn fields(&self) -> Map<String, Value> {
+        let mut map: Map<String, Value> = Map::new();
+        for (field, value) in [
+            ("name", &self.name),
+            ("about", &self.about),
+            ("picture", &self.picture),
+            ("nip05", &self.nip05),
+        ] {
+            if let Some(value) = value {
+                let _ = map.insert(field.to_owned(), Value::String(value.clone()));
+            }
+        }
+        for (field, value) in &self.other {
+            if !value.is_null() {
+                let _ = map.insert(field.clone(), value.clone());
+            }
+        }
+        map
+    }
+
+    fn set_field(&mut self, field: String, value: Value) {
+        let slot = match field.as_str() {
+            "name" => &mut self.name,
+            "about" => &mut self.about,
+            "picture" => &mut self.picture,
+            "nip05" => &mut self.nip05,
+            _ => {
+                let _ = self.other.insert(field, value);
+                return;
+            }
+        };
+        if let Value::String(s) = value {
+            *slot = Some(s);
+        }
+    }
+}
+
+/// A field that differs between two versions of some `Metadata`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MetadataChange {
+    /// The field name, e.g. "lud16"
+    pub field: String,
+
+    /// The value before, if it was set
+    pub old: Option<Value>,
+
+    /// The value after, if it is set
+    pub new: Option<Value>,
+}
+
+/// Limits applied by `Metadata::sanitized()`. Lengths are in characters.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MetadataLimits {
+    /// The longest `name` and `display_name` may be
+    pub max_name_len: usize,
+
+    /// The longest `about` may be
+    pub max_about_len: usize,
+
+    /// The longest `picture`, `banner` and `website` may be. Longer URLs are
+    /// removed rather than cut short.
+    pub max_url_len: usize,
+}
+
+impl Default for MetadataLimits {
+    fn default() -> MetadataLimits {
+        MetadataLimits {
+            max_name_len: 64,
+            max_about_len: 2000,
+            max_url_len: 1024,
+        }
+    }
+}
+
+/// What `Metadata::sanitized()` did to a field
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SanitizeAction {
+    /// Control, invisible or bidirectional override characters were removed
+    Stripped,
+
+    /// It was cut short to the limit
+    Truncated,
+
+    /// It was removed, as an invalid URL or as nothing was left of it
+    Removed,
+}
+
+/// A field altered by `Metadata::sanitized()`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SanitizedField {
+    /// The field name, e.g. "display_name"
+    pub field: String,
+
+    /// What was done to it
+    pub action: SanitizeAction,
+}
+
+// Characters that change how text around them is shown without being seen
+// themselves, which can make one name look like another. The zero width
+// joiner and variation selectors are kept, as emoji need them.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{ad}'
+            | '\u{34f}'
+            | '\u{61c}'
+            | '\u{115f}'
+            | '\u{1160}'
+            | '\u{17b4}'
+            | '\u{17b5}'
+            | '\u{180e}'
+            | '\u{200b}'
+            | '\u{200c}'
+            | '\u{200e}'
+            | '\u{200f}'
+            | '\u{202a}'..='\u{202e}'
+            | '\u{2060}'..='\u{206f}'
+            | '\u{3164}'
+            | '\u{feff}'
+            | '\u{ffa0}'
+    )
+}
+
+// Bidirectional embeddings, overrides and isolates, which can reverse the
+// text after them
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
+}
+
+// Clean up a text field, reporting what was done to it
+fn sanitize_text(
+    field: &str,
+    value: &str,
+    strip: impl Fn(char) -> bool,
+    max_len: usize,
+    report: &mut Vec<SanitizedField>,
+) -> Option<String> {
+    let mut action = |action| {
+        report.push(SanitizedField {
+            field: field.to_owned(),
+            action,
+        })
+    };
+    let mut text: String = value.chars().filter(|c| !strip(*c)).collect();
+    if text.len() != value.len() {
+        action(SanitizeAction::Stripped);
+    }
+    if text.chars().count() > max_len {
+        text = text.chars().take(max_len).collect();
+        action(SanitizeAction::Truncated);
+    }
+    if text.trim().is_empty() && !value.is_empty() {
+        action(SanitizeAction::Removed);
+        return None;
+    }
+    Some(text)
+}
+
+// Keep a URL field only if it is a valid http(s) URL within the limit
+fn sanitize_url(
+    field: &str,
+    value: &str,
+    max_len: usize,
+    report: &mut Vec<SanitizedField>,
+) -> Option<String> {
+    let valid = value.chars().count() <= max_len
+        && Url::try_from_str(value).is_ok_and(|u| matches!(u.scheme(), "http" | "https"));
+    if valid {
+        Some(value.to_owned())
+    } else {
+        report.push(SanitizedField {
+            field: field.to_owned(),
+            action: SanitizeAction::Removed,
+        });
+        None
+    }
+}
+
+impl Metadata {
+    /// A copy safe to show, along with what had to be changed to make it so:
+    ///
+    /// * `name` and `display_name` lose control characters and invisible
+    ///   characters such as zero-width spaces and bidirectional overrides,
+    ///   which are used to impersonate others, and are cut to
+    ///   `max_name_len`
+    /// * `about` loses control characters other than line breaks and tabs,
+    ///   and bidirectional overrides, and is cut to `max_about_len`
+    /// * `picture`, `banner` and `website` are removed unless they are valid
+    ///   http(s) URLs no longer than `max_url_len`
+    ///
+    /// Other fields are left as they are.
+    pub fn sanitized(&self, limits: &MetadataLimits) -> (Metadata, Vec<SanitizedField>) {
+        let mut report: Vec<SanitizedField> = Vec::new();
+        let mut metadata = self.clone();
+        let name_strip = |c: char| c.is_control() || is_invisible(c);
+        let about_strip =
+            |c: char| (c.is_control() && !matches!(c, '\n' | '\t')) || is_bidi_control(c);
+
+        metadata.name = self.name.as_deref().and_then(|name| {
+            sanitize_text("name", name, name_strip, limits.max_name_len, &mut report)
+        });
+        metadata.about = self.about.as_deref().and_then(|about| {
+            sanitize_text(
+                "about",
+                about,
+                about_strip,
+                limits.max_about_len,
+                &mut report,
+            )
+        });
+        metadata.picture = self
+            .picture
+            .as_deref()
+            .and_then(|url| sanitize_url("picture", url, limits.max_url_len, &mut report));
+
+        for field in ["display_name", "banner", "website"] {
+            let Some(Value::String(value)) = self.other.get(field) else {
+                continue;
+            };
+            let sanitized = if field == "display_name" {
+                sanitize_text(field, value, name_strip, limits.max_name_len, &mut report)
+            } else {
+                sanitize_url(field, value, limits.max_url_len, &mut report)
+            };
+            match sanitized {
+                Some(value) => {
+                    let _ = metadata
+                        .other
+                        .insert(field.to_owned(), Value::String(value));
+                }
+                None => {
+                    let _ = metadata.other.remove(field);
+                }
+            }
+        }
+
+        (metadata, report)
+    }
 }
 
 impl Serialize for Metadata {
@@ -195,4 +470,106 @@ mod test {
             Some("https://walletofsatoshi.com/.well-known/lnurlp/decentbun13")
         );
     }
+
+    #[test]
+    fn test_metadata_sanitized() {
+        let json = "{\"name\":\"jb55\\u200b\",\"display_name\":\"\\u202eWill\",\"about\":\"Line one\\nLine two\\u0007\",\"picture\":\"javascript:alert(1)\",\"banner\":\"https://example.com/banner.jpg\",\"website\":\"not a url\",\"lud16\":\"jb55@example.com\"}";
+        let m: Metadata = serde_json::from_str(json).unwrap();
+        let limits = MetadataLimits {
+            max_about_len: 10,
+            ..Default::default()
+        };
+
+        let (clean, report) = m.sanitized(&limits);
+        assert_eq!(clean.name.as_deref(), Some("jb55"));
+        assert_eq!(clean.other.get("display_name"), Some(&json!("Will")));
+        assert_eq!(clean.about.as_deref(), Some("Line one\nL"));
+        assert_eq!(clean.picture, None);
+        assert_eq!(
+            clean.other.get("banner"),
+            Some(&json!("https://example.com/banner.jpg"))
+        );
+        assert_eq!(clean.other.get("website"), None);
+        assert_eq!(clean.other.get("lud16"), m.other.get("lud16"));
+
+        let fix = |field: &str, action| SanitizedField {
+            field: field.to_owned(),
+            action,
+        };
+        assert_eq!(
+            report,
+            vec![
+                fix("name", SanitizeAction::Stripped),
+                fix("about", SanitizeAction::Stripped),
+                fix("about", SanitizeAction::Truncated),
+                fix("picture", SanitizeAction::Removed),
+                fix("display_name", SanitizeAction::Stripped),
+                fix("website", SanitizeAction::Removed),
+            ]
+        );
+
+        // Nothing to do the second time
+        assert!(clean.sanitized(&limits).1.is_empty());
+
+        // A name that is nothing but invisible characters is removed
+        let mut m = Metadata::new();
+        m.name = Some("\u{200b}\u{200b}".to_owned());
+        let (clean, report) = m.sanitized(&MetadataLimits::default());
+        assert_eq!(clean.name, None);
+        assert_eq!(report.last().unwrap().action, SanitizeAction::Removed);
+    }
+
+    #[test]
+    fn test_metadata_merge_and_diff() {
+        let mut ours = Metadata::mock();
+        let _ = ours
+            .other
+            .insert("lud16".to_owned(), json!("jb55@example.com"));
+
+        // A client that only knows about name and about
+        let mut newer = Metadata::new();
+        newer.name = Some("will".to_owned());
+        newer.about = Some("hi".to_owned());
+        let _ = newer.other.insert("lud16".to_owned(), Value::Null);
+
+        let before = ours.clone();
+        ours.merge(&newer);
+        assert_eq!(ours.name.as_deref(), Some("will"));
+        assert_eq!(ours.about.as_deref(), Some("hi"));
+        assert_eq!(ours.nip05.as_deref(), Some("jb55.com"));
+        assert_eq!(ours.other.get("lud16"), Some(&json!("jb55@example.com")));
+        assert_eq!(
+            ours.other.get("display_name"),
+            before.other.get("display_name")
+        );
+
+        let changes = before.diff(&ours);
+        assert_eq!(
+            changes,
+            vec![
+                MetadataChange {
+                    field: "about".to_owned(),
+                    old: None,
+                    new: Some(json!("hi")),
+                },
+                MetadataChange {
+                    field: "name".to_owned(),
+                    old: Some(json!("jb55")),
+                    new: Some(json!("will")),
+                },
+            ]
+        );
+
+        let mut removed = ours.clone();
+        removed.nip05 = None;
+        assert_eq!(
+            ours.diff(&removed),
+            vec![MetadataChange {
+                field: "nip05".to_owned(),
+                old: Some(json!("jb55.com")),
+                new: None,
+            }]
+        );
+        assert!(ours.diff(&ours).is_empty());
+    }
 }
@@ -11,62 +11,183 @@ macro_rules! test_serde {
     };
 }
 
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+mod binary;
+
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+
+mod attachment;
+pub use attachment::{Attachment, AttachmentSource};
+
+mod auth;
+pub use auth::AuthState;
+
+mod calendar;
+pub use calendar::{
+    CalendarDate, CalendarEvent, CalendarEventTime, CalendarParticipant, CalendarRsvp, RsvpStatus,
+};
+
+mod chain;
+pub use chain::{verify_chain, ChainBuilder, CHAIN_MARKER};
+
+mod classified;
+pub use classified::{ClassifiedListing, ClassifiedStatus, Price};
+
 mod client_message;
 pub use client_message::ClientMessage;
 
+mod clock;
+#[cfg(feature = "std")]
+pub use clock::{set_clock, with_clock, SystemClock};
+pub use clock::{Clock, ManualClock};
+
+mod community;
+pub use community::{Community, CommunityApproval, CommunityModerator, CommunityRelay};
+
+mod compact;
+
 mod content;
 pub use content::{ContentSegment, ShatteredContent, Span};
 
+mod deletion;
+pub use deletion::DeletionRequest;
+
 mod delegation;
 pub use delegation::{DelegationConditions, EventDelegation};
 
+mod diff;
+pub use diff::{DiffLine, EventDiff};
+
+mod dvm;
+pub use dvm::{JobAmount, JobFeedback, JobInput, JobInputType, JobRequest, JobResult, JobStatus};
+
 mod event;
-pub use event::{Event, PreEvent, ZapData};
+pub use event::{
+    AddressReference, CommentScope, Event, EventReference, IdHasher, PersonReference, PreEvent,
+    Thread, ZapData,
+};
+
+mod event_envelope;
+pub use event_envelope::EventEnvelope;
 
 mod event_kind;
-pub use event_kind::{EventKind, EventKindIterator, EventKindOrRange};
+pub use event_kind::{ContentVisibility, EventKind, EventKindIterator, EventKindOrRange};
 
 mod event_pointer;
 pub use event_pointer::EventPointer;
 
+mod event_store;
+pub use event_store::EventStore;
+
 mod event_addr;
 pub use event_addr::EventAddr;
 
+mod external_identity;
+pub use external_identity::{ExternalIdentity, IdentityPlatform};
+
+mod feed;
+#[cfg(feature = "rayon")]
+pub use feed::par_verify;
+pub use feed::{dedup_events, merge_sorted_event_streams, MergedEvents};
+
 mod filter;
 pub use filter::Filter;
 
+mod gift_wrap;
+pub use gift_wrap::Rumor;
+
+mod highlight;
+pub use highlight::{Highlight, HighlightAttribution, HighlightSource};
+
 mod id;
 pub use id::{Id, IdHex, IdHexPrefix};
 
+mod identity;
+pub use identity::{Identity, KeySource, Keyring};
+
+mod intern;
+
+mod jsonl;
+pub use jsonl::{read_jsonl, write_jsonl, JsonlError, JsonlErrorMode, JsonlReader};
+
+mod key_migration;
+pub use key_migration::KeyMigration;
+
+mod live;
+pub use live::{LiveEvent, LiveParticipant, LiveStatus};
+
 mod metadata;
-pub use metadata::Metadata;
+pub use metadata::{Metadata, MetadataChange, MetadataLimits, SanitizeAction, SanitizedField};
 
 mod nip05;
 pub use nip05::Nip05;
 
+mod nip44;
+pub use nip44::ConversationKey;
+
 mod nostr_url;
-pub use nostr_url::{find_nostr_bech32_pos, find_nostr_url_pos, NostrBech32, NostrUrl};
+pub use nostr_url::{
+    find_nostr_bech32_pos, find_nostr_url_pos, parse_any, NostrBech32, NostrEntity, NostrUrl,
+};
+
+#[cfg(feature = "ots")]
+mod ots;
+#[cfg(feature = "ots")]
+pub use ots::{verify_ots, OtsRequest};
+
+mod ots_attestation;
+pub use ots_attestation::OtsAttestation;
+
+mod parse_limits;
+pub use parse_limits::ParseLimits;
 
 mod pay_request_data;
 pub use pay_request_data::PayRequestData;
 
+mod policy;
+pub use policy::{Policy, PolicyViolation, RelayFit};
+
+mod poll;
+pub use poll::{Poll, PollOption, PollResponse, PollTally, PollType};
+
+mod pow;
+pub use pow::{pow_estimate_iterations, Difficulty, PowMiner, PowProgress, PowStatus};
+
 mod private_key;
 pub use private_key::{EncryptedPrivateKey, KeySecurity, PrivateKey};
 
+mod private_message;
+#[cfg(feature = "std")]
+pub use private_message::DmGiftWrap;
+pub use private_message::PrivateDirectMessage;
+
 mod profile;
 pub use profile::Profile;
 
 mod public_key;
-pub use public_key::{PublicKey, PublicKeyHex, PublicKeyHexPrefix};
+pub use public_key::{Parity, PublicKey, PublicKeyHex, PublicKeyHexPrefix};
 
 mod relay_message;
-pub use relay_message::RelayMessage;
+pub use relay_message::{
+    parse_relay_frame, CountResult, MachineReadablePrefix, RawEvent, RelayFrame, RelayMessage,
+    RelayReason,
+};
+
+mod relay_hint;
+pub use relay_hint::{RelayHint, RelayHintSource};
 
 mod relay_information_document;
 pub use relay_information_document::{
     Fee, RelayFees, RelayInformationDocument, RelayLimitation, RelayRetention,
 };
 
+mod report;
+pub use report::{Report, ReportTarget, ReportType};
+
+mod retention;
+pub use retention::RetentionPolicy;
+
 mod satoshi;
 pub use satoshi::MilliSatoshi;
 
@@ -76,17 +197,46 @@ pub use signature::{Signature, SignatureHex};
 mod relay_list;
 pub use relay_list::{SimpleRelayList, SimpleRelayUsage};
 
+mod relay_usage;
+pub use relay_usage::{RelayAssignment, RelayScore, RelayUsage, RelayUsagePicture};
+
+mod strict_json;
+
+mod subscription;
+pub use subscription::{Subscription, SubscriptionSet, SubscriptionState};
+
 mod subscription_id;
 pub use subscription_id::SubscriptionId;
 
 mod tag;
 pub use tag::Tag;
 
+mod threshold;
+pub use threshold::{PartialSignature, SigningSession};
+
+mod torrent;
+pub use torrent::{Torrent, TorrentFile};
+
+mod unsigned_event;
+pub use unsigned_event::UnsignedEvent;
+
 mod unixtime;
 pub use unixtime::Unixtime;
 
 mod url;
-pub use self::url::{RelayUrl, UncheckedUrl, Url};
+pub use self::url::{RelayOrigin, RelayUrl, UncheckedUrl, Url};
+
+mod verified_event;
+pub use verified_event::VerifiedEvent;
+
+mod wallet_connect;
+pub use wallet_connect::{
+    TransactionType, WalletError, WalletErrorCode, WalletInfo, WalletRequest, WalletResponse,
+    WalletResult, WalletTransaction,
+};
+
+mod wiki;
+pub use wiki::{normalize_wiki_topic, WikiArticle};
 
 #[cfg(test)]
 mod test {
@@ -0,0 +1,282 @@
+//! NIP-44 version 2 encryption
+//!
+//! The conversation key is derived from the ECDH shared point, and a fresh
+//! message key set is expanded from it for each message using a random nonce.
+//! Messages are padded to hide their length, encrypted with ChaCha20, and
+//! authenticated with HMAC-SHA256.
+
+use crate::{Error, PrivateKey, PublicKey};
+use base64::Engine;
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use sha2::Sha256;
+use std::fmt;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+const VERSION: u8 = 2;
+const SALT: &[u8] = b"nip44-v2";
+const MIN_PLAINTEXT_LEN: usize = 1;
+const MAX_PLAINTEXT_LEN: usize = 65535;
+// version + nonce + (length prefix + smallest padded plaintext) + mac
+const MIN_PAYLOAD_LEN: usize = 1 + 32 + 2 + 32 + 32;
+const MAX_PAYLOAD_LEN: usize = 1 + 32 + 2 + 65536 + 32;
+
+/// The key two parties share for NIP-44 encryption, derived from one's
+/// private key and the other's public key.
+///
+/// Deriving it needs an elliptic curve multiplication, so when exchanging
+/// many messages with someone, compute it once and keep it (it implements
+/// `Hash` and `Eq` so it can go in a cache). Either party derives the same key.
+/// It is zeroized when dropped.
+#[derive(Clone, Eq, Hash, PartialEq)]
+pub struct ConversationKey([u8; 32]);
+
+impl ConversationKey {
+    /// Derive the conversation key shared by `private_key` and `public_key`
+    pub fn new(private_key: &PrivateKey, public_key: &PublicKey) -> ConversationKey {
+        let shared_x = private_key.shared_secret(public_key);
+        let (prk, _) = Hkdf::<Sha256>::extract(Some(SALT), shared_x.raw_secret_bytes());
+        ConversationKey(prk.into())
+    }
+
+    /// Use a conversation key that was derived elsewhere
+    pub fn from_bytes(bytes: [u8; 32]) -> ConversationKey {
+        ConversationKey(bytes)
+    }
+
+    /// The raw key. Keep it secret.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Encrypt `plaintext` (1 to 65535 bytes), returning the base64 payload
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, Error> {
+        let mut nonce = [0u8; 32];
+        OsRng.fill_bytes(&mut nonce);
+        self.encrypt_with_nonce(plaintext, &nonce)
+    }
+
+    // Encrypt with a given nonce. Only tests and test vectors should choose
+    // the nonce.
+    pub(crate) fn encrypt_with_nonce(
+        &self,
+        plaintext: &str,
+        nonce: &[u8; 32],
+    ) -> Result<String, Error> {
+        let len = plaintext.len();
+        if !(MIN_PLAINTEXT_LEN..=MAX_PLAINTEXT_LEN).contains(&len) {
+            return Err(Error::Encryption);
+        }
+
+        let (chacha_key, chacha_nonce, hmac_key) = message_keys(&self.0, nonce);
+
+        let mut buffer: Vec<u8> = Vec::with_capacity(2 + calc_padded_len(len));
+        buffer.extend((len as u16).to_be_bytes());
+        buffer.extend(plaintext.as_bytes());
+        buffer.resize(2 + calc_padded_len(len), 0);
+        ChaCha20::new(&chacha_key.into(), &chacha_nonce.into()).apply_keystream(&mut buffer);
+
+        let mac = hmac_aad(&hmac_key, nonce, &buffer).finalize().into_bytes();
+
+        let mut payload: Vec<u8> = Vec::with_capacity(1 + 32 + buffer.len() + 32);
+        payload.push(VERSION);
+        payload.extend(nonce);
+        payload.extend(buffer);
+        payload.extend(mac);
+        Ok(base64::engine::general_purpose::STANDARD.encode(payload))
+    }
+
+    /// Decrypt a base64 payload
+    pub fn decrypt(&self, payload: &str) -> Result<String, Error> {
+        // A leading '#' flags a future non-base64 encoding
+        if payload.starts_with('#') {
+            return Err(Error::UnsupportedEncryptionVersion(b'#'));
+        }
+        let payload = base64::engine::general_purpose::STANDARD.decode(payload)?;
+        if let Some(&version) = payload.first() {
+            if version != VERSION {
+                return Err(Error::UnsupportedEncryptionVersion(version));
+            }
+        }
+        if !(MIN_PAYLOAD_LEN..=MAX_PAYLOAD_LEN).contains(&payload.len()) {
+            return Err(Error::BadEncryptedMessage);
+        }
+
+        let mut nonce = [0u8; 32];
+        nonce.copy_from_slice(&payload[1..33]);
+        let (ciphertext, mac) = payload[33..].split_at(payload.len() - 33 - 32);
+
+        let (chacha_key, chacha_nonce, hmac_key) = message_keys(&self.0, &nonce);
+        hmac_aad(&hmac_key, &nonce, ciphertext)
+            .verify_slice(mac)
+            .map_err(|_| Error::BadEncryptedMessage)?;
+
+        let mut buffer = ciphertext.to_vec();
+        ChaCha20::new(&chacha_key.into(), &chacha_nonce.into()).apply_keystream(&mut buffer);
+
+        let len = u16::from_be_bytes([buffer[0], buffer[1]]) as usize;
+        if len < MIN_PLAINTEXT_LEN || buffer.len() != 2 + calc_padded_len(len) {
+            return Err(Error::BadEncryptedMessage);
+        }
+        buffer.truncate(2 + len);
+        let _ = buffer.drain(..2);
+        String::from_utf8(buffer).map_err(|e| e.utf8_error().into())
+    }
+}
+
+impl fmt::Debug for ConversationKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ConversationKey(..)")
+    }
+}
+
+impl Drop for ConversationKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for ConversationKey {}
+
+// The per-message ChaCha20 key, ChaCha20 nonce and HMAC key
+fn message_keys(conversation_key: &[u8; 32], nonce: &[u8; 32]) -> ([u8; 32], [u8; 12], [u8; 32]) {
+    let mut okm = [0u8; 76];
+    // Cannot fail: the PRK is the right length and 76 bytes is in range
+    Hkdf::<Sha256>::from_prk(conversation_key)
+        .unwrap()
+        .expand(nonce, &mut okm)
+        .unwrap();
+    let mut chacha_key = [0u8; 32];
+    let mut chacha_nonce = [0u8; 12];
+    let mut hmac_key = [0u8; 32];
+    chacha_key.copy_from_slice(&okm[0..32]);
+    chacha_nonce.copy_from_slice(&okm[32..44]);
+    hmac_key.copy_from_slice(&okm[44..76]);
+    (chacha_key, chacha_nonce, hmac_key)
+}
+
+// The length a plaintext of `len` bytes is padded to
+fn calc_padded_len(len: usize) -> usize {
+    if len <= 32 {
+        return 32;
+    }
+    let next_power = 1 << (usize::BITS - (len - 1).leading_zeros());
+    let chunk = if next_power <= 256 {
+        32
+    } else {
+        next_power / 8
+    };
+    chunk * ((len - 1) / chunk + 1)
+}
+
+fn hmac_aad(hmac_key: &[u8; 32], nonce: &[u8; 32], ciphertext: &[u8]) -> Hmac<Sha256> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(hmac_key).unwrap();
+    mac.update(nonce);
+    mac.update(ciphertext);
+    mac
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_calc_padded_len() {
+        for (len, padded) in [
+            (1, 32),
+            (32, 32),
+            (33, 64),
+            (37, 64),
+            (64, 64),
+            (65, 96),
+            (100, 128),
+            (256, 256),
+            (257, 320),
+            (383, 384),
+            (384, 384),
+            (385, 448),
+            (1024, 1024),
+            (1025, 1280),
+            (65535, 65536),
+        ] {
+            assert_eq!(calc_padded_len(len), padded, "{len}");
+        }
+    }
+
+    #[test]
+    fn test_nip44_vector() {
+        let sec1 = PrivateKey::try_from_hex_string(
+            "0000000000000000000000000000000000000000000000000000000000000001",
+        )
+        .unwrap();
+        let sec2 = PrivateKey::try_from_hex_string(
+            "0000000000000000000000000000000000000000000000000000000000000002",
+        )
+        .unwrap();
+        let key = ConversationKey::new(&sec1, &sec2.public_key());
+        assert_eq!(
+            hex::encode(key.as_bytes()),
+            "c41c775356fd92eadc63ff5a0dc1da211b268cbea22316767095b2871ea1412d"
+        );
+        assert_eq!(key, ConversationKey::new(&sec2, &sec1.public_key()));
+
+        let mut nonce = [0u8; 32];
+        nonce[31] = 1;
+        let payload = key.encrypt_with_nonce("a", &nonce).unwrap();
+        assert_eq!(
+            payload,
+            "AgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABee0G5VSK0/9YypIObAtDKfYEAjD35uVkHyB0F4DwrcNaCXlCWZKaArsGrY6M9wnuTMxWfp1RTN9Xga8no+kF5Vsb"
+        );
+        assert_eq!(key.decrypt(&payload).unwrap(), "a");
+    }
+
+    #[test]
+    fn test_nip44_round_trip() {
+        let key = ConversationKey::new(&PrivateKey::mock(), &PrivateKey::generate().public_key());
+        for len in [1, 31, 32, 33, 300, 65535] {
+            let plaintext = "x".repeat(len);
+            let payload = key.encrypt(&plaintext).unwrap();
+            assert_eq!(key.decrypt(&payload).unwrap(), plaintext);
+        }
+        assert!(key.encrypt("").is_err());
+        assert!(key.encrypt(&"x".repeat(65536)).is_err());
+
+        // Tampering is detected
+        let payload = key.encrypt("hello").unwrap();
+        let mut bytes = base64::engine::general_purpose::STANDARD
+            .decode(&payload)
+            .unwrap();
+        bytes[40] ^= 1;
+        let tampered = base64::engine::general_purpose::STANDARD.encode(bytes);
+        assert!(matches!(
+            key.decrypt(&tampered),
+            Err(Error::BadEncryptedMessage)
+        ));
+        assert!(matches!(
+            key.decrypt("#abc"),
+            Err(Error::UnsupportedEncryptionVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_conversation_key_reuse() {
+        let alice = PrivateKey::mock();
+        let bob = PrivateKey::generate();
+        let key = alice.conversation_key(&bob.public_key());
+        assert_eq!(format!("{key:?}"), "ConversationKey(..)");
+
+        let mut cache = std::collections::HashMap::new();
+        let _ = cache.insert(bob.public_key(), key.clone());
+        let payload = cache[&bob.public_key()].encrypt("hi bob").unwrap();
+        assert_eq!(
+            bob.nip44_decrypt(&alice.public_key(), &payload).unwrap(),
+            "hi bob"
+        );
+        let payload = bob.nip44_encrypt(&alice.public_key(), "hi alice").unwrap();
+        assert_eq!(key.decrypt(&payload).unwrap(), "hi alice");
+        assert_eq!(ConversationKey::from_bytes(*key.as_bytes()), key);
+    }
+}
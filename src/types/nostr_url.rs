@@ -5,7 +5,7 @@ use lazy_static::lazy_static;
 
 /// A bech32 sequence representing a nostr object (or set of objects)
 // note, internally we store them as the object the sequence represents
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum NostrBech32 {
     /// naddr - a NostrBech32 parameterized replaceable event coordinate
     EventAddr(EventAddr),
@@ -55,6 +55,11 @@ impl NostrBech32 {
         NostrBech32::EventPointer(ep)
     }
 
+    /// Create from an `EventAddr`
+    pub fn new_event_addr(ea: EventAddr) -> NostrBech32 {
+        NostrBech32::EventAddr(ea)
+    }
+
     /// Create from an `UncheckedUrl`
     pub fn new_relay(url: UncheckedUrl) -> NostrBech32 {
         NostrBech32::Relay(url)
@@ -126,7 +131,7 @@ impl NostrBech32 {
             let mut pos = 0;
             loop {
                 // we need at least 2 more characters for anything meaningful
-                if pos > tlv.len() - 2 {
+                if pos + 2 > tlv.len() {
                     break;
                 }
                 let ty = tlv[pos];
@@ -157,7 +162,7 @@ impl NostrBech32 {
 }
 
 /// A Nostr URL (starting with 'nostr:')
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct NostrUrl(pub NostrBech32);
 
 impl std::fmt::Display for NostrUrl {
@@ -182,6 +187,11 @@ impl NostrUrl {
         NostrBech32::try_from_string(s.get(6..).unwrap()).map(NostrUrl)
     }
 
+    /// Get the `NostrBech32` entity this URL refers to
+    pub fn entity(&self) -> &NostrBech32 {
+        &self.0
+    }
+
     /// Find all `NostrUrl`s in a string, returned in the order found
     /// (If not prefixed with 'nostr:' they will not count, see NostrBech32)
     pub fn find_all_in_string(s: &str) -> Vec<NostrUrl> {
@@ -219,6 +229,95 @@ impl NostrUrl {
     }
 }
 
+impl From<NostrBech32> for NostrUrl {
+    fn from(bech32: NostrBech32) -> NostrUrl {
+        NostrUrl(bech32)
+    }
+}
+
+impl From<NostrUrl> for NostrBech32 {
+    fn from(url: NostrUrl) -> NostrBech32 {
+        url.0
+    }
+}
+
+/// Anything a user might paste to point at something on nostr, as found by
+/// `parse_any()`
+#[derive(Clone, Debug, PartialEq)]
+pub enum NostrEntity {
+    /// An naddr
+    EventAddr(EventAddr),
+    /// An nevent
+    EventPointer(EventPointer),
+    /// A note, or 64 hex characters that cannot be a public key
+    Id(Id),
+    /// An nprofile
+    Profile(Profile),
+    /// An npub
+    Pubkey(PublicKey),
+    /// 64 hex characters, which could be either a public key or an event id
+    PubkeyOrId(PublicKey, Id),
+    /// An nrelay, or a ws:// or wss:// URL
+    Relay(UncheckedUrl),
+}
+
+impl From<NostrBech32> for NostrEntity {
+    fn from(bech32: NostrBech32) -> NostrEntity {
+        match bech32 {
+            NostrBech32::EventAddr(ea) => NostrEntity::EventAddr(ea),
+            NostrBech32::EventPointer(ep) => NostrEntity::EventPointer(ep),
+            NostrBech32::Id(id) => NostrEntity::Id(id),
+            NostrBech32::Profile(p) => NostrEntity::Profile(p),
+            NostrBech32::Pubkey(pk) => NostrEntity::Pubkey(pk),
+            NostrBech32::Relay(url) => NostrEntity::Relay(url),
+        }
+    }
+}
+
+impl std::str::FromStr for NostrEntity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<NostrEntity, Error> {
+        parse_any(s)
+    }
+}
+
+/// Parse whatever a user typed or pasted into a `NostrEntity`: a NIP-19
+/// string (npub, note, nprofile, nevent, naddr or nrelay), the same as a
+/// `nostr:` URL, a relay URL, or 64 hex characters. Surrounding whitespace
+/// is ignored, and so is case where bech32 allows it.
+///
+/// Secret keys (nsec) are refused rather than quietly turned into public keys.
+pub fn parse_any(s: &str) -> Result<NostrEntity, Error> {
+    let s = s.trim();
+    let s = match s.get(..6) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("nostr:") => s.get(6..).unwrap(),
+        _ => s,
+    };
+
+    if s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit()) {
+        let id = Id::try_from_hex_string(s)?;
+        return Ok(match PublicKey::try_from_hex_string(s) {
+            Ok(pubkey) => NostrEntity::PubkeyOrId(pubkey, id),
+            Err(_) => NostrEntity::Id(id),
+        });
+    }
+
+    if let Ok(relay) = super::RelayUrl::try_from_str(s) {
+        return Ok(NostrEntity::Relay(relay.to_unchecked_url()));
+    }
+
+    // bech32 may be all uppercase (as in QR codes) but not mixed case
+    if s.chars().all(|c| !c.is_ascii_lowercase()) {
+        if let Some(bech32) = NostrBech32::try_from_string(&s.to_ascii_lowercase()) {
+            return Ok(bech32.into());
+        }
+    }
+    NostrBech32::try_from_string(s)
+        .map(|bech32| bech32.into())
+        .ok_or_else(|| Error::UnrecognizedEntity(s.to_owned()))
+}
+
 /// Returns start and end position of next valid NostrBech32
 pub fn find_nostr_bech32_pos(s: &str) -> Option<(usize, usize)> {
     // BECH32 Alphabet:
@@ -383,6 +482,32 @@ nostr:nrelay1qqvhwumn8ghj7un9d3shjtnwdaehgunfvd5zumrpdejqpdl8ln
         assert_eq!(NostrUrl::find_all_in_string(sample).len(), 11);
     }
 
+    #[test]
+    fn test_nostr_url_roundtrip() {
+        let samples = [
+            "nostr:npub1sn0wdenkukak0d9dfczzeacvhkrgz92ak56egt7vdgzn8pv2wfqqhrjdv9",
+            "nostr:nprofile1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaksjlyr9p",
+            "nostr:note1fntxtkcy9pjwucqwa9mddn7v03wwwsu9j330jj350nvhpky2tuaspk6nqc",
+            "nostr:nevent1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaks343fay",
+        ];
+        for sample in samples {
+            let nurl = NostrUrl::try_from_string(sample).unwrap();
+            assert_eq!(format!("{nurl}"), sample);
+        }
+
+        let ea = EventAddr::mock();
+        let nurl: NostrUrl = NostrBech32::new_event_addr(ea.clone()).into();
+        let parsed = NostrUrl::try_from_string(&format!("{nurl}")).unwrap();
+        assert_eq!(parsed, nurl);
+        assert_eq!(parsed.entity(), &NostrBech32::EventAddr(ea));
+
+        // Missing the scheme
+        assert!(NostrUrl::try_from_string(
+            "npub1sn0wdenkukak0d9dfczzeacvhkrgz92ak56egt7vdgzn8pv2wfqqhrjdv9"
+        )
+        .is_none());
+    }
+
     #[test]
     fn test_generate_nrelay() {
         let url = UncheckedUrl("wss://nostr.mikedilger.com/".to_owned());
@@ -390,4 +515,40 @@ nostr:nrelay1qqvhwumn8ghj7un9d3shjtnwdaehgunfvd5zumrpdejqpdl8ln
         let nurl = NostrUrl(nb32);
         println!("{}", nurl);
     }
+
+    #[test]
+    fn test_parse_any() {
+        let npub = "npub1sn0wdenkukak0d9dfczzeacvhkrgz92ak56egt7vdgzn8pv2wfqqhrjdv9";
+        let pubkey = PublicKey::try_from_bech32_string(npub).unwrap();
+        let hex = pubkey.as_hex_string();
+
+        for input in [
+            npub.to_owned(),
+            format!("  nostr:{npub}\n"),
+            format!("NOSTR:{}", npub.to_uppercase()),
+        ] {
+            assert_eq!(parse_any(&input).unwrap(), NostrEntity::Pubkey(pubkey));
+        }
+        assert!(matches!(
+            parse_any(&format!("nostr:{hex}")).unwrap(),
+            NostrEntity::PubkeyOrId(pk, id) if pk == pubkey && id.as_hex_string() == hex
+        ));
+        assert!(matches!(
+            parse_any(&"ff".repeat(32)).unwrap(),
+            NostrEntity::Id(_)
+        ));
+        assert!(matches!(
+            parse_any("note1fntxtkcy9pjwucqwa9mddn7v03wwwsu9j330jj350nvhpky2tuaspk6nqc").unwrap(),
+            NostrEntity::Id(_)
+        ));
+        assert_eq!(
+            parse_any("wss://nostr.mikedilger.com").unwrap(),
+            NostrEntity::Relay(UncheckedUrl("wss://nostr.mikedilger.com/".to_owned()))
+        );
+
+        let mut key = crate::types::PrivateKey::mock();
+        assert!(parse_any(&key.as_bech32_string()).is_err());
+        assert!(parse_any("hello").is_err());
+        assert!(parse_any(npub.get(..npub.len() - 1).unwrap()).is_err());
+    }
 }
@@ -0,0 +1,378 @@
+//! OpenTimestamps (NIP-03) proof creation and verification
+//!
+//! Creating a proof happens in two steps because it requires talking to an
+//! OpenTimestamps calendar server, which this crate does not do. First create an
+//! `OtsRequest` for the event id and POST its `calendar_digest()` to a calendar's
+//! `/digest` endpoint. Then hand the response body to `OtsRequest::into_proof()`
+//! to get the base64 string that goes in the event's `ots` field.
+//!
+//! Freshly created proofs only carry a pending attestation. Once the calendar has
+//! committed to the bitcoin chain the proof must be upgraded (by any OpenTimestamps
+//! client) before it can be verified here.
+
+use crate::{Error, Id, Unixtime};
+use base64::Engine;
+use k256::sha2::{Digest, Sha256};
+use rand_core::{OsRng, RngCore};
+
+const HEADER_MAGIC: &[u8] = b"\x00OpenTimestamps\x00\x00Proof\x00\xbf\x89\xe2\xe8\x84\xe8\x92\x94";
+const MAJOR_VERSION: u8 = 1;
+
+const OP_SHA256: u8 = 0x08;
+const OP_APPEND: u8 = 0xf0;
+const OP_PREPEND: u8 = 0xf1;
+const OP_REVERSE: u8 = 0xf2;
+const OP_HEXLIFY: u8 = 0xf3;
+
+const TAG_ATTESTATION: u8 = 0x00;
+const TAG_FORK: u8 = 0xff;
+
+const BITCOIN_ATTESTATION: [u8; 8] = [0x05, 0x88, 0x96, 0x0d, 0x73, 0xd7, 0x19, 0x01];
+
+// Guard against maliciously deep or large proofs
+const MAX_DEPTH: usize = 256;
+const MAX_MSG_LEN: usize = 4096;
+
+/// A request to timestamp an event id with an OpenTimestamps calendar
+#[derive(Clone, Copy, Debug)]
+pub struct OtsRequest {
+    id: Id,
+    nonce: [u8; 16],
+}
+
+impl OtsRequest {
+    /// Start a timestamp request for an event id
+    pub fn new(id: Id) -> OtsRequest {
+        let mut nonce = [0u8; 16];
+        OsRng.fill_bytes(&mut nonce);
+        OtsRequest { id, nonce }
+    }
+
+    /// The digest to submit to a calendar server. The event id is blinded
+    /// with a random nonce so calendars do not learn it.
+    pub fn calendar_digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.id.0);
+        hasher.update(self.nonce);
+        hasher.finalize().into()
+    }
+
+    /// Build the proof from a calendar server's response to `calendar_digest()`,
+    /// returning it base64 encoded for use in an event's `ots` field
+    pub fn into_proof(self, calendar_response: &[u8]) -> Result<String, Error> {
+        // Make sure the calendar gave us something we understand
+        let mut reader = Reader::new(calendar_response);
+        let _ = Timestamp::read(&mut reader, 0)?;
+        if !reader.is_empty() {
+            return Err(Error::Ots("Trailing bytes in calendar response".to_owned()));
+        }
+
+        let mut proof: Vec<u8> = Vec::new();
+        proof.extend_from_slice(HEADER_MAGIC);
+        proof.push(MAJOR_VERSION);
+        proof.push(OP_SHA256);
+        proof.extend_from_slice(&self.id.0);
+        proof.push(OP_APPEND);
+        write_varbytes(&mut proof, &self.nonce);
+        proof.push(OP_SHA256);
+        proof.extend_from_slice(calendar_response);
+
+        Ok(base64::engine::general_purpose::STANDARD.encode(proof))
+    }
+}
+
+/// Verify a base64 encoded OpenTimestamps proof of `id` against the bitcoin chain.
+///
+/// `block_header` must return the 80-byte bitcoin block header at the given height,
+/// from a source the caller trusts. Returns the time of the earliest block that
+/// attests to the id.
+pub fn verify_ots<F>(ots: &str, id: Id, block_header: F) -> Result<Unixtime, Error>
+where
+    F: Fn(u64) -> Option<[u8; 80]>,
+{
+    let bytes = base64::engine::general_purpose::STANDARD.decode(ots)?;
+    let mut reader = Reader::new(&bytes);
+
+    if reader.read_bytes(HEADER_MAGIC.len())? != HEADER_MAGIC {
+        return Err(Error::Ots("Not an OpenTimestamps proof".to_owned()));
+    }
+    if reader.read_varuint()? != MAJOR_VERSION as u64 {
+        return Err(Error::Ots("Unsupported OpenTimestamps version".to_owned()));
+    }
+    if reader.read_u8()? != OP_SHA256 {
+        return Err(Error::Ots("Proof is not over a SHA256 digest".to_owned()));
+    }
+    if reader.read_bytes(32)? != id.0 {
+        return Err(Error::Ots("Proof is for a different event id".to_owned()));
+    }
+    let timestamp = Timestamp::read(&mut reader, 0)?;
+    if !reader.is_empty() {
+        return Err(Error::Ots("Trailing bytes in proof".to_owned()));
+    }
+
+    let mut attestations: Vec<(u64, Vec<u8>)> = Vec::new();
+    timestamp.bitcoin_attestations(id.0.to_vec(), &mut attestations)?;
+    if attestations.is_empty() {
+        return Err(Error::Ots(
+            "No bitcoin attestation (the proof may need upgrading)".to_owned(),
+        ));
+    }
+
+    let mut earliest: Option<Unixtime> = None;
+    for (height, msg) in attestations {
+        let header = match block_header(height) {
+            Some(h) => h,
+            None => continue,
+        };
+        // The merkle root is stored in the header in internal byte order,
+        // which is how the proof commits to it.
+        if msg.len() != 32 || msg[..] != header[36..68] {
+            return Err(Error::Ots(format!(
+                "Attestation does not match block {height}"
+            )));
+        }
+        let mut time_bytes = [0u8; 4];
+        time_bytes.copy_from_slice(&header[68..72]);
+        let time = Unixtime(u32::from_le_bytes(time_bytes) as i64);
+        earliest = Some(earliest.map_or(time, |e| e.min(time)));
+    }
+
+    earliest.ok_or_else(|| Error::Ots("No block header for any attestation".to_owned()))
+}
+
+#[derive(Debug)]
+enum Op {
+    Sha256,
+    Append(Vec<u8>),
+    Prepend(Vec<u8>),
+    Reverse,
+    Hexlify,
+}
+
+impl Op {
+    fn apply(&self, msg: &[u8]) -> Result<Vec<u8>, Error> {
+        let out = match self {
+            Op::Sha256 => Sha256::digest(msg).to_vec(),
+            Op::Append(arg) => [msg, arg].concat(),
+            Op::Prepend(arg) => [arg, msg].concat(),
+            Op::Reverse => msg.iter().rev().copied().collect(),
+            Op::Hexlify => hex::encode(msg).into_bytes(),
+        };
+        if out.len() > MAX_MSG_LEN {
+            return Err(Error::Ots("Message too long".to_owned()));
+        }
+        Ok(out)
+    }
+}
+
+#[derive(Debug)]
+enum Attestation {
+    Bitcoin(u64),
+    Other,
+}
+
+#[derive(Debug, Default)]
+struct Timestamp {
+    attestations: Vec<Attestation>,
+    ops: Vec<(Op, Timestamp)>,
+}
+
+impl Timestamp {
+    fn read(reader: &mut Reader<'_>, depth: usize) -> Result<Timestamp, Error> {
+        if depth > MAX_DEPTH {
+            return Err(Error::Ots("Proof nested too deeply".to_owned()));
+        }
+        let mut timestamp = Timestamp::default();
+        loop {
+            let mut tag = reader.read_u8()?;
+            let last = tag != TAG_FORK;
+            if !last {
+                tag = reader.read_u8()?;
+            }
+            if tag == TAG_ATTESTATION {
+                let kind = reader.read_bytes(8)?;
+                let payload = reader.read_varbytes()?;
+                if kind == BITCOIN_ATTESTATION {
+                    let height = Reader::new(payload).read_varuint()?;
+                    timestamp.attestations.push(Attestation::Bitcoin(height));
+                } else {
+                    timestamp.attestations.push(Attestation::Other);
+                }
+            } else {
+                let op = match tag {
+                    OP_SHA256 => Op::Sha256,
+                    OP_APPEND => Op::Append(reader.read_varbytes()?.to_vec()),
+                    OP_PREPEND => Op::Prepend(reader.read_varbytes()?.to_vec()),
+                    OP_REVERSE => Op::Reverse,
+                    OP_HEXLIFY => Op::Hexlify,
+                    other => {
+                        return Err(Error::Ots(format!("Unsupported operation 0x{other:02x}")))
+                    }
+                };
+                let child = Timestamp::read(reader, depth + 1)?;
+                timestamp.ops.push((op, child));
+            }
+            if last {
+                return Ok(timestamp);
+            }
+        }
+    }
+
+    fn bitcoin_attestations(
+        &self,
+        msg: Vec<u8>,
+        output: &mut Vec<(u64, Vec<u8>)>,
+    ) -> Result<(), Error> {
+        for attestation in self.attestations.iter() {
+            if let Attestation::Bitcoin(height) = attestation {
+                output.push((*height, msg.clone()));
+            }
+        }
+        for (op, child) in self.ops.iter() {
+            child.bitcoin_attestations(op.apply(&msg)?, output)?;
+        }
+        Ok(())
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self.pos.saturating_add(len);
+        let out = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or_else(|| Error::Ots("Unexpected end of proof".to_owned()))?;
+        self.pos = end;
+        Ok(out)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_varuint(&mut self) -> Result<u64, Error> {
+        let mut value: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let b = self.read_u8()?;
+            if shift > 63 {
+                return Err(Error::Ots("Integer too large".to_owned()));
+            }
+            value |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    fn read_varbytes(&mut self) -> Result<&'a [u8], Error> {
+        let len = self.read_varuint()?;
+        if len > MAX_MSG_LEN as u64 {
+            return Err(Error::Ots("Field too long".to_owned()));
+        }
+        self.read_bytes(len as usize)
+    }
+}
+
+fn write_varuint(output: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let b = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            output.push(b);
+            return;
+        }
+        output.push(b | 0x80);
+    }
+}
+
+fn write_varbytes(output: &mut Vec<u8>, bytes: &[u8]) {
+    write_varuint(output, bytes.len() as u64);
+    output.extend_from_slice(bytes);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // A calendar response committing `digest` into a fake block's merkle root
+    // (prepend some bytes and hash), attested at `height`
+    fn mock_calendar_response(height: u64) -> (Vec<u8>, Vec<u8>) {
+        let mut response: Vec<u8> = Vec::new();
+        response.push(OP_PREPEND);
+        write_varbytes(&mut response, b"sibling");
+        response.push(OP_SHA256);
+        response.push(TAG_ATTESTATION);
+        response.extend_from_slice(&BITCOIN_ATTESTATION);
+        let mut payload = Vec::new();
+        write_varuint(&mut payload, height);
+        write_varbytes(&mut response, &payload);
+        (response, b"sibling".to_vec())
+    }
+
+    fn mock_header(merkle_root: &[u8], time: u32) -> [u8; 80] {
+        let mut header = [0u8; 80];
+        header[36..68].copy_from_slice(merkle_root);
+        header[68..72].copy_from_slice(&time.to_le_bytes());
+        header
+    }
+
+    #[test]
+    fn test_ots_roundtrip() {
+        let id = Id::mock();
+        let request = OtsRequest::new(id);
+        let digest = request.calendar_digest();
+        let (response, sibling) = mock_calendar_response(800_000);
+        let proof = request.into_proof(&response).unwrap();
+
+        let merkle_root: [u8; 32] = Sha256::digest([&sibling[..], &digest[..]].concat()).into();
+        let header = mock_header(&merkle_root, 1_690_000_000);
+
+        let time = verify_ots(&proof, id, |height| {
+            if height == 800_000 {
+                Some(header)
+            } else {
+                None
+            }
+        })
+        .unwrap();
+        assert_eq!(time, Unixtime(1_690_000_000));
+
+        // Wrong header
+        let bad_header = mock_header(&[1; 32], 1_690_000_000);
+        assert!(verify_ots(&proof, id, |_| Some(bad_header)).is_err());
+
+        // Wrong id
+        let mut other = id;
+        other.0[0] ^= 1;
+        assert!(verify_ots(&proof, other, |_| Some(header)).is_err());
+    }
+
+    #[test]
+    fn test_ots_pending_only() {
+        let id = Id::mock();
+        let request = OtsRequest::new(id);
+        let mut response: Vec<u8> = vec![TAG_ATTESTATION];
+        response.extend_from_slice(&[0x83, 0xdf, 0xe3, 0x0d, 0x2e, 0xf9, 0x0c, 0x8e]);
+        write_varbytes(&mut response, b"\x1bhttps://a.pool.example.com");
+        let proof = request.into_proof(&response).unwrap();
+        assert!(verify_ots(&proof, id, |_| None).is_err());
+
+        // Garbage from the calendar is rejected up front
+        assert!(OtsRequest::new(id).into_proof(&[0xf0, 0x05]).is_err());
+    }
+}
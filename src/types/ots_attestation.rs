@@ -0,0 +1,113 @@
+use super::{Event, EventKind, Id, Tag, UncheckedUrl};
+use crate::Error;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+
+/// An OpenTimestamps attestation of another event (NIP-03, kind 1040)
+///
+/// This is the event-based way of publishing a proof, as an alternative to
+/// the `ots` field of the attested event itself.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct OtsAttestation {
+    /// The id of the attested event, from the 'e' tag
+    pub event: Id,
+
+    /// A relay where the attested event can be found
+    pub relay: Option<UncheckedUrl>,
+
+    /// The kind of the attested event, from the 'k' tag
+    pub kind: Option<EventKind>,
+
+    /// The base64 encoded OpenTimestamps proof file. This is the event content.
+    pub proof: String,
+}
+
+impl OtsAttestation {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> OtsAttestation {
+        OtsAttestation {
+            event: Id::mock(),
+            relay: Some(UncheckedUrl::mock()),
+            kind: Some(EventKind::TextNote),
+            proof: "AE9wZW5UaW1lc3RhbXBzAABQcm9vZgC/ieLohOiSlAE=".to_owned(),
+        }
+    }
+
+    /// An attestation of `event` with the given base64 encoded proof
+    pub fn new(event: &Event, relay: Option<UncheckedUrl>, proof: String) -> OtsAttestation {
+        OtsAttestation {
+            event: event.id,
+            relay,
+            kind: Some(event.kind),
+            proof,
+        }
+    }
+
+    /// Read an attestation from the tags and content of a kind 1040 event.
+    /// Returns None if there is no 'e' tag.
+    pub fn from_tags(tags: &[Tag], content: &str) -> Option<OtsAttestation> {
+        let (event, relay, _) = tags.iter().find_map(|t| t.parse_event().ok())?;
+        let kind = tags.iter().find_map(|t| t.parse_kind().ok());
+        Some(OtsAttestation {
+            event,
+            relay,
+            kind,
+            proof: content.to_owned(),
+        })
+    }
+
+    /// The tags of a kind 1040 event for this attestation
+    pub fn to_tags(&self) -> Vec<Tag> {
+        let mut tags = vec![Tag::new_event(self.event, self.relay.clone(), None)];
+        if let Some(kind) = self.kind {
+            tags.push(Tag::new_kind(kind));
+        }
+        tags
+    }
+
+    /// The raw bytes of the OpenTimestamps proof file
+    pub fn proof_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(base64::engine::general_purpose::STANDARD.decode(&self.proof)?)
+    }
+
+    /// Verify the proof against the attested event id. See `verify_ots()` for
+    /// what `block_header` must do.
+    #[cfg(feature = "ots")]
+    pub fn verify<F>(&self, block_header: F) -> Result<super::Unixtime, Error>
+    where
+        F: Fn(u64) -> Option<[u8; 80]>,
+    {
+        super::verify_ots(&self.proof, self.event, block_header)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {OtsAttestation, test_ots_attestation_serde}
+
+    #[test]
+    fn test_ots_attestation_tags() {
+        let attestation = OtsAttestation::mock();
+        let tags = attestation.to_tags();
+        let parsed = OtsAttestation::from_tags(&tags, &attestation.proof).unwrap();
+        assert_eq!(parsed, attestation);
+        assert!(parsed
+            .proof_bytes()
+            .unwrap()
+            .starts_with(b"\x00OpenTimestamps"));
+
+        assert!(OtsAttestation::from_tags(&[Tag::new_kind(EventKind::TextNote)], "").is_none());
+
+        let garbage = OtsAttestation {
+            proof: "not base64!".to_owned(),
+            ..attestation
+        };
+        assert!(garbage.proof_bytes().is_err());
+    }
+}
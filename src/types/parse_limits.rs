@@ -0,0 +1,519 @@
+//! Size limits enforced while parsing untrusted JSON
+//!
+//! Limits are checked as each tag and string is read, so an oversized event is
+//! rejected before its tags are collected rather than after.
+
+use super::event::ByteCounter;
+use super::{
+    ClientMessage, Event, EventKind, Filter, Id, PublicKey, Signature, SubscriptionId, Tag,
+    Unixtime,
+};
+use crate::Error;
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Value};
+use std::cell::Cell;
+use std::fmt;
+
+/// Limits on the size of an event, enforced while it is deserialized.
+/// Relays should apply these to anything read from the network.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ParseLimits {
+    /// The most tags an event may have
+    pub max_tags: usize,
+
+    /// The most elements (including the tag name) a tag may have
+    pub max_tag_elements: usize,
+
+    /// The longest (in bytes) the content, a tag element or the ots field may be
+    pub max_string_len: usize,
+
+    /// The longest (in bytes) the JSON of a whole event or message may be
+    pub max_json_len: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> ParseLimits {
+        ParseLimits {
+            max_tags: 2000,
+            max_tag_elements: 100,
+            max_string_len: 256 * 1024,
+            max_json_len: 1024 * 1024,
+        }
+    }
+}
+
+impl ParseLimits {
+    /// No limits at all
+    pub const UNLIMITED: ParseLimits = ParseLimits {
+        max_tags: usize::MAX,
+        max_tag_elements: usize::MAX,
+        max_string_len: usize::MAX,
+        max_json_len: usize::MAX,
+    };
+}
+
+// The seeds record which limit was hit here, so it can be returned as our own
+// error rather than as a serde_json message
+type Violation = Cell<Option<Error>>;
+
+fn exceeded<E: de::Error>(violation: &Violation, error: Error) -> E {
+    let e = E::custom(&error);
+    violation.set(Some(error));
+    e
+}
+
+fn finish<T>(result: Result<T, serde_json::Error>, violation: Violation) -> Result<T, Error> {
+    match (result, violation.into_inner()) {
+        (Ok(t), _) => Ok(t),
+        (Err(_), Some(error)) => Err(error),
+        (Err(e), None) => Err(e.into()),
+    }
+}
+
+pub(crate) fn parse_event(json: &str, limits: &ParseLimits) -> Result<Event, Error> {
+    if json.len() > limits.max_json_len {
+        return Err(Error::JsonTooLong(limits.max_json_len));
+    }
+    let violation: Violation = Cell::new(None);
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let result = EventSeed {
+        limits,
+        violation: &violation,
+    }
+    .deserialize(&mut deserializer)
+    .and_then(|event| deserializer.end().map(|_| event));
+    finish(result, violation)
+}
+
+// How every event is deserialized, with or without limits, so that the
+// ways of parsing an event cannot come to differ
+pub(super) fn deserialize_event<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    limits: &ParseLimits,
+) -> Result<Event, D::Error> {
+    let violation: Violation = Cell::new(None);
+    EventSeed {
+        limits,
+        violation: &violation,
+    }
+    .deserialize(deserializer)
+}
+
+// Sets a field, failing if it was already set
+fn set_once<T, E: de::Error>(slot: &mut Option<T>, value: T, name: &'static str) -> Result<(), E> {
+    if slot.is_some() {
+        return Err(E::duplicate_field(name));
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+pub(crate) fn parse_client_message(
+    json: &str,
+    limits: &ParseLimits,
+) -> Result<ClientMessage, Error> {
+    if json.len() > limits.max_json_len {
+        return Err(Error::JsonTooLong(limits.max_json_len));
+    }
+    let violation: Violation = Cell::new(None);
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let result = ClientMessageSeed {
+        limits,
+        violation: &violation,
+    }
+    .deserialize(&mut deserializer)
+    .and_then(|message| deserializer.end().map(|_| message));
+    finish(result, violation)
+}
+
+#[derive(Clone, Copy)]
+struct ClientMessageSeed<'a> {
+    limits: &'a ParseLimits,
+    violation: &'a Violation,
+}
+
+impl<'de> DeserializeSeed<'de> for ClientMessageSeed<'_> {
+    type Value = ClientMessage;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<ClientMessage, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ClientMessageSeed<'_> {
+    type Value = ClientMessage;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a sequence of strings")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<ClientMessage, A::Error> {
+        let event_seed = EventSeed {
+            limits: self.limits,
+            violation: self.violation,
+        };
+        let word: String = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::custom("Message missing initial string field"))?;
+        match word.as_str() {
+            "EVENT" | "AUTH" => {
+                let event = seq
+                    .next_element_seed(event_seed)?
+                    .ok_or_else(|| de::Error::custom("Message missing event field"))?;
+                if word == "EVENT" {
+                    Ok(ClientMessage::Event(Box::new(event)))
+                } else {
+                    Ok(ClientMessage::Auth(Box::new(event)))
+                }
+            }
+            "REQ" | "COUNT" => {
+                let id: SubscriptionId = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::custom("Message missing id field"))?;
+                let mut filters: Vec<Filter> = vec![];
+                while let Some(filter) = seq.next_element()? {
+                    filters.push(filter);
+                }
+                if word == "REQ" {
+                    Ok(ClientMessage::Req(id, filters))
+                } else {
+                    Ok(ClientMessage::Count(id, filters))
+                }
+            }
+            "CLOSE" => {
+                let id: SubscriptionId = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::custom("Message missing id field"))?;
+                Ok(ClientMessage::Close(id))
+            }
+            _ => Err(de::Error::custom(format!("Unknown Message: {word}"))),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct EventSeed<'a> {
+    limits: &'a ParseLimits,
+    violation: &'a Violation,
+}
+
+impl<'a> EventSeed<'a> {
+    fn string(self) -> StringSeed<'a> {
+        StringSeed {
+            max_len: self.limits.max_string_len,
+            violation: self.violation,
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for EventSeed<'_> {
+    type Value = Event;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Event, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for EventSeed<'_> {
+    type Value = Event;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an event")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Event, M::Error> {
+        let string = self.string();
+        let mut id: Option<Id> = None;
+        let mut pubkey: Option<PublicKey> = None;
+        let mut created_at: Option<Unixtime> = None;
+        let mut kind: Option<EventKind> = None;
+        let mut tags: Option<Vec<Tag>> = None;
+        let mut content: Option<String> = None;
+        let mut ots: Option<Option<String>> = None;
+        let mut sig: Option<Signature> = None;
+        let mut extra: Map<String, Value> = Map::new();
+        while let Some(key) = map.next_key_seed(string)? {
+            match key.as_str() {
+                "id" => set_once(&mut id, map.next_value()?, "id")?,
+                "pubkey" => set_once(&mut pubkey, map.next_value()?, "pubkey")?,
+                "created_at" => set_once(&mut created_at, map.next_value()?, "created_at")?,
+                "kind" => set_once(&mut kind, map.next_value()?, "kind")?,
+                "tags" => set_once(&mut tags, map.next_value_seed(TagsSeed(self))?, "tags")?,
+                "content" => set_once(&mut content, map.next_value_seed(string)?, "content")?,
+                "ots" => set_once(&mut ots, map.next_value_seed(OptionSeed(string))?, "ots")?,
+                "sig" => set_once(&mut sig, map.next_value()?, "sig")?,
+                _ => {
+                    if extra.contains_key(&key) {
+                        let _ = map.next_value::<IgnoredAny>()?;
+                        return Err(de::Error::custom(format!("duplicate field `{key}`")));
+                    }
+                    let value: Value = map.next_value()?;
+                    let max = self.limits.max_string_len;
+                    if max < usize::MAX {
+                        let mut counter = ByteCounter(0);
+                        let _ = serde_json::to_writer(&mut counter, &value);
+                        if counter.0 > max {
+                            return Err(exceeded(self.violation, Error::StringTooLong(max)));
+                        }
+                    }
+                    let _ = extra.insert(key, value);
+                }
+            }
+        }
+        Ok(Event {
+            id: id.ok_or_else(|| de::Error::missing_field("id"))?,
+            pubkey: pubkey.ok_or_else(|| de::Error::missing_field("pubkey"))?,
+            created_at: created_at.ok_or_else(|| de::Error::missing_field("created_at"))?,
+            kind: kind.ok_or_else(|| de::Error::missing_field("kind"))?,
+            tags: tags.ok_or_else(|| de::Error::missing_field("tags"))?,
+            content: content.ok_or_else(|| de::Error::missing_field("content"))?,
+            ots: ots.flatten(),
+            sig: sig.ok_or_else(|| de::Error::missing_field("sig"))?,
+            extra,
+        })
+    }
+}
+
+struct TagsSeed<'a>(EventSeed<'a>);
+
+impl<'de> DeserializeSeed<'de> for TagsSeed<'_> {
+    type Value = Vec<Tag>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Vec<Tag>, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TagsSeed<'_> {
+    type Value = Vec<Tag>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a sequence of tags")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<Tag>, A::Error> {
+        let limits = self.0.limits;
+        let mut tags: Vec<Tag> = Vec::new();
+        while let Some(tag) = seq.next_element_seed(TagSeed(self.0))? {
+            if tags.len() == limits.max_tags {
+                return Err(exceeded(
+                    self.0.violation,
+                    Error::TooManyTags(limits.max_tags),
+                ));
+            }
+            tags.push(tag);
+        }
+        Ok(tags)
+    }
+}
+
+struct TagSeed<'a>(EventSeed<'a>);
+
+impl<'de> DeserializeSeed<'de> for TagSeed<'_> {
+    type Value = Tag;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Tag, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TagSeed<'_> {
+    type Value = Tag;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a sequence of strings")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Tag, A::Error> {
+        let limits = self.0.limits;
+        let string = self.0.string();
+        let mut fields: Vec<String> = Vec::new();
+        while let Some(field) = seq.next_element_seed(string)? {
+            if fields.len() == limits.max_tag_elements {
+                return Err(exceeded(
+                    self.0.violation,
+                    Error::TooManyTagElements(limits.max_tag_elements),
+                ));
+            }
+            fields.push(field);
+        }
+        Ok(Tag::from_strings(fields))
+    }
+}
+
+#[derive(Clone, Copy)]
+struct StringSeed<'a> {
+    max_len: usize,
+    violation: &'a Violation,
+}
+
+impl<'de> DeserializeSeed<'de> for StringSeed<'_> {
+    type Value = String;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<String, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl Visitor<'_> for StringSeed<'_> {
+    type Value = String;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a string")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<String, E> {
+        if v.len() > self.max_len {
+            return Err(exceeded(self.violation, Error::StringTooLong(self.max_len)));
+        }
+        Ok(v.to_owned())
+    }
+}
+
+// For the optional ots field
+struct OptionSeed<'a>(StringSeed<'a>);
+
+impl<'de> DeserializeSeed<'de> for OptionSeed<'_> {
+    type Value = Option<String>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_option(self)
+    }
+}
+
+impl<'de> Visitor<'de> for OptionSeed<'_> {
+    type Value = Option<String>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a string or null")
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Option<String>, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Option<String>, E> {
+        Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        self.0.deserialize(deserializer).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn event_json(num_tags: usize, tag_len: usize, content: &str) -> String {
+        let mut event = Event::mock();
+        event.tags = vec![Tag::from_strings(vec!["t".to_owned(); tag_len]); num_tags];
+        event.content = content.to_owned();
+        serde_json::to_string(&event).unwrap()
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        let limits = ParseLimits {
+            max_tags: 3,
+            max_tag_elements: 4,
+            max_string_len: 10,
+            max_json_len: 1000,
+        };
+
+        let json = event_json(3, 4, "0123456789");
+        let event = Event::from_json_limited(&json, &limits).unwrap();
+        assert_eq!(event, serde_json::from_str::<Event>(&json).unwrap());
+
+        let json = event_json(4, 1, "");
+        assert!(matches!(
+            Event::from_json_limited(&json, &limits),
+            Err(Error::TooManyTags(3))
+        ));
+        let json = event_json(1, 5, "");
+        assert!(matches!(
+            Event::from_json_limited(&json, &limits),
+            Err(Error::TooManyTagElements(4))
+        ));
+        let json = event_json(0, 0, "01234567890");
+        assert!(matches!(
+            Event::from_json_limited(&json, &limits),
+            Err(Error::StringTooLong(10))
+        ));
+        let json = event_json(0, 0, &" ".repeat(1000));
+        assert!(matches!(
+            Event::from_json_limited(&json, &limits),
+            Err(Error::JsonTooLong(1000))
+        ));
+
+        // Other errors come through as usual
+        assert!(matches!(
+            Event::from_json_limited("{}", &limits),
+            Err(Error::SerdeJson(_))
+        ));
+        let json = event_json(4, 1, "");
+        assert!(Event::from_json_limited(&json, &ParseLimits::UNLIMITED).is_ok());
+    }
+
+    #[test]
+    fn test_extra_fields_limited() {
+        let limits = ParseLimits {
+            max_string_len: 20,
+            ..Default::default()
+        };
+        let mut event = Event::mock();
+        event.tags = vec![];
+        event.content = "".to_owned();
+        let _ = event
+            .extra
+            .insert("seen".to_owned(), Value::from(vec!["wss://a.example"]));
+        let json = serde_json::to_string(&event).unwrap();
+
+        // Both ways of parsing keep the extra field, and agree
+        let limited = Event::from_json_limited(&json, &limits).unwrap();
+        assert_eq!(limited, event);
+        assert_eq!(limited, serde_json::from_str::<Event>(&json).unwrap());
+        assert_eq!(serde_json::to_string(&limited).unwrap(), json);
+
+        // An extra field counts against the string limit
+        let _ = event.extra.insert(
+            "seen".to_owned(),
+            Value::from(vec!["wss://a.example", "wss://b.example"]),
+        );
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(matches!(
+            Event::from_json_limited(&json, &limits),
+            Err(Error::StringTooLong(20))
+        ));
+        assert!(serde_json::from_str::<Event>(&json).is_ok());
+
+        let duplicated = json.replacen("{", r#"{"seen":1,"#, 1);
+        assert!(Event::from_json_limited(&duplicated, &limits).is_err());
+        assert!(serde_json::from_str::<Event>(&duplicated).is_err());
+    }
+
+    #[test]
+    fn test_client_message_limits() {
+        let limits = ParseLimits {
+            max_tags: 1,
+            ..Default::default()
+        };
+        let json = format!("[\"EVENT\",{}]", event_json(2, 2, "hi"));
+        assert!(matches!(
+            ClientMessage::from_json_limited(&json, &limits),
+            Err(Error::TooManyTags(1))
+        ));
+
+        let message = ClientMessage::mock();
+        let json = serde_json::to_string(&message).unwrap();
+        assert_eq!(
+            ClientMessage::from_json_limited(&json, &ParseLimits::default()).unwrap(),
+            message
+        );
+        let json = r#"["REQ","sub",{"kinds":[1]}]"#;
+        assert_eq!(
+            ClientMessage::from_json_limited(json, &limits).unwrap(),
+            serde_json::from_str::<ClientMessage>(json).unwrap()
+        );
+    }
+}
@@ -0,0 +1,450 @@
+use super::{
+    Event, EventKind, Id, MachineReadablePrefix, PreEvent, RelayInformationDocument,
+    RelayLimitation, RelayReason, Signature, Tag, Unixtime,
+};
+use serde_json::Map;
+use thiserror::Error;
+
+// `["EVENT",` and `]` around the event in the message that carries it
+const EVENT_MESSAGE_OVERHEAD: usize = 10;
+
+/// Limits an event must meet to be accepted, usually taken from the
+/// `limitation` section of a relay's information document (NIP-11).
+/// Unset limits are not checked.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Policy {
+    /// The longest an `["EVENT", ...]` message may be, in bytes
+    pub max_message_length: Option<usize>,
+
+    /// The longest the content may be, in characters
+    pub max_content_length: Option<usize>,
+
+    /// The most tags an event may have
+    pub max_event_tags: Option<usize>,
+
+    /// The least proof of work (NIP-13) an event must have, in bits
+    pub min_pow_difficulty: Option<u8>,
+
+    /// How many seconds in the past created_at may be
+    pub created_at_lower_limit: Option<u64>,
+
+    /// How many seconds in the future created_at may be
+    pub created_at_upper_limit: Option<u64>,
+}
+
+/// Why an event does not meet a `Policy`
+#[derive(Clone, Copy, Debug, Eq, Error, PartialEq)]
+pub enum PolicyViolation {
+    /// The message carrying the event is too long
+    #[error("event message is {0} bytes, the limit is {1}")]
+    MessageTooLong(usize, usize),
+
+    /// The content is too long
+    #[error("content is {0} characters, the limit is {1}")]
+    ContentTooLong(usize, usize),
+
+    /// There are too many tags
+    #[error("event has {0} tags, the limit is {1}")]
+    TooManyTags(usize, usize),
+
+    /// There is not enough proof of work
+    #[error("difficulty {0} is less than {1}")]
+    InsufficientPow(u8, u8),
+
+    /// created_at is too far in the past
+    #[error("created_at is too far in the past")]
+    TooOld,
+
+    /// created_at is too far in the future
+    #[error("created_at is too far in the future")]
+    TooFarInFuture,
+}
+
+impl PolicyViolation {
+    /// The reason to give in an OK message when rejecting the event
+    pub fn reason(&self) -> RelayReason {
+        let prefix = match *self {
+            PolicyViolation::InsufficientPow(..) => MachineReadablePrefix::Pow,
+            _ => MachineReadablePrefix::Invalid,
+        };
+        RelayReason::new(prefix, &self.to_string())
+    }
+}
+
+impl Policy {
+    /// Check `event` against this policy, taking `now` as the current time
+    pub fn check(&self, event: &Event, now: Unixtime) -> Result<(), PolicyViolation> {
+        if let Some(max) = self.max_message_length {
+            let len = event.serialized_len() + EVENT_MESSAGE_OVERHEAD;
+            if len > max {
+                return Err(PolicyViolation::MessageTooLong(len, max));
+            }
+        }
+        if let Some(max) = self.max_content_length {
+            let len = event.content.chars().count();
+            if len > max {
+                return Err(PolicyViolation::ContentTooLong(len, max));
+            }
+        }
+        if let Some(max) = self.max_event_tags {
+            if event.tag_count() > max {
+                return Err(PolicyViolation::TooManyTags(event.tag_count(), max));
+            }
+        }
+        if let Some(min) = self.min_pow_difficulty {
+            let pow = event.pow();
+            if pow < min {
+                return Err(PolicyViolation::InsufficientPow(pow, min));
+            }
+        }
+        self.check_created_at(event.created_at, now)
+    }
+
+    fn check_created_at(&self, created_at: Unixtime, now: Unixtime) -> Result<(), PolicyViolation> {
+        let age = now.0.saturating_sub(created_at.0);
+        if let Some(lower) = self.created_at_lower_limit {
+            if age > 0 && age as u64 > lower {
+                return Err(PolicyViolation::TooOld);
+            }
+        }
+        if let Some(upper) = self.created_at_upper_limit {
+            if age < 0 && age.unsigned_abs() > upper {
+                return Err(PolicyViolation::TooFarInFuture);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<&RelayLimitation> for Policy {
+    fn from(limitation: &RelayLimitation) -> Policy {
+        Policy {
+            max_message_length: limitation.max_message_length,
+            max_content_length: limitation.max_content_length,
+            max_event_tags: limitation.max_event_tags,
+            min_pow_difficulty: limitation
+                .min_pow_difficulty
+                .map(|d| d.min(u8::MAX as usize) as u8),
+            created_at_lower_limit: limitation.created_at_lower_limit,
+            created_at_upper_limit: limitation.created_at_upper_limit,
+        }
+    }
+}
+
+/// An event shaped to a relay's limits by `PreEvent::fit_to_relay`
+#[derive(Clone, Debug)]
+pub struct RelayFit {
+    /// The events to publish, in order. There is more than one if the
+    /// content had to be split.
+    pub events: Vec<PreEvent>,
+
+    /// The proof of work (NIP-13) each event needs, in bits. Sign them with
+    /// `Event::new_with_pow` if this is set.
+    pub pow: Option<u8>,
+}
+
+impl PreEvent {
+    /// Shape this event to the limits in a relay's information document
+    /// (NIP-11), taking `now` as the current time. A text note whose
+    /// content is too long is split into several notes, at whitespace where
+    /// possible. Anything else that does not fit is an error, as it cannot
+    /// be changed without changing its meaning.
+    pub fn fit_to_relay(
+        mut self,
+        document: &RelayInformationDocument,
+        now: Unixtime,
+    ) -> Result<RelayFit, PolicyViolation> {
+        let policy = match &document.limitation {
+            Some(limitation) => Policy::from(limitation),
+            None => Policy::default(),
+        };
+        let pow = policy.min_pow_difficulty.filter(|bits| *bits > 0);
+
+        policy.check_created_at(self.created_at, now)?;
+
+        // A fresh nonce tag is added when the proof of work is done
+        if pow.is_some() {
+            self.tags.retain(|t| t.tagname() != "nonce");
+        }
+        let tag_count = self.tags.len() + usize::from(pow.is_some());
+        if let Some(max) = policy.max_event_tags {
+            if tag_count > max {
+                return Err(PolicyViolation::TooManyTags(tag_count, max));
+            }
+        }
+
+        // The message length without the content, allowing for the largest
+        // nonce tag
+        let mut empty = PreEvent {
+            content: String::new(),
+            ..self.clone()
+        };
+        if let Some(bits) = pow {
+            empty.tags.push(Tag::new_nonce(u64::MAX, Some(bits as u32)));
+        }
+        let base_len = message_len(empty);
+        let content_bytes = match policy.max_message_length {
+            Some(max) if base_len >= max => {
+                return Err(PolicyViolation::MessageTooLong(base_len, max))
+            }
+            Some(max) => Some(max - base_len),
+            None => None,
+        };
+        let fits = |chars: usize, bytes: usize| {
+            policy.max_content_length.is_none_or(|max| chars <= max)
+                && content_bytes.is_none_or(|max| bytes <= max)
+        };
+
+        let chars = self.content.chars().count();
+        let bytes: usize = self.content.chars().map(escaped_len).sum();
+        if fits(chars, bytes) {
+            return Ok(RelayFit {
+                events: vec![self],
+                pow,
+            });
+        }
+        if self.kind != EventKind::TextNote {
+            return Err(match policy.max_content_length {
+                Some(max) if chars > max => PolicyViolation::ContentTooLong(chars, max),
+                _ => PolicyViolation::MessageTooLong(
+                    base_len + bytes,
+                    policy.max_message_length.unwrap_or_default(),
+                ),
+            });
+        }
+
+        let mut events: Vec<PreEvent> = Vec::new();
+        let mut rest = self.content.trim();
+        while !rest.is_empty() {
+            // The longest prefix that fits
+            let (mut end, mut chars, mut bytes) = (0, 0, 0);
+            for (index, c) in rest.char_indices() {
+                if !fits(chars + 1, bytes + escaped_len(c)) {
+                    break;
+                }
+                chars += 1;
+                bytes += escaped_len(c);
+                end = index + c.len_utf8();
+            }
+            if end == 0 {
+                // Only possible if a single escaped character is too long
+                return Err(PolicyViolation::MessageTooLong(
+                    base_len + rest.chars().map(escaped_len).next().unwrap_or_default(),
+                    policy.max_message_length.unwrap_or_default(),
+                ));
+            }
+            // Break at whitespace rather than in the middle of a word
+            if end < rest.len() {
+                if let Some(space) = rest.split_at(end).0.rfind(char::is_whitespace) {
+                    if space > 0 {
+                        end = space;
+                    }
+                }
+            }
+            let (chunk, remainder) = rest.split_at(end);
+            events.push(PreEvent {
+                content: chunk.trim_end().to_owned(),
+                ..self.clone()
+            });
+            rest = remainder.trim_start();
+        }
+        Ok(RelayFit { events, pow })
+    }
+}
+
+// The length of the EVENT message carrying this event once signed
+fn message_len(input: PreEvent) -> usize {
+    let event = Event {
+        id: Id([0; 32]),
+        pubkey: input.pubkey,
+        created_at: input.created_at,
+        kind: input.kind,
+        tags: input.tags,
+        content: input.content,
+        ots: input.ots,
+        sig: Signature([0; 64]),
+        extra: Map::new(),
+    };
+    event.serialized_len() + EVENT_MESSAGE_OVERHEAD
+}
+
+// The length of a character in a JSON string
+fn escaped_len(c: char) -> usize {
+    match c {
+        '"' | '\\' | '\n' | '\r' | '\t' | '\u{08}' | '\u{0C}' => 2,
+        c if (c as u32) < 0x20 => 6,
+        c => c.len_utf8(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::PrivateKey;
+
+    fn event(content: &str, tags: usize, created_at: Unixtime) -> Event {
+        let privkey = PrivateKey::mock();
+        let pre = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at,
+            kind: EventKind::TextNote,
+            tags: vec![Tag::new_hashtag("nostr".to_owned()); tags],
+            content: content.to_owned(),
+            ots: None,
+        };
+        Event::new(pre, &privkey).unwrap()
+    }
+
+    #[test]
+    fn test_policy() {
+        let now = Unixtime(1_700_000_000);
+        let policy = Policy {
+            max_content_length: Some(5),
+            max_event_tags: Some(2),
+            created_at_lower_limit: Some(3600),
+            created_at_upper_limit: Some(60),
+            ..Default::default()
+        };
+        assert_eq!(policy.check(&event("héllo", 2, now), now), Ok(()));
+        assert_eq!(
+            policy.check(&event("hello!", 0, now), now),
+            Err(PolicyViolation::ContentTooLong(6, 5))
+        );
+        assert_eq!(
+            policy.check(&event("", 3, now), now),
+            Err(PolicyViolation::TooManyTags(3, 2))
+        );
+        assert_eq!(
+            policy.check(&event("", 0, Unixtime(now.0 - 3601)), now),
+            Err(PolicyViolation::TooOld)
+        );
+        assert_eq!(
+            policy.check(&event("", 0, Unixtime(now.0 + 61)), now),
+            Err(PolicyViolation::TooFarInFuture)
+        );
+
+        let e = event("hi", 1, now);
+        assert_eq!(e.serialized_len(), serde_json::to_string(&e).unwrap().len());
+        assert_eq!(e.tag_count(), 1);
+        let message = serde_json::to_string(&("EVENT", &e)).unwrap();
+        let policy = Policy {
+            max_message_length: Some(message.len() - 1),
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.check(&e, now),
+            Err(PolicyViolation::MessageTooLong(
+                message.len(),
+                message.len() - 1
+            ))
+        );
+
+        let policy = Policy {
+            min_pow_difficulty: Some(255),
+            ..Default::default()
+        };
+        let violation = policy.check(&e, now).unwrap_err();
+        assert_eq!(violation.reason().prefix, Some(MachineReadablePrefix::Pow));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_fit_to_relay() {
+        let now = Unixtime(1_700_000_000);
+        let privkey = PrivateKey::mock();
+        let pre = |kind: EventKind, content: &str| PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: now,
+            kind,
+            tags: vec![Tag::new_hashtag("nostr".to_owned())],
+            content: content.to_owned(),
+            ots: None,
+        };
+        let mut doc = RelayInformationDocument::default();
+        let fit = pre(EventKind::TextNote, "hello")
+            .fit_to_relay(&doc, now)
+            .unwrap();
+        assert_eq!(fit.events.len(), 1);
+        assert_eq!(fit.pow, None);
+
+        doc.limitation = Some(RelayLimitation {
+            max_content_length: Some(12),
+            min_pow_difficulty: Some(8),
+            ..Default::default()
+        });
+        let fit = pre(EventKind::TextNote, "the quick brown fox jumps over")
+            .fit_to_relay(&doc, now)
+            .unwrap();
+        let contents: Vec<&str> = fit.events.iter().map(|e| e.content.as_str()).collect();
+        assert_eq!(contents, vec!["the quick", "brown fox", "jumps over"]);
+        assert_eq!(fit.pow, Some(8));
+        let fit = pre(EventKind::TextNote, "abcdefghijklmnopqrstuvwxyz")
+            .fit_to_relay(&doc, now)
+            .unwrap();
+        assert_eq!(fit.events[0].content, "abcdefghijkl");
+        assert_eq!(fit.events.len(), 3);
+
+        // Other kinds are not split
+        assert_eq!(
+            pre(EventKind::LongFormContent, "the quick brown fox")
+                .fit_to_relay(&doc, now)
+                .unwrap_err(),
+            PolicyViolation::ContentTooLong(19, 12)
+        );
+
+        // The nonce tag counts against the tag limit
+        doc.limitation = Some(RelayLimitation {
+            max_event_tags: Some(1),
+            min_pow_difficulty: Some(8),
+            ..Default::default()
+        });
+        assert_eq!(
+            pre(EventKind::TextNote, "hi")
+                .fit_to_relay(&doc, now)
+                .unwrap_err(),
+            PolicyViolation::TooManyTags(2, 1)
+        );
+
+        // Each split note fits in a message once signed with proof of work
+        let long = "lorem ipsum \"dolor\" sit amet ".repeat(20);
+        let empty_len = {
+            let mut e = pre(EventKind::TextNote, "");
+            e.tags.push(Tag::new_nonce(u64::MAX, Some(4)));
+            message_len(e)
+        };
+        doc.limitation = Some(RelayLimitation {
+            max_message_length: Some(empty_len + 100),
+            min_pow_difficulty: Some(4),
+            ..Default::default()
+        });
+        let fit = pre(EventKind::TextNote, &long)
+            .fit_to_relay(&doc, now)
+            .unwrap();
+        assert!(fit.events.len() > 1);
+        for e in fit.events {
+            let event = Event::new_with_pow(e, &privkey, 4, None).unwrap();
+            let policy = Policy::from(doc.limitation.as_ref().unwrap());
+            assert_eq!(policy.check(&event, now), Ok(()));
+        }
+
+        doc.limitation = Some(RelayLimitation {
+            created_at_lower_limit: Some(60),
+            ..Default::default()
+        });
+        assert_eq!(
+            pre(EventKind::TextNote, "hi")
+                .fit_to_relay(&doc, Unixtime(now.0 + 61))
+                .unwrap_err(),
+            PolicyViolation::TooOld
+        );
+    }
+
+    #[test]
+    fn test_policy_from_limitation() {
+        let doc = RelayInformationDocument::mock();
+        let policy = Policy::from(doc.limitation.as_ref().unwrap());
+        assert_eq!(policy.max_event_tags, Some(100));
+        assert_eq!(policy.min_pow_difficulty, Some(30));
+        assert_eq!(policy.created_at_upper_limit, Some(300));
+    }
+}
@@ -0,0 +1,434 @@
+#[cfg(feature = "std")]
+use super::PreEvent;
+use super::{Event, EventKind, Id, PublicKey, Tag, UncheckedUrl, Unixtime};
+#[cfg(feature = "std")]
+use crate::Error;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+use std::collections::HashMap;
+use std::fmt;
+
+/// How many options a voter may choose in a poll (NIP-88)
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+#[serde(rename_all = "lowercase")]
+pub enum PollType {
+    /// One option
+    #[default]
+    SingleChoice,
+
+    /// Any number of options
+    MultipleChoice,
+}
+
+impl PollType {
+    /// The string used on the wire for this poll type
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            PollType::SingleChoice => "singlechoice",
+            PollType::MultipleChoice => "multiplechoice",
+        }
+    }
+
+    /// Parse a poll type from the wire, or None if it is not recognized
+    pub fn parse(s: &str) -> Option<PollType> {
+        match s {
+            "singlechoice" => Some(PollType::SingleChoice),
+            "multiplechoice" => Some(PollType::MultipleChoice),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PollType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// An option in a poll (NIP-88), from an 'option' tag
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct PollOption {
+    /// The id responses refer to the option by, which is alphanumeric
+    pub id: String,
+
+    /// What is shown to voters
+    pub label: String,
+}
+
+/// A poll (NIP-88, kind 1068)
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct Poll {
+    /// The question. This is the event content.
+    pub question: String,
+
+    /// The options, in the order to show them
+    pub options: Vec<PollOption>,
+
+    /// The relays responses should be sent to and fetched from
+    pub relays: Vec<UncheckedUrl>,
+
+    /// How many options a voter may choose. Single choice if not given.
+    pub poll_type: PollType,
+
+    /// When voting closes. Responses after this are not counted.
+    pub ends_at: Option<Unixtime>,
+}
+
+impl Poll {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> Poll {
+        let mut poll = Poll::new("Tabs or spaces?".to_owned(), PollType::SingleChoice);
+        let _ = poll.add_option("Tabs".to_owned());
+        let _ = poll.add_option("Spaces".to_owned());
+        poll.relays = vec![UncheckedUrl("wss://relay.example.com".to_owned())];
+        poll.ends_at = Some(Unixtime(1_700_086_400));
+        poll
+    }
+
+    /// A poll with no options yet
+    pub fn new(question: String, poll_type: PollType) -> Poll {
+        Poll {
+            question,
+            poll_type,
+            ..Default::default()
+        }
+    }
+
+    /// Is `id` usable as an option id: non-empty and alphanumeric?
+    pub fn is_valid_option_id(id: &str) -> bool {
+        !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric())
+    }
+
+    /// Add an option with the next unused numeric id, returning that id
+    pub fn add_option(&mut self, label: String) -> String {
+        let id = (0..)
+            .map(|n: usize| n.to_string())
+            .find(|id| self.option(id).is_none())
+            .unwrap_or_default();
+        self.options.push(PollOption {
+            id: id.clone(),
+            label,
+        });
+        id
+    }
+
+    /// The option with this id
+    pub fn option(&self, id: &str) -> Option<&PollOption> {
+        self.options.iter().find(|o| o.id == id)
+    }
+
+    /// Has voting closed by `now`?
+    pub fn has_ended(&self, now: Unixtime) -> bool {
+        self.ends_at.is_some_and(|ends_at| now > ends_at)
+    }
+
+    /// Read a poll from the tags and content of a kind 1068 event. Options
+    /// whose id is not valid, or repeats an earlier option's, are dropped.
+    pub fn from_tags(tags: &[Tag], content: &str) -> Poll {
+        let mut poll = Poll::new(content.to_owned(), PollType::SingleChoice);
+        for tag in tags {
+            match tag.tagname() {
+                "option" => {
+                    let id = tag.get_index(1);
+                    if Poll::is_valid_option_id(id) && poll.option(id).is_none() {
+                        poll.options.push(PollOption {
+                            id: id.to_owned(),
+                            label: tag.get_index(2).to_owned(),
+                        });
+                    }
+                }
+                "relay" if !tag.value().is_empty() => {
+                    poll.relays.push(UncheckedUrl::from_str(tag.value()))
+                }
+                "polltype" => poll.poll_type = PollType::parse(tag.value()).unwrap_or_default(),
+                "endsAt" => poll.ends_at = tag.value().parse::<i64>().ok().map(Unixtime),
+                _ => {}
+            }
+        }
+        poll
+    }
+
+    /// Write this poll out as tags for a kind 1068 event
+    pub fn to_tags(&self) -> Vec<Tag> {
+        let mut tags: Vec<Tag> = self
+            .options
+            .iter()
+            .map(|o| Tag::new(&["option", &o.id, &o.label]))
+            .collect();
+        for relay in &self.relays {
+            tags.push(Tag::new(&["relay", relay.as_str()]));
+        }
+        tags.push(Tag::new(&["polltype", self.poll_type.as_str()]));
+        if let Some(ends_at) = self.ends_at {
+            tags.push(Tag::new(&["endsAt", &ends_at.0.to_string()]));
+        }
+        tags
+    }
+
+    /// The ids of the options a voter chose, as this poll counts them:
+    /// unknown ids and repeats are dropped, and for a single choice poll
+    /// only the first is kept
+    pub fn choices<'a>(&self, option_ids: &'a [String]) -> Vec<&'a str> {
+        let mut choices: Vec<&str> = Vec::new();
+        for id in option_ids {
+            if self.option(id).is_some() && !choices.contains(&id.as_str()) {
+                choices.push(id);
+            }
+        }
+        if self.poll_type == PollType::SingleChoice {
+            choices.truncate(1);
+        }
+        choices
+    }
+
+    /// Count the votes in `responses` to this poll, whose event Id is
+    /// `poll_id`. Each voter's latest response to the poll before it ended is
+    /// their vote. Responses' signatures are not checked here, and the caller
+    /// may want to only pass responses from people it trusts.
+    pub fn tally(&self, poll_id: Id, responses: &[Event]) -> PollTally {
+        let mut latest: HashMap<PublicKey, (&Event, PollResponse)> = HashMap::new();
+        for event in responses {
+            let Some(response) = event.poll_response() else {
+                continue;
+            };
+            if response.poll != poll_id
+                || self
+                    .ends_at
+                    .is_some_and(|ends_at| event.created_at > ends_at)
+            {
+                continue;
+            }
+            let newer = match latest.get(&event.pubkey) {
+                // Like a replaceable event, the lowest id wins a tie
+                Some((previous, _)) => {
+                    (event.created_at, std::cmp::Reverse(event.id))
+                        > (previous.created_at, std::cmp::Reverse(previous.id))
+                }
+                None => true,
+            };
+            if newer {
+                let _ = latest.insert(event.pubkey, (event, response));
+            }
+        }
+
+        let mut counts: HashMap<&str, u64> = HashMap::new();
+        let mut voters: u64 = 0;
+        for (_, response) in latest.values() {
+            let choices = self.choices(&response.options);
+            if !choices.is_empty() {
+                voters += 1;
+            }
+            for id in choices {
+                *counts.entry(id).or_default() += 1;
+            }
+        }
+
+        PollTally {
+            counts: self
+                .options
+                .iter()
+                .map(|o| (o.clone(), counts.get(o.id.as_str()).copied().unwrap_or(0)))
+                .collect(),
+            voters,
+        }
+    }
+}
+
+/// A response to a poll (NIP-88, kind 1018)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct PollResponse {
+    /// The poll, from the 'e' tag
+    pub poll: Id,
+
+    /// The ids of the chosen options, from 'response' tags
+    pub options: Vec<String>,
+}
+
+impl PollResponse {
+    /// Read a response from the tags of a kind 1018 event. Returns None if
+    /// there is no 'e' tag.
+    pub fn from_tags(tags: &[Tag]) -> Option<PollResponse> {
+        let (poll, _, _) = tags.iter().find_map(|t| t.parse_event().ok())?;
+        let options = tags
+            .iter()
+            .filter(|t| t.tagname() == "response")
+            .map(|t| t.value().to_owned())
+            .collect();
+        Some(PollResponse { poll, options })
+    }
+
+    /// The tags of a kind 1018 event for this response
+    pub fn to_tags(&self) -> Vec<Tag> {
+        let mut tags = vec![Tag::new_event(self.poll, None, None)];
+        for id in &self.options {
+            tags.push(Tag::new(&["response", id]));
+        }
+        tags
+    }
+}
+
+/// The votes counted in a poll
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PollTally {
+    /// Each option with its number of votes, in the poll's order
+    pub counts: Vec<(PollOption, u64)>,
+
+    /// The number of people who voted for at least one option
+    pub voters: u64,
+}
+
+impl PollTally {
+    /// The number of votes for the option with this id
+    pub fn count(&self, id: &str) -> u64 {
+        self.counts
+            .iter()
+            .find(|(o, _)| o.id == id)
+            .map(|(_, n)| *n)
+            .unwrap_or(0)
+    }
+}
+
+impl Event {
+    /// If this is a NIP-88 poll (kind 1068), get its details
+    pub fn poll(&self) -> Option<Poll> {
+        if self.kind != EventKind::Poll {
+            return None;
+        }
+        Some(Poll::from_tags(&self.tags, &self.content))
+    }
+
+    /// If this is a NIP-88 poll response (kind 1018), get its details
+    pub fn poll_response(&self) -> Option<PollResponse> {
+        if self.kind != EventKind::PollResponse {
+            return None;
+        }
+        PollResponse::from_tags(&self.tags)
+    }
+}
+
+#[cfg(feature = "std")]
+impl PreEvent {
+    /// Create a NIP-88 poll PreEvent (kind 1068)
+    pub fn new_poll(pubkey: PublicKey, poll: &Poll) -> Result<PreEvent, Error> {
+        if poll.options.is_empty() {
+            return Err(Error::InvalidEvent("poll has no options".to_owned()));
+        }
+        Ok(PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::Poll,
+            tags: poll.to_tags(),
+            content: poll.question.clone(),
+            ots: None,
+        })
+    }
+
+    /// Create a NIP-88 response (kind 1018) to the poll event `poll`,
+    /// choosing the options with these ids. They must be options of the
+    /// poll, and only one may be chosen in a single choice poll.
+    pub fn new_poll_response(
+        pubkey: PublicKey,
+        poll: &Event,
+        option_ids: &[&str],
+    ) -> Result<PreEvent, Error> {
+        let details = poll.poll().ok_or(Error::WrongEventKind)?;
+        if let Some(id) = option_ids.iter().find(|id| details.option(id).is_none()) {
+            return Err(Error::InvalidEvent(format!("'{id}' is not a poll option")));
+        }
+        if details.poll_type == PollType::SingleChoice && option_ids.len() > 1 {
+            return Err(Error::InvalidEvent(
+                "only one option may be chosen in this poll".to_owned(),
+            ));
+        }
+        let response = PollResponse {
+            poll: poll.id,
+            options: option_ids.iter().map(|id| (*id).to_owned()).collect(),
+        };
+        Ok(PreEvent {
+            pubkey,
+            created_at: Unixtime::now(),
+            kind: EventKind::PollResponse,
+            tags: response.to_tags(),
+            content: "".to_owned(),
+            ots: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {Poll, test_poll_serde}
+
+    #[test]
+    fn test_poll_tags() {
+        let poll = Poll::mock();
+        assert_eq!(poll.options[1].id, "1");
+        assert_eq!(Poll::from_tags(&poll.to_tags(), &poll.question), poll);
+
+        let tags = vec![
+            Tag::new(&["option", "a", "Yes"]),
+            Tag::new(&["option", "a", "Again"]),
+            Tag::new(&["option", "no way", "No"]),
+            Tag::new(&["polltype", "rankedchoice"]),
+        ];
+        let poll = Poll::from_tags(&tags, "?");
+        assert_eq!(poll.options.len(), 1);
+        assert_eq!(poll.poll_type, PollType::SingleChoice);
+        assert_eq!(poll.ends_at, None);
+    }
+
+    #[test]
+    fn test_poll_tally() {
+        let poll = Poll::mock();
+        let poll_id = Id::mock();
+        let ends_at = poll.ends_at.unwrap();
+        let voters: Vec<PublicKey> = (0..5).map(|_| PublicKey::mock()).collect();
+        let response = |n: usize, at: i64, options: &[&str]| {
+            let mut event = Event::mock();
+            event.kind = EventKind::PollResponse;
+            event.pubkey = voters[n];
+            event.created_at = Unixtime(ends_at.0 + at);
+            event.tags = PollResponse {
+                poll: poll_id,
+                options: options.iter().map(|o| (*o).to_owned()).collect(),
+            }
+            .to_tags();
+            event
+        };
+
+        let responses = vec![
+            response(0, -10, &["0"]),
+            // Changed their mind
+            response(1, -10, &["0"]),
+            response(1, -5, &["1"]),
+            // Only the first of several counts in a single choice poll
+            response(2, -10, &["1", "0"]),
+            // Unknown options are not votes
+            response(3, -10, &["7"]),
+            // Too late
+            response(4, 10, &["0"]),
+        ];
+        let tally = poll.tally(poll_id, &responses);
+        assert_eq!(tally.count("0"), 1);
+        assert_eq!(tally.count("1"), 2);
+        assert_eq!(tally.voters, 3);
+
+        let mut multiple = poll.clone();
+        multiple.poll_type = PollType::MultipleChoice;
+        multiple.ends_at = None;
+        let tally = multiple.tally(poll_id, &responses);
+        assert_eq!(tally.count("0"), 3);
+        assert_eq!(tally.count("1"), 2);
+        assert_eq!(tally.voters, 4);
+
+        assert_eq!(poll.tally(Id([1; 32]), &responses).voters, 0);
+    }
+}
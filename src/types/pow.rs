@@ -0,0 +1,333 @@
+use super::event::get_leading_zero_bits;
+use super::{Event, Id, IdHasher, PreEvent, PrivateKey, Tag};
+use crate::Error;
+use std::time::Duration;
+
+/// The number of hashes expected, on average, to find an Id with `bits`
+/// leading zero bits (NIP-13)
+pub fn pow_estimate_iterations(bits: u8) -> f64 {
+    Difficulty(bits).expected_hashes()
+}
+
+/// A proof of work difficulty: the number of leading zero bits in an Id
+/// (NIP-13)
+///
+/// Each attempt succeeds with probability 2^-bits regardless of how many came
+/// before, so the expected work remaining never goes down while mining. The
+/// estimates here are averages; actual runs vary widely.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Difficulty(pub u8);
+
+impl Difficulty {
+    /// The number of hashes expected to reach this difficulty
+    pub fn expected_hashes(&self) -> f64 {
+        2f64.powi(self.0 as i32)
+    }
+
+    /// The time expected to reach this difficulty at `hashrate` hashes per
+    /// second. Saturates at `Duration::MAX`.
+    pub fn expected_time(&self, hashrate: f64) -> Duration {
+        Duration::try_from_secs_f64(self.expected_hashes() / hashrate).unwrap_or(Duration::MAX)
+    }
+
+    /// The most difficulty expected within `hashes` hashes
+    pub fn from_expected_hashes(hashes: f64) -> Difficulty {
+        if hashes < 1.0 {
+            return Difficulty(0);
+        }
+        Difficulty(hashes.log2().floor().min(u8::MAX as f64) as u8)
+    }
+
+    /// The most difficulty expected within `time` at `hashrate` hashes per
+    /// second
+    pub fn for_time(time: Duration, hashrate: f64) -> Difficulty {
+        Difficulty::from_expected_hashes(time.as_secs_f64() * hashrate)
+    }
+}
+
+impl From<u8> for Difficulty {
+    fn from(bits: u8) -> Difficulty {
+        Difficulty(bits)
+    }
+}
+
+/// Progress reported while mining with `Event::new_with_pow()`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PowProgress {
+    /// The difficulty being mined for
+    pub target: Difficulty,
+
+    /// Most leading zero bits found so far
+    pub best_work: u8,
+
+    /// Hashes tried so far, across all threads. This may lag slightly.
+    pub attempts: u64,
+
+    /// Time spent mining so far
+    pub elapsed: Duration,
+
+    /// Whether the target has been reached
+    pub done: bool,
+}
+
+impl PowProgress {
+    /// Hashes per second so far
+    pub fn hashrate(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.attempts as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// The expected time remaining at the hashrate so far, or None when done
+    /// or before there is a hashrate to go on. Because every attempt is an
+    /// independent chance, this does not shrink as mining goes on.
+    pub fn eta(&self) -> Option<Duration> {
+        let hashrate = self.hashrate();
+        if self.done || hashrate <= 0.0 {
+            return None;
+        }
+        Some(self.target.expected_time(hashrate))
+    }
+}
+
+/// Progress reported by `PowMiner::step()`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowStatus {
+    /// Still mining
+    Working {
+        /// Nonces tried so far
+        attempts: u64,
+
+        /// Most leading zero bits found so far
+        best_work: u8,
+    },
+
+    /// A nonce meeting the target was found
+    Found {
+        /// The nonce
+        nonce: u64,
+
+        /// Leading zero bits of the resulting Id (at least the target)
+        work: u8,
+    },
+}
+
+/// A resumable NIP-13 proof of work miner.
+///
+/// Unlike `Event::new_with_pow()` this does not spawn threads. Call `step()`
+/// repeatedly with a small number of attempts from wherever suits you (an async
+/// task, `spawn_blocking`, a web worker), then `into_pre_event()` once it has
+/// found a nonce. To mine in parallel, give each miner a different starting
+/// nonce.
+#[derive(Clone, Debug)]
+pub struct PowMiner {
+    input: PreEvent,
+    prefix: IdHasher,
+    target: u8,
+    next_nonce: u64,
+    attempts: u64,
+    best_work: u8,
+    found: Option<(u64, u8)>,
+}
+
+impl PowMiner {
+    /// Prepare to mine `input` until its Id has `zero_bits` leading zero bits.
+    /// Any nonce tags already in `input` are replaced.
+    pub fn new(mut input: PreEvent, zero_bits: u8) -> PowMiner {
+        input.tags.retain(|t| t.tagname() != "nonce");
+
+        // The nonce tag goes last, so everything before it can be hashed once
+        let mut prefix = IdHasher::new(&input.pubkey, input.created_at, input.kind);
+        for tag in input.tags.iter() {
+            prefix.tag(tag);
+        }
+
+        PowMiner {
+            input,
+            prefix,
+            target: zero_bits,
+            next_nonce: 0,
+            attempts: 0,
+            best_work: 0,
+            found: None,
+        }
+    }
+
+    /// Start trying nonces from `nonce` rather than zero
+    pub fn starting_at(mut self, nonce: u64) -> PowMiner {
+        self.next_nonce = nonce;
+        self
+    }
+
+    /// Try up to `attempts` more nonces
+    pub fn step(&mut self, attempts: u64) -> PowStatus {
+        if let Some((nonce, work)) = self.found {
+            return PowStatus::Found { nonce, work };
+        }
+
+        for _ in 0..attempts {
+            let nonce = self.next_nonce;
+            self.next_nonce = self.next_nonce.wrapping_add(1);
+            self.attempts += 1;
+
+            let Id(id) = self.hash_with(nonce);
+            let work = get_leading_zero_bits(&id);
+            if work > self.best_work {
+                self.best_work = work;
+            }
+            if work >= self.target {
+                self.found = Some((nonce, work));
+                return PowStatus::Found { nonce, work };
+            }
+        }
+
+        PowStatus::Working {
+            attempts: self.attempts,
+            best_work: self.best_work,
+        }
+    }
+
+    /// Nonces tried so far
+    pub fn attempts(&self) -> u64 {
+        self.attempts
+    }
+
+    /// Most leading zero bits found so far
+    pub fn best_work(&self) -> u8 {
+        self.best_work
+    }
+
+    /// The event with the winning nonce tag, if one has been found
+    pub fn into_pre_event(mut self) -> Option<PreEvent> {
+        let (nonce, _) = self.found?;
+        self.input.tags.push(self.nonce_tag(nonce));
+        Some(self.input)
+    }
+
+    /// Sign the event with the winning nonce tag, if one has been found
+    pub fn into_event(self, privkey: &PrivateKey) -> Result<Option<Event>, Error> {
+        match self.into_pre_event() {
+            Some(pre) => Ok(Some(Event::new(pre, privkey)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn nonce_tag(&self, nonce: u64) -> Tag {
+        Tag::new_nonce(nonce, Some(self.target as u32))
+    }
+
+    fn hash_with(&self, nonce: u64) -> Id {
+        let mut hasher = self.prefix.clone();
+        hasher.tag(&self.nonce_tag(nonce));
+        hasher.finish(&self.input.content)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EventKind, Unixtime};
+
+    #[test]
+    fn test_pow_miner() {
+        let privkey = PrivateKey::mock();
+        let pre = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime::mock(),
+            kind: EventKind::TextNote,
+            tags: vec![Tag::mock(), Tag::new_nonce(5, Some(1))],
+            content: "Mining".to_owned(),
+            ots: None,
+        };
+
+        let mut miner = PowMiner::new(pre, 8);
+        let status = loop {
+            match miner.step(100) {
+                PowStatus::Working { attempts, .. } => {
+                    assert_eq!(attempts, miner.attempts());
+                    assert!(miner.best_work() < 8);
+                }
+                found => break found,
+            }
+        };
+        // Stepping again doesn't lose the result
+        assert_eq!(miner.step(100), status);
+
+        let event = miner.into_event(&privkey).unwrap().unwrap();
+        assert!(event.verify(None).is_ok());
+        assert!(event.pow() >= 8);
+        assert_eq!(
+            event.tags.iter().filter(|t| t.tagname() == "nonce").count(),
+            1
+        );
+        assert_eq!(event.tags.last().unwrap().tagname(), "nonce");
+        // pow() is capped at the committed target
+        if let PowStatus::Found { work, .. } = status {
+            assert!(work >= event.pow());
+        }
+    }
+
+    #[test]
+    fn test_difficulty() {
+        assert_eq!(pow_estimate_iterations(0), 1.0);
+        assert_eq!(pow_estimate_iterations(20), 1_048_576.0);
+        assert_eq!(
+            Difficulty(20).expected_time(1_048_576.0),
+            Duration::from_secs(1)
+        );
+        assert_eq!(Difficulty(255).expected_time(1.0), Duration::MAX);
+        assert_eq!(Difficulty(8).expected_time(0.0), Duration::MAX);
+        assert_eq!(
+            Difficulty::from_expected_hashes(1_048_576.0),
+            Difficulty(20)
+        );
+        assert_eq!(
+            Difficulty::from_expected_hashes(1_048_575.0),
+            Difficulty(19)
+        );
+        assert_eq!(Difficulty::from_expected_hashes(0.5), Difficulty(0));
+        assert_eq!(
+            Difficulty::for_time(Duration::from_secs(60), 1_000_000.0),
+            Difficulty(25)
+        );
+
+        let progress = PowProgress {
+            target: Difficulty(20),
+            best_work: 12,
+            attempts: 2_000_000,
+            elapsed: Duration::from_secs(2),
+            done: false,
+        };
+        assert_eq!(progress.hashrate(), 1_000_000.0);
+        assert_eq!(progress.eta(), Some(Duration::from_secs_f64(1.048576)));
+        assert_eq!(
+            PowProgress {
+                done: true,
+                ..progress
+            }
+            .eta(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pow_miner_not_found() {
+        let pre = PreEvent {
+            pubkey: PrivateKey::mock().public_key(),
+            created_at: Unixtime::mock(),
+            kind: EventKind::TextNote,
+            tags: vec![],
+            content: "".to_owned(),
+            ots: None,
+        };
+        let mut miner = PowMiner::new(pre, 200).starting_at(1000);
+        assert!(matches!(
+            miner.step(10),
+            PowStatus::Working { attempts: 10, .. }
+        ));
+        assert!(miner.into_pre_event().is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Error, Id, PublicKey, Signature};
+use crate::{ConversationKey, Error, Id, PublicKey, Signature};
 use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
 use base64::Engine;
 use bech32::{FromBase32, ToBase32};
@@ -7,23 +7,32 @@ use chacha20poly1305::{
     XChaCha20Poly1305,
 };
 use derive_more::Display;
-use hmac::Hmac;
+use hmac::{Hmac, Mac};
 use k256::ecdh::SharedSecret;
 use k256::ecdsa::signature::Signer;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::elliptic_curve::PrimeField;
 use k256::schnorr::signature::hazmat::PrehashSigner;
 use k256::schnorr::SigningKey;
+use k256::{NonZeroScalar, Scalar, SecretKey};
 use pbkdf2::pbkdf2;
 use rand_core::{OsRng, RngCore};
 use serde::{Deserialize, Serialize};
-use sha2::Sha256;
+use sha2::{Sha256, Sha512};
 use std::convert::TryFrom;
 use std::ops::Deref;
-use zeroize::Zeroize;
+use std::str::FromStr;
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 // This allows us to detect bad decryptions with wrong passwords.
 const V1_CHECK_VALUE: [u8; 11] = [15, 91, 241, 148, 90, 143, 101, 12, 172, 255, 103];
 const V1_HMAC_ROUNDS: u32 = 100_000;
 
+// NIP-06 derivation path is m/44'/1237'/<account>'/0/0
+const BIP32_HARDENED: u32 = 0x8000_0000;
+const NIP06_PURPOSE: u32 = 44;
+const NIP06_COIN_TYPE: u32 = 1237;
+
 /// This is an encrypted private key.
 #[derive(Clone, Debug, Display, Serialize, Deserialize)]
 pub struct EncryptedPrivateKey(pub String);
@@ -45,6 +54,34 @@ impl EncryptedPrivateKey {
         PrivateKey::import_encrypted(self, password)
     }
 
+    /// Decrypt just long enough to sign a 32-byte hash. The decrypted key is
+    /// zeroized before this returns.
+    pub fn sign_id(&self, password: &str, id: Id) -> Result<Signature, Error> {
+        let private_key = self.decrypt(password)?;
+        private_key.sign_id(id)
+    }
+
+    /// Decrypt just long enough to sign a message (which is hashed with SHA-256
+    /// first internally). The decrypted key is zeroized before this returns.
+    pub fn sign(&self, password: &str, message: &[u8]) -> Result<Signature, Error> {
+        let private_key = self.decrypt(password)?;
+        private_key.sign(message)
+    }
+
+    /// The security level the key had when it was encrypted, if it can be
+    /// determined without decrypting (version 2 only)
+    pub fn key_security(&self) -> Option<KeySecurity> {
+        let data = bech32::decode(&self.0).ok()?;
+        if data.0 != "ncryptsec" {
+            return None;
+        }
+        let data = Vec::<u8>::from_base32(&data.1).ok()?;
+        if data.len() < 91 || data[0] != 2 {
+            return None;
+        }
+        KeySecurity::try_from(data[2 + 16 + 24]).ok()
+    }
+
     /// Version
     ///
     /// Version -1:
@@ -98,7 +135,7 @@ impl EncryptedPrivateKey {
 ///
 /// We offer no Strong security via the PrivateKey structure. If we support
 /// hardware tokens in the future, it will probably be via a different structure.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[repr(u8)]
 pub enum KeySecurity {
     /// This means that the key was exposed in a way such that this library
@@ -132,9 +169,15 @@ impl TryFrom<u8> for KeySecurity {
 }
 
 /// This is a private key which is to be kept secret and is used to prove identity
+///
+/// The secret is zeroized when the key is dropped. There is no `zeroize()`
+/// that would leave a usable key behind; drop the key to be rid of it.
 #[allow(missing_debug_implementations)]
 pub struct PrivateKey(SigningKey, KeySecurity);
 
+// SigningKey zeroizes its secret on drop
+impl ZeroizeOnDrop for PrivateKey {}
+
 impl PrivateKey {
     /// Generate a new `PrivateKey` (which can be used to get the `PublicKey`)
     pub fn generate() -> PrivateKey {
@@ -166,8 +209,10 @@ impl PrivateKey {
     /// This creates a key with `KeySecurity::Weak`.  Use `generate()` or
     /// `import_encrypted()` for `KeySecurity::Medium`
     pub fn try_from_hex_string(v: &str) -> Result<PrivateKey, Error> {
-        let vec: Vec<u8> = hex::decode(v)?;
-        Ok(PrivateKey(SigningKey::from_bytes(&vec)?, KeySecurity::Weak))
+        let mut vec: Vec<u8> = hex::decode(v)?;
+        let signing_key = SigningKey::from_bytes(&vec);
+        vec.zeroize();
+        Ok(PrivateKey(signing_key?, KeySecurity::Weak))
     }
 
     /// Export as a bech32 encoded string
@@ -193,28 +238,80 @@ impl PrivateKey {
         if data.0 != "nsec" {
             Err(Error::WrongBech32("nsec".to_string(), data.0))
         } else {
-            let decoded = Vec::<u8>::from_base32(&data.1)?;
-            Ok(PrivateKey(
-                SigningKey::from_bytes(&decoded)?,
-                KeySecurity::Weak,
-            ))
+            let mut decoded = Vec::<u8>::from_base32(&data.1)?;
+            let signing_key = SigningKey::from_bytes(&decoded);
+            decoded.zeroize();
+            Ok(PrivateKey(signing_key?, KeySecurity::Weak))
         }
     }
 
+    /// Generate a new random 24-word BIP-39 mnemonic, for use with `from_mnemonic()`
+    pub fn generate_mnemonic() -> String {
+        let mut entropy = [0u8; 32];
+        OsRng.fill_bytes(&mut entropy);
+        let mnemonic = bip39::Mnemonic::from_entropy(&entropy).expect("32 bytes is valid entropy");
+        entropy.zeroize();
+        mnemonic.to_string()
+    }
+
+    /// Derive a key from a BIP-39 mnemonic as specified in NIP-06, along the
+    /// path m/44'/1237'/<account>'/0/0. Use an empty passphrase if there is none.
+    ///
+    /// This creates a key with `KeySecurity::Weak` since the mnemonic is the
+    /// secret in plaintext.
+    pub fn from_mnemonic(
+        phrase: &str,
+        passphrase: &str,
+        account: u32,
+    ) -> Result<PrivateKey, Error> {
+        let mnemonic = bip39::Mnemonic::parse(phrase)?;
+        let mut seed = mnemonic.to_seed(passphrase);
+
+        let result = (|| {
+            let (mut key, mut chain_code) = bip32_split(b"Bitcoin seed", &seed)?;
+            for index in [
+                NIP06_PURPOSE | BIP32_HARDENED,
+                NIP06_COIN_TYPE | BIP32_HARDENED,
+                account | BIP32_HARDENED,
+                0,
+                0,
+            ] {
+                (key, chain_code) = bip32_derive_child(&key, &chain_code, index)?;
+            }
+            chain_code.zeroize();
+            Ok(PrivateKey(SigningKey::from(key), KeySecurity::Weak))
+        })();
+
+        seed.zeroize();
+        result
+    }
+
     /// Sign a 32-bit hash
     pub fn sign_id(&self, id: Id) -> Result<Signature, Error> {
         let signature = self.0.sign_prehash(&id.0)?;
-        Ok(Signature(signature))
+        Ok(Signature(signature.to_bytes()))
+    }
+
+    // Sign a 32-bit hash with the given auxiliary randomness instead of fresh
+    // randomness, so the signature is reproducible
+    #[cfg(feature = "test_utils")]
+    pub(crate) fn sign_id_with_aux_rand(
+        &self,
+        id: Id,
+        aux_rand: &[u8; 32],
+    ) -> Result<Signature, Error> {
+        let signature = self.0.sign_prehash_with_aux_rand(&id.0, aux_rand)?;
+        Ok(Signature(signature.to_bytes()))
     }
 
     /// Sign a message (this hashes with SHA-256 first internally)
     pub fn sign(&self, message: &[u8]) -> Result<Signature, Error> {
         let signature = self.0.try_sign(message)?;
-        Ok(Signature(signature))
+        Ok(Signature(signature.to_bytes()))
     }
 
     // Generate a shared secret with someone elses public key
-    fn shared_secret(&self, other: &PublicKey) -> SharedSecret {
+    pub(crate) fn shared_secret(&self, other: &PublicKey) -> SharedSecret {
         k256::ecdh::diffie_hellman(self.0.as_nonzero_scalar(), other.0.as_affine())
     }
 
@@ -251,6 +348,25 @@ impl PrivateKey {
         )
     }
 
+    /// Encrypt content to `other` according to NIP-44 (version 2), returning
+    /// the base64 payload
+    ///
+    /// This derives the conversation key each time. To exchange many messages
+    /// with `other`, keep a `ConversationKey` instead.
+    pub fn nip44_encrypt(&self, other: &PublicKey, plaintext: &str) -> Result<String, Error> {
+        self.conversation_key(other).encrypt(plaintext)
+    }
+
+    /// Decrypt a NIP-44 payload from `other`
+    pub fn nip44_decrypt(&self, other: &PublicKey, payload: &str) -> Result<String, Error> {
+        self.conversation_key(other).decrypt(payload)
+    }
+
+    /// Derive the NIP-44 conversation key shared with `other`
+    pub fn conversation_key(&self, other: &PublicKey) -> ConversationKey {
+        ConversationKey::new(self, other)
+    }
+
     /// Export in a (non-portable) encrypted form. This does not downgrade
     /// the security of the key, but you are responsible to keep it encrypted.
     /// You should not attempt to decrypt it, only use `import_encrypted()` on
@@ -507,15 +623,10 @@ impl PrivateKey {
 
     // Hash/Stretch password with scrypt into a 32-byte (256-bit) key
     fn password_to_key_v2(password: &str, salt: &[u8; 16], log_n: u8) -> Result<[u8; 32], Error> {
-        let params = match scrypt::Params::new(log_n, 8, 1, 32) {
-            // r=8, p=1
-            Ok(p) => p,
-            Err(_) => return Err(Error::Scrypt),
-        };
+        // r=8, p=1
+        let params = scrypt::Params::new(log_n, 8, 1, 32)?;
         let mut key: [u8; 32] = [0; 32];
-        if scrypt::scrypt(password.as_bytes(), salt, &params, &mut key).is_err() {
-            return Err(Error::Scrypt);
-        }
+        scrypt::scrypt(password.as_bytes(), salt, &params, &mut key)?;
         Ok(key)
     }
 
@@ -526,6 +637,62 @@ impl PrivateKey {
     }
 }
 
+// HMAC-SHA512 split into a secret key (left half) and chain code (right half)
+fn bip32_split(key: &[u8], data: &[u8]) -> Result<(NonZeroScalar, [u8; 32]), Error> {
+    let mut mac =
+        <Hmac<Sha512> as Mac>::new_from_slice(key).map_err(|_| Error::InvalidPrivateKey)?;
+    mac.update(data);
+    let mut output = mac.finalize().into_bytes();
+
+    let mut chain_code = [0u8; 32];
+    chain_code.copy_from_slice(&output[32..]);
+    let scalar: Option<Scalar> =
+        Scalar::from_repr(*k256::FieldBytes::from_slice(&output[..32])).into();
+    output.zeroize();
+    let scalar = scalar.ok_or(Error::InvalidPrivateKey)?;
+    let key: Option<NonZeroScalar> = NonZeroScalar::new(scalar).into();
+    Ok((key.ok_or(Error::InvalidPrivateKey)?, chain_code))
+}
+
+// BIP-32 private parent key to private child key derivation
+fn bip32_derive_child(
+    parent: &NonZeroScalar,
+    chain_code: &[u8; 32],
+    index: u32,
+) -> Result<(NonZeroScalar, [u8; 32]), Error> {
+    let mut data: Vec<u8> = Vec::with_capacity(37);
+    if index & BIP32_HARDENED != 0 {
+        data.push(0);
+        data.extend_from_slice(&parent.to_repr());
+    } else {
+        let public = SecretKey::from(*parent).public_key();
+        data.extend_from_slice(public.to_encoded_point(true).as_bytes());
+    }
+    data.extend_from_slice(&index.to_be_bytes());
+
+    let result = bip32_split(chain_code, &data);
+    data.zeroize();
+    let (tweak, child_chain_code) = result?;
+
+    let child: Option<NonZeroScalar> = NonZeroScalar::new(*tweak + **parent).into();
+    Ok((child.ok_or(Error::InvalidPrivateKey)?, child_chain_code))
+}
+
+/// Parses either an nsec or hex, giving a key with `KeySecurity::Weak`.
+/// There is deliberately no `Display`, so that a key cannot end up in a log
+/// by accident; use `as_bech32_string()` or `as_hex_string()`.
+impl FromStr for PrivateKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<PrivateKey, Error> {
+        if s.starts_with("nsec1") {
+            PrivateKey::try_from_bech32_string(s)
+        } else {
+            PrivateKey::try_from_hex_string(s)
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -545,6 +712,92 @@ mod test {
         assert_eq!(pk.key_security(), KeySecurity::Medium)
     }
 
+    #[test]
+    fn test_privkey_from_str() {
+        let mut pk = PrivateKey::generate();
+        let from_nsec: PrivateKey = pk.as_bech32_string().parse().unwrap();
+        let from_hex: PrivateKey = pk.as_hex_string().parse().unwrap();
+        assert_eq!(from_nsec.public_key(), pk.public_key());
+        assert_eq!(from_hex.public_key(), pk.public_key());
+        assert_eq!(from_hex.key_security(), KeySecurity::Weak);
+        assert!(pk
+            .public_key()
+            .as_bech32_string()
+            .parse::<PrivateKey>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_encrypted_sign() {
+        let pk = PrivateKey::generate();
+        let exported = pk.export_encrypted("secret", 13).unwrap();
+        assert_eq!(exported.key_security(), Some(KeySecurity::Medium));
+
+        let message = b"sign me";
+        let sig = exported.sign("secret", message).unwrap();
+        assert!(pk.public_key().verify(message, sig).is_ok());
+        assert!(exported.sign("wrong", message).is_err());
+        assert!(exported.sign_id("secret", Id::mock()).is_ok());
+    }
+
+    #[test]
+    fn test_privkey_nip06() {
+        // Test vectors from NIP-06
+        let pk = PrivateKey::from_mnemonic(
+            "leader monkey parrot ring guide accident before fence cannon height naive bean",
+            "",
+            0,
+        )
+        .unwrap();
+        // Compare public keys, because hex export normalizes the secret to the
+        // one with an even y coordinate (as BIP-340 does)
+        let expected = PrivateKey::try_from_hex_string(
+            "7f7ff03d123792d6ac594bfa67bf6d0c0ab55b6b1fdb6249303fe861f1ccba9a",
+        )
+        .unwrap();
+        assert_eq!(pk.public_key(), expected.public_key());
+        assert_eq!(
+            pk.public_key().as_hex_string(),
+            "17162c921dc4d2518f9a101db33695df1afb56ab82f5ff3e5da6eec3ca5cd917"
+        );
+
+        let pk = PrivateKey::from_mnemonic(
+            "what bleak badge arrange retreat wolf trade produce cricket blur garlic valid proud rude strong choose busy staff weather area salt hollow arm fade",
+            "",
+            0,
+        )
+        .unwrap();
+        let expected = PrivateKey::try_from_hex_string(
+            "c15d739894c81a2fcfd3a2df85a0d2c0dbc47a280d092799f144d73d7ae78add",
+        )
+        .unwrap();
+        assert_eq!(pk.public_key(), expected.public_key());
+
+        let phrase = PrivateKey::generate_mnemonic();
+        assert_eq!(phrase.split(' ').count(), 24);
+        let a = PrivateKey::from_mnemonic(&phrase, "", 0).unwrap();
+        let b = PrivateKey::from_mnemonic(&phrase, "", 1).unwrap();
+        let c = PrivateKey::from_mnemonic(&phrase, "passphrase", 0).unwrap();
+        assert_ne!(a.public_key(), b.public_key());
+        assert_ne!(a.public_key(), c.public_key());
+        assert_eq!(
+            a.public_key(),
+            PrivateKey::from_mnemonic(&phrase, "", 0)
+                .unwrap()
+                .public_key()
+        );
+        assert!(PrivateKey::from_mnemonic("not a valid mnemonic", "", 0).is_err());
+    }
+
+    #[test]
+    fn test_privkey_zeroize() {
+        fn zeroized_on_drop<T: ZeroizeOnDrop>(_: &T) {}
+        let pk = PrivateKey::generate();
+        zeroized_on_drop(&pk);
+        zeroized_on_drop(&pk.0);
+        assert!(KeySecurity::Weak < KeySecurity::Medium);
+    }
+
     #[test]
     fn test_import_old_formats() {
         let decrypted = "a28129ab0b70c8d5e75aaf510ec00bff47fde7ca4ab9e3d9315c77edc86f037f";
@@ -607,6 +860,21 @@ mod test {
         assert_eq!(decoded.1, KeySecurity::Weak);
     }
 
+    #[test]
+    fn test_privkey_bech32_nip19_example() {
+        let nsec = "nsec1vl029mgpspedva04g90vltkh6fvh240zqtv9k0t9af8935ke9laqsnlfe5";
+        let hex = "67dea2ed018072d675f5415ecfaed7d2597555e202d85b3d65ea4e58d2d92ffa";
+
+        let mut pk = PrivateKey::try_from_bech32_string(nsec).unwrap();
+        assert_eq!(pk.key_security(), KeySecurity::Weak);
+        assert_eq!(pk.as_hex_string(), hex);
+        assert_eq!(pk.as_bech32_string(), nsec);
+
+        // An npub is not an nsec
+        let npub = "npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg";
+        assert!(PrivateKey::try_from_bech32_string(npub).is_err());
+    }
+
     #[test]
     fn test_privkey_nip04() {
         let private_key = PrivateKey::mock();
@@ -0,0 +1,178 @@
+#[cfg(feature = "std")]
+use super::{Event, PrivateKey, RelayUrl};
+use super::{EventKind, Id, PreEvent, PublicKey, Rumor, Tag, Unixtime};
+use crate::Error;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+/// A private direct message (NIP-17), sent as a kind 14 rumor sealed and gift
+/// wrapped separately for each participant
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrivateDirectMessage {
+    /// Who the message is to. Together with the sender, these make up the
+    /// chat room.
+    pub recipients: Vec<PublicKey>,
+
+    /// The message, as plain text
+    pub content: String,
+
+    /// The conversation title
+    pub subject: Option<String>,
+
+    /// The message this replies to
+    pub reply_to: Option<Id>,
+}
+
+/// A gift wrapped private direct message for one participant, and the relays
+/// it should be sent to
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct DmGiftWrap {
+    /// The participant who can open it
+    pub recipient: PublicKey,
+
+    /// The kind 1059 gift wrap
+    pub event: Event,
+
+    /// The participant's DM relays (kind 10050). If this is empty they have not
+    /// said where they want DMs, and NIP-17 says not to send it.
+    pub relays: Vec<RelayUrl>,
+}
+
+impl PrivateDirectMessage {
+    /// A message to `recipients`
+    pub fn new(recipients: Vec<PublicKey>, content: String) -> PrivateDirectMessage {
+        PrivateDirectMessage {
+            recipients,
+            content,
+            subject: None,
+            reply_to: None,
+        }
+    }
+
+    /// The unsigned kind 14 rumor carrying this message
+    pub fn rumor(&self, sender: PublicKey, created_at: Unixtime) -> Result<Rumor, Error> {
+        let mut tags: Vec<Tag> = self
+            .recipients
+            .iter()
+            .map(|pk| Tag::new_pubkey((*pk).into(), None, None))
+            .collect();
+        if let Some(id) = self.reply_to {
+            tags.push(Tag::new_event(id, None, None));
+        }
+        if let Some(subject) = &self.subject {
+            tags.push(Tag::new_subject(subject.clone()));
+        }
+        Rumor::new(PreEvent {
+            pubkey: sender,
+            created_at,
+            kind: EventKind::DirectMessage,
+            tags,
+            content: self.content.clone(),
+            ots: None,
+        })
+    }
+
+    /// Read a message from a kind 14 rumor. Returns None for other kinds.
+    pub fn from_rumor(rumor: &Rumor) -> Option<PrivateDirectMessage> {
+        if rumor.kind != EventKind::DirectMessage {
+            return None;
+        }
+        let mut message = PrivateDirectMessage::new(vec![], rumor.content.clone());
+        for tag in &rumor.tags {
+            if let Ok((pkhex, _, _)) = tag.parse_pubkey() {
+                if let Ok(pk) = PublicKey::try_from(pkhex) {
+                    message.recipients.push(pk);
+                }
+            } else if let Ok((id, _, _)) = tag.parse_event() {
+                message.reply_to = Some(id);
+            } else if let Ok(subject) = tag.parse_subject() {
+                message.subject = Some(subject);
+            }
+        }
+        Some(message)
+    }
+
+    /// Build the full rumor, seal and gift wrap chain: one gift wrap for each
+    /// recipient and one for the sender, so the sender can read their own
+    /// messages from other devices. `dm_relays` holds each participant's DM
+    /// relays, from their kind 10050 events.
+    #[cfg(feature = "std")]
+    pub fn gift_wraps(
+        &self,
+        sender: &PrivateKey,
+        dm_relays: &HashMap<PublicKey, Vec<RelayUrl>>,
+    ) -> Result<Vec<DmGiftWrap>, Error> {
+        let sender_pubkey = sender.public_key();
+        let rumor = self.rumor(sender_pubkey, Unixtime::now())?;
+        let mut participants: Vec<PublicKey> = self.recipients.clone();
+        participants.push(sender_pubkey);
+        let mut output: Vec<DmGiftWrap> = Vec::with_capacity(participants.len());
+        for pubkey in participants {
+            if output.iter().any(|w| w.recipient == pubkey) {
+                continue;
+            }
+            output.push(DmGiftWrap {
+                recipient: pubkey,
+                event: rumor.gift_wrap(sender, &pubkey)?,
+                relays: dm_relays.get(&pubkey).cloned().unwrap_or_default(),
+            });
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_private_direct_message() {
+        let alice = PrivateKey::mock();
+        let bob = PrivateKey::generate();
+        let carol = PrivateKey::generate();
+        let bob_relay = RelayUrl::try_from_str("wss://dm.example.com").unwrap();
+        let pre = PreEvent::new_dm_relays(bob.public_key(), std::slice::from_ref(&bob_relay));
+        let bob_dm_relays = Event::new(pre, &bob).unwrap();
+        let mut dm_relays = HashMap::new();
+        let _ = dm_relays.insert(bob.public_key(), bob_dm_relays.dm_relays().unwrap());
+
+        let mut message = PrivateDirectMessage::new(
+            vec![bob.public_key(), carol.public_key()],
+            "Hi both".to_owned(),
+        );
+        message.subject = Some("Plans".to_owned());
+        let wraps = message.gift_wraps(&alice, &dm_relays).unwrap();
+        assert_eq!(wraps.len(), 3);
+        assert_eq!(wraps[0].relays, vec![bob_relay]);
+        assert!(wraps[1].relays.is_empty());
+        assert_eq!(wraps[2].recipient, alice.public_key());
+
+        for (wrap, key) in wraps.iter().zip([&bob, &carol, &alice]) {
+            assert_eq!(wrap.event.kind, EventKind::GiftWrap);
+            assert_ne!(wrap.event.pubkey, alice.public_key());
+            assert!(wrap.event.created_at <= Unixtime::now());
+            let rumor = wrap.event.unwrap_gift_wrap(key).unwrap();
+            assert_eq!(rumor.pubkey, alice.public_key());
+            assert_eq!(
+                PrivateDirectMessage::from_rumor(&rumor),
+                Some(message.clone())
+            );
+        }
+
+        // Only the recipient can open it
+        assert!(wraps[0].event.unwrap_gift_wrap(&carol).is_err());
+    }
+
+    #[test]
+    fn test_seal_must_match_rumor_author() {
+        let alice = PrivateKey::mock();
+        let mallory = PrivateKey::generate();
+        let message = PrivateDirectMessage::new(vec![mallory.public_key()], "Hi".to_owned());
+        let rumor = message.rumor(alice.public_key(), Unixtime::now()).unwrap();
+        assert!(matches!(
+            rumor.seal(&mallory, &alice.public_key()),
+            Err(Error::SealMismatch)
+        ));
+    }
+}
@@ -1,4 +1,4 @@
-use super::{PublicKey, UncheckedUrl};
+use super::{PublicKey, RelayHint, RelayHintSource, Tag, UncheckedUrl};
 use crate::Error;
 use bech32::{FromBase32, ToBase32};
 use serde::{Deserialize, Serialize};
@@ -17,6 +17,11 @@ pub struct Profile {
 }
 
 impl Profile {
+    /// The relays as hints, including any that are not valid relay URLs
+    pub fn relay_hints(&self) -> Vec<RelayHint> {
+        RelayHint::from_urls(&self.relays, RelayHintSource::Profile)
+    }
+
     /// Export as a bech32 encoded string ("nprofile")
     pub fn as_bech32_string(&self) -> String {
         // Compose
@@ -49,7 +54,7 @@ impl Profile {
             let mut pos = 0;
             loop {
                 // we need at least 2 more characters for anything meaningful
-                if pos > tlv.len() - 2 {
+                if pos + 2 > tlv.len() {
                     break;
                 }
                 let ty = tlv[pos];
@@ -103,6 +108,16 @@ impl Profile {
     }
 }
 
+impl From<Profile> for Tag {
+    fn from(profile: Profile) -> Tag {
+        Tag::new_pubkey(
+            profile.pubkey.into(),
+            profile.relays.into_iter().next(),
+            None,
+        )
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -140,4 +155,14 @@ mod test {
         // Try converting bech32 to profile
         assert_eq!(profile, Profile::try_from_bech32_string(bech32).unwrap());
     }
+
+    #[test]
+    fn test_profile_to_tag() {
+        let profile = Profile::mock();
+        let tag: Tag = profile.clone().into();
+        let (pubkey, recommended_relay_url, petname) = tag.parse_pubkey().unwrap();
+        assert_eq!(pubkey, profile.pubkey.into());
+        assert_eq!(recommended_relay_url, profile.relays.first().cloned());
+        assert_eq!(petname, None);
+    }
 }
@@ -1,15 +1,33 @@
-use crate::{Error, PrivateKey, Signature};
+use super::id::is_lowercase_hex;
+use crate::{Error, Id, PrivateKey, Signature};
 use bech32::{FromBase32, ToBase32};
-use derive_more::{AsMut, AsRef, Deref, Display, From, FromStr, Into};
+use derive_more::{AsMut, AsRef, Deref, Display, From, Into};
 use k256::ecdsa::signature::Verifier;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
 use k256::schnorr::VerifyingKey;
 use serde::de::{Deserializer, Visitor};
 use serde::ser::Serializer;
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "speedy")]
 use speedy::{Context, Readable, Reader, Writable, Writer};
+use std::borrow::Borrow;
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use subtle::{Choice, ConstantTimeEq};
+
+/// The parity of the y coordinate of a full secp256k1 public key.
+///
+/// Nostr public keys are x-only (BIP-340), which always stand for the point with
+/// an even y coordinate, so the parity is lost when converting a full key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Parity {
+    /// Even y coordinate (SEC1 prefix 0x02)
+    Even,
+
+    /// Odd y coordinate (SEC1 prefix 0x03)
+    Odd,
+}
 
 /// This is a public key, which identifies an actor (usually a person) and is shared.
 #[derive(AsMut, AsRef, Copy, Clone, Debug, Deref, Eq, From, Into, PartialEq)]
@@ -23,6 +41,12 @@ impl PublicKey {
         hex::encode(self.0.to_bytes())
     }
 
+    /// Whether the hex form of this key starts with `prefix`, as in a NIP-01
+    /// filter
+    pub fn starts_with_hex(&self, prefix: &str) -> bool {
+        super::id::bytes_start_with_hex(&self.0.to_bytes(), prefix)
+    }
+
     /// Create from a hexadecimal string
     pub fn try_from_hex_string(v: &str) -> Result<PublicKey, Error> {
         let vec: Vec<u8> = hex::decode(v)?;
@@ -73,9 +97,51 @@ impl PublicKey {
         self.0.to_bytes().to_vec()
     }
 
+    /// Import from a SEC1 encoded secp256k1 key, either compressed (33 bytes)
+    /// or uncompressed (65 bytes). Returns the x-only key along with the parity
+    /// of the full key's y coordinate.
+    pub fn from_sec1_bytes(bytes: &[u8]) -> Result<(PublicKey, Parity), Error> {
+        let full = k256::PublicKey::from_sec1_bytes(bytes)
+            .map_err(|e| Error::from(e).context("Invalid SEC1 public key"))?;
+        let point = full.to_encoded_point(false);
+        let (x, y) = match (point.x(), point.y()) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return Err(Error::InvalidPublicKey),
+        };
+        let parity = if y[31] & 1 == 0 {
+            Parity::Even
+        } else {
+            Parity::Odd
+        };
+        Ok((PublicKey(VerifyingKey::from_bytes(x)?), parity))
+    }
+
+    /// Export as a compressed 33-byte SEC1 key with the given parity. Use
+    /// `Parity::Even` for the point that nostr signatures verify against.
+    pub fn to_compressed_bytes(&self, parity: Parity) -> [u8; 33] {
+        let mut output = [0u8; 33];
+        output[0] = match parity {
+            Parity::Even => 0x02,
+            Parity::Odd => 0x03,
+        };
+        output[1..].copy_from_slice(&self.0.to_bytes());
+        output
+    }
+
+    /// The full secp256k1 public key (the one with an even y coordinate)
+    pub fn to_full_key(&self) -> k256::PublicKey {
+        k256::PublicKey::from(&self.0)
+    }
+
     /// Verify a signed message
     pub fn verify(&self, message: &[u8], signature: Signature) -> Result<(), Error> {
-        Ok(self.0.verify(message, &signature.0)?)
+        Ok(self.0.verify(message, &signature.to_k256()?)?)
+    }
+
+    /// Verify a signature over an event id (or any 32-byte hash), as made by
+    /// `PrivateKey::sign_id()`
+    pub fn verify_id(&self, id: Id, signature: &Signature) -> Result<(), Error> {
+        Ok(self.0.verify_raw(&id.0, &signature.to_k256()?)?)
     }
 
     // Mock data for testing
@@ -93,6 +159,26 @@ impl PublicKey {
     }
 }
 
+/// Displays as an npub. Use `as_hex_string()` for hex.
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_bech32_string())
+    }
+}
+
+/// Parses either an npub or hex
+impl FromStr for PublicKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<PublicKey, Error> {
+        if s.starts_with("npub1") {
+            PublicKey::try_from_bech32_string(s)
+        } else {
+            PublicKey::try_from_hex_string(s)
+        }
+    }
+}
+
 impl Serialize for PublicKey {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -175,8 +261,11 @@ impl<C: Context> Writable<C> for PublicKey {
 /// This is a public key, which identifies an actor (usually a person) and is shared, as a hex string
 ///
 /// You can convert from a `PublicKey` into this with `From`/`Into`.  You can convert this back to a `PublicKey` with `TryFrom`/`TryInto`.
-#[derive(AsMut, AsRef, Clone, Debug, Deref, Display, Eq, From, FromStr, Hash, Into, PartialEq)]
-#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+///
+/// It is always 64 lowercase hex digits; construction fails otherwise. Equality
+/// is checked in constant time.
+#[derive(AsRef, Clone, Debug, Deref, Display, Eq, Into)]
+#[cfg_attr(feature = "speedy", derive(Writable))]
 pub struct PublicKeyHex(String);
 
 impl PublicKeyHex {
@@ -194,8 +283,21 @@ impl PublicKeyHex {
 
     /// Export as a bech32 encoded string
     pub fn as_bech32_string(&self) -> String {
-        let vec: Vec<u8> = hex::decode(&self.0).unwrap();
-        bech32::encode("npub", vec.to_base32(), bech32::Variant::Bech32).unwrap()
+        bech32::encode("npub", self.as_bytes().to_base32(), bech32::Variant::Bech32).unwrap()
+    }
+
+    /// The 32 bytes of the x coordinate. These may not be a valid point on
+    /// the curve; use `TryFrom` to get a `PublicKey`.
+    pub fn as_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        // Cannot fail: a PublicKeyHex is always 64 hex digits
+        let _ = hex::decode_to_slice(&self.0, &mut bytes);
+        bytes
+    }
+
+    /// Import from a bech32 encoded string ("npub")
+    pub fn try_from_bech32_string(s: &str) -> Result<PublicKeyHex, Error> {
+        Ok(PublicKey::try_from_bech32_string(s)?.into())
     }
 
     /// Try from &str
@@ -205,11 +307,7 @@ impl PublicKeyHex {
 
     /// Try from String
     pub fn try_from_string(s: String) -> Result<PublicKeyHex, Error> {
-        if s.len() != 64 {
-            return Err(Error::InvalidPublicKey);
-        }
-        let vec: Vec<u8> = hex::decode(&s)?;
-        if vec.len() != 32 {
+        if s.len() != 64 || !is_lowercase_hex(&s) {
             return Err(Error::InvalidPublicKey);
         }
         Ok(PublicKeyHex(s))
@@ -234,6 +332,46 @@ impl PublicKeyHex {
     }
 }
 
+impl ConstantTimeEq for PublicKeyHex {
+    fn ct_eq(&self, other: &PublicKeyHex) -> Choice {
+        self.0.as_bytes().ct_eq(other.0.as_bytes())
+    }
+}
+
+impl PartialEq for PublicKeyHex {
+    fn eq(&self, other: &PublicKeyHex) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Hash for PublicKeyHex {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl Borrow<str> for PublicKeyHex {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for PublicKeyHex {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<PublicKeyHex, Error> {
+        PublicKeyHex::try_from_str(s)
+    }
+}
+
+impl TryFrom<String> for PublicKeyHex {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<PublicKeyHex, Error> {
+        PublicKeyHex::try_from_string(s)
+    }
+}
+
 impl TryFrom<&str> for PublicKeyHex {
     type Error = Error;
 
@@ -248,11 +386,37 @@ impl From<PublicKey> for PublicKeyHex {
     }
 }
 
+impl TryFrom<&[u8]> for PublicKey {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<PublicKey, Error> {
+        PublicKey::from_bytes(bytes)
+    }
+}
+
+impl TryFrom<&PublicKeyHex> for PublicKey {
+    type Error = Error;
+
+    fn try_from(pkh: &PublicKeyHex) -> Result<PublicKey, Error> {
+        PublicKey::from_bytes(&pkh.as_bytes())
+    }
+}
+
 impl TryFrom<PublicKeyHex> for PublicKey {
     type Error = Error;
 
     fn try_from(pkh: PublicKeyHex) -> Result<PublicKey, Error> {
-        PublicKey::try_from_hex_string(&pkh.0)
+        PublicKey::try_from(&pkh)
+    }
+}
+
+#[cfg(feature = "speedy")]
+impl<'a, C: Context> Readable<'a, C> for PublicKeyHex {
+    #[inline]
+    fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
+        let s = String::read_from(reader)?;
+        let pkhex = PublicKeyHex::try_from_string(s).map_err(|e| speedy::Error::custom(e))?;
+        Ok(pkhex)
     }
 }
 
@@ -292,10 +456,10 @@ impl Visitor<'_> for PublicKeyHexVisitor {
                 "PublicKeyHex is not 64 characters long",
             ));
         }
-
-        let vec: Vec<u8> = hex::decode(v).map_err(|e| serde::de::Error::custom(format!("{e}")))?;
-        if vec.len() != 32 {
-            return Err(serde::de::Error::custom("Invalid PublicKeyHex"));
+        if !is_lowercase_hex(v) {
+            return Err(serde::de::Error::custom(
+                "PublicKeyHex is not lowercase hex",
+            ));
         }
 
         Ok(PublicKeyHex(v.to_owned()))
@@ -304,23 +468,12 @@ impl Visitor<'_> for PublicKeyHexVisitor {
 
 /// This is a public key prefix, which identifies an actor (usually a person) and is shared, as a hex string
 ///
+/// It is always at most 64 lowercase hex digits.
 #[derive(
-    AsMut,
-    AsRef,
-    Clone,
-    Debug,
-    Deref,
-    Deserialize,
-    Display,
-    Eq,
-    From,
-    FromStr,
-    Hash,
-    Into,
-    PartialEq,
-    Serialize,
+    AsRef, Clone, Debug, Deref, Deserialize, Display, Eq, Hash, Into, PartialEq, Serialize,
 )]
 #[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+#[serde(try_from = "String")]
 pub struct PublicKeyHexPrefix(String);
 
 impl PublicKeyHexPrefix {
@@ -337,16 +490,9 @@ impl PublicKeyHexPrefix {
 
     /// Try from String
     pub fn try_from_string(s: String) -> Result<PublicKeyHexPrefix, Error> {
-        if s.len() > 64 {
+        if s.len() > 64 || !is_lowercase_hex(&s) {
             return Err(Error::InvalidPublicKeyPrefix);
         }
-        if s.chars().any(|c| !c.is_ascii_hexdigit()) {
-            return Err(Error::InvalidPublicKeyPrefix);
-        }
-        // let vec: Vec<u8> = hex::decode(&s)?;
-        // if vec.len() > 32 {
-        //    return Err(Error::InvalidPublicKeyPrefix);
-        // }
         Ok(PublicKeyHexPrefix(s))
     }
 
@@ -372,6 +518,20 @@ impl From<PublicKeyHex> for PublicKeyHexPrefix {
     }
 }
 
+impl From<PublicKey> for PublicKeyHexPrefix {
+    fn from(pubkey: PublicKey) -> PublicKeyHexPrefix {
+        PublicKeyHexPrefix(pubkey.as_hex_string())
+    }
+}
+
+impl FromStr for PublicKeyHexPrefix {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<PublicKeyHexPrefix, Error> {
+        PublicKeyHexPrefix::try_from_str(s)
+    }
+}
+
 impl TryFrom<&str> for PublicKeyHexPrefix {
     type Error = Error;
 
@@ -380,6 +540,14 @@ impl TryFrom<&str> for PublicKeyHexPrefix {
     }
 }
 
+impl TryFrom<String> for PublicKeyHexPrefix {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<PublicKeyHexPrefix, Error> {
+        PublicKeyHexPrefix::try_from_string(s)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -388,6 +556,30 @@ mod test {
     test_serde! {PublicKeyHex, test_public_key_hex_serde}
     test_serde! {PublicKeyHexPrefix, test_public_key_hex_prefix_serde}
 
+    #[test]
+    fn test_pubkey_prefix() {
+        let pk = PublicKey::mock_deterministic();
+        assert!(pk.starts_with_hex("ee11a"));
+        assert!(!pk.starts_with_hex("ee11b"));
+
+        let pkhex = PublicKeyHex::from(pk);
+        let prefix = pkhex.prefix(5);
+        assert_eq!(prefix.as_str(), "ee11a");
+        assert!(prefix.matches(&pkhex));
+        assert!(pkhex == PublicKeyHex::mock_deterministic());
+        assert!(!bool::from(pkhex.ct_eq(&PublicKeyHex::mock())));
+    }
+
+    #[test]
+    fn test_pubkey_from_str() {
+        let pk = PublicKey::mock();
+        assert_eq!(pk.to_string(), pk.as_bech32_string());
+        assert_eq!(pk.to_string().parse::<PublicKey>().unwrap(), pk);
+        assert_eq!(pk.as_hex_string().parse::<PublicKey>().unwrap(), pk);
+        assert!("npub1xyz".parse::<PublicKey>().is_err());
+        assert!(Id::mock().as_bech32_string().parse::<PublicKey>().is_err());
+    }
+
     #[test]
     fn test_pubkey_bech32() {
         let pk = PublicKey::mock();
@@ -399,4 +591,63 @@ mod test {
 
         assert_eq!(pk, decoded);
     }
+
+    #[test]
+    fn test_pubkey_sec1() {
+        let pk = PublicKey::mock_deterministic();
+
+        let even = pk.to_compressed_bytes(Parity::Even);
+        assert_eq!(even[0], 0x02);
+        assert_eq!(
+            PublicKey::from_sec1_bytes(&even).unwrap(),
+            (pk, Parity::Even)
+        );
+
+        // The odd key is a different point with the same x-only key
+        let odd = pk.to_compressed_bytes(Parity::Odd);
+        assert_eq!(PublicKey::from_sec1_bytes(&odd).unwrap(), (pk, Parity::Odd));
+
+        let uncompressed = pk.to_full_key().to_encoded_point(false);
+        assert_eq!(uncompressed.len(), 65);
+        assert_eq!(
+            PublicKey::from_sec1_bytes(uncompressed.as_bytes()).unwrap(),
+            (pk, Parity::Even)
+        );
+        assert_eq!(
+            PublicKey::try_from_bech32_string(&pk.as_bech32_string()).unwrap(),
+            pk
+        );
+
+        assert!(PublicKey::from_sec1_bytes(&even[1..]).is_err());
+        let mut bad = even;
+        bad[0] = 0x04;
+        assert!(PublicKey::from_sec1_bytes(&bad).is_err());
+    }
+
+    #[test]
+    fn test_pubkey_verify_id() {
+        let private_key = PrivateKey::generate();
+        let pk = private_key.public_key();
+        let id = Id::mock();
+        let sig = private_key.sign_id(id).unwrap();
+        assert!(pk.verify_id(id, &sig).is_ok());
+        assert!(PublicKey::mock().verify_id(id, &sig).is_err());
+        let mut other = id;
+        other.0[0] ^= 1;
+        assert!(pk.verify_id(other, &sig).is_err());
+    }
+
+    #[test]
+    fn test_pubkey_bech32_nip19_example() {
+        let npub = "npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg";
+        let hex = "7e7e9c42a91bfef19fa929e5fda1b72e0ebc1a4c1141673e2794234d86addf4e";
+
+        let pk = PublicKey::try_from_bech32_string(npub).unwrap();
+        assert_eq!(pk.as_hex_string(), hex);
+        assert_eq!(pk.as_bech32_string(), npub);
+
+        let pkh = PublicKeyHex::try_from_bech32_string(npub).unwrap();
+        assert_eq!(pkh.as_str(), hex);
+        assert_eq!(pkh.as_bech32_string(), npub);
+    }
 }
@@ -0,0 +1,89 @@
+use super::{RelayUrl, UncheckedUrl};
+use crate::Error;
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+
+/// Where a relay hint was found
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub enum RelayHintSource {
+    /// An 'e', 'E' or 'q' tag, where to find the event referred to
+    EventTag,
+
+    /// A 'p' or 'P' tag, where to find the person referred to
+    PubkeyTag,
+
+    /// An 'a' or 'A' tag, where to find the address referred to
+    AddressTag,
+
+    /// An nprofile
+    Profile,
+
+    /// An nevent
+    EventPointer,
+
+    /// An naddr
+    EventAddr,
+
+    /// A relay list
+    RelayList,
+}
+
+/// A relay URL exactly as some event or link gave it, which may not be a
+/// valid relay URL, and where it came from
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct RelayHint {
+    /// The URL as given
+    pub url: UncheckedUrl,
+
+    /// Where it was found
+    pub source: RelayHintSource,
+}
+
+impl RelayHint {
+    /// Create a relay hint
+    pub fn new(url: UncheckedUrl, source: RelayHintSource) -> RelayHint {
+        RelayHint { url, source }
+    }
+
+    /// The hint as a relay URL, or why it is not one
+    pub fn relay_url(&self) -> Result<RelayUrl, Error> {
+        RelayUrl::try_from_unchecked_url(&self.url)
+    }
+
+    /// Whether the hint is a valid relay URL
+    pub fn is_valid(&self) -> bool {
+        self.relay_url().is_ok()
+    }
+
+    pub(crate) fn from_urls(urls: &[UncheckedUrl], source: RelayHintSource) -> Vec<RelayHint> {
+        urls.iter()
+            .map(|url| RelayHint::new(url.clone(), source))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_relay_hint() {
+        let hint = RelayHint::new(
+            UncheckedUrl::from_str("wss://Relay.Example.com/"),
+            RelayHintSource::EventTag,
+        );
+        assert!(hint.is_valid());
+        assert_eq!(
+            hint.relay_url().unwrap(),
+            RelayUrl::try_from_str("wss://relay.example.com").unwrap()
+        );
+
+        let hint = RelayHint::new(
+            UncheckedUrl::from_str("https://relay.example.com"),
+            RelayHintSource::PubkeyTag,
+        );
+        assert!(matches!(hint.relay_url(), Err(Error::InvalidUrlScheme(_))));
+    }
+}
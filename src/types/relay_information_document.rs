@@ -9,7 +9,7 @@ use speedy::{Readable, Writable};
 use std::fmt;
 
 /// Relay limitations
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 #[cfg_attr(feature = "speedy", derive(Readable, Writable))]
 pub struct RelayLimitation {
     /// max message length
@@ -66,6 +66,16 @@ pub struct RelayLimitation {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub payment_required: Option<bool>,
+
+    /// How many seconds in the past an event's created_at may be
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub created_at_lower_limit: Option<u64>,
+
+    /// How many seconds in the future an event's created_at may be
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub created_at_upper_limit: Option<u64>,
 }
 
 impl fmt::Display for RelayLimitation {
@@ -104,6 +114,12 @@ impl fmt::Display for RelayLimitation {
         if let Some(pr) = &self.payment_required {
             write!(f, " PaymentRequired=\"{pr}\"")?;
         }
+        if let Some(call) = &self.created_at_lower_limit {
+            write!(f, " CreatedAtLowerLimit=\"{call}\"")?;
+        }
+        if let Some(caul) = &self.created_at_upper_limit {
+            write!(f, " CreatedAtUpperLimit=\"{caul}\"")?;
+        }
         Ok(())
     }
 }
@@ -325,6 +341,8 @@ impl RelayInformationDocument {
                 min_pow_difficulty: Some(30),
                 auth_required: Some(true),
                 payment_required: Some(true),
+                created_at_lower_limit: Some(94608000),
+                created_at_upper_limit: Some(300),
             }),
             retention: vec![
                 RelayRetention {
@@ -353,7 +371,7 @@ impl RelayInformationDocument {
                 },
                 RelayRetention {
                     kinds: vec![EventKindOrRange::Range(vec![
-                        EventKind::Other(30000),
+                        EventKind::FollowSets,
                         EventKind::Other(39999),
                     ])],
                     time: None,
@@ -1,4 +1,4 @@
-use super::UncheckedUrl;
+use super::{RelayHint, RelayHintSource, UncheckedUrl};
 use serde::de::{Deserializer, MapAccess, Visitor};
 use serde::ser::{SerializeMap, Serializer};
 use serde::{Deserialize, Serialize};
@@ -33,6 +33,12 @@ impl Default for SimpleRelayUsage {
 pub struct SimpleRelayList(pub HashMap<UncheckedUrl, SimpleRelayUsage>);
 
 impl SimpleRelayList {
+    /// The relays as hints, including any that are not valid relay URLs
+    pub fn relay_hints(&self) -> Vec<RelayHint> {
+        let urls: Vec<UncheckedUrl> = self.0.keys().cloned().collect();
+        RelayHint::from_urls(&urls, RelayHintSource::RelayList)
+    }
+
     #[allow(dead_code)]
     pub(crate) fn mock() -> SimpleRelayList {
         let mut map: HashMap<UncheckedUrl, SimpleRelayUsage> = HashMap::new();
@@ -1,9 +1,12 @@
 use super::{Event, Id, SubscriptionId};
+use crate::Error;
 use serde::de::Error as DeError;
 use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
-use serde::ser::{Serialize, SerializeSeq, Serializer};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use serde_json::value::RawValue;
 #[cfg(feature = "speedy")]
 use speedy::{Readable, Writable};
+use std::borrow::Cow;
 use std::fmt;
 
 /// A message from a relay to a client
@@ -20,10 +23,215 @@ pub enum RelayMessage {
     Eose(SubscriptionId),
 
     /// Used to notify clients if an event was successuful
-    Ok(Id, bool, String),
+    Ok(Id, bool, RelayReason),
 
     /// Used to send authentication challenges
     Auth(String),
+
+    /// The relay ended a subscription, or refused to start it
+    Closed {
+        /// The subscription that was closed
+        subscription: SubscriptionId,
+
+        /// Why it was closed
+        reason: RelayReason,
+    },
+
+    /// The answer to a COUNT request (NIP-45)
+    Count(SubscriptionId, CountResult),
+}
+
+/// The number of events matching a COUNT request (NIP-45)
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct CountResult {
+    /// How many events match
+    pub count: u64,
+
+    /// Whether the count is an estimate
+    pub approximate: bool,
+}
+
+impl Serialize for CountResult {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("count", &self.count)?;
+        if self.approximate {
+            map.serialize_entry("approximate", &true)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for CountResult {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(serde::Deserialize)]
+        struct Fields {
+            count: u64,
+            #[serde(default)]
+            approximate: bool,
+        }
+        let fields = Fields::deserialize(deserializer)?;
+        Ok(CountResult {
+            count: fields.count,
+            approximate: fields.approximate,
+        })
+    }
+}
+
+/// The standardized machine-readable prefix on the message of an OK or CLOSED
+/// (NIP-01)
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub enum MachineReadablePrefix {
+    /// The relay already has the event
+    Duplicate,
+
+    /// The event did not have enough proof of work
+    Pow,
+
+    /// The author or client is blocked
+    Blocked,
+
+    /// Too many events or requests were sent too quickly
+    RateLimited,
+
+    /// The event or request was malformed
+    Invalid,
+
+    /// The relay requires authentication (NIP-42) first
+    AuthRequired,
+
+    /// The authenticated user is not allowed to do this
+    Restricted,
+
+    /// Something went wrong on the relay
+    Error,
+}
+
+impl MachineReadablePrefix {
+    /// The prefix as written on the wire, without the colon
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            MachineReadablePrefix::Duplicate => "duplicate",
+            MachineReadablePrefix::Pow => "pow",
+            MachineReadablePrefix::Blocked => "blocked",
+            MachineReadablePrefix::RateLimited => "rate-limited",
+            MachineReadablePrefix::Invalid => "invalid",
+            MachineReadablePrefix::AuthRequired => "auth-required",
+            MachineReadablePrefix::Restricted => "restricted",
+            MachineReadablePrefix::Error => "error",
+        }
+    }
+
+    /// Parse a prefix (without the colon), or None if it is not a standard one
+    pub fn parse(s: &str) -> Option<MachineReadablePrefix> {
+        match s {
+            "duplicate" => Some(MachineReadablePrefix::Duplicate),
+            "pow" => Some(MachineReadablePrefix::Pow),
+            "blocked" => Some(MachineReadablePrefix::Blocked),
+            "rate-limited" => Some(MachineReadablePrefix::RateLimited),
+            "invalid" => Some(MachineReadablePrefix::Invalid),
+            "auth-required" => Some(MachineReadablePrefix::AuthRequired),
+            "restricted" => Some(MachineReadablePrefix::Restricted),
+            "error" => Some(MachineReadablePrefix::Error),
+            _ => None,
+        }
+    }
+
+    /// Might the same thing succeed if tried again later (after authenticating,
+    /// in the case of `AuthRequired`)? Rejections for what the event is or who
+    /// sent it will not change by retrying.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            *self,
+            MachineReadablePrefix::RateLimited
+                | MachineReadablePrefix::AuthRequired
+                | MachineReadablePrefix::Error
+        )
+    }
+}
+
+impl fmt::Display for MachineReadablePrefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// The message a relay gives with an OK or CLOSED, split into its machine-readable prefix
+/// (if it has a standard one) and the human-readable text that follows
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct RelayReason {
+    /// The standardized prefix, if any
+    pub prefix: Option<MachineReadablePrefix>,
+
+    /// The whitespace between the prefix's colon and the text, usually a
+    /// single space, kept so that the message is written back as it came
+    pub separator: String,
+
+    /// The human-readable text. If there is no standard prefix this is the
+    /// whole message.
+    pub text: String,
+}
+
+impl RelayReason {
+    /// Create a reason with a prefix, separated from the text by a space
+    pub fn new(prefix: MachineReadablePrefix, text: &str) -> RelayReason {
+        let separator = if text.is_empty() { "" } else { " " };
+        RelayReason {
+            prefix: Some(prefix),
+            separator: separator.to_owned(),
+            text: text.to_owned(),
+        }
+    }
+
+    /// Split a message from a relay into its prefix and text
+    pub fn parse(message: &str) -> RelayReason {
+        if let Some((prefix, text)) = message.split_once(':') {
+            if let Some(prefix) = MachineReadablePrefix::parse(prefix) {
+                let trimmed = text.trim_start();
+                let separator = text.get(..text.len() - trimmed.len()).unwrap_or_default();
+                return RelayReason {
+                    prefix: Some(prefix),
+                    separator: separator.to_owned(),
+                    text: trimmed.to_owned(),
+                };
+            }
+        }
+        RelayReason {
+            prefix: None,
+            separator: "".to_owned(),
+            text: message.to_owned(),
+        }
+    }
+}
+
+impl From<&str> for RelayReason {
+    fn from(message: &str) -> RelayReason {
+        RelayReason::parse(message)
+    }
+}
+
+impl From<String> for RelayReason {
+    fn from(message: String) -> RelayReason {
+        RelayReason::parse(&message)
+    }
+}
+
+impl fmt::Display for RelayReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.prefix {
+            Some(prefix) => write!(f, "{prefix}:{}{}", self.separator, self.text),
+            None => write!(f, "{}", self.text),
+        }
+    }
 }
 
 impl RelayMessage {
@@ -64,7 +272,7 @@ impl Serialize for RelayMessage {
                 seq.serialize_element("OK")?;
                 seq.serialize_element(&id)?;
                 seq.serialize_element(&ok)?;
-                seq.serialize_element(&message)?;
+                seq.serialize_element(&message.to_string())?;
                 seq.end()
             }
             RelayMessage::Auth(challenge) => {
@@ -73,6 +281,23 @@ impl Serialize for RelayMessage {
                 seq.serialize_element(&challenge)?;
                 seq.end()
             }
+            RelayMessage::Closed {
+                subscription,
+                reason,
+            } => {
+                let mut seq = serializer.serialize_seq(Some(3))?;
+                seq.serialize_element("CLOSED")?;
+                seq.serialize_element(&subscription)?;
+                seq.serialize_element(&reason.to_string())?;
+                seq.end()
+            }
+            RelayMessage::Count(id, result) => {
+                let mut seq = serializer.serialize_seq(Some(3))?;
+                seq.serialize_element("COUNT")?;
+                seq.serialize_element(&id)?;
+                seq.serialize_element(&result)?;
+                seq.end()
+            }
         }
     }
 }
@@ -99,52 +324,296 @@ impl<'de> Visitor<'de> for RelayMessageVisitor {
     where
         A: SeqAccess<'de>,
     {
-        let word: &str = seq
+        let word: String = seq
             .next_element()?
             .ok_or_else(|| DeError::custom("Message missing initial string field"))?;
+        visit_message(&word, seq)
+    }
+}
+
+// The rest of a message after its initial string
+fn visit_message<'de, A>(word: &str, mut seq: A) -> Result<RelayMessage, A::Error>
+where
+    A: SeqAccess<'de>,
+{
+    if word == "EVENT" {
+        let id: SubscriptionId = seq
+            .next_element()?
+            .ok_or_else(|| DeError::custom("Message missing id field"))?;
+        let event: Event = seq
+            .next_element()?
+            .ok_or_else(|| DeError::custom("Message missing event field"))?;
+        Ok(RelayMessage::Event(id, Box::new(event)))
+    } else if word == "NOTICE" {
+        let s: String = seq
+            .next_element()?
+            .ok_or_else(|| DeError::custom("Message missing string field"))?;
+        Ok(RelayMessage::Notice(s))
+    } else if word == "EOSE" {
+        let id: SubscriptionId = seq
+            .next_element()?
+            .ok_or_else(|| DeError::custom("Message missing id field"))?;
+        Ok(RelayMessage::Eose(id))
+    } else if word == "OK" {
+        let id: Id = seq
+            .next_element()?
+            .ok_or_else(|| DeError::custom("Message missing id field"))?;
+        let ok: bool = seq
+            .next_element()?
+            .ok_or_else(|| DeError::custom("Message missing ok field"))?;
+        let message: String = seq
+            .next_element()?
+            .ok_or_else(|| DeError::custom("Message missing string field"))?;
+        Ok(RelayMessage::Ok(id, ok, RelayReason::parse(&message)))
+    } else if word == "AUTH" {
+        let challenge: String = seq
+            .next_element()?
+            .ok_or_else(|| DeError::custom("Message missing challenge field"))?;
+        Ok(RelayMessage::Auth(challenge))
+    } else if word == "CLOSED" {
+        let subscription: SubscriptionId = seq
+            .next_element()?
+            .ok_or_else(|| DeError::custom("Message missing id field"))?;
+        let message: String = seq
+            .next_element()?
+            .ok_or_else(|| DeError::custom("Message missing string field"))?;
+        Ok(RelayMessage::Closed {
+            subscription,
+            reason: RelayReason::parse(&message),
+        })
+    } else if word == "COUNT" {
+        let id: SubscriptionId = seq
+            .next_element()?
+            .ok_or_else(|| DeError::custom("Message missing id field"))?;
+        let result: CountResult = seq
+            .next_element()?
+            .ok_or_else(|| DeError::custom("Message missing count field"))?;
+        Ok(RelayMessage::Count(id, result))
+    } else {
+        Err(DeError::custom(format!("Unknown Message: {word}")))
+    }
+}
+
+/// A relay message parsed in one pass straight from the websocket frame,
+/// leaving any event in it unparsed and unverified. See `parse_relay_frame`.
+#[derive(Clone, Debug)]
+pub enum RelayFrame<'a> {
+    /// An event matching a subscription
+    Event(SubscriptionId, RawEvent<'a>),
+
+    /// Any other message, fully parsed
+    Other(RelayMessage),
+}
+
+impl RelayFrame<'_> {
+    /// Parse the event, if any, and convert into an owned `RelayMessage`.
+    /// The event's signature is not verified.
+    pub fn into_message(self) -> Result<RelayMessage, Error> {
+        match self {
+            RelayFrame::Event(sub, raw) => Ok(RelayMessage::Event(sub, Box::new(raw.parse()?))),
+            RelayFrame::Other(message) => Ok(message),
+        }
+    }
+}
+
+/// The JSON of an event, borrowed from the frame it arrived in
+#[derive(Clone, Copy, Debug)]
+pub struct RawEvent<'a>(&'a RawValue);
+
+impl<'a> RawEvent<'a> {
+    /// The JSON text, e.g. to store or forward without re-serializing
+    pub fn json(&self) -> &'a str {
+        self.0.get()
+    }
+
+    /// Parse the event. The signature is not verified; call `Event::verify`
+    /// on the events you keep.
+    pub fn parse(&self) -> Result<Event, Error> {
+        Ok(serde_json::from_str(self.0.get())?)
+    }
+}
+
+/// Parse a relay message from the bytes of a websocket frame in a single
+/// pass. An event's JSON is borrowed from `frame` rather than parsed, so
+/// readers can drop events they have already seen (or do not want) before
+/// paying to parse and verify them.
+pub fn parse_relay_frame(frame: &[u8]) -> Result<RelayFrame<'_>, Error> {
+    Ok(serde_json::from_slice(frame)?)
+}
+
+impl<'de> Deserialize<'de> for RelayFrame<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(RelayFrameVisitor)
+    }
+}
+
+struct RelayFrameVisitor;
+
+impl<'de> Visitor<'de> for RelayFrameVisitor {
+    type Value = RelayFrame<'de>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a relay message")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<RelayFrame<'de>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let word: Cow<'de, str> = seq
+            .next_element::<CowStr<'de>>()?
+            .ok_or_else(|| DeError::custom("Message missing initial string field"))?
+            .0;
         if word == "EVENT" {
             let id: SubscriptionId = seq
                 .next_element()?
                 .ok_or_else(|| DeError::custom("Message missing id field"))?;
-            let event: Event = seq
+            let event: &'de RawValue = seq
                 .next_element()?
                 .ok_or_else(|| DeError::custom("Message missing event field"))?;
-            Ok(RelayMessage::Event(id, Box::new(event)))
-        } else if word == "NOTICE" {
-            let s: String = seq
-                .next_element()?
-                .ok_or_else(|| DeError::custom("Message missing string field"))?;
-            Ok(RelayMessage::Notice(s))
-        } else if word == "EOSE" {
-            let id: SubscriptionId = seq
-                .next_element()?
-                .ok_or_else(|| DeError::custom("Message missing id field"))?;
-            Ok(RelayMessage::Eose(id))
-        } else if word == "OK" {
-            let id: Id = seq
-                .next_element()?
-                .ok_or_else(|| DeError::custom("Message missing id field"))?;
-            let ok: bool = seq
-                .next_element()?
-                .ok_or_else(|| DeError::custom("Message missing ok field"))?;
-            let message: String = seq
-                .next_element()?
-                .ok_or_else(|| DeError::custom("Message missing string field"))?;
-            Ok(RelayMessage::Ok(id, ok, message))
-        } else if word == "AUTH" {
-            let challenge: String = seq
-                .next_element()?
-                .ok_or_else(|| DeError::custom("Message missing challenge field"))?;
-            Ok(RelayMessage::Auth(challenge))
+            Ok(RelayFrame::Event(id, RawEvent(event)))
         } else {
-            Err(DeError::custom(format!("Unknown Message: {word}")))
+            Ok(RelayFrame::Other(visit_message(&word, seq)?))
         }
     }
 }
 
+// A string borrowed from the input unless it has escapes
+struct CowStr<'a>(Cow<'a, str>);
+
+impl<'de> Deserialize<'de> for CowStr<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct CowStrVisitor;
+
+        impl<'de> Visitor<'de> for CowStrVisitor {
+            type Value = CowStr<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a string")
+            }
+
+            fn visit_borrowed_str<E: DeError>(self, v: &'de str) -> Result<CowStr<'de>, E> {
+                Ok(CowStr(Cow::Borrowed(v)))
+            }
+
+            fn visit_str<E: DeError>(self, v: &str) -> Result<CowStr<'de>, E> {
+                Ok(CowStr(Cow::Owned(v.to_owned())))
+            }
+        }
+
+        deserializer.deserialize_str(CowStrVisitor)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     test_serde! {RelayMessage, test_relay_message_serde}
+
+    #[test]
+    fn test_ok_reason() {
+        let json = r#"["OK","b1a649ebe8b435ec71d3784793f3bbf4b93e64e17568a741aecd4c7ddeafce30",false,"rate-limited: slow down there chief"]"#;
+        let message: RelayMessage = serde_json::from_str(json).unwrap();
+        let RelayMessage::Ok(_, ok, reason) = &message else {
+            panic!("not an OK");
+        };
+        assert!(!ok);
+        assert_eq!(
+            reason,
+            &RelayReason::new(MachineReadablePrefix::RateLimited, "slow down there chief")
+        );
+        assert!(reason.prefix.unwrap().is_retryable());
+        assert_eq!(serde_json::to_string(&message).unwrap(), json);
+
+        let reason = RelayReason::parse("duplicate:");
+        assert_eq!(reason.prefix, Some(MachineReadablePrefix::Duplicate));
+        assert_eq!(reason.text, "");
+        assert_eq!(reason.to_string(), "duplicate:");
+
+        let json = r#"["CLOSED","sub1","auth-required: we only serve members"]"#;
+        let message: RelayMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            message,
+            RelayMessage::Closed {
+                subscription: SubscriptionId("sub1".to_owned()),
+                reason: RelayReason::new(
+                    MachineReadablePrefix::AuthRequired,
+                    "we only serve members"
+                ),
+            }
+        );
+        assert_eq!(serde_json::to_string(&message).unwrap(), json);
+
+        // Whatever separates the prefix from the text is kept
+        for message in [
+            "blocked:no space",
+            "blocked:  two spaces",
+            "error:\tTab",
+            "pow: ",
+        ] {
+            let reason = RelayReason::parse(message);
+            assert!(reason.prefix.is_some());
+            assert_eq!(reason.to_string(), message);
+        }
+        assert_eq!(RelayReason::parse("blocked:no space").text, "no space");
+
+        for message in ["", "not a prefix: hi", "Blocked: you", "pow"] {
+            let reason = RelayReason::parse(message);
+            assert_eq!(reason.prefix, None);
+            assert_eq!(reason.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn test_count() {
+        let json = r#"["COUNT","c1",{"count":238}]"#;
+        let message: RelayMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            message,
+            RelayMessage::Count(
+                SubscriptionId("c1".to_owned()),
+                CountResult {
+                    count: 238,
+                    approximate: false
+                }
+            )
+        );
+        assert_eq!(serde_json::to_string(&message).unwrap(), json);
+
+        let json = r#"["COUNT","c1",{"count":93412452,"approximate":true}]"#;
+        let message: RelayMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::to_string(&message).unwrap(), json);
+    }
+
+    #[test]
+    fn test_parse_relay_frame() {
+        let event = Event::mock();
+        let message = RelayMessage::Event(SubscriptionId::mock(), Box::new(event.clone()));
+        let frame = serde_json::to_vec(&message).unwrap();
+        let RelayFrame::Event(sub, raw) = parse_relay_frame(&frame).unwrap() else {
+            panic!("not an EVENT");
+        };
+        assert_eq!(sub, SubscriptionId::mock());
+        assert_eq!(raw.json(), serde_json::to_string(&event).unwrap());
+        assert_eq!(raw.parse().unwrap(), event);
+        assert_eq!(
+            parse_relay_frame(&frame).unwrap().into_message().unwrap(),
+            message
+        );
+
+        let frame = br#"["\u0045OSE","sub1"]"#;
+        assert_eq!(
+            parse_relay_frame(frame).unwrap().into_message().unwrap(),
+            RelayMessage::Eose(SubscriptionId("sub1".to_owned()))
+        );
+        assert!(parse_relay_frame(br#"["EVENT","sub1"]"#).is_err());
+        assert!(parse_relay_frame(br#"["NOPE"]"#).is_err());
+    }
 }
@@ -0,0 +1,562 @@
+use super::{Event, EventKind, PublicKey, RelayUrl, SimpleRelayList, SimpleRelayUsage, Unixtime};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitOrAssign, Sub};
+
+/// The ways a client uses a relay, as a set of flags
+///
+/// These combine with `|`, e.g. `RelayUsage::READ | RelayUsage::INBOX`.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize,
+)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct RelayUsage(u8);
+
+impl RelayUsage {
+    /// Read the events of people we follow from this relay
+    pub const READ: RelayUsage = RelayUsage(1 << 0);
+
+    /// Publish our own events to this relay
+    pub const WRITE: RelayUsage = RelayUsage(1 << 1);
+
+    /// Look up other people's relay lists (kind 10002) on this relay
+    pub const DISCOVER: RelayUsage = RelayUsage(1 << 2);
+
+    /// Receive direct messages on this relay (kind 10050)
+    pub const DM: RelayUsage = RelayUsage(1 << 3);
+
+    /// This relay is in our relay list as one we write to (NIP-65)
+    pub const OUTBOX: RelayUsage = RelayUsage(1 << 4);
+
+    /// This relay is in our relay list as one we read mentions from (NIP-65)
+    pub const INBOX: RelayUsage = RelayUsage(1 << 5);
+
+    const ALL_BITS: u8 = (1 << 6) - 1;
+
+    const NAMES: [(RelayUsage, &'static str); 6] = [
+        (RelayUsage::READ, "read"),
+        (RelayUsage::WRITE, "write"),
+        (RelayUsage::DISCOVER, "discover"),
+        (RelayUsage::DM, "dm"),
+        (RelayUsage::OUTBOX, "outbox"),
+        (RelayUsage::INBOX, "inbox"),
+    ];
+
+    /// No usage
+    pub const fn empty() -> RelayUsage {
+        RelayUsage(0)
+    }
+
+    /// Every usage
+    pub const fn all() -> RelayUsage {
+        RelayUsage(Self::ALL_BITS)
+    }
+
+    /// The raw bits, for storage
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// From raw bits, dropping any that are not defined
+    pub const fn from_bits_truncate(bits: u8) -> RelayUsage {
+        RelayUsage(bits & Self::ALL_BITS)
+    }
+
+    /// Whether no flags are set
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether all the flags in `other` are set
+    pub const fn contains(&self, other: RelayUsage) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether any of the flags in `other` are set
+    pub const fn intersects(&self, other: RelayUsage) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Set the flags in `other`
+    pub fn insert(&mut self, other: RelayUsage) {
+        self.0 |= other.0;
+    }
+
+    /// Clear the flags in `other`
+    pub fn remove(&mut self, other: RelayUsage) {
+        self.0 &= !other.0;
+    }
+
+    /// Set or clear the flags in `other`
+    pub fn set(&mut self, other: RelayUsage, value: bool) {
+        if value {
+            self.insert(other);
+        } else {
+            self.remove(other);
+        }
+    }
+}
+
+impl BitOr for RelayUsage {
+    type Output = RelayUsage;
+
+    fn bitor(self, rhs: RelayUsage) -> RelayUsage {
+        RelayUsage(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for RelayUsage {
+    fn bitor_assign(&mut self, rhs: RelayUsage) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for RelayUsage {
+    type Output = RelayUsage;
+
+    fn bitand(self, rhs: RelayUsage) -> RelayUsage {
+        RelayUsage(self.0 & rhs.0)
+    }
+}
+
+impl Sub for RelayUsage {
+    type Output = RelayUsage;
+
+    fn sub(self, rhs: RelayUsage) -> RelayUsage {
+        RelayUsage(self.0 & !rhs.0)
+    }
+}
+
+impl fmt::Display for RelayUsage {
+    /// The set flags by name, separated by `|`, e.g. `read|inbox`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for (flag, name) in Self::NAMES {
+            if self.contains(flag) {
+                if !first {
+                    f.write_str("|")?;
+                }
+                f.write_str(name)?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<SimpleRelayUsage> for RelayUsage {
+    fn from(simple: SimpleRelayUsage) -> RelayUsage {
+        let mut usage = RelayUsage::empty();
+        usage.set(RelayUsage::READ, simple.read);
+        usage.set(RelayUsage::WRITE, simple.write);
+        usage
+    }
+}
+
+/// A relay picked to fetch the events of some people from (the gossip, or
+/// outbox, model), and who it was picked for
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct RelayAssignment {
+    /// The relay
+    pub relay_url: RelayUrl,
+
+    /// The people whose events we will fetch from it
+    pub pubkeys: Vec<PublicKey>,
+}
+
+impl RelayAssignment {
+    /// An assignment with nobody yet
+    pub fn new(relay_url: RelayUrl) -> RelayAssignment {
+        RelayAssignment {
+            relay_url,
+            pubkeys: Vec::new(),
+        }
+    }
+
+    /// Add people to this assignment, skipping any already in it
+    pub fn merge_pubkeys(&mut self, pubkeys: &[PublicKey]) {
+        for pubkey in pubkeys {
+            if !self.pubkeys.contains(pubkey) {
+                self.pubkeys.push(*pubkey);
+            }
+        }
+    }
+}
+
+/// How a relay has served us, and how much the user trusts it, for ranking
+/// relays against each other
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct RelayScore {
+    /// How we use the relay
+    pub usage: RelayUsage,
+
+    /// The user's rank for the relay, 0 (never use) to 9. The default is 3.
+    pub rank: u8,
+
+    /// How many times connecting or fetching succeeded
+    pub success_count: u64,
+
+    /// How many times connecting or fetching failed
+    pub failure_count: u64,
+
+    /// When it last succeeded
+    pub last_success_at: Option<Unixtime>,
+
+    /// When it last failed
+    pub last_failure_at: Option<Unixtime>,
+}
+
+impl Default for RelayScore {
+    fn default() -> RelayScore {
+        RelayScore {
+            usage: RelayUsage::empty(),
+            rank: 3,
+            success_count: 0,
+            failure_count: 0,
+            last_success_at: None,
+            last_failure_at: None,
+        }
+    }
+}
+
+impl RelayScore {
+    /// Highest rank
+    pub const MAX_RANK: u8 = 9;
+
+    /// Record a success at `when`
+    pub fn record_success(&mut self, when: Unixtime) {
+        self.success_count = self.success_count.saturating_add(1);
+        self.last_success_at = Some(when);
+    }
+
+    /// Record a failure at `when`
+    pub fn record_failure(&mut self, when: Unixtime) {
+        self.failure_count = self.failure_count.saturating_add(1);
+        self.last_failure_at = Some(when);
+    }
+
+    /// The share of attempts that succeeded. A relay we have not tried yet
+    /// counts as 0.5, and a few results only move it part of the way.
+    pub fn success_rate(&self) -> f32 {
+        let successes = self.success_count as f64 + 1.0;
+        let attempts = self.success_count as f64 + self.failure_count as f64 + 2.0;
+        (successes / attempts) as f32
+    }
+
+    /// A score from 0.0 to 1.0: the success rate scaled by the user's rank.
+    /// Relays ranked 0 always score 0.0.
+    pub fn score(&self) -> f32 {
+        let rank = self.rank.min(Self::MAX_RANK) as f32 / Self::MAX_RANK as f32;
+        rank * self.success_rate()
+    }
+}
+
+/// Which relays one person uses, and how, put together from their relay
+/// list (kind 10002), DM relay list (kind 10050) and the relays in the
+/// content of their contact list (kind 3), for choosing where to find their
+/// events and where to send them things (the outbox model)
+///
+/// Their relay list sets `OUTBOX` (relays they write to) and `INBOX` (relays
+/// they read mentions from). The contact list relays are an older way of
+/// saying the same, used only if there is no relay list. The DM relay list
+/// sets `DM`. Only the newest event of each kind counts.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RelayUsagePicture {
+    relay_list: Option<PictureSource>,
+    contact_list: Option<PictureSource>,
+    dm_relay_list: Option<PictureSource>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct PictureSource {
+    created_at: Unixtime,
+    relays: Vec<(RelayUrl, RelayUsage)>,
+}
+
+impl RelayUsagePicture {
+    /// How long it takes for what an event says about relays to count for
+    /// half as much, in seconds (90 days). People change relays, and an old
+    /// list is more likely to be stale.
+    pub const HALF_LIFE: i64 = 90 * 24 * 60 * 60;
+
+    /// An empty picture
+    pub fn new() -> RelayUsagePicture {
+        RelayUsagePicture::default()
+    }
+
+    /// A picture from someone's events. Events of other kinds are ignored,
+    /// and the caller should pass only events by the one person.
+    pub fn from_events<'a, I: IntoIterator<Item = &'a Event>>(events: I) -> RelayUsagePicture {
+        let mut picture = RelayUsagePicture::new();
+        for event in events {
+            let _ = picture.add_event(event);
+        }
+        picture
+    }
+
+    /// Take in an event, if it is a relay-bearing kind and newer than the one
+    /// of its kind already taken in. Returns whether it was taken in. Invalid
+    /// relay URLs are skipped.
+    pub fn add_event(&mut self, event: &Event) -> bool {
+        let (slot, relays) = match event.kind {
+            EventKind::RelayList => {
+                let relays = event
+                    .tags
+                    .iter()
+                    .filter_map(|t| t.parse_reference().ok())
+                    .filter_map(|(url, marker)| {
+                        let usage = match marker.as_deref() {
+                            Some("read") => RelayUsage::INBOX,
+                            Some("write") => RelayUsage::OUTBOX,
+                            _ => RelayUsage::INBOX | RelayUsage::OUTBOX,
+                        };
+                        Some((RelayUrl::try_from_unchecked_url(&url).ok()?, usage))
+                    })
+                    .collect();
+                (&mut self.relay_list, relays)
+            }
+            EventKind::ContactList => {
+                let Ok(list) = serde_json::from_str::<SimpleRelayList>(&event.content) else {
+                    return false;
+                };
+                let relays = list
+                    .0
+                    .iter()
+                    .filter_map(|(url, simple)| {
+                        let mut usage = RelayUsage::empty();
+                        usage.set(RelayUsage::INBOX, simple.read);
+                        usage.set(RelayUsage::OUTBOX, simple.write);
+                        Some((RelayUrl::try_from_unchecked_url(url).ok()?, usage))
+                    })
+                    .collect();
+                (&mut self.contact_list, relays)
+            }
+            EventKind::DmRelayList => {
+                let relays = event
+                    .dm_relays()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|url| (url, RelayUsage::DM))
+                    .collect();
+                (&mut self.dm_relay_list, relays)
+            }
+            _ => return false,
+        };
+        if slot
+            .as_ref()
+            .is_some_and(|s| s.created_at >= event.created_at)
+        {
+            return false;
+        }
+        *slot = Some(PictureSource {
+            created_at: event.created_at,
+            relays,
+        });
+        true
+    }
+
+    // The sources that count, with the contact list only as a fallback
+    fn sources(&self) -> impl Iterator<Item = &PictureSource> {
+        let read_write = self.relay_list.as_ref().or(self.contact_list.as_ref());
+        read_write.into_iter().chain(self.dm_relay_list.as_ref())
+    }
+
+    /// How the person uses a relay
+    pub fn usage(&self, relay: &RelayUrl) -> RelayUsage {
+        let mut usage = RelayUsage::empty();
+        for source in self.sources() {
+            for (url, u) in source.relays.iter() {
+                if url == relay {
+                    usage |= *u;
+                }
+            }
+        }
+        usage
+    }
+
+    /// Every relay the person uses, and how
+    pub fn relays(&self) -> BTreeMap<RelayUrl, RelayUsage> {
+        let mut output: BTreeMap<RelayUrl, RelayUsage> = BTreeMap::new();
+        for source in self.sources() {
+            for (url, usage) in source.relays.iter() {
+                *output.entry(url.clone()).or_default() |= *usage;
+            }
+        }
+        output
+    }
+
+    /// The relays used in any of the ways in `usage`, each weighted from 1.0
+    /// down by the age at `now` of the newest event saying so, best first.
+    /// E.g. `RelayUsage::OUTBOX` for where to find their events, or
+    /// `RelayUsage::INBOX` for where to send them mentions.
+    pub fn relays_for(&self, usage: RelayUsage, now: Unixtime) -> Vec<(RelayUrl, f32)> {
+        let mut weights: BTreeMap<&RelayUrl, f32> = BTreeMap::new();
+        for source in self.sources() {
+            let weight = recency_weight(source.created_at, now);
+            for (url, u) in source.relays.iter() {
+                if u.intersects(usage) {
+                    let w = weights.entry(url).or_insert(0.0);
+                    *w = w.max(weight);
+                }
+            }
+        }
+        let mut output: Vec<(RelayUrl, f32)> = weights
+            .into_iter()
+            .map(|(url, weight)| (url.clone(), weight))
+            .collect();
+        output.sort_by(|a, b| b.1.total_cmp(&a.1));
+        output
+    }
+}
+
+// Halves every HALF_LIFE, and is 1.0 for events from the future
+fn recency_weight(created_at: Unixtime, now: Unixtime) -> f32 {
+    let age = now.0.saturating_sub(created_at.0).max(0);
+    0.5_f64.powf(age as f64 / RelayUsagePicture::HALF_LIFE as f64) as f32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_relay_usage() {
+        let mut usage = RelayUsage::READ | RelayUsage::INBOX;
+        assert!(usage.contains(RelayUsage::READ));
+        assert!(!usage.contains(RelayUsage::READ | RelayUsage::WRITE));
+        assert!(usage.intersects(RelayUsage::READ | RelayUsage::WRITE));
+        assert_eq!(usage.to_string(), "read|inbox");
+
+        usage.set(RelayUsage::DM, true);
+        usage.remove(RelayUsage::READ);
+        assert_eq!(usage, RelayUsage::DM | RelayUsage::INBOX);
+        assert_eq!(usage - RelayUsage::DM, RelayUsage::INBOX);
+        assert_eq!(RelayUsage::from_bits_truncate(0xff), RelayUsage::all());
+        assert_eq!(RelayUsage::from_bits_truncate(usage.bits()), usage);
+        assert_eq!(RelayUsage::empty().to_string(), "");
+
+        let simple = SimpleRelayUsage {
+            read: true,
+            write: true,
+        };
+        assert_eq!(
+            RelayUsage::from(simple),
+            RelayUsage::READ | RelayUsage::WRITE
+        );
+    }
+
+    #[test]
+    fn test_relay_score() {
+        let mut score = RelayScore::default();
+        assert_eq!(score.success_rate(), 0.5);
+        score.record_success(Unixtime(1));
+        score.record_success(Unixtime(2));
+        score.record_failure(Unixtime(3));
+        assert_eq!(score.success_rate(), 0.6);
+        assert_eq!(score.last_failure_at, Some(Unixtime(3)));
+
+        let better = RelayScore { rank: 9, ..score };
+        assert!(better.score() > score.score());
+        let never = RelayScore { rank: 0, ..score };
+        assert_eq!(never.score(), 0.0);
+    }
+
+    #[test]
+    fn test_relay_assignment() {
+        let alice = PublicKey::mock();
+        let bob = PublicKey::mock_deterministic();
+        let mut assignment =
+            RelayAssignment::new(RelayUrl::try_from_str("wss://relay.example.com").unwrap());
+        assignment.merge_pubkeys(&[alice]);
+        assignment.merge_pubkeys(&[alice, bob]);
+        assert_eq!(assignment.pubkeys, vec![alice, bob]);
+    }
+
+    #[test]
+    fn test_relay_usage_picture() {
+        use crate::types::{PreEvent, PrivateKey, Tag, UncheckedUrl};
+
+        let privkey = PrivateKey::mock();
+        let event = |kind: EventKind, created_at: i64, tags: Vec<Tag>, content: &str| {
+            let pre = PreEvent {
+                pubkey: privkey.public_key(),
+                created_at: Unixtime(created_at),
+                kind,
+                tags,
+                content: content.to_owned(),
+                ots: None,
+            };
+            Event::new(pre, &privkey).unwrap()
+        };
+        let relay = |s: &str| RelayUrl::try_from_str(s).unwrap();
+        let r_tag = |url: &str, marker: Option<&str>| {
+            Tag::new_reference(UncheckedUrl::from_str(url), marker.map(|m| m.to_owned()))
+        };
+
+        let day = 24 * 60 * 60;
+        let now = 1_000 * day;
+        let contacts = event(
+            EventKind::ContactList,
+            now - 400 * day,
+            vec![],
+            r#"{"wss://old.example.com":{"read":true,"write":true}}"#,
+        );
+        let old_list = event(
+            EventKind::RelayList,
+            now - 200 * day,
+            vec![r_tag("wss://gone.example.com", None)],
+            "",
+        );
+        let list = event(
+            EventKind::RelayList,
+            now - 90 * day,
+            vec![
+                r_tag("wss://both.example.com", None),
+                r_tag("wss://read.example.com", Some("read")),
+                r_tag("wss://write.example.com", Some("write")),
+                r_tag("not a url", None),
+            ],
+            "",
+        );
+        let dm = event(
+            EventKind::DmRelayList,
+            now,
+            vec![Tag::new(&["relay", "wss://both.example.com"])],
+            "",
+        );
+
+        // Only the contact list so far
+        let mut picture = RelayUsagePicture::from_events([&contacts]);
+        assert_eq!(
+            picture.usage(&relay("wss://old.example.com")),
+            RelayUsage::INBOX | RelayUsage::OUTBOX
+        );
+
+        // The newest relay list replaces the contact list relays
+        assert!(picture.add_event(&list));
+        assert!(!picture.add_event(&old_list));
+        assert!(picture.add_event(&dm));
+        assert!(!picture.add_event(&Event::mock()));
+        assert_eq!(
+            picture.usage(&relay("wss://both.example.com")),
+            RelayUsage::INBOX | RelayUsage::OUTBOX | RelayUsage::DM
+        );
+        assert!(picture.usage(&relay("wss://old.example.com")).is_empty());
+        assert_eq!(picture.relays().len(), 3);
+
+        let outbox = picture.relays_for(RelayUsage::OUTBOX, Unixtime(now));
+        let urls: Vec<RelayUrl> = outbox.iter().map(|(url, _)| url.clone()).collect();
+        assert!(urls.contains(&relay("wss://write.example.com")));
+        assert!(!urls.contains(&relay("wss://read.example.com")));
+        assert!((outbox[0].1 - 0.5).abs() < 0.001);
+
+        // The DM relay list is newer, so that relay ranks first for DMs
+        let dm_relays = picture.relays_for(RelayUsage::DM, Unixtime(now));
+        assert_eq!(dm_relays, vec![(relay("wss://both.example.com"), 1.0)]);
+    }
+}
@@ -0,0 +1,123 @@
+use super::{Id, PublicKeyHex};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+use std::fmt;
+
+/// The reason for a report (NIP-56)
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+#[serde(rename_all = "lowercase")]
+pub enum ReportType {
+    /// Depictions of nudity, porn, etc.
+    Nudity,
+
+    /// Virus, trojan horse, worm, malware, etc.
+    Malware,
+
+    /// Profanity, hateful speech, etc.
+    Profanity,
+
+    /// Something which may be illegal in some jurisdiction
+    Illegal,
+
+    /// Spam
+    Spam,
+
+    /// Someone pretending to be someone else
+    Impersonation,
+
+    /// Some other reason, or one we do not recognize
+    Other,
+}
+
+impl ReportType {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> ReportType {
+        ReportType::Spam
+    }
+
+    /// The string used on the wire for this report type
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ReportType::Nudity => "nudity",
+            ReportType::Malware => "malware",
+            ReportType::Profanity => "profanity",
+            ReportType::Illegal => "illegal",
+            ReportType::Spam => "spam",
+            ReportType::Impersonation => "impersonation",
+            ReportType::Other => "other",
+        }
+    }
+}
+
+impl From<&str> for ReportType {
+    fn from(s: &str) -> ReportType {
+        match s {
+            "nudity" => ReportType::Nudity,
+            "malware" => ReportType::Malware,
+            "profanity" => ReportType::Profanity,
+            "illegal" => ReportType::Illegal,
+            "spam" => ReportType::Spam,
+            "impersonation" => ReportType::Impersonation,
+            _ => ReportType::Other,
+        }
+    }
+}
+
+impl fmt::Display for ReportType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// What a report (NIP-56) is about
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReportTarget {
+    /// A person
+    Pubkey(PublicKeyHex),
+
+    /// An event, along with its author
+    Event {
+        /// The reported event
+        id: Id,
+
+        /// The author of the reported event
+        author: PublicKeyHex,
+    },
+}
+
+/// A report (NIP-56) extracted from a kind 1984 event
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Report {
+    /// What is being reported
+    pub target: ReportTarget,
+
+    /// Why it is being reported
+    pub report_type: ReportType,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {ReportType, test_report_type_serde}
+
+    #[test]
+    fn test_report_type_strings() {
+        for rt in [
+            ReportType::Nudity,
+            ReportType::Malware,
+            ReportType::Profanity,
+            ReportType::Illegal,
+            ReportType::Spam,
+            ReportType::Impersonation,
+            ReportType::Other,
+        ] {
+            assert_eq!(ReportType::from(rt.as_str()), rt);
+            assert_eq!(serde_json::to_string(&rt).unwrap(), format!("\"{}\"", rt));
+        }
+        assert_eq!(ReportType::from("whatever"), ReportType::Other);
+    }
+}
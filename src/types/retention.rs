@@ -0,0 +1,143 @@
+use super::{Event, EventKind, RelayInformationDocument, RelayRetention, Unixtime};
+use std::time::Duration;
+
+/// How long a relay keeps events of each kind, from the `retention` entries
+/// of its relay information document (NIP-11)
+///
+/// The entry for a kind is the first one listing it, or failing that the
+/// first one listing no kinds. A `time` of 0 or a `count` of 0 means events
+/// are not stored, and a missing `time` means they are kept forever.
+/// Ephemeral events are never stored, whatever the entries say.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RetentionPolicy {
+    entries: Vec<RelayRetention>,
+}
+
+impl RetentionPolicy {
+    /// A policy from retention entries. With none, everything but
+    /// ephemeral events is kept forever.
+    pub fn new(entries: Vec<RelayRetention>) -> RetentionPolicy {
+        RetentionPolicy { entries }
+    }
+
+    /// The policy a relay advertises in its information document
+    pub fn from_document(document: &RelayInformationDocument) -> RetentionPolicy {
+        RetentionPolicy::new(document.retention.clone())
+    }
+
+    /// The entry that applies to events of this kind, if any
+    pub fn entry_for(&self, kind: EventKind) -> Option<&RelayRetention> {
+        self.entry_index(kind).map(|i| &self.entries[i])
+    }
+
+    // The position of the entry that applies to events of this kind, which
+    // tells which kinds share a count limit
+    pub(super) fn entry_index(&self, kind: EventKind) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|e| e.kinds.iter().any(|k| k.contains(kind)))
+            .or_else(|| self.entries.iter().position(|e| e.kinds.is_empty()))
+    }
+
+    /// Whether events of this kind are stored at all
+    pub fn stores(&self, kind: EventKind) -> bool {
+        if kind.is_ephemeral() {
+            return false;
+        }
+        match self.entry_for(kind) {
+            Some(entry) => entry.time != Some(0) && entry.count != Some(0),
+            None => true,
+        }
+    }
+
+    /// How long events of this kind are kept, or None if forever. Zero if
+    /// they are not stored.
+    pub fn max_age(&self, kind: EventKind) -> Option<Duration> {
+        if !self.stores(kind) {
+            return Some(Duration::ZERO);
+        }
+        self.entry_for(kind)
+            .and_then(|entry| entry.time)
+            .map(|secs| Duration::from_secs(secs as u64))
+    }
+
+    /// How many of the most recent events of the kinds sharing this kind's
+    /// entry are kept, or None if there is no limit
+    pub fn max_count(&self, kind: EventKind) -> Option<usize> {
+        if !self.stores(kind) {
+            return Some(0);
+        }
+        self.entry_for(kind).and_then(|entry| entry.count)
+    }
+
+    /// Whether the relay still keeps this event at `now`, going by its age
+    /// since `created_at`. Count limits depend on the other events stored,
+    /// so they are not checked here; see `max_count`.
+    pub fn retains(&self, event: &Event, now: Unixtime) -> bool {
+        if !self.stores(event.kind) {
+            return false;
+        }
+        match self.max_age(event.kind) {
+            Some(age) => {
+                let max = i64::try_from(age.as_secs()).unwrap_or(i64::MAX);
+                now.0.saturating_sub(event.created_at.0) <= max
+            }
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_retention_policy() {
+        let policy = RetentionPolicy::from_document(&RelayInformationDocument::mock());
+
+        // Listed kinds and ranges, with the catch-all entry for the rest
+        assert_eq!(
+            policy.max_age(EventKind::TextNote),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(
+            policy.max_age(EventKind::Reaction),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(
+            policy.max_age(EventKind::Other(45000)),
+            Some(Duration::from_secs(100))
+        );
+        assert_eq!(policy.max_age(EventKind::FollowSets), None);
+        assert_eq!(policy.max_count(EventKind::FollowSets), Some(1000));
+        assert_eq!(policy.max_count(EventKind::ContactList), Some(10000));
+
+        // Ephemeral events are never stored
+        assert!(!policy.stores(EventKind::Auth));
+        assert_eq!(policy.max_count(EventKind::Auth), Some(0));
+
+        let mut event = Event::mock();
+        event.kind = EventKind::TextNote;
+        event.created_at = Unixtime(1_700_000_000);
+        assert!(policy.retains(&event, Unixtime(1_700_003_600)));
+        assert!(!policy.retains(&event, Unixtime(1_700_003_601)));
+
+        let policy = RetentionPolicy::new(vec![RelayRetention {
+            kinds: vec![],
+            time: Some(0),
+            count: None,
+        }]);
+        assert!(!policy.stores(EventKind::TextNote));
+        assert!(!policy.retains(&event, event.created_at));
+        assert!(RetentionPolicy::default().retains(&event, Unixtime(i64::MAX)));
+
+        // A time limit beyond i64 keeps everything rather than wrapping
+        let policy = RetentionPolicy::new(vec![RelayRetention {
+            kinds: vec![],
+            time: Some(usize::MAX),
+            count: None,
+        }]);
+        assert!(policy.retains(&event, event.created_at));
+        assert!(policy.retains(&event, Unixtime(i64::MAX)));
+    }
+}
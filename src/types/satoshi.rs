@@ -1,7 +1,11 @@
+use super::classified::decimal_to_scaled;
 use derive_more::{AsMut, AsRef, Deref, Display, From, Into};
 use serde::{Deserialize, Serialize};
 use std::ops::Add;
 
+const MSATS_PER_SAT: u64 = 1_000;
+const MSATS_PER_BTC: u64 = 100_000_000_000;
+
 /// Bitcoin amount measured in millisatoshi
 #[derive(
     AsMut,
@@ -28,6 +32,98 @@ impl MilliSatoshi {
     pub(crate) fn mock() -> MilliSatoshi {
         MilliSatoshi(15423000)
     }
+
+    /// Convert from satoshis, or None if it would overflow
+    pub fn from_sats(sats: u64) -> Option<MilliSatoshi> {
+        sats.checked_mul(MSATS_PER_SAT).map(MilliSatoshi)
+    }
+
+    /// The whole number of satoshis, rounding down
+    pub fn to_sats(&self) -> u64 {
+        self.0 / MSATS_PER_SAT
+    }
+
+    /// Parse an amount of bitcoin as a decimal string like "0.00021". Returns
+    /// None if it is not a plain decimal, is finer than a millisatoshi, or is
+    /// too large.
+    pub fn from_btc(btc: &str) -> Option<MilliSatoshi> {
+        decimal_to_scaled(btc, 11).map(MilliSatoshi)
+    }
+
+    /// The exact amount in bitcoin as a decimal string, without trailing zeros
+    pub fn to_btc_string(&self) -> String {
+        let whole = self.0 / MSATS_PER_BTC;
+        let frac = format!("{:011}", self.0 % MSATS_PER_BTC);
+        let frac = frac.trim_end_matches('0');
+        if frac.is_empty() {
+            whole.to_string()
+        } else {
+            format!("{whole}.{frac}")
+        }
+    }
+
+    /// Add, or None on overflow
+    pub fn checked_add(self, rhs: MilliSatoshi) -> Option<MilliSatoshi> {
+        self.0.checked_add(rhs.0).map(MilliSatoshi)
+    }
+
+    /// Subtract, or None if `rhs` is larger
+    pub fn checked_sub(self, rhs: MilliSatoshi) -> Option<MilliSatoshi> {
+        self.0.checked_sub(rhs.0).map(MilliSatoshi)
+    }
+
+    /// Total a number of amounts, or None on overflow
+    pub fn checked_sum<I>(amounts: I) -> Option<MilliSatoshi>
+    where
+        I: IntoIterator<Item = MilliSatoshi>,
+    {
+        amounts
+            .into_iter()
+            .try_fold(MilliSatoshi(0), MilliSatoshi::checked_add)
+    }
+
+    /// Format for display in satoshis, with comma thousands separators
+    /// regardless of locale, e.g. "1 sat", "1,000 sats" or "21.5 sats"
+    pub fn format_sats(&self) -> String {
+        let mut output = group_thousands(self.to_sats());
+        let msats = self.0 % MSATS_PER_SAT;
+        if msats != 0 {
+            let frac = format!("{msats:03}");
+            output.push('.');
+            output.push_str(frac.trim_end_matches('0'));
+        }
+        if self.0 == MSATS_PER_SAT {
+            output.push_str(" sat");
+        } else {
+            output.push_str(" sats");
+        }
+        output
+    }
+
+    /// Format for display in millisatoshis, with comma thousands separators
+    /// regardless of locale, e.g. "21,000 msats"
+    pub fn format_msats(&self) -> String {
+        let mut output = group_thousands(self.0);
+        if self.0 == 1 {
+            output.push_str(" msat");
+        } else {
+            output.push_str(" msats");
+        }
+        output
+    }
+}
+
+// Write a number with a comma between each group of three digits
+fn group_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut output = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i != 0 && (digits.len() - i).is_multiple_of(3) {
+            output.push(',');
+        }
+        output.push(c);
+    }
+    output
 }
 
 impl Add<MilliSatoshi> for MilliSatoshi {
@@ -50,5 +146,55 @@ mod test {
         let b = MilliSatoshi(3000);
         let c = a + b;
         assert_eq!(c.0, 18000);
+
+        assert_eq!(a.checked_sub(b), Some(MilliSatoshi(12000)));
+        assert_eq!(b.checked_sub(a), None);
+        assert_eq!(MilliSatoshi(u64::MAX).checked_add(b), None);
+        assert_eq!(
+            MilliSatoshi::checked_sum([a, b, b]),
+            Some(MilliSatoshi(21000))
+        );
+        assert_eq!(MilliSatoshi::checked_sum([a, MilliSatoshi(u64::MAX)]), None);
+        assert_eq!(MilliSatoshi::checked_sum([]), Some(MilliSatoshi(0)));
+    }
+
+    #[test]
+    fn test_millisatoshi_conversion() {
+        assert_eq!(MilliSatoshi::from_sats(21), Some(MilliSatoshi(21000)));
+        assert_eq!(MilliSatoshi::from_sats(u64::MAX), None);
+        assert_eq!(MilliSatoshi(21999).to_sats(), 21);
+
+        assert_eq!(
+            MilliSatoshi::from_btc("1"),
+            Some(MilliSatoshi(MSATS_PER_BTC))
+        );
+        assert_eq!(
+            MilliSatoshi::from_btc("0.00021"),
+            Some(MilliSatoshi(21_000_000))
+        );
+        assert_eq!(MilliSatoshi::from_btc("0.000000000001"), None);
+        assert_eq!(MilliSatoshi::from_btc("-1"), None);
+        assert_eq!(MilliSatoshi(21_000_000).to_btc_string(), "0.00021");
+        assert_eq!(
+            MilliSatoshi(150_000_000_001).to_btc_string(),
+            "1.50000000001"
+        );
+        assert_eq!(MilliSatoshi(2 * MSATS_PER_BTC).to_btc_string(), "2");
+        assert_eq!(MilliSatoshi(0).to_btc_string(), "0");
+    }
+
+    #[test]
+    fn test_millisatoshi_format() {
+        assert_eq!(MilliSatoshi(21000).format_sats(), "21 sats");
+        assert_eq!(MilliSatoshi(1000).format_sats(), "1 sat");
+        assert_eq!(MilliSatoshi(0).format_sats(), "0 sats");
+        assert_eq!(MilliSatoshi(1_000_000).format_sats(), "1,000 sats");
+        assert_eq!(MilliSatoshi(21_500).format_sats(), "21.5 sats");
+        assert_eq!(
+            MilliSatoshi(1_234_567_890_123).format_sats(),
+            "1,234,567,890.123 sats"
+        );
+        assert_eq!(MilliSatoshi(1).format_msats(), "1 msat");
+        assert_eq!(MilliSatoshi(123_456).format_msats(), "123,456 msats");
     }
 }
@@ -1,28 +1,60 @@
-use crate::{Error, Event};
-use derive_more::{AsMut, AsRef, Deref, Display, From, FromStr, Into};
+use super::id::is_lowercase_hex;
+use crate::{Error, Event, Id, PublicKey};
+use derive_more::{AsMut, AsRef, Deref, Display, From, Into};
 use k256::schnorr::Signature as KSignature;
 use serde::de::Error as DeserializeError;
 use serde::de::{Deserialize as De, Deserializer, Visitor};
 use serde::ser::{Serialize as Se, Serializer};
-use serde::{Deserialize, Serialize};
 #[cfg(feature = "speedy")]
 use speedy::{Context, Readable, Reader, Writable, Writer};
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use subtle::{Choice, ConstantTimeEq};
 
 /// A Schnorr signature that signs an Event, taken on the Event Id field
-#[derive(AsMut, AsRef, Clone, Copy, Debug, Deref, Eq, From, Into, PartialEq)]
-pub struct Signature(pub KSignature);
+///
+/// This holds the 64 bytes as given, so copying, comparing and hashing it
+/// are cheap. They are checked to be a well-formed signature only when
+/// verifying.
+#[derive(AsMut, AsRef, Clone, Copy, Debug, Deref, Eq, From, Hash, Into, PartialEq)]
+pub struct Signature(pub [u8; 64]);
 
 impl Signature {
     /// Render into a hexadecimal string
     pub fn as_hex_string(&self) -> String {
-        hex::encode(self.0.to_bytes())
+        hex::encode(self.0)
     }
 
     /// Create from a hexadecimal string
     pub fn try_from_hex_string(v: &str) -> Result<Signature, Error> {
-        let vec: Vec<u8> = hex::decode(v)?;
-        Ok(Signature(KSignature::try_from(&*vec)?))
+        let mut bytes = [0u8; 64];
+        hex::decode_to_slice(v, &mut bytes).map_err(|e| match e {
+            hex::FromHexError::InvalidStringLength => Error::WrongLengthHexString,
+            e => Error::from(e),
+        })?;
+        Ok(Signature(bytes))
+    }
+
+    /// The 64 bytes of the signature
+    pub fn as_bytes(&self) -> &[u8; 64] {
+        &self.0
+    }
+
+    /// From the 64 bytes of a signature
+    pub fn from_bytes(bytes: [u8; 64]) -> Signature {
+        Signature(bytes)
+    }
+
+    /// Verify this as `pubkey`'s signature over an event id or any other
+    /// 32-byte hash, as made by `PrivateKey::sign_id()`
+    pub fn verify(&self, pubkey: &PublicKey, id: Id) -> Result<(), Error> {
+        pubkey.verify_id(id, self)
+    }
+
+    // As k256 needs it to verify
+    pub(crate) fn to_k256(self) -> Result<KSignature, Error> {
+        Ok(KSignature::try_from(&self.0[..])?)
     }
 
     // Mock data for testing
@@ -33,12 +65,20 @@ impl Signature {
     }
 }
 
+impl TryFrom<&[u8]> for Signature {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Signature, Error> {
+        Ok(Signature(<[u8; 64]>::try_from(bytes)?))
+    }
+}
+
 impl Se for Signature {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        serializer.serialize_str(&hex::encode(self.to_bytes()))
+        serializer.serialize_str(&hex::encode(self.0))
     }
 }
 
@@ -64,18 +104,14 @@ impl Visitor<'_> for SignatureVisitor {
     where
         E: serde::de::Error,
     {
-        let vec: Vec<u8> = hex::decode(v).map_err(|e| serde::de::Error::custom(format!("{e}")))?;
-
-        // If we don't catch this ourselves, the below from_bytes will panic when it
-        // gets into an assertion within generic-array
-        if vec.len() != 64 {
-            return Err(serde::de::Error::custom("Signature is not 64 bytes long"));
-        }
-
-        let ksig: KSignature =
-            KSignature::try_from(&*vec).map_err(|e| DeserializeError::custom(format!("{e}")))?;
-
-        Ok(Signature(ksig))
+        let mut bytes = [0u8; 64];
+        hex::decode_to_slice(v, &mut bytes).map_err(|e| match e {
+            hex::FromHexError::InvalidStringLength => {
+                DeserializeError::custom("Signature is not 64 bytes long")
+            }
+            e => DeserializeError::custom(format!("{e}")),
+        })?;
+        Ok(Signature(bytes))
     }
 }
 
@@ -83,14 +119,12 @@ impl Visitor<'_> for SignatureVisitor {
 impl<'a, C: Context> Readable<'a, C> for Signature {
     #[inline]
     fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
-        let bytes = <[u8; 32]>::read_from(reader)?;
-        let sig = KSignature::try_from(&bytes[..]).map_err(|e| speedy::Error::custom(e))?;
-        Ok(Signature(sig))
+        Ok(Signature(<[u8; 64]>::read_from(reader)?))
     }
 
     #[inline]
     fn minimum_bytes_needed() -> usize {
-        32
+        64
     }
 }
 
@@ -98,34 +132,22 @@ impl<'a, C: Context> Readable<'a, C> for Signature {
 impl<C: Context> Writable<C> for Signature {
     #[inline]
     fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
-        self.0.to_bytes().write_to(writer)
+        self.0.write_to(writer)
     }
 
     #[inline]
     fn bytes_needed(&self) -> Result<usize, C::Error> {
-        Ok(32)
+        Ok(64)
     }
 }
 
 /// A Schnorr signature that signs an Event, taken on the Event Id field, as a hex string
-#[derive(
-    AsMut,
-    AsRef,
-    Clone,
-    Debug,
-    Deref,
-    Deserialize,
-    Display,
-    Eq,
-    From,
-    FromStr,
-    Hash,
-    Into,
-    PartialEq,
-    Serialize,
-)]
-#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
-pub struct SignatureHex(pub String);
+///
+/// It is always 128 lowercase hex digits; construction fails otherwise.
+/// Equality is checked in constant time.
+#[derive(AsRef, Clone, Debug, Deref, Display, Eq, Into)]
+#[cfg_attr(feature = "speedy", derive(Writable))]
+pub struct SignatureHex(String);
 
 impl SignatureHex {
     // Mock data for testing
@@ -133,6 +155,71 @@ impl SignatureHex {
     pub(crate) fn mock() -> SignatureHex {
         From::from(Signature::mock())
     }
+
+    /// Try from &str
+    pub fn try_from_str(s: &str) -> Result<SignatureHex, Error> {
+        Self::try_from_string(s.to_owned())
+    }
+
+    /// Try from String
+    pub fn try_from_string(s: String) -> Result<SignatureHex, Error> {
+        if s.len() != 128 || !is_lowercase_hex(&s) {
+            return Err(Error::InvalidSignature);
+        }
+        Ok(SignatureHex(s))
+    }
+
+    /// As &str
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Into String
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl ConstantTimeEq for SignatureHex {
+    fn ct_eq(&self, other: &SignatureHex) -> Choice {
+        self.0.as_bytes().ct_eq(other.0.as_bytes())
+    }
+}
+
+impl PartialEq for SignatureHex {
+    fn eq(&self, other: &SignatureHex) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Hash for SignatureHex {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl FromStr for SignatureHex {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<SignatureHex, Error> {
+        SignatureHex::try_from_str(s)
+    }
+}
+
+impl TryFrom<&str> for SignatureHex {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<SignatureHex, Error> {
+        SignatureHex::try_from_str(s)
+    }
+}
+
+impl TryFrom<String> for SignatureHex {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<SignatureHex, Error> {
+        SignatureHex::try_from_string(s)
+    }
 }
 
 impl From<Signature> for SignatureHex {
@@ -149,9 +236,119 @@ impl TryFrom<SignatureHex> for Signature {
     }
 }
 
+#[cfg(feature = "speedy")]
+impl<'a, C: Context> Readable<'a, C> for SignatureHex {
+    #[inline]
+    fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
+        let s = String::read_from(reader)?;
+        let sighex = SignatureHex::try_from_string(s).map_err(|e| speedy::Error::custom(e))?;
+        Ok(sighex)
+    }
+}
+
+impl Se for SignatureHex {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> De<'de> for SignatureHex {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(SignatureHexVisitor)
+    }
+}
+
+struct SignatureHexVisitor;
+
+impl Visitor<'_> for SignatureHexVisitor {
+    type Value = SignatureHex;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a lowercase hexadecimal string representing 64 bytes")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<SignatureHex, E>
+    where
+        E: serde::de::Error,
+    {
+        if v.len() != 128 {
+            return Err(DeserializeError::custom(
+                "SignatureHex is not 128 characters long",
+            ));
+        }
+        if !is_lowercase_hex(v) {
+            return Err(DeserializeError::custom(
+                "SignatureHex is not lowercase hex",
+            ));
+        }
+
+        Ok(SignatureHex(v.to_owned()))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     test_serde! {Signature, test_signature_serde}
+    test_serde! {SignatureHex, test_signature_hex_serde}
+
+    #[test]
+    fn test_signature_bytes() {
+        let sig = Signature::mock();
+        assert_eq!(Signature::from_bytes(*sig.as_bytes()), sig);
+        assert_eq!(Signature::try_from(&sig.as_bytes()[..]).unwrap(), sig);
+        assert!(Signature::try_from(&sig.as_bytes()[..63]).is_err());
+        assert_eq!(
+            Signature::try_from_hex_string(&sig.as_hex_string()).unwrap(),
+            sig
+        );
+        assert!(matches!(
+            Signature::try_from_hex_string("abcd"),
+            Err(Error::WrongLengthHexString)
+        ));
+        assert!(serde_json::from_str::<Signature>(r#""abcd""#).is_err());
+    }
+
+    #[test]
+    fn test_signature_hex() {
+        let sighex = SignatureHex::mock();
+        assert_eq!(SignatureHex::try_from_str(sighex.as_str()).unwrap(), sighex);
+        assert_eq!(sighex.as_str().parse::<SignatureHex>().unwrap(), sighex);
+        assert_eq!(
+            SignatureHex::try_from_str(&sighex.as_str().to_uppercase())
+                .unwrap_err()
+                .to_string(),
+            "Invalid Signature"
+        );
+        assert!(SignatureHex::try_from(sighex.as_str().get(..126).unwrap()).is_err());
+        assert!(SignatureHex::try_from("zz".repeat(64)).is_err());
+        assert!(serde_json::from_str::<SignatureHex>(r#""abcd""#).is_err());
+        assert_eq!(
+            Signature::try_from(sighex.clone()).unwrap(),
+            Signature::try_from_hex_string(sighex.as_str()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_signature_verify() {
+        let private_key = crate::PrivateKey::generate();
+        let pubkey = private_key.public_key();
+        let id = Id::mock();
+        let sig = private_key.sign_id(id).unwrap();
+        assert!(sig.verify(&pubkey, id).is_ok());
+        assert!(id.verify_signature(&pubkey, &sig).is_ok());
+        assert!(sig.verify(&PublicKey::mock(), id).is_err());
+        assert!(sig.verify(&pubkey, Event::mock().id).is_err());
+
+        let mut bad = sig;
+        bad.0[0] ^= 1;
+        assert!(bad.verify(&pubkey, id).is_err());
+    }
 }
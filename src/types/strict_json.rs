@@ -0,0 +1,144 @@
+//! Strict checks for incoming JSON, beyond what serde_json enforces
+
+use crate::Error;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::collections::HashSet;
+use std::fmt;
+
+/// Check that `json` contains no duplicate object keys and uses only the escape
+/// sequences NIP-01 serialization would produce, so that every implementation
+/// reads it the same way. The error says what was wrong and where.
+pub(crate) fn check_strict(json: &str) -> Result<(), Error> {
+    check_escapes(json)?;
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    if let Err(e) = NoDuplicateKeys::deserialize(&mut deserializer) {
+        return Err(Error::NonCanonicalJson(e.to_string()));
+    }
+    Ok(())
+}
+
+// Only \" \\ \n \r \t \b \f are canonical, plus \u00XX for the remaining
+// control characters which have no short form.
+fn check_escapes(json: &str) -> Result<(), Error> {
+    let bytes = json.as_bytes();
+    let mut in_string = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => in_string = !in_string,
+            b'\\' if in_string => {
+                let pos = i;
+                let ok = match bytes.get(i + 1) {
+                    Some(b'"' | b'\\' | b'n' | b'r' | b't' | b'b' | b'f') => true,
+                    Some(b'u') => {
+                        let value = json
+                            .get(i + 2..i + 6)
+                            .and_then(|h| u16::from_str_radix(h, 16).ok());
+                        i += 4;
+                        matches!(value, Some(c) if c < 0x20
+                            && !matches!(c, 0x08 | 0x09 | 0x0a | 0x0c | 0x0d))
+                    }
+                    _ => false,
+                };
+                if !ok {
+                    return Err(Error::NonCanonicalJson(format!(
+                        "non-canonical escape sequence at byte {pos}"
+                    )));
+                }
+                i += 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+// Walks any JSON value, failing on an object with a repeated key
+struct NoDuplicateKeys;
+
+impl<'de> Deserialize<'de> for NoDuplicateKeys {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(NoDuplicateKeysVisitor)
+    }
+}
+
+struct NoDuplicateKeysVisitor;
+
+impl<'de> Visitor<'de> for NoDuplicateKeysVisitor {
+    type Value = NoDuplicateKeys;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "any JSON value")
+    }
+
+    fn visit_bool<E>(self, _v: bool) -> Result<NoDuplicateKeys, E> {
+        Ok(NoDuplicateKeys)
+    }
+
+    fn visit_i64<E>(self, _v: i64) -> Result<NoDuplicateKeys, E> {
+        Ok(NoDuplicateKeys)
+    }
+
+    fn visit_u64<E>(self, _v: u64) -> Result<NoDuplicateKeys, E> {
+        Ok(NoDuplicateKeys)
+    }
+
+    fn visit_f64<E>(self, _v: f64) -> Result<NoDuplicateKeys, E> {
+        Ok(NoDuplicateKeys)
+    }
+
+    fn visit_str<E>(self, _v: &str) -> Result<NoDuplicateKeys, E> {
+        Ok(NoDuplicateKeys)
+    }
+
+    fn visit_unit<E>(self) -> Result<NoDuplicateKeys, E> {
+        Ok(NoDuplicateKeys)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<NoDuplicateKeys, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        while seq.next_element::<NoDuplicateKeys>()?.is_some() {}
+        Ok(NoDuplicateKeys)
+    }
+
+    fn visit_map<M>(self, mut map: M) -> Result<NoDuplicateKeys, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut keys: HashSet<String> = HashSet::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if keys.contains(&key) {
+                return Err(de::Error::custom(format!("duplicate key \"{key}\"")));
+            }
+            let _ = map.next_value::<NoDuplicateKeys>()?;
+            let _ = keys.insert(key);
+        }
+        Ok(NoDuplicateKeys)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check_strict() {
+        assert!(check_strict(r#"{"a":[1,"x\n\"\\"],"b":{"a":null}}"#).is_ok());
+        assert!(check_strict(r#"{"a":"\u0000\u001f"}"#).is_ok());
+        assert!(check_strict(r#"{"a":"A😀"}"#).is_ok());
+
+        assert!(check_strict(r#"{"a":1,"a":2}"#).is_err());
+        assert!(check_strict(r#"{"a":[{"b":1,"b":1}]}"#).is_err());
+        assert!(check_strict(r#"{"a":"\u0041"}"#).is_err());
+        assert!(check_strict(r#"{"a":"\u000a"}"#).is_err());
+        assert!(check_strict(r#"{"a":"\/"}"#).is_err());
+        assert!(check_strict(r#"{"a":"\ud83d\ude00"}"#).is_err());
+        assert!(check_strict(r#"{"a":NaN}"#).is_err());
+    }
+}
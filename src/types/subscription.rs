@@ -0,0 +1,317 @@
+use super::{ClientMessage, Filter, PublicKey, RelayMessage, SubscriptionId};
+
+/// Where a subscription is in its life with a relay
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SubscriptionState {
+    /// The REQ has been sent and stored events are arriving
+    Pending,
+
+    /// The relay has sent EOSE, so new events arrive as they are published
+    Live,
+
+    /// The relay closed the subscription. Its reason was in the CLOSED
+    /// message.
+    Closed,
+}
+
+/// A subscription to one relay
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Subscription {
+    /// The id, unique among the subscriptions on the connection
+    pub id: SubscriptionId,
+
+    /// The filters, any of which an event must match
+    pub filters: Vec<Filter>,
+
+    /// Where it is in its life
+    pub state: SubscriptionState,
+}
+
+impl Subscription {
+    /// The REQ message that opens (or replaces) this subscription
+    pub fn req(&self) -> ClientMessage {
+        ClientMessage::Req(self.id.clone(), self.filters.clone())
+    }
+}
+
+/// The subscriptions open on one relay connection. This does no networking:
+/// changes return the REQ and CLOSE messages needed to bring the relay in
+/// line, which the caller sends.
+///
+/// A REQ with the id of an open subscription replaces it, so a changed
+/// subscription needs only one REQ, and unchanged ones need nothing.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SubscriptionSet {
+    subscriptions: Vec<Subscription>,
+}
+
+impl SubscriptionSet {
+    /// An empty set
+    pub fn new() -> SubscriptionSet {
+        SubscriptionSet::default()
+    }
+
+    /// The subscription with this id
+    pub fn get(&self, id: &SubscriptionId) -> Option<&Subscription> {
+        self.subscriptions.iter().find(|s| &s.id == id)
+    }
+
+    /// All subscriptions
+    pub fn iter(&self) -> impl Iterator<Item = &Subscription> {
+        self.subscriptions.iter()
+    }
+
+    /// The number of subscriptions
+    pub fn len(&self) -> usize {
+        self.subscriptions.len()
+    }
+
+    /// Whether there are no subscriptions
+    pub fn is_empty(&self) -> bool {
+        self.subscriptions.is_empty()
+    }
+
+    /// Open or change a subscription. Returns a REQ unless it already has
+    /// these filters, and a CLOSE if `filters` is empty.
+    ///
+    /// One the relay closed is not sent again with the same filters, as the
+    /// relay would likely close it again; see `reopen()`.
+    pub fn set(&mut self, id: SubscriptionId, filters: Vec<Filter>) -> Option<ClientMessage> {
+        if filters.is_empty() {
+            return self.close(&id);
+        }
+        match self.subscriptions.iter_mut().find(|s| s.id == id) {
+            Some(sub) if sub.filters == filters => None,
+            Some(sub) => {
+                sub.filters = filters;
+                sub.state = SubscriptionState::Pending;
+                Some(sub.req())
+            }
+            None => {
+                let sub = Subscription {
+                    id,
+                    filters,
+                    state: SubscriptionState::Pending,
+                };
+                let req = sub.req();
+                self.subscriptions.push(sub);
+                Some(req)
+            }
+        }
+    }
+
+    /// Send a subscription the relay closed again, unchanged, such as after
+    /// authenticating. Returns a REQ unless it is not closed.
+    pub fn reopen(&mut self, id: &SubscriptionId) -> Option<ClientMessage> {
+        let sub = self
+            .subscriptions
+            .iter_mut()
+            .find(|s| &s.id == id && s.state == SubscriptionState::Closed)?;
+        sub.state = SubscriptionState::Pending;
+        Some(sub.req())
+    }
+
+    /// Close a subscription. Returns a CLOSE unless it was not open.
+    pub fn close(&mut self, id: &SubscriptionId) -> Option<ClientMessage> {
+        let index = self.subscriptions.iter().position(|s| &s.id == id)?;
+        let sub = self.subscriptions.remove(index);
+        if sub.state == SubscriptionState::Closed {
+            None
+        } else {
+            Some(ClientMessage::Close(sub.id))
+        }
+    }
+
+    /// Make the set match `desired`, returning the messages to get there:
+    /// a REQ for each new or changed subscription, and a CLOSE for each one
+    /// no longer wanted
+    pub fn sync(&mut self, desired: Vec<(SubscriptionId, Vec<Filter>)>) -> Vec<ClientMessage> {
+        let unwanted: Vec<SubscriptionId> = self
+            .subscriptions
+            .iter()
+            .filter(|s| !desired.iter().any(|(id, _)| id == &s.id))
+            .map(|s| s.id.clone())
+            .collect();
+        let mut messages: Vec<ClientMessage> = Vec::new();
+        for id in unwanted {
+            messages.extend(self.close(&id));
+        }
+        for (id, filters) in desired {
+            messages.extend(self.set(id, filters));
+        }
+        messages
+    }
+
+    /// Add someone to every filter that selects by author, as when the user
+    /// follows them. Returns a REQ for each subscription that changed.
+    pub fn follow(&mut self, pubkey: PublicKey) -> Vec<ClientMessage> {
+        self.update_authors(|filter| {
+            if !filter.authors.is_empty() {
+                filter.add_author(pubkey);
+            }
+            true
+        })
+    }
+
+    /// Remove someone from every filter that selects by author, as when the
+    /// user unfollows them. A filter left with no authors is dropped rather
+    /// than left to match everyone, and a subscription left with no filters
+    /// is closed.
+    pub fn unfollow(&mut self, pubkey: PublicKey) -> Vec<ClientMessage> {
+        self.update_authors(|filter| {
+            if filter.authors.is_empty() {
+                return true;
+            }
+            filter.del_author(pubkey);
+            !filter.authors.is_empty()
+        })
+    }
+
+    // Apply `update` to every filter of the subscriptions that select by
+    // author, dropping filters it returns false for. Others are left alone.
+    fn update_authors<F: Fn(&mut Filter) -> bool>(&mut self, update: F) -> Vec<ClientMessage> {
+        let desired: Vec<(SubscriptionId, Vec<Filter>)> = self
+            .subscriptions
+            .iter()
+            .filter(|sub| sub.filters.iter().any(|f| !f.authors.is_empty()))
+            .map(|sub| {
+                let mut filters = sub.filters.clone();
+                filters.retain_mut(&update);
+                (sub.id.clone(), filters)
+            })
+            .collect();
+        let mut messages: Vec<ClientMessage> = Vec::new();
+        for (id, filters) in desired {
+            messages.extend(self.set(id, filters));
+        }
+        messages
+    }
+
+    /// Track a message from the relay: EOSE makes a subscription live, and
+    /// CLOSED marks it closed
+    pub fn handle(&mut self, message: &RelayMessage) {
+        let (id, state) = match message {
+            RelayMessage::Eose(id) => (id, SubscriptionState::Live),
+            RelayMessage::Closed { subscription, .. } => (subscription, SubscriptionState::Closed),
+            _ => return,
+        };
+        if let Some(sub) = self.subscriptions.iter_mut().find(|s| &s.id == id) {
+            sub.state = state;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{EventKind, PrivateKey, RelayReason};
+
+    fn sub_id(s: &str) -> SubscriptionId {
+        SubscriptionId(s.to_owned())
+    }
+
+    #[test]
+    fn test_subscription_set() {
+        let mut set = SubscriptionSet::new();
+        let filter = Filter::new().kind(EventKind::Metadata);
+        let req = set.set(sub_id("a"), vec![filter.clone()]);
+        assert_eq!(
+            req,
+            Some(ClientMessage::Req(sub_id("a"), vec![filter.clone()]))
+        );
+        assert_eq!(set.set(sub_id("a"), vec![filter.clone()]), None);
+        assert_eq!(
+            set.get(&sub_id("a")).unwrap().state,
+            SubscriptionState::Pending
+        );
+
+        set.handle(&RelayMessage::Eose(sub_id("a")));
+        assert_eq!(
+            set.get(&sub_id("a")).unwrap().state,
+            SubscriptionState::Live
+        );
+
+        // A closed subscription is only sent again when asked to, or when
+        // its filters change, and is not closed again
+        set.handle(&RelayMessage::Closed {
+            subscription: sub_id("a"),
+            reason: RelayReason::from("error: shutting down"),
+        });
+        assert_eq!(set.set(sub_id("a"), vec![filter.clone()]), None);
+        assert_eq!(
+            set.reopen(&sub_id("a")),
+            Some(ClientMessage::Req(sub_id("a"), vec![filter.clone()]))
+        );
+        assert_eq!(set.reopen(&sub_id("a")), None);
+        set.handle(&RelayMessage::Closed {
+            subscription: sub_id("a"),
+            reason: RelayReason::from("error: shutting down"),
+        });
+        assert_eq!(set.close(&sub_id("a")), None);
+        assert!(set.is_empty());
+
+        let _ = set.set(sub_id("a"), vec![filter.clone()]);
+        let _ = set.set(sub_id("b"), vec![filter.clone()]);
+        let other = Filter::new().kind(EventKind::TextNote);
+        let messages = set.sync(vec![
+            (sub_id("b"), vec![filter.clone()]),
+            (sub_id("c"), vec![other.clone()]),
+        ]);
+        assert_eq!(
+            messages,
+            vec![
+                ClientMessage::Close(sub_id("a")),
+                ClientMessage::Req(sub_id("c"), vec![other]),
+            ]
+        );
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn test_follow_unfollow() {
+        let alice = PrivateKey::mock().public_key();
+        let bob = PrivateKey::generate().public_key();
+        let mut set = SubscriptionSet::new();
+        let feed = Filter::new().author(alice).kind(EventKind::TextNote);
+        let global = Filter::new().kind(EventKind::TextNote);
+        let _ = set.set(sub_id("feed"), vec![feed.clone()]);
+        let _ = set.set(sub_id("global"), vec![global]);
+
+        let messages = set.follow(bob);
+        let followed = feed.clone().author(bob);
+        assert_eq!(
+            messages,
+            vec![ClientMessage::Req(sub_id("feed"), vec![followed])]
+        );
+        assert!(set.follow(bob).is_empty());
+
+        // Subscriptions the relay closed are not sent again unless they
+        // select by author
+        let feed_closed = Filter::new().author(alice);
+        let _ = set.set(sub_id("closed"), vec![feed_closed.clone()]);
+        for id in ["global", "closed"] {
+            set.handle(&RelayMessage::Closed {
+                subscription: sub_id(id),
+                reason: RelayReason::from("auth-required: sign in first"),
+            });
+        }
+        let carol = PrivateKey::generate().public_key();
+        assert_eq!(
+            set.follow(carol),
+            vec![
+                ClientMessage::Req(sub_id("feed"), vec![feed.clone().author(bob).author(carol)]),
+                ClientMessage::Req(sub_id("closed"), vec![feed_closed.author(carol)]),
+            ]
+        );
+        let _ = set.unfollow(carol);
+        let _ = set.close(&sub_id("closed"));
+
+        let _ = set.unfollow(bob);
+        assert_eq!(set.get(&sub_id("feed")).unwrap().filters, vec![feed]);
+        assert_eq!(
+            set.unfollow(alice),
+            vec![ClientMessage::Close(sub_id("feed"))]
+        );
+        assert_eq!(set.len(), 1);
+    }
+}
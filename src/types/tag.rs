@@ -1,225 +1,601 @@
+use super::intern::{intern, intern_owned};
 use crate::{
-    DelegationConditions, EventKind, Id, PublicKeyHex, SignatureHex, UncheckedUrl, Unixtime,
+    DelegationConditions, Error, EventAddr, EventKind, ExternalIdentity, Id, IdentityPlatform,
+    PublicKey, PublicKeyHex, SignatureHex, UncheckedUrl, Unixtime,
 };
-use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
-use serde::ser::{Serialize, SerializeSeq, Serializer};
+use serde::de::{Deserializer, SeqAccess, Visitor};
+use serde::ser::{SerializeSeq, Serializer};
+use serde::{Deserialize, Serialize};
 #[cfg(feature = "speedy")]
-use speedy::{Readable, Writable};
+use speedy::{Context, Readable, Reader, Writable, Writer};
 use std::fmt;
+use std::sync::Arc;
 
 /// A tag on an Event
-#[derive(Clone, Debug, Eq, PartialEq)]
-#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
-pub enum Tag {
-    /// Address 'a' tag to a parameterized replaceable event
-    Address {
-        /// EventKind
-        kind: EventKind,
-
-        /// Author
-        pubkey: PublicKeyHex,
+///
+/// This is kept in exactly the form it takes on the wire so that events always
+/// re-serialize byte-for-byte as they were signed. Use the `new_*` functions to
+/// build well-known tags and the `parse_*` functions to interpret them.
+///
+/// Tag names, markers and relay URLs are shared between tags rather than
+/// each tag holding its own copy, to save memory when holding many events.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Tag(Vec<Arc<str>>);
 
-        /// d-tag identifier
-        d: String,
+impl Tag {
+    /// Create a new tag from its fields, the first being the tag name
+    pub fn new(fields: &[&str]) -> Tag {
+        Tag(fields.iter().map(|f| intern(f)).collect())
+    }
 
-        /// Relay URL
-        relay_url: Option<UncheckedUrl>,
+    /// Create a new tag from owned fields, the first being the tag name
+    pub fn from_strings(fields: Vec<String>) -> Tag {
+        Tag(fields.into_iter().map(intern_owned).collect())
+    }
 
-        /// Trailing
-        trailing: Vec<String>,
-    },
+    /// Get the fields of the tag, the first being the tag name
+    pub fn fields(&self) -> &[Arc<str>] {
+        &self.0
+    }
 
-    /// Content Warning to alert client to hide content until user approves
-    ContentWarning {
-        /// Content warning
-        warning: String,
+    /// Consume the tag, returning its fields
+    pub fn into_inner(self) -> Vec<String> {
+        self.0.iter().map(|f| f.to_string()).collect()
+    }
 
-        /// Trailing
-        trailing: Vec<String>,
-    },
+    /// Get the field at `index`, or an empty string if there is none
+    pub fn get_index(&self, index: usize) -> &str {
+        self.0.get(index).map(|s| &**s).unwrap_or("")
+    }
 
-    /// Delegation (Delegated Event Signing)
-    Delegation {
-        /// Public key of the delegator
-        pubkey: PublicKeyHex,
+    /// Set the field at `index`, padding with empty strings if needed
+    pub fn set_index(&mut self, index: usize, value: String) {
+        while self.0.len() <= index {
+            self.0.push(intern(""));
+        }
+        self.0[index] = intern_owned(value);
+    }
 
-        /// Conditions query string
-        conditions: DelegationConditions,
+    /// Append a field to the end of the tag
+    pub fn push_value(&mut self, value: String) {
+        self.0.push(intern_owned(value));
+    }
+
+    /// Get the tag name for the tag (the first string in the array)
+    pub fn tagname(&self) -> &str {
+        self.get_index(0)
+    }
+
+    /// Get the main value of the tag (the second string in the array)
+    pub fn value(&self) -> &str {
+        self.get_index(1)
+    }
+
+    /// Is the tag empty (having not even a tag name)?
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 
-        /// 64-byte schnorr signature of the sha256 hash of the delegation token
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> Tag {
+        Tag::new_event(Id::mock(), Some(UncheckedUrl::mock()), None)
+    }
+
+    fn expect(&self, tagname: &str, min_len: usize) -> Result<(), Error> {
+        if self.tagname() != tagname {
+            return Err(Error::TagMismatch);
+        }
+        if self.0.len() < min_len {
+            return Err(Error::TagMissingField(self.0.len()));
+        }
+        Ok(())
+    }
+
+    fn optional(&self, index: usize) -> Option<&str> {
+        match self.get_index(index) {
+            "" => None,
+            s => Some(s),
+        }
+    }
+
+    fn optional_url(&self, index: usize) -> Option<UncheckedUrl> {
+        self.optional(index).map(UncheckedUrl::from_str)
+    }
+
+    fn with_optionals(mut self, optionals: &[Option<&str>]) -> Tag {
+        // Only write out as many optional fields as are needed
+        if let Some(last) = optionals.iter().rposition(|o| o.is_some()) {
+            for o in &optionals[..=last] {
+                self.0.push(intern(o.unwrap_or("")));
+            }
+        }
+        self
+    }
+
+    /// Create an 'a' address tag, `["a", <address>, <relay>, <marker>]`.
+    /// There is room for one relay hint, so only the first of the address's
+    /// relays is written; pass any others separately, such as in an naddr.
+    pub fn new_address(ea: &EventAddr, marker: Option<String>) -> Tag {
+        Tag::new(&["a", &address_string(ea)])
+            .with_optionals(&[ea.relays.first().map(|u| u.as_str()), marker.as_deref()])
+    }
+
+    /// Parse an 'a' address tag, `["a", <address>, <relay>, <marker>]`,
+    /// returning the address and any marker. Any later fields are ignored.
+    pub fn parse_address(&self) -> Result<(EventAddr, Option<String>), Error> {
+        self.expect("a", 2)?;
+        let ea = parse_address_string(self.value(), self.optional_url(2))?;
+        let marker = self.optional(3).map(|m| m.to_owned());
+        Ok((ea, marker))
+    }
+
+    /// Create an 'A' root address tag (NIP-22)
+    pub fn new_root_address(ea: &EventAddr) -> Tag {
+        Tag::new(&["A", &address_string(ea)])
+            .with_optionals(&[ea.relays.first().map(|u| u.as_str())])
+    }
+
+    /// Parse an 'A' root address tag (NIP-22)
+    pub fn parse_root_address(&self) -> Result<EventAddr, Error> {
+        self.expect("A", 2)?;
+        parse_address_string(self.value(), self.optional_url(2))
+    }
+
+    /// Create an 'alt' tag (NIP-31), a short description of the event for
+    /// clients that do not know its kind
+    pub fn new_alt(description: String) -> Tag {
+        Tag::from_strings(vec!["alt".to_owned(), description])
+    }
+
+    /// Parse an 'alt' tag (NIP-31)
+    pub fn parse_alt(&self) -> Result<String, Error> {
+        self.expect("alt", 2)?;
+        Ok(self.value().to_owned())
+    }
+
+    /// Create a 'content-warning' tag
+    pub fn new_content_warning(warning: Option<String>) -> Tag {
+        Tag::new(&["content-warning"]).with_optionals(&[warning.as_deref()])
+    }
+
+    /// Parse a 'content-warning' tag, returning the reason if any
+    pub fn parse_content_warning(&self) -> Result<Option<String>, Error> {
+        self.expect("content-warning", 1)?;
+        Ok(self.optional(1).map(|s| s.to_owned()))
+    }
+
+    /// Create a 'delegation' tag
+    pub fn new_delegation(
+        pubkey: PublicKeyHex,
+        conditions: DelegationConditions,
         sig: SignatureHex,
+    ) -> Tag {
+        Tag::new(&[
+            "delegation",
+            pubkey.as_str(),
+            &conditions.as_string(),
+            sig.as_str(),
+        ])
+    }
 
-        /// Trailing
-        trailing: Vec<String>,
-    },
+    /// Parse a 'delegation' tag
+    pub fn parse_delegation(
+        &self,
+    ) -> Result<(PublicKeyHex, DelegationConditions, SignatureHex), Error> {
+        self.expect("delegation", 4)?;
+        let pubkey = PublicKeyHex::try_from_str(self.get_index(1))?;
+        let conditions = DelegationConditions::try_from_str(self.get_index(2))?;
+        let sig = SignatureHex::try_from_str(self.get_index(3))?;
+        Ok((pubkey, conditions, sig))
+    }
 
-    /// This is a reference to an event, where the first string is the event Id.
-    /// The second string is defined in NIP-01 as an optional URL, but subsequent
-    /// 'e' NIPs define more data and interpretations.
-    Event {
-        /// The Id of some other event that this event refers to
+    /// Create an 'e' event tag
+    pub fn new_event(
         id: Id,
-
-        /// A recommended relay URL to find that other event
         recommended_relay_url: Option<UncheckedUrl>,
+        marker: Option<String>,
+    ) -> Tag {
+        Tag::new(&["e", &id.as_hex_string()]).with_optionals(&[
+            recommended_relay_url.as_ref().map(|u| u.as_str()),
+            marker.as_deref(),
+        ])
+    }
 
-        /// A marker (commonly things like 'reply')
+    /// Create an 'e' event tag that also names the event's author, as the
+    /// fifth element (NIP-10): `["e", <id>, <relay>, <marker>, <pubkey>]`
+    pub fn new_event_with_author(
+        id: Id,
+        recommended_relay_url: Option<UncheckedUrl>,
         marker: Option<String>,
+        author: Option<PublicKeyHex>,
+    ) -> Tag {
+        Tag::new(&["e", &id.as_hex_string()]).with_optionals(&[
+            recommended_relay_url.as_ref().map(|u| u.as_str()),
+            marker.as_deref(),
+            author.as_ref().map(|pk| pk.as_str()),
+        ])
+    }
 
-        /// Trailing
-        trailing: Vec<String>,
-    },
+    /// Parse an 'e' event tag, returning the id, recommended relay url, and marker
+    pub fn parse_event(&self) -> Result<(Id, Option<UncheckedUrl>, Option<String>), Error> {
+        self.expect("e", 2)?;
+        let id = Id::try_from_hex_string(self.value())?;
+        Ok((
+            id,
+            self.optional_url(2),
+            self.optional(3).map(|s| s.to_owned()),
+        ))
+    }
 
-    /// A time when the event should be considered expired
-    Expiration {
-        /// Expiration Time
-        time: Unixtime,
+    /// Create an 'E' root event tag (NIP-22)
+    pub fn new_root_event(
+        id: Id,
+        recommended_relay_url: Option<UncheckedUrl>,
+        pubkey: Option<PublicKeyHex>,
+    ) -> Tag {
+        Tag::new(&["E", &id.as_hex_string()]).with_optionals(&[
+            recommended_relay_url.as_ref().map(|u| u.as_str()),
+            pubkey.as_ref().map(|pk| pk.as_str()),
+        ])
+    }
 
-        /// Trailing
-        trailing: Vec<String>,
-    },
+    /// Parse an 'E' root event tag (NIP-22), returning the id, recommended relay
+    /// url, and author
+    pub fn parse_root_event(
+        &self,
+    ) -> Result<(Id, Option<UncheckedUrl>, Option<PublicKeyHex>), Error> {
+        self.expect("E", 2)?;
+        let id = Id::try_from_hex_string(self.value())?;
+        let pubkey = match self.optional(3) {
+            Some(pk) => Some(PublicKeyHex::try_from_str(pk)?),
+            None => None,
+        };
+        Ok((id, self.optional_url(2), pubkey))
+    }
 
-    /// 'p' This is a reference to a user by public key, where the first string is
-    /// the PublicKey. The second string is defined in NIP-01 as an optional URL,
-    /// but subsqeuent NIPs define more data and interpretations.
-    Pubkey {
-        /// The public key of the identity that this event refers to
+    /// Create an 'expiration' tag
+    pub fn new_expiration(time: Unixtime) -> Tag {
+        Tag::new(&["expiration", &time.0.to_string()])
+    }
+
+    /// Parse an 'expiration' tag
+    pub fn parse_expiration(&self) -> Result<Unixtime, Error> {
+        self.expect("expiration", 2)?;
+        Ok(Unixtime(self.value().parse::<i64>()?))
+    }
+
+    /// Create a 'g' geohash tag
+    pub fn new_geohash(geohash: String) -> Tag {
+        Tag::from_strings(vec!["g".to_owned(), geohash])
+    }
+
+    /// Parse a 'g' geohash tag
+    pub fn parse_geohash(&self) -> Result<String, Error> {
+        self.expect("g", 2)?;
+        Ok(self.value().to_owned())
+    }
+
+    /// The size in meters, east-west then north-south, of a geohash cell with
+    /// `precision` characters. This is at the equator; cells get narrower
+    /// towards the poles.
+    pub fn geohash_cell_size(precision: usize) -> (f64, f64) {
+        const METERS_PER_DEGREE: f64 = 111_320.0;
+        let bits = 5 * precision.min(12) as i32;
+        let lon_bits = (bits + 1) / 2;
+        let lat_bits = bits / 2;
+        (
+            360.0 / 2f64.powi(lon_bits) * METERS_PER_DEGREE,
+            180.0 / 2f64.powi(lat_bits) * METERS_PER_DEGREE,
+        )
+    }
+
+    /// The longest geohash precision, from 1 to 12, whose cells are at least
+    /// `radius_meters` across in both directions.
+    ///
+    /// To find everything within the radius of a point, filter on the 'g' tag
+    /// prefixes of this length for the point's cell and its neighbours.
+    pub fn geohash_precision_for_radius(radius_meters: f64) -> usize {
+        (1..=12)
+            .rev()
+            .find(|&p| {
+                let (w, h) = Tag::geohash_cell_size(p);
+                w.min(h) >= radius_meters
+            })
+            .unwrap_or(1)
+    }
+
+    /// Create a 't' hashtag tag
+    pub fn new_hashtag(hashtag: String) -> Tag {
+        Tag::from_strings(vec!["t".to_owned(), hashtag])
+    }
+
+    /// Parse a 't' hashtag tag
+    pub fn parse_hashtag(&self) -> Result<String, Error> {
+        self.expect("t", 2)?;
+        Ok(self.value().to_owned())
+    }
+
+    /// Create an 'i' external identity tag (NIP-39)
+    pub fn new_external_identity(identity: &ExternalIdentity) -> Tag {
+        Tag::new(&[
+            "i",
+            &format!("{}:{}", identity.platform, identity.identity),
+            &identity.proof,
+        ])
+    }
+
+    /// Parse an 'i' external identity tag (NIP-39)
+    pub fn parse_external_identity(&self) -> Result<ExternalIdentity, Error> {
+        self.expect("i", 3)?;
+        let (platform, identity) = self
+            .value()
+            .split_once(':')
+            .filter(|(platform, identity)| !platform.is_empty() && !identity.is_empty())
+            .ok_or_else(|| Error::InvalidExternalIdentity(self.value().to_owned()))?;
+        Ok(ExternalIdentity {
+            platform: IdentityPlatform::parse(platform),
+            identity: identity.to_owned(),
+            proof: self.get_index(2).to_owned(),
+        })
+    }
+
+    /// Create a 'd' identifier tag
+    pub fn new_identifier(d: String) -> Tag {
+        Tag::from_strings(vec!["d".to_owned(), d])
+    }
+
+    /// Parse a 'd' identifier tag. A missing value is an implicit empty string.
+    pub fn parse_identifier(&self) -> Result<String, Error> {
+        self.expect("d", 1)?;
+        Ok(self.value().to_owned())
+    }
+
+    /// Create a 'k' kind tag (NIP-22)
+    pub fn new_kind(kind: EventKind) -> Tag {
+        Tag::new(&["k", &u32::from(kind).to_string()])
+    }
+
+    /// Parse a 'k' kind tag (NIP-22)
+    pub fn parse_kind(&self) -> Result<EventKind, Error> {
+        self.expect("k", 2)?;
+        Ok(self.value().parse::<u32>()?.into())
+    }
+
+    /// Create a 'K' root kind tag (NIP-22)
+    pub fn new_root_kind(kind: EventKind) -> Tag {
+        Tag::new(&["K", &u32::from(kind).to_string()])
+    }
+
+    /// Parse a 'K' root kind tag (NIP-22)
+    pub fn parse_root_kind(&self) -> Result<EventKind, Error> {
+        self.expect("K", 2)?;
+        Ok(self.value().parse::<u32>()?.into())
+    }
+
+    /// Create an 'l' label tag (NIP-32)
+    pub fn new_label(label: String, namespace: Option<String>) -> Tag {
+        Tag::from_strings(vec!["l".to_owned(), label]).with_optionals(&[namespace.as_deref()])
+    }
+
+    /// Parse an 'l' label tag (NIP-32), returning the label and its namespace
+    pub fn parse_label(&self) -> Result<(String, Option<String>), Error> {
+        self.expect("l", 2)?;
+        Ok((
+            self.value().to_owned(),
+            self.optional(2).map(|s| s.to_owned()),
+        ))
+    }
+
+    /// Create an 'L' label namespace tag (NIP-32)
+    pub fn new_label_namespace(namespace: String) -> Tag {
+        Tag::from_strings(vec!["L".to_owned(), namespace])
+    }
+
+    /// Parse an 'L' label namespace tag (NIP-32)
+    pub fn parse_label_namespace(&self) -> Result<String, Error> {
+        self.expect("L", 2)?;
+        Ok(self.value().to_owned())
+    }
+
+    /// Create a 'nonce' tag for proof of work
+    pub fn new_nonce(nonce: u64, target: Option<u32>) -> Tag {
+        Tag::new(&["nonce", &nonce.to_string()])
+            .with_optionals(&[target.map(|t| t.to_string()).as_deref()])
+    }
+
+    /// Parse a 'nonce' tag, returning the nonce and target number of bits.
+    ///
+    /// The target belongs in the third field, but some clients put other fields
+    /// first, so if that is empty or not a number the last field is tried.
+    pub fn parse_nonce(&self) -> Result<(u64, Option<u32>), Error> {
+        self.expect("nonce", 2)?;
+        let nonce = self.value().trim().parse::<u64>()?;
+        let third = self.get_index(2).trim();
+        let target = match third.parse::<u32>() {
+            Ok(t) => Some(t),
+            Err(e) => {
+                let last = match self.0.len() {
+                    0..=3 => None,
+                    _ => self.0.last().and_then(|l| l.trim().parse::<u32>().ok()),
+                };
+                match last {
+                    Some(t) => Some(t),
+                    None if third.is_empty() => None,
+                    None => return Err(e.into()),
+                }
+            }
+        };
+        Ok((nonce, target))
+    }
+
+    /// Create a 'parameter' tag
+    pub fn new_parameter(param: String) -> Tag {
+        Tag::from_strings(vec!["parameter".to_owned(), param])
+    }
+
+    /// Parse a 'parameter' tag. A missing value is an implicit empty string.
+    pub fn parse_parameter(&self) -> Result<String, Error> {
+        self.expect("parameter", 1)?;
+        Ok(self.value().to_owned())
+    }
+
+    /// Create a '-' protected tag (NIP-70)
+    pub fn new_protected() -> Tag {
+        Tag::new(&["-"])
+    }
+
+    /// Is this a '-' protected tag (NIP-70)?
+    pub fn is_protected(&self) -> bool {
+        self.tagname() == "-"
+    }
+
+    /// Create a 'p' pubkey tag
+    pub fn new_pubkey(
         pubkey: PublicKeyHex,
+        recommended_relay_url: Option<UncheckedUrl>,
+        petname: Option<String>,
+    ) -> Tag {
+        Tag::new(&["p", pubkey.as_str()]).with_optionals(&[
+            recommended_relay_url.as_ref().map(|u| u.as_str()),
+            petname.as_deref(),
+        ])
+    }
+
+    /// Parse a 'p' pubkey tag, returning the public key, recommended relay url, and
+    /// petname
+    pub fn parse_pubkey(
+        &self,
+    ) -> Result<(PublicKeyHex, Option<UncheckedUrl>, Option<String>), Error> {
+        self.expect("p", 2)?;
+        let pubkey = PublicKeyHex::try_from_str(self.value())?;
+        Ok((
+            pubkey,
+            self.optional_url(2),
+            self.optional(3).map(|s| s.to_owned()),
+        ))
+    }
 
-        /// A recommended relay URL to find information on that public key
+    /// Parse a 'p' pubkey tag like `parse_pubkey()`, but also require the key
+    /// to be an actual x-only point on the curve. Garbage keys that are merely
+    /// 64 hex digits are rejected, with the curve error as the source.
+    pub fn parse_pubkey_strict(
+        &self,
+    ) -> Result<(PublicKey, Option<UncheckedUrl>, Option<String>), Error> {
+        let (pubkey, relay, petname) = self.parse_pubkey()?;
+        let pubkey =
+            PublicKey::try_from(&pubkey).map_err(|e| e.context("Invalid 'p' tag pubkey"))?;
+        Ok((pubkey, relay, petname))
+    }
+
+    /// Create a 'P' root pubkey tag (NIP-22)
+    pub fn new_root_pubkey(
+        pubkey: PublicKeyHex,
         recommended_relay_url: Option<UncheckedUrl>,
+    ) -> Tag {
+        Tag::new(&["P", pubkey.as_str()])
+            .with_optionals(&[recommended_relay_url.as_ref().map(|u| u.as_str())])
+    }
 
-        /// A petname given to this identity by the event author
-        petname: Option<String>,
+    /// Parse a 'P' root pubkey tag (NIP-22)
+    pub fn parse_root_pubkey(&self) -> Result<(PublicKeyHex, Option<UncheckedUrl>), Error> {
+        self.expect("P", 2)?;
+        let pubkey = PublicKeyHex::try_from_str(self.value())?;
+        Ok((pubkey, self.optional_url(2)))
+    }
 
-        /// Trailing
-        trailing: Vec<String>,
-    },
+    /// Create a 'published_at' tag, the time a long-form article (NIP-23) was
+    /// first published
+    pub fn new_published_at(time: Unixtime) -> Tag {
+        Tag::new(&["published_at", &time.0.to_string()])
+    }
 
-    /// 't' A hashtag
-    Hashtag {
-        /// Hashtag
-        hashtag: String,
+    /// Parse a 'published_at' tag
+    pub fn parse_published_at(&self) -> Result<Unixtime, Error> {
+        self.expect("published_at", 2)?;
+        Ok(Unixtime(self.value().parse::<i64>()?))
+    }
 
-        /// Trailing
-        trailing: Vec<String>,
-    },
+    /// Create a 'q' quote tag (NIP-18)
+    pub fn new_quote(id: Id, relay_url: Option<UncheckedUrl>, pubkey: Option<PublicKeyHex>) -> Tag {
+        Tag::new(&["q", &id.as_hex_string()]).with_optionals(&[
+            relay_url.as_ref().map(|u| u.as_str()),
+            pubkey.as_ref().map(|pk| pk.as_str()),
+        ])
+    }
 
-    /// 'r' A reference to a URL
-    Reference {
-        /// A relay url
-        url: UncheckedUrl,
+    /// Parse a 'q' quote tag (NIP-18), returning the id, relay url, and author
+    pub fn parse_quote(&self) -> Result<(Id, Option<UncheckedUrl>, Option<PublicKeyHex>), Error> {
+        self.expect("q", 2)?;
+        let id = Id::try_from_hex_string(self.value())?;
+        let pubkey = match self.optional(3) {
+            Some(pk) => Some(PublicKeyHex::try_from_str(pk)?),
+            None => None,
+        };
+        Ok((id, self.optional_url(2), pubkey))
+    }
 
-        /// An optional marker
-        marker: Option<String>,
+    /// Create an 'r' reference tag
+    pub fn new_reference(url: UncheckedUrl, marker: Option<String>) -> Tag {
+        Tag::new(&["r", url.as_str()]).with_optionals(&[marker.as_deref()])
+    }
 
-        /// Trailing
-        trailing: Vec<String>,
-    },
-
-    /// 'g' A geohash
-    Geohash {
-        /// A geohash
-        geohash: String,
-
-        /// Trailing
-        trailing: Vec<String>,
-    },
-
-    /// 'd' Identifier tag
-    Identifier {
-        /// 'd' indentifier
-        d: String,
-
-        /// Trailing
-        trailing: Vec<String>,
-    },
-
-    /// A subject. The first string is the subject. Should only be in TextNote events.
-    Subject {
-        /// The subject
-        subject: String,
-
-        /// Trailing
-        trailing: Vec<String>,
-    },
-
-    /// A nonce tag for Proof of Work
-    Nonce {
-        /// A random number that makes the event hash meet the proof of work required
-        nonce: String,
-
-        /// The target number of bits for the proof of work
-        target: Option<String>,
-
-        /// Trailing
-        trailing: Vec<String>,
-    },
-
-    /// Parameter of a parameterized replaceable event
-    Parameter {
-        /// Parameter
-        param: String,
-
-        /// Trailing
-        trailing: Vec<String>,
-    },
-
-    /// Title (30023 long form)
-    Title {
-        /// Title
-        title: String,
-
-        /// Trailing
-        trailing: Vec<String>,
-    },
-
-    /// Any other tag
-    Other {
-        /// The tag name
-        tag: String,
-
-        /// The subsequent fields
-        data: Vec<String>,
-    },
-
-    /// An empty array (kept so signature remains valid across ser/de)
-    Empty,
-}
+    /// Parse an 'r' reference tag, returning the url and marker
+    pub fn parse_reference(&self) -> Result<(UncheckedUrl, Option<String>), Error> {
+        self.expect("r", 2)?;
+        Ok((
+            UncheckedUrl::from_str(self.value()),
+            self.optional(2).map(|s| s.to_owned()),
+        ))
+    }
 
-impl Tag {
-    /// Get the tag name for the tag (the first string in the array)a
-    pub fn tagname(&self) -> String {
-        match self {
-            Tag::Address { .. } => "address".to_string(),
-            Tag::ContentWarning { .. } => "content-warning".to_string(),
-            Tag::Delegation { .. } => "delegation".to_string(),
-            Tag::Event { .. } => "e".to_string(),
-            Tag::Expiration { .. } => "expiration".to_string(),
-            Tag::Pubkey { .. } => "p".to_string(),
-            Tag::Hashtag { .. } => "t".to_string(),
-            Tag::Reference { .. } => "r".to_string(),
-            Tag::Geohash { .. } => "g".to_string(),
-            Tag::Identifier { .. } => "d".to_string(),
-            Tag::Subject { .. } => "subject".to_string(),
-            Tag::Nonce { .. } => "nonce".to_string(),
-            Tag::Parameter { .. } => "parameter".to_string(),
-            Tag::Title { .. } => "title".to_string(),
-            Tag::Other { tag, .. } => tag.clone(),
-            Tag::Empty => panic!("empty tags have no tagname"),
-        }
+    /// Create a 'subject' tag
+    pub fn new_subject(subject: String) -> Tag {
+        Tag::from_strings(vec!["subject".to_owned(), subject])
     }
 
-    // Mock data for testing
-    #[allow(dead_code)]
-    pub(crate) fn mock() -> Tag {
-        Tag::Event {
-            id: Id::mock(),
-            recommended_relay_url: Some(UncheckedUrl::mock()),
-            marker: None,
-            trailing: Vec::new(),
-        }
+    /// Parse a 'subject' tag
+    pub fn parse_subject(&self) -> Result<String, Error> {
+        self.expect("subject", 2)?;
+        Ok(self.value().to_owned())
+    }
+
+    /// Create a 'title' tag
+    pub fn new_title(title: String) -> Tag {
+        Tag::from_strings(vec!["title".to_owned(), title])
     }
+
+    /// Parse a 'title' tag
+    pub fn parse_title(&self) -> Result<String, Error> {
+        self.expect("title", 2)?;
+        Ok(self.value().to_owned())
+    }
+}
+
+fn address_string(ea: &EventAddr) -> String {
+    format!(
+        "{}:{}:{}",
+        u32::from(ea.kind),
+        ea.author.as_hex_string(),
+        ea.d
+    )
+}
+
+fn parse_address_string(s: &str, relay_url: Option<UncheckedUrl>) -> Result<EventAddr, Error> {
+    let mut parts = s.splitn(3, ':');
+    let kind: u32 = parts
+        .next()
+        .ok_or(Error::InvalidEventAddr)?
+        .parse::<u32>()
+        .map_err(|e| Error::from(e).context("Invalid event addr kind"))?;
+    let author = PublicKey::try_from_hex_string(parts.next().ok_or(Error::InvalidEventAddr)?)?;
+    let d = parts.next().ok_or(Error::InvalidEventAddr)?;
+    Ok(EventAddr {
+        d: d.to_owned(),
+        relays: relay_url.into_iter().collect(),
+        kind: kind.into(),
+        author,
+    })
 }
 
 impl Serialize for Tag {
@@ -227,219 +603,16 @@ impl Serialize for Tag {
     where
         S: Serializer,
     {
-        match self {
-            Tag::Address {
-                kind,
-                pubkey,
-                d,
-                relay_url,
-                trailing,
-            } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("a")?;
-                let k: u32 = From::from(*kind);
-                let s = format!("{}:{}:{}", k, pubkey, d);
-                seq.serialize_element(&s)?;
-                if let Some(ru) = relay_url {
-                    seq.serialize_element(ru)?;
-                } else if !trailing.is_empty() {
-                    seq.serialize_element("")?;
-                }
-                for s in trailing {
-                    seq.serialize_element(s)?;
-                }
-                seq.end()
-            }
-            Tag::ContentWarning { warning, trailing } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("content-warning")?;
-                seq.serialize_element(warning)?;
-                for s in trailing {
-                    seq.serialize_element(s)?;
-                }
-                seq.end()
-            }
-            Tag::Delegation {
-                pubkey,
-                conditions,
-                sig,
-                trailing,
-            } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("delegation")?;
-                seq.serialize_element(pubkey)?;
-                seq.serialize_element(conditions)?;
-                seq.serialize_element(sig)?;
-                for s in trailing {
-                    seq.serialize_element(s)?;
-                }
-                seq.end()
-            }
-            Tag::Event {
-                id,
-                recommended_relay_url,
-                marker,
-                trailing,
-            } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("e")?;
-                seq.serialize_element(id)?;
-                if let Some(rru) = recommended_relay_url {
-                    seq.serialize_element(rru)?;
-                } else if marker.is_some() || !trailing.is_empty() {
-                    seq.serialize_element("")?;
-                }
-                if let Some(m) = marker {
-                    seq.serialize_element(m)?;
-                } else if !trailing.is_empty() {
-                    seq.serialize_element("")?;
-                }
-                for s in trailing {
-                    seq.serialize_element(s)?;
-                }
-                seq.end()
-            }
-            Tag::Expiration { time, trailing } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("expiration")?;
-                seq.serialize_element(time)?;
-                for s in trailing {
-                    seq.serialize_element(s)?;
-                }
-                seq.end()
-            }
-            Tag::Pubkey {
-                pubkey,
-                recommended_relay_url,
-                petname,
-                trailing,
-            } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("p")?;
-                seq.serialize_element(pubkey)?;
-                if let Some(rru) = recommended_relay_url {
-                    seq.serialize_element(rru)?;
-                } else if petname.is_some() || !trailing.is_empty() {
-                    seq.serialize_element("")?;
-                }
-                if let Some(pn) = petname {
-                    seq.serialize_element(pn)?;
-                } else if !trailing.is_empty() {
-                    seq.serialize_element("")?;
-                }
-                for s in trailing {
-                    seq.serialize_element(s)?;
-                }
-                seq.end()
-            }
-            Tag::Hashtag { hashtag, trailing } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("t")?;
-                seq.serialize_element(hashtag)?;
-                for s in trailing {
-                    seq.serialize_element(s)?;
-                }
-                seq.end()
-            }
-            Tag::Reference {
-                url,
-                marker,
-                trailing,
-            } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("r")?;
-                seq.serialize_element(url)?;
-                if let Some(m) = marker {
-                    seq.serialize_element(m)?
-                } else if !trailing.is_empty() {
-                    seq.serialize_element("")?
-                }
-                for s in trailing {
-                    seq.serialize_element(s)?;
-                }
-                seq.end()
-            }
-            Tag::Geohash { geohash, trailing } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("g")?;
-                seq.serialize_element(geohash)?;
-                for s in trailing {
-                    seq.serialize_element(s)?;
-                }
-                seq.end()
-            }
-            Tag::Identifier { d, trailing } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("d")?;
-                seq.serialize_element(d)?;
-                for s in trailing {
-                    seq.serialize_element(s)?;
-                }
-                seq.end()
-            }
-            Tag::Subject { subject, trailing } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("subject")?;
-                seq.serialize_element(subject)?;
-                for s in trailing {
-                    seq.serialize_element(s)?;
-                }
-                seq.end()
-            }
-            Tag::Nonce {
-                nonce,
-                target,
-                trailing,
-            } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("nonce")?;
-                seq.serialize_element(nonce)?;
-                if let Some(t) = target {
-                    seq.serialize_element(t)?;
-                } else if !trailing.is_empty() {
-                    seq.serialize_element("")?;
-                }
-                for s in trailing {
-                    seq.serialize_element(s)?;
-                }
-                seq.end()
-            }
-            Tag::Parameter { param, trailing } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("parameter")?;
-                seq.serialize_element(param)?;
-                for s in trailing {
-                    seq.serialize_element(s)?;
-                }
-                seq.end()
-            }
-            Tag::Title { title, trailing } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element("title")?;
-                seq.serialize_element(title)?;
-                for s in trailing {
-                    seq.serialize_element(s)?;
-                }
-                seq.end()
-            }
-            Tag::Other { tag, data } => {
-                let mut seq = serializer.serialize_seq(None)?;
-                seq.serialize_element(tag)?;
-                for s in data.iter() {
-                    seq.serialize_element(s)?;
-                }
-                seq.end()
-            }
-            Tag::Empty => {
-                let seq = serializer.serialize_seq(Some(0))?;
-                seq.end()
-            }
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for field in &self.0 {
+            seq.serialize_element(&**field)?;
         }
+        seq.end()
     }
 }
 
 impl<'de> Deserialize<'de> for Tag {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    fn deserialize<D>(deserializer: D) -> Result<Tag, D::Error>
     where
         D: Deserializer<'de>,
     {
@@ -452,333 +625,82 @@ struct TagVisitor;
 impl<'de> Visitor<'de> for TagVisitor {
     type Value = Tag;
 
-    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "a sequence of strings")
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "an array of strings")
     }
 
     fn visit_seq<A>(self, mut seq: A) -> Result<Tag, A::Error>
     where
         A: SeqAccess<'de>,
     {
-        let tagname: &str = match seq.next_element()? {
-            Some(e) => e,
-            None => return Ok(Tag::Empty),
-        };
-        if tagname == "a" {
-            if let Some(a) = seq.next_element::<&str>()? {
-                let relay_url: Option<UncheckedUrl> = seq.next_element()?;
-                let mut trailing: Vec<String> = Vec::new();
-                while let Some(s) = seq.next_element()? {
-                    trailing.push(s);
-                }
+        // Cap the preallocation, as the size hint comes from the input
+        let mut fields: Vec<Arc<str>> = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(16));
+        while let Some(TagField(field)) = seq.next_element()? {
+            fields.push(field);
+        }
+        Ok(Tag(fields))
+    }
+}
 
-                let fail = || -> Tag {
-                    match relay_url {
-                        Some(ref url) => {
-                            let mut fv = vec![a.to_string(), url.as_str().to_owned()];
-                            fv.extend(trailing.clone());
-                            Tag::Other {
-                                tag: tagname.to_string(),
-                                data: fv,
-                            }
-                        }
-                        None => Tag::Other {
-                            tag: tagname.to_string(),
-                            data: vec![a.to_string()],
-                        },
-                    }
-                };
+// A tag field, shared as it is read without first being copied into a String
+struct TagField(Arc<str>);
 
-                let parts: Vec<&str> = a.split(':').collect();
-                if parts.len() < 3 {
-                    return Ok(fail());
-                }
-                let kindnum: u32 = match parts[0].parse::<u32>() {
-                    Ok(u) => u,
-                    Err(_) => return Ok(fail()),
-                };
-                let kind: EventKind = From::from(kindnum);
-                let pubkey: PublicKeyHex = match PublicKeyHex::try_from_str(parts[1]) {
-                    Ok(pk) => pk,
-                    Err(_) => return Ok(fail()),
-                };
-                Ok(Tag::Address {
-                    kind,
-                    pubkey,
-                    d: parts[2].to_string(),
-                    relay_url,
-                    trailing,
-                })
-            } else {
-                Ok(Tag::Other {
-                    tag: tagname.to_string(),
-                    data: vec![],
-                })
-            }
-        } else if tagname == "content-warning" {
-            let msg = match seq.next_element()? {
-                Some(s) => s,
-                None => {
-                    return Ok(Tag::Other {
-                        tag: tagname.to_string(),
-                        data: vec![],
-                    });
-                }
-            };
-            let mut trailing: Vec<String> = Vec::new();
-            while let Some(s) = seq.next_element()? {
-                trailing.push(s);
-            }
-            Ok(Tag::ContentWarning {
-                warning: msg,
-                trailing,
-            })
-        } else if tagname == "delegation" {
-            let pubkey: PublicKeyHex = match seq.next_element()? {
-                Some(pk) => pk,
-                None => {
-                    return Ok(Tag::Other {
-                        tag: tagname.to_string(),
-                        data: vec![],
-                    });
-                }
-            };
-            let conditions: DelegationConditions = match seq.next_element()? {
-                Some(c) => c,
-                None => {
-                    return Ok(Tag::Other {
-                        tag: tagname.to_string(),
-                        data: vec![pubkey.into_string()],
-                    });
-                }
-            };
-            let sig: SignatureHex = match seq.next_element()? {
-                Some(s) => s,
-                None => {
-                    return Ok(Tag::Other {
-                        tag: tagname.to_string(),
-                        data: vec![pubkey.into_string(), conditions.as_string()],
-                    });
-                }
-            };
-            let mut trailing: Vec<String> = Vec::new();
-            while let Some(s) = seq.next_element()? {
-                trailing.push(s);
-            }
-            Ok(Tag::Delegation {
-                pubkey,
-                conditions,
-                sig,
-                trailing,
-            })
-        } else if tagname == "e" {
-            let id: Id = match seq.next_element()? {
-                Some(id) => id,
-                None => {
-                    return Ok(Tag::Other {
-                        tag: tagname.to_string(),
-                        data: vec![],
-                    });
-                }
-            };
-            let recommended_relay_url: Option<UncheckedUrl> = seq.next_element()?;
-            let marker: Option<String> = seq.next_element()?;
-            let mut trailing: Vec<String> = Vec::new();
-            while let Some(s) = seq.next_element()? {
-                trailing.push(s);
-            }
-            Ok(Tag::Event {
-                id,
-                recommended_relay_url,
-                marker,
-                trailing,
-            })
-        } else if tagname == "expiration" {
-            let time = match seq.next_element()? {
-                Some(t) => t,
-                None => {
-                    return Ok(Tag::Other {
-                        tag: tagname.to_string(),
-                        data: vec![],
-                    });
-                }
-            };
-            let mut trailing: Vec<String> = Vec::new();
-            while let Some(s) = seq.next_element()? {
-                trailing.push(s);
-            }
-            Ok(Tag::Expiration { time, trailing })
-        } else if tagname == "p" {
-            let pubkey: PublicKeyHex = match seq.next_element()? {
-                Some(p) => p,
-                None => {
-                    return Ok(Tag::Other {
-                        tag: tagname.to_string(),
-                        data: vec![],
-                    });
-                }
-            };
-            let recommended_relay_url: Option<UncheckedUrl> = seq.next_element()?;
-            let petname: Option<String> = seq.next_element()?;
-            let mut trailing: Vec<String> = Vec::new();
-            while let Some(s) = seq.next_element()? {
-                trailing.push(s);
-            }
-            Ok(Tag::Pubkey {
-                pubkey,
-                recommended_relay_url,
-                petname,
-                trailing,
-            })
-        } else if tagname == "t" {
-            let tag = match seq.next_element()? {
-                Some(t) => t,
-                None => {
-                    return Ok(Tag::Other {
-                        tag: tagname.to_string(),
-                        data: vec![],
-                    });
-                }
-            };
-            let mut trailing: Vec<String> = Vec::new();
-            while let Some(s) = seq.next_element()? {
-                trailing.push(s);
-            }
-            Ok(Tag::Hashtag {
-                hashtag: tag,
-                trailing,
-            })
-        } else if tagname == "r" {
-            let refr: UncheckedUrl = match seq.next_element()? {
-                Some(r) => r,
-                None => {
-                    return Ok(Tag::Other {
-                        tag: tagname.to_string(),
-                        data: vec![],
-                    });
-                }
-            };
-            let marker: Option<String> = seq.next_element()?;
-            let mut trailing: Vec<String> = Vec::new();
-            while let Some(s) = seq.next_element()? {
-                trailing.push(s);
-            }
-            Ok(Tag::Reference {
-                url: refr,
-                marker,
-                trailing,
-            })
-        } else if tagname == "g" {
-            let geo = match seq.next_element()? {
-                Some(g) => g,
-                None => {
-                    return Ok(Tag::Other {
-                        tag: tagname.to_string(),
-                        data: vec![],
-                    });
-                }
-            };
-            let mut trailing: Vec<String> = Vec::new();
-            while let Some(s) = seq.next_element()? {
-                trailing.push(s);
-            }
-            Ok(Tag::Geohash {
-                geohash: geo,
-                trailing,
-            })
-        } else if tagname == "d" {
-            let id = match seq.next_element()? {
-                Some(id) => id,
-                None => {
-                    // Implicit empty value
-                    return Ok(Tag::Identifier {
-                        d: "".to_string(),
-                        trailing: Vec::new(),
-                    });
-                }
-            };
-            let mut trailing: Vec<String> = Vec::new();
-            while let Some(s) = seq.next_element()? {
-                trailing.push(s);
-            }
-            Ok(Tag::Identifier { d: id, trailing })
-        } else if tagname == "subject" {
-            let sub = match seq.next_element()? {
-                Some(s) => s,
-                None => {
-                    return Ok(Tag::Other {
-                        tag: tagname.to_string(),
-                        data: vec![],
-                    });
-                }
-            };
-            let mut trailing: Vec<String> = Vec::new();
-            while let Some(s) = seq.next_element()? {
-                trailing.push(s);
-            }
-            Ok(Tag::Subject {
-                subject: sub,
-                trailing,
-            })
-        } else if tagname == "nonce" {
-            let nonce = match seq.next_element()? {
-                Some(n) => n,
-                None => {
-                    return Ok(Tag::Other {
-                        tag: tagname.to_string(),
-                        data: vec![],
-                    });
-                }
-            };
-            let target: Option<String> = seq.next_element()?;
-            let mut trailing: Vec<String> = Vec::new();
-            while let Some(s) = seq.next_element()? {
-                trailing.push(s);
-            }
-            Ok(Tag::Nonce {
-                nonce,
-                target,
-                trailing,
-            })
-        } else if tagname == "parameter" {
-            let param = match seq.next_element()? {
-                Some(s) => s,
-                None => "".to_owned(), // implicit parameter
-            };
-            let mut trailing: Vec<String> = Vec::new();
-            while let Some(s) = seq.next_element()? {
-                trailing.push(s);
-            }
-            Ok(Tag::Parameter { param, trailing })
-        } else if tagname == "title" {
-            let title = match seq.next_element()? {
-                Some(s) => s,
-                None => {
-                    return Ok(Tag::Other {
-                        tag: tagname.to_string(),
-                        data: vec![],
-                    });
-                }
-            };
-            let mut trailing: Vec<String> = Vec::new();
-            while let Some(s) = seq.next_element()? {
-                trailing.push(s);
-            }
-            Ok(Tag::Title { title, trailing })
-        } else {
-            let mut data = Vec::new();
-            loop {
-                match seq.next_element()? {
-                    None => {
-                        return Ok(Tag::Other {
-                            tag: tagname.to_string(),
-                            data,
-                        })
-                    }
-                    Some(s) => data.push(s),
-                }
-            }
-        }
+impl<'de> Deserialize<'de> for TagField {
+    fn deserialize<D>(deserializer: D) -> Result<TagField, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(TagFieldVisitor)
+    }
+}
+
+struct TagFieldVisitor;
+
+impl Visitor<'_> for TagFieldVisitor {
+    type Value = TagField;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a string")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<TagField, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(TagField(intern(v)))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<TagField, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(TagField(intern_owned(v)))
+    }
+}
+
+#[cfg(feature = "speedy")]
+impl<'a, C: Context> Readable<'a, C> for Tag {
+    #[inline]
+    fn read_from<R: Reader<'a, C>>(reader: &mut R) -> Result<Self, C::Error> {
+        Ok(Tag::from_strings(Vec::<String>::read_from(reader)?))
+    }
+
+    #[inline]
+    fn minimum_bytes_needed() -> usize {
+        <Vec<String> as Readable<'a, C>>::minimum_bytes_needed()
+    }
+}
+
+#[cfg(feature = "speedy")]
+impl<C: Context> Writable<C> for Tag {
+    #[inline]
+    fn write_to<T: ?Sized + Writer<C>>(&self, writer: &mut T) -> Result<(), C::Error> {
+        self.clone().into_inner().write_to(writer)
+    }
+
+    #[inline]
+    fn bytes_needed(&self) -> Result<usize, C::Error> {
+        Writable::<C>::bytes_needed(&self.clone().into_inner())
     }
 }
 
@@ -788,28 +710,198 @@ mod test {
 
     test_serde! {Tag, test_tag_serde}
 
+    #[test]
+    fn test_shared_fields() {
+        let json = r#"[["p","c6d2ad1e3fa6fe5e5e1d3a0b63d2f8e8bd26b1b4e1e0dd27f9e71a47c4a10d2a","wss://nos.lol","mention"],["p","0b63d2f8e8bd26b1b4e1e0dd27f9e71a47c4a10d2ac6d2ad1e3fa6fe5e5e1d3a","wss://nos.lol","mention"]]"#;
+        let tags: Vec<Tag> = serde_json::from_str(json).unwrap();
+        for index in [0, 2, 3] {
+            assert!(Arc::ptr_eq(
+                &tags[0].fields()[index],
+                &tags[1].fields()[index]
+            ));
+        }
+        assert!(Arc::ptr_eq(
+            &tags[0].fields()[0],
+            &Tag::new(&["p", "x"]).fields()[0]
+        ));
+        assert_eq!(serde_json::to_string(&tags).unwrap(), json);
+    }
+
     #[test]
     fn test_a_tag() {
-        let tag = Tag::Address {
-            kind: EventKind::LongFormContent,
-            pubkey: PublicKeyHex::mock_deterministic(),
+        let ea = EventAddr {
             d: "Testing123".to_owned(),
-            relay_url: Some(UncheckedUrl("wss://relay.mikedilger.com/".to_string())),
-            trailing: Vec::new(),
+            relays: vec![UncheckedUrl("wss://relay.mikedilger.com/".to_string())],
+            kind: EventKind::LongFormContent,
+            author: PublicKey::mock_deterministic(),
         };
+        let tag = Tag::new_address(&ea, None);
         let string = serde_json::to_string(&tag).unwrap();
-        let tag2 = serde_json::from_str(&string).unwrap();
+        let tag2: Tag = serde_json::from_str(&string).unwrap();
         assert_eq!(tag, tag2);
+        assert_eq!(tag2.parse_address().unwrap(), (ea.clone(), None));
 
-        let tag = Tag::Address {
-            kind: EventKind::LongFormContent,
-            pubkey: PublicKeyHex::mock_deterministic(),
+        let ea = EventAddr {
+            relays: vec![],
+            ..ea
+        };
+        let tag = Tag::new_address(&ea, Some("mention".to_owned()));
+        let string = serde_json::to_string(&tag).unwrap();
+        assert!(string.ends_with(r#":Testing123","","mention"]"#));
+        let tag2: Tag = serde_json::from_str(&string).unwrap();
+        assert_eq!(
+            tag2.parse_address().unwrap(),
+            (ea, Some("mention".to_owned()))
+        );
+
+        // Only the first relay hint fits the standard shape
+        let mut ea = EventAddr {
             d: "Testing123".to_owned(),
-            relay_url: None,
-            trailing: Vec::new(),
+            relays: vec![
+                UncheckedUrl("wss://one.example.com/".to_string()),
+                UncheckedUrl("wss://two.example.com/".to_string()),
+            ],
+            kind: EventKind::LongFormContent,
+            author: PublicKey::mock_deterministic(),
         };
+        let tag = Tag::new_address(&ea, Some("reply".to_owned()));
+        assert_eq!(
+            serde_json::to_string(&tag).unwrap(),
+            format!(
+                r#"["a","30023:{}:Testing123","wss://one.example.com/","reply"]"#,
+                PublicKey::mock_deterministic().as_hex_string()
+            )
+        );
+        ea.relays.truncate(1);
+        assert_eq!(
+            tag.parse_address().unwrap(),
+            (ea.clone(), Some("reply".to_owned()))
+        );
+
+        // Fields past the marker are not taken as relays or markers
+        let mut tag = Tag::new_address(&ea, None);
+        tag.push_value("".to_owned());
+        tag.push_value("wss://three.example.com/".to_owned());
+        assert_eq!(tag.parse_address().unwrap(), (ea, None));
+
+        // d-tags may contain colons
+        let tag: Tag = serde_json::from_str(&format!(
+            r#"["a","30023:{}:a:b"]"#,
+            PublicKey::mock_deterministic().as_hex_string()
+        ))
+        .unwrap();
+        assert_eq!(tag.parse_address().unwrap().0.d, "a:b");
+    }
+
+    #[test]
+    fn test_wire_form_preserved() {
+        // Unusual forms must re-serialize exactly, or the signature breaks
+        let wire = r#"[["e","5df64b33303d62afc799bdc36d178c07b2e1f0d824f31b7dc812219440affab6","",""],["p"],["expiration",""],["x","1",""],[]]"#;
+        let tags: Vec<Tag> = serde_json::from_str(wire).unwrap();
+        assert_eq!(serde_json::to_string(&tags).unwrap(), wire);
+        assert!(tags[1].parse_pubkey().is_err());
+        assert!(tags[2].parse_expiration().is_err());
+        assert!(tags[4].is_empty());
+    }
+
+    #[test]
+    fn test_tag_mismatch() {
+        let tag = Tag::new_hashtag("nostr".to_owned());
+        assert!(matches!(tag.parse_subject(), Err(Error::TagMismatch)));
+        assert_eq!(tag.parse_hashtag().unwrap(), "nostr");
+    }
+
+    #[test]
+    fn test_q_tag() {
+        let tag = Tag::new_quote(Id::mock(), None, Some(PublicKeyHex::mock_deterministic()));
         let string = serde_json::to_string(&tag).unwrap();
-        let tag2 = serde_json::from_str(&string).unwrap();
-        assert_eq!(tag, tag2);
+        assert!(string.starts_with(r#"["q","#));
+        let tag2: Tag = serde_json::from_str(&string).unwrap();
+        assert_eq!(
+            tag2.parse_quote().unwrap(),
+            (Id::mock(), None, Some(PublicKeyHex::mock_deterministic()))
+        );
+
+        let tag: Tag = serde_json::from_str(&format!(
+            r#"["q","{}","wss://relay.example.com/"]"#,
+            Id::mock().as_hex_string()
+        ))
+        .unwrap();
+        assert_eq!(
+            tag.parse_quote().unwrap(),
+            (
+                Id::mock(),
+                Some(UncheckedUrl("wss://relay.example.com/".to_owned())),
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn test_nip22_tags() {
+        let pubkey = PublicKeyHex::mock_deterministic();
+        let ea = EventAddr {
+            d: "abc".to_owned(),
+            relays: vec![],
+            kind: EventKind::LongFormContent,
+            author: PublicKey::mock_deterministic(),
+        };
+        let tags = vec![
+            Tag::new_root_address(&ea),
+            Tag::new_root_event(
+                Id::mock(),
+                Some(UncheckedUrl("wss://relay.example.com/".to_owned())),
+                Some(pubkey.clone()),
+            ),
+            Tag::new_root_kind(EventKind::LongFormContent),
+            Tag::new_root_pubkey(pubkey.clone(), None),
+            Tag::new_kind(EventKind::Comment),
+        ];
+        let string = serde_json::to_string(&tags).unwrap();
+        assert_eq!(
+            string,
+            format!(
+                r#"[["A","30023:{pubkey}:abc"],["E","{}","wss://relay.example.com/","{pubkey}"],["K","30023"],["P","{pubkey}"],["k","1111"]]"#,
+                Id::mock().as_hex_string()
+            )
+        );
+        let tags2: Vec<Tag> = serde_json::from_str(&string).unwrap();
+        assert_eq!(tags2[0].parse_root_address().unwrap(), ea);
+        assert_eq!(tags2[1].parse_root_event().unwrap().2, Some(pubkey));
+        assert_eq!(
+            tags2[2].parse_root_kind().unwrap(),
+            EventKind::LongFormContent
+        );
+        assert_eq!(tags2[4].parse_kind().unwrap(), EventKind::Comment);
+    }
+
+    #[test]
+    fn test_label_tags() {
+        let tags = vec![
+            Tag::new_label_namespace("ISO-639-1".to_owned()),
+            Tag::new_label("en".to_owned(), Some("ISO-639-1".to_owned())),
+            Tag::new_label("funny".to_owned(), None),
+        ];
+        let string = serde_json::to_string(&tags).unwrap();
+        assert_eq!(
+            string,
+            r#"[["L","ISO-639-1"],["l","en","ISO-639-1"],["l","funny"]]"#
+        );
+        let tags2: Vec<Tag> = serde_json::from_str(&string).unwrap();
+        assert_eq!(tags, tags2);
+        assert_eq!(tags2[2].parse_label().unwrap(), ("funny".to_owned(), None));
+    }
+
+    #[test]
+    fn test_geohash_precision() {
+        let (w, h) = Tag::geohash_cell_size(6);
+        assert_eq!((w.round(), h.round()), (1223.0, 611.0));
+        let (w, h) = Tag::geohash_cell_size(7);
+        assert_eq!((w.round(), h.round()), (153.0, 153.0));
+
+        assert_eq!(Tag::geohash_precision_for_radius(1000.0), 5);
+        assert_eq!(Tag::geohash_precision_for_radius(100.0), 7);
+        assert_eq!(Tag::geohash_precision_for_radius(0.0), 12);
+        assert_eq!(Tag::geohash_precision_for_radius(1e8), 1);
     }
 }
@@ -0,0 +1,141 @@
+use super::{Event, PreEvent, PublicKey, Signature, UnsignedEvent};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+
+/// One participant's share of a threshold or multi-party signature, such as
+/// a MuSig2 or FROST partial signature
+///
+/// This crate does not do the signing scheme itself. The share is carried
+/// as the scheme encodes it, for whatever combines the shares into the
+/// final 64-byte schnorr signature.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct PartialSignature {
+    /// Which participant made this share, as the scheme numbers them
+    pub participant: u32,
+
+    /// The share, as encoded by the scheme
+    pub share: Vec<u8>,
+}
+
+impl PartialSignature {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> PartialSignature {
+        PartialSignature {
+            participant: 1,
+            share: vec![0x5a; 32],
+        }
+    }
+}
+
+/// An event being signed by a group whose shared (aggregate) key is the
+/// event's pubkey, collecting partial signatures until they can be combined
+///
+/// Each participant signs `digest()`. Once the shares have been combined
+/// outside this crate, `finish()` makes the event.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SigningSession {
+    /// The event being signed
+    pub unsigned: UnsignedEvent,
+
+    /// The shares collected so far, at most one per participant
+    pub partials: Vec<PartialSignature>,
+}
+
+impl SigningSession {
+    /// Start signing `pre` with the group's `aggregate_key`, which must be
+    /// the pubkey of the event
+    pub fn new(pre: PreEvent, aggregate_key: &PublicKey) -> Result<SigningSession, Error> {
+        if pre.pubkey != *aggregate_key {
+            return Err(Error::WrongPublicKey);
+        }
+        Ok(SigningSession {
+            unsigned: UnsignedEvent::new(pre)?,
+            partials: vec![],
+        })
+    }
+
+    /// The 32 bytes every participant signs, which are the event Id
+    pub fn digest(&self) -> [u8; 32] {
+        self.unsigned.id.0
+    }
+
+    /// Add a participant's share. Returns false, leaving the session as it
+    /// was, if that participant has already given one.
+    pub fn add_partial(&mut self, partial: PartialSignature) -> bool {
+        if self
+            .partials
+            .iter()
+            .any(|p| p.participant == partial.participant)
+        {
+            return false;
+        }
+        self.partials.push(partial);
+        true
+    }
+
+    /// Whether at least `threshold` participants have given a share
+    pub fn has_threshold(&self, threshold: usize) -> bool {
+        self.partials.len() >= threshold
+    }
+
+    /// Make the event from the combined signature, which must verify
+    /// against the aggregate key
+    pub fn finish(self, aggregate: Signature) -> Result<Event, Error> {
+        self.unsigned.add_signature(aggregate)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{EventKind, PrivateKey, Unixtime};
+
+    test_serde! {PartialSignature, test_partial_signature_serde}
+
+    #[test]
+    fn test_signing_session() {
+        // A single key stands in for the group, as the combining is done
+        // outside this crate
+        let group_key = PrivateKey::mock();
+        let pre = PreEvent {
+            pubkey: group_key.public_key(),
+            created_at: Unixtime(1_700_000_000),
+            kind: EventKind::TextNote,
+            tags: vec![],
+            content: "From all of us".to_owned(),
+            ots: None,
+        };
+        assert!(matches!(
+            SigningSession::new(pre.clone(), &PublicKey::mock()),
+            Err(Error::WrongPublicKey)
+        ));
+
+        let mut session = SigningSession::new(pre.clone(), &group_key.public_key()).unwrap();
+        assert_eq!(session.digest(), pre.hash().0);
+
+        let share = |participant: u32| PartialSignature {
+            participant,
+            share: vec![participant as u8; 32],
+        };
+        assert!(session.add_partial(share(1)));
+        assert!(!session.add_partial(share(1)));
+        assert!(!session.has_threshold(2));
+        assert!(session.add_partial(share(3)));
+        assert!(session.has_threshold(2));
+
+        let json = serde_json::to_string(&session).unwrap();
+        let session: SigningSession = serde_json::from_str(&json).unwrap();
+
+        let wrong = PrivateKey::generate().sign_id(session.unsigned.id).unwrap();
+        assert!(session.clone().finish(wrong).is_err());
+
+        let aggregate = group_key.sign_id(session.unsigned.id).unwrap();
+        let event = session.finish(aggregate).unwrap();
+        assert_eq!(event.pubkey, group_key.public_key());
+        assert!(event.verify(None).is_ok());
+    }
+}
@@ -0,0 +1,189 @@
+use super::{Tag, UncheckedUrl};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+
+/// A file in a torrent (NIP-35), from a 'file' tag
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct TorrentFile {
+    /// The path of the file within the torrent
+    pub path: String,
+
+    /// The size in bytes, if given
+    pub size: Option<u64>,
+}
+
+/// A torrent (NIP-35, kind 2003)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct Torrent {
+    /// The BitTorrent v1 infohash, as 40 lowercase hex characters
+    pub infohash: String,
+
+    /// Title
+    pub title: Option<String>,
+
+    /// A long description. This is the event content.
+    pub description: String,
+
+    /// The files in the torrent
+    pub files: Vec<TorrentFile>,
+
+    /// Trackers to find peers with
+    pub trackers: Vec<UncheckedUrl>,
+
+    /// External identifiers of the content, e.g. "imdb:tt15239678" or
+    /// "tmdb:movie:693134", from 'i' tags
+    pub external_ids: Vec<String>,
+
+    /// Hashtags, which NIP-35 uses for categories such as "movie" or "4k"
+    pub hashtags: Vec<String>,
+}
+
+impl Torrent {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> Torrent {
+        Torrent {
+            infohash: "c9e15763f722f23e98a29decdfae341b98d53056".to_owned(),
+            title: Some("Big Buck Bunny".to_owned()),
+            description: "An open movie".to_owned(),
+            files: vec![
+                TorrentFile {
+                    path: "Big Buck Bunny/Big Buck Bunny.mp4".to_owned(),
+                    size: Some(276134947),
+                },
+                TorrentFile {
+                    path: "Big Buck Bunny/poster.jpg".to_owned(),
+                    size: None,
+                },
+            ],
+            trackers: vec![UncheckedUrl("udp://tracker.example.com:1337".to_owned())],
+            external_ids: vec!["imdb:tt1254207".to_owned()],
+            hashtags: vec!["movie".to_owned()],
+        }
+    }
+
+    /// Is `s` a valid v1 infohash (40 hex characters)?
+    pub fn is_valid_infohash(s: &str) -> bool {
+        s.len() == 40 && s.bytes().all(|b| b.is_ascii_hexdigit())
+    }
+
+    /// Read a torrent from the tags and content of a kind 2003 event.
+    /// Returns None if there is no valid infohash.
+    pub fn from_tags(tags: &[Tag], content: &str) -> Option<Torrent> {
+        let infohash = tags
+            .iter()
+            .filter(|t| t.tagname() == "x")
+            .map(|t| t.get_index(1))
+            .find(|x| Torrent::is_valid_infohash(x))?
+            .to_ascii_lowercase();
+        let mut torrent = Torrent {
+            infohash,
+            title: None,
+            description: content.to_owned(),
+            files: vec![],
+            trackers: vec![],
+            external_ids: vec![],
+            hashtags: vec![],
+        };
+        for tag in tags {
+            let value = tag.get_index(1);
+            if value.is_empty() {
+                continue;
+            }
+            match tag.tagname() {
+                "title" => torrent.title = Some(value.to_owned()),
+                "file" => torrent.files.push(TorrentFile {
+                    path: value.to_owned(),
+                    size: tag.get_index(2).parse().ok(),
+                }),
+                "tracker" => torrent.trackers.push(UncheckedUrl::from_str(value)),
+                "i" => torrent.external_ids.push(value.to_owned()),
+                "t" => torrent.hashtags.push(value.to_owned()),
+                _ => {}
+            }
+        }
+        Some(torrent)
+    }
+
+    /// Write this torrent out as tags for a kind 2003 event. The description
+    /// goes in the content.
+    pub fn to_tags(&self) -> Vec<Tag> {
+        let mut tags: Vec<Tag> = Vec::new();
+        if let Some(title) = &self.title {
+            tags.push(Tag::new_title(title.clone()));
+        }
+        tags.push(Tag::new(&["x", &self.infohash]));
+        for file in &self.files {
+            let mut tag = Tag::new(&["file", &file.path]);
+            if let Some(size) = file.size {
+                tag.push_value(size.to_string());
+            }
+            tags.push(tag);
+        }
+        for tracker in &self.trackers {
+            tags.push(Tag::new(&["tracker", tracker.as_str()]));
+        }
+        for id in &self.external_ids {
+            tags.push(Tag::new(&["i", id]));
+        }
+        for hashtag in &self.hashtags {
+            tags.push(Tag::new_hashtag(hashtag.clone()));
+        }
+        tags
+    }
+
+    /// The total size in bytes of the files whose sizes are known
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().filter_map(|f| f.size).sum()
+    }
+
+    /// A magnet link for this torrent, including its title and trackers
+    pub fn magnet_link(&self) -> String {
+        let encode =
+            |s: &str| url::form_urlencoded::byte_serialize(s.as_bytes()).collect::<String>();
+        let mut link = format!("magnet:?xt=urn:btih:{}", self.infohash);
+        if let Some(title) = &self.title {
+            link.push_str("&dn=");
+            link.push_str(&encode(title));
+        }
+        for tracker in &self.trackers {
+            link.push_str("&tr=");
+            link.push_str(&encode(tracker.as_str()));
+        }
+        link
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {Torrent, test_torrent_serde}
+
+    #[test]
+    fn test_torrent_tags() {
+        let torrent = Torrent::mock();
+        let tags = torrent.to_tags();
+        assert_eq!(
+            Torrent::from_tags(&tags, &torrent.description),
+            Some(torrent.clone())
+        );
+        assert_eq!(torrent.total_size(), 276134947);
+        assert_eq!(
+            torrent.magnet_link(),
+            "magnet:?xt=urn:btih:c9e15763f722f23e98a29decdfae341b98d53056\
+             &dn=Big+Buck+Bunny&tr=udp%3A%2F%2Ftracker.example.com%3A1337"
+        );
+
+        let tags = vec![Tag::new(&["x", "not-a-hash"])];
+        assert_eq!(Torrent::from_tags(&tags, ""), None);
+        let tags = vec![Tag::new(&["x", "C9E15763F722F23E98A29DECDFAE341B98D53056"])];
+        assert_eq!(
+            Torrent::from_tags(&tags, "").unwrap().infohash,
+            torrent.infohash
+        );
+    }
+}
@@ -3,7 +3,7 @@ use derive_more::{AsMut, AsRef, Deref, Display, From, Into};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "speedy")]
 use speedy::{Readable, Writable};
-use std::ops::{Add, Sub};
+use std::ops::{Add, AddAssign, Sub, SubAssign};
 use std::time::Duration;
 
 /// An integer count of the number of seconds from 1st January 1970.
@@ -31,9 +31,102 @@ use std::time::Duration;
 pub struct Unixtime(pub i64);
 
 impl Unixtime {
-    /// Get the current unixtime (depends on the system clock being accurate)
-    pub fn now() -> Result<Unixtime, Error> {
-        Ok(Unixtime(std::time::UNIX_EPOCH.elapsed()?.as_secs() as i64))
+    /// Get the current unixtime from the clock in force (see `Clock`), which
+    /// is the system clock unless another has been set
+    #[cfg(feature = "std")]
+    pub fn now() -> Unixtime {
+        super::clock::now()
+    }
+
+    /// Get the current unixtime from `clock`
+    pub fn now_with(clock: &dyn super::Clock) -> Unixtime {
+        clock.now()
+    }
+
+    /// Parse an RFC 3339 timestamp such as `2023-06-01T12:30:00Z` or
+    /// `2023-06-01T14:30:00.250+02:00`. Fractional seconds are truncated.
+    pub fn from_rfc3339(s: &str) -> Result<Unixtime, Error> {
+        let err = || Error::InvalidTime(s.to_owned());
+        let b = s.as_bytes();
+        if b.len() < 20 || b[4] != b'-' || b[7] != b'-' || b[13] != b':' || b[16] != b':' {
+            return Err(err());
+        }
+        if !matches!(b[10], b'T' | b't' | b' ') {
+            return Err(err());
+        }
+        let num = |range: std::ops::Range<usize>| -> Result<i64, Error> {
+            let digits = b.get(range).ok_or_else(err)?;
+            if !digits.iter().all(|d| d.is_ascii_digit()) {
+                return Err(err());
+            }
+            Ok(digits.iter().fold(0, |n, d| n * 10 + (d - b'0') as i64))
+        };
+        let year = num(0..4)?;
+        let month = num(5..7)?;
+        let day = num(8..10)?;
+        let hour = num(11..13)?;
+        let minute = num(14..16)?;
+        // Allow a leap second, which we fold into the next second
+        let second = num(17..19)?;
+        if !(1..=12).contains(&month)
+            || day < 1
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 60
+        {
+            return Err(err());
+        }
+
+        let mut pos = 19;
+        if b[pos] == b'.' {
+            pos += 1;
+            let start = pos;
+            while pos < b.len() && b[pos].is_ascii_digit() {
+                pos += 1;
+            }
+            if pos == start {
+                return Err(err());
+            }
+        }
+        let offset = match b.get(pos) {
+            Some(b'Z') | Some(b'z') if pos + 1 == b.len() => 0,
+            Some(sign @ (b'+' | b'-')) if pos + 6 == b.len() && b[pos + 3] == b':' => {
+                let oh = num(pos + 1..pos + 3)?;
+                let om = num(pos + 4..pos + 6)?;
+                if oh > 23 || om > 59 {
+                    return Err(err());
+                }
+                let offset = oh * 3600 + om * 60;
+                if *sign == b'-' {
+                    -offset
+                } else {
+                    offset
+                }
+            }
+            _ => return Err(err()),
+        };
+
+        let days = days_from_civil(year, month, day);
+        Ok(Unixtime(
+            days * 86400 + hour * 3600 + minute * 60 + second - offset,
+        ))
+    }
+
+    /// Format as an RFC 3339 timestamp in UTC, e.g. `2023-06-01T12:30:00Z`
+    pub fn to_rfc3339(&self) -> String {
+        let days = self.0.div_euclid(86400);
+        let secs = self.0.rem_euclid(86400);
+        let (year, month, day) = civil_from_days(days);
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year,
+            month,
+            day,
+            secs / 3600,
+            (secs % 3600) / 60,
+            secs % 60
+        )
     }
 
     // Mock data for testing
@@ -59,6 +152,18 @@ impl Sub<Duration> for Unixtime {
     }
 }
 
+impl AddAssign<Duration> for Unixtime {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign<Duration> for Unixtime {
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
 impl Sub<Unixtime> for Unixtime {
     type Output = Duration;
 
@@ -67,25 +172,105 @@ impl Sub<Unixtime> for Unixtime {
     }
 }
 
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 of a proleptic Gregorian date.
+// See http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// Inverse of days_from_civil()
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     test_serde! {Unixtime, test_unixtime_serde}
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_print_now() {
-        println!("NOW: {}", Unixtime::now().unwrap());
+        println!("NOW: {}", Unixtime::now());
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_unixtime_math() {
-        let now = Unixtime::now().unwrap();
+        let now = Unixtime::now();
         let fut = now + std::time::Duration::from_secs(70);
         assert!(fut > now);
         assert_eq!(fut.0 - now.0, 70);
         let back = fut - std::time::Duration::from_secs(70);
         assert_eq!(now, back);
         assert_eq!(now - back, std::time::Duration::ZERO);
+        let mut t = now;
+        t += Duration::from_secs(5);
+        t -= Duration::from_secs(2);
+        assert_eq!(t.0 - now.0, 3);
+    }
+
+    #[test]
+    fn test_unixtime_rfc3339() {
+        assert_eq!(Unixtime(0).to_rfc3339(), "1970-01-01T00:00:00Z");
+        assert_eq!(Unixtime::mock().to_rfc3339(), "2022-11-16T04:18:06Z");
+        assert_eq!(Unixtime(-1).to_rfc3339(), "1969-12-31T23:59:59Z");
+        assert_eq!(
+            Unixtime::from_rfc3339("2022-11-16T04:18:06Z").unwrap(),
+            Unixtime::mock()
+        );
+        assert_eq!(
+            Unixtime::from_rfc3339("2022-11-16T06:48:06.123+02:30").unwrap(),
+            Unixtime::mock()
+        );
+        assert_eq!(
+            Unixtime::from_rfc3339("2024-02-29T00:00:00-00:00").unwrap(),
+            Unixtime(1709164800)
+        );
+        for t in [0, 951782400, 1709164800, 4102444799, -86401] {
+            let u = Unixtime(t);
+            assert_eq!(Unixtime::from_rfc3339(&u.to_rfc3339()).unwrap(), u);
+        }
+        for bad in [
+            "",
+            "2022-11-16",
+            "2022-11-16T04:18:06",
+            "2023-02-29T00:00:00Z",
+            "2022-13-01T00:00:00Z",
+            "2022-11-16T04:18:06+0200",
+            "2022-11-16T04:18:06.Z",
+            "2022-11-16T04:18:06Zjunk",
+        ] {
+            assert!(Unixtime::from_rfc3339(bad).is_err(), "{bad}");
+        }
     }
 }
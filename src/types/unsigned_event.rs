@@ -0,0 +1,171 @@
+use super::{Event, EventKind, Id, PreEvent, PublicKey, Signature, Tag, Unixtime};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+
+/// An event with its Id but not yet its signature, in the form a NIP-07
+/// browser extension takes for `window.nostr.signEvent()`
+///
+/// This serializes as `{"id","pubkey","created_at","kind","tags","content"}`
+/// with no `sig`. When deserializing, the `id` may be left out and is then
+/// computed, but if given it must match the rest of the event.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(try_from = "UnsignedEventJson")]
+pub struct UnsignedEvent {
+    /// The Id of the event, generated as a SHA256 of the inner event data
+    pub id: Id,
+
+    /// The public key of the actor who is creating the event
+    pub pubkey: PublicKey,
+
+    /// The time at which the event was created
+    pub created_at: Unixtime,
+
+    /// The kind of event
+    pub kind: EventKind,
+
+    /// A set of tags that apply to the event
+    pub tags: Vec<Tag>,
+
+    /// The content of the event
+    pub content: String,
+}
+
+// What is accepted when deserializing, before the Id is computed or checked
+#[derive(Deserialize)]
+struct UnsignedEventJson {
+    #[serde(default)]
+    id: Option<Id>,
+    pubkey: PublicKey,
+    created_at: Unixtime,
+    kind: EventKind,
+    #[serde(default)]
+    tags: Vec<Tag>,
+    #[serde(default)]
+    content: String,
+}
+
+impl TryFrom<UnsignedEventJson> for UnsignedEvent {
+    type Error = Error;
+
+    fn try_from(json: UnsignedEventJson) -> Result<UnsignedEvent, Error> {
+        let unsigned = UnsignedEvent::new(PreEvent {
+            pubkey: json.pubkey,
+            created_at: json.created_at,
+            kind: json.kind,
+            tags: json.tags,
+            content: json.content,
+            ots: None,
+        })?;
+        match json.id {
+            Some(id) if id != unsigned.id => Err(Error::HashMismatch),
+            _ => Ok(unsigned),
+        }
+    }
+}
+
+impl UnsignedEvent {
+    /// Create an unsigned event from a PreEvent, computing its Id. Any `ots`
+    /// is dropped, as signers do not carry it.
+    pub fn new(input: PreEvent) -> Result<UnsignedEvent, Error> {
+        let id = input.hash();
+        Ok(UnsignedEvent {
+            id,
+            pubkey: input.pubkey,
+            created_at: input.created_at,
+            kind: input.kind,
+            tags: input.tags,
+            content: input.content,
+        })
+    }
+
+    /// The PreEvent this unsigned event was made from
+    pub fn to_pre_event(&self) -> PreEvent {
+        PreEvent {
+            pubkey: self.pubkey,
+            created_at: self.created_at,
+            kind: self.kind,
+            tags: self.tags.clone(),
+            content: self.content.clone(),
+            ots: None,
+        }
+    }
+
+    /// Complete the event with a signature from a signer. The Id is computed
+    /// again, in case a field was changed after it was made, and the
+    /// signature is verified against it.
+    pub fn add_signature(self, sig: Signature) -> Result<Event, Error> {
+        if self.to_pre_event().hash() != self.id {
+            return Err(Error::HashMismatch);
+        }
+        sig.verify(&self.pubkey, self.id)?;
+        Ok(Event {
+            id: self.id,
+            pubkey: self.pubkey,
+            created_at: self.created_at,
+            kind: self.kind,
+            tags: self.tags,
+            content: self.content,
+            ots: None,
+            sig,
+            extra: Map::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::PrivateKey;
+
+    #[test]
+    fn test_unsigned_event() {
+        let privkey = PrivateKey::mock();
+        let pre = PreEvent {
+            pubkey: privkey.public_key(),
+            created_at: Unixtime(1_700_000_000),
+            kind: EventKind::TextNote,
+            tags: vec![Tag::new_hashtag("nostr".to_owned())],
+            content: "Hello \"web\"".to_owned(),
+            ots: None,
+        };
+        let unsigned = UnsignedEvent::new(pre.clone()).unwrap();
+
+        let json = serde_json::to_string(&unsigned).unwrap();
+        assert!(json.starts_with(&format!("{{\"id\":\"{}\",", unsigned.id.as_hex_string())));
+        assert!(!json.contains("sig"));
+        assert_eq!(
+            serde_json::from_str::<UnsignedEvent>(&json).unwrap(),
+            unsigned
+        );
+
+        // Without an id, it is computed
+        let template = format!(
+            r#"{{"pubkey":"{}","created_at":1700000000,"kind":1,"tags":[["t","nostr"]],"content":"Hello \"web\""}}"#,
+            privkey.public_key().as_hex_string()
+        );
+        assert_eq!(
+            serde_json::from_str::<UnsignedEvent>(&template).unwrap(),
+            unsigned
+        );
+
+        // A wrong id is rejected
+        let wrong = json.replace("Hello", "Goodbye");
+        assert!(serde_json::from_str::<UnsignedEvent>(&wrong).is_err());
+
+        let sig = privkey.sign_id(unsigned.id).unwrap();
+        let event = unsigned.clone().add_signature(sig).unwrap();
+        assert_eq!(event.id, Event::new(pre, &privkey).unwrap().id);
+        assert!(event.verify(None).is_ok());
+
+        let other = PrivateKey::generate().sign_id(unsigned.id).unwrap();
+        assert!(unsigned.clone().add_signature(other).is_err());
+
+        let mut changed = unsigned;
+        changed.content = "changed".to_owned();
+        assert!(matches!(
+            changed.add_signature(sig),
+            Err(Error::HashMismatch)
+        ));
+    }
+}
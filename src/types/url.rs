@@ -89,6 +89,10 @@ impl Url {
         // We use the url crate to parse and normalize
         let url = url::Url::parse(s.trim())?;
 
+        if !matches!(url.scheme(), "http" | "https" | "ws" | "wss") {
+            return Err(Error::InvalidUrlScheme(url.scheme().to_owned()));
+        }
+
         if !url.has_authority() {
             return Err(Error::InvalidUrlMissingAuthority);
         }
@@ -132,6 +136,39 @@ impl Url {
         &self.0
     }
 
+    /// The scheme, one of `http`, `https`, `ws` or `wss`
+    pub fn scheme(&self) -> &str {
+        self.0.split_once("://").map(|(s, _)| s).unwrap_or_default()
+    }
+
+    /// The host (punycode encoded if internationalized)
+    pub fn host(&self) -> String {
+        self.parsed()
+            .and_then(|u| u.host_str().map(|h| h.to_owned()))
+            .unwrap_or_default()
+    }
+
+    /// The port, if one other than the scheme's default was given
+    pub fn port(&self) -> Option<u16> {
+        self.parsed().and_then(|u| u.port())
+    }
+
+    /// The path, which is at least `/`
+    pub fn path(&self) -> String {
+        self.parsed()
+            .map(|u| u.path().to_owned())
+            .unwrap_or_default()
+    }
+
+    /// The query string, without the leading `?`
+    pub fn query(&self) -> Option<String> {
+        self.parsed().and_then(|u| u.query().map(|q| q.to_owned()))
+    }
+
+    fn parsed(&self) -> Option<url::Url> {
+        url::Url::parse(&self.0).ok()
+    }
+
     // Mock data for testing
     #[allow(dead_code)]
     pub(crate) fn mock() -> Url {
@@ -139,6 +176,28 @@ impl Url {
     }
 }
 
+impl TryFrom<UncheckedUrl> for Url {
+    type Error = Error;
+
+    fn try_from(u: UncheckedUrl) -> Result<Url, Error> {
+        Url::try_from_unchecked_url(&u)
+    }
+}
+
+impl TryFrom<&UncheckedUrl> for Url {
+    type Error = Error;
+
+    fn try_from(u: &UncheckedUrl) -> Result<Url, Error> {
+        Url::try_from_unchecked_url(u)
+    }
+}
+
+impl From<Url> for UncheckedUrl {
+    fn from(u: Url) -> UncheckedUrl {
+        UncheckedUrl(u.0)
+    }
+}
+
 /// A Url validated as a nostr relay url in canonical form
 /// We don't serialize/deserialize these directly, see `UncheckedUrl` for that
 #[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
@@ -161,6 +220,16 @@ impl RelayUrl {
             return Err(Error::InvalidUrlScheme(url.scheme().to_owned()));
         }
 
+        // Parsing already lowercased the host, converted it to punycode and
+        // dropped a default port. Fragments never reach the relay, and a trailing
+        // slash on a path is not significant to relays.
+        let mut url = url;
+        url.set_fragment(None);
+        if url.path().len() > 1 && url.path().ends_with('/') {
+            let path = url.path().trim_end_matches('/').to_owned();
+            url.set_path(&path);
+        }
+
         Ok(RelayUrl(url.as_str().to_owned()))
     }
 
@@ -200,6 +269,54 @@ impl RelayUrl {
         &self.0
     }
 
+    /// The host part of the url (punycode encoded if internationalized)
+    pub fn host(&self) -> String {
+        match url::Url::parse(&self.0) {
+            Ok(url) => url.host_str().unwrap_or_default().to_owned(),
+            Err(_) => String::new(),
+        }
+    }
+
+    /// The relay's origin (scheme, host and port), ignoring any path
+    pub fn origin(&self) -> RelayOrigin {
+        match url::Url::parse(&self.0) {
+            Ok(url) => RelayOrigin(url.origin().ascii_serialization()),
+            Err(_) => RelayOrigin(self.0.clone()),
+        }
+    }
+
+    /// Is this relay a Tor onion service?
+    pub fn is_onion(&self) -> bool {
+        self.host().ends_with(".onion")
+    }
+
+    /// Is this relay on localhost or a private network?
+    pub fn is_local(&self) -> bool {
+        let url = match url::Url::parse(&self.0) {
+            Ok(url) => url,
+            Err(_) => return false,
+        };
+        match url.host() {
+            Some(url::Host::Domain(d)) => {
+                d == "localhost" || d.ends_with(".localhost") || d.ends_with(".local")
+            }
+            Some(url::Host::Ipv4(addr)) => {
+                addr.is_loopback()
+                    || addr.is_private()
+                    || addr.is_link_local()
+                    || addr.is_unspecified()
+            }
+            Some(url::Host::Ipv6(addr)) => {
+                let first = addr.segments()[0];
+                addr.is_loopback()
+                    || addr.is_unspecified()
+                    || (first & 0xfe00) == 0xfc00 // unique local
+                    || (first & 0xffc0) == 0xfe80 // link local
+            }
+            None => false,
+        }
+    }
+
     // Mock data for testing
     #[allow(dead_code)]
     pub(crate) fn mock() -> Url {
@@ -207,6 +324,42 @@ impl RelayUrl {
     }
 }
 
+/// The origin of a relay: its scheme, host and (non-default) port, such as
+/// `wss://relay.example.com`. Relays that differ only by path share an origin.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct RelayOrigin(pub String);
+
+impl fmt::Display for RelayOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl RelayOrigin {
+    /// As &str
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Convert into a RelayUrl (at the root path)
+    pub fn to_relay_url(&self) -> RelayUrl {
+        RelayUrl(format!("{}/", self.0))
+    }
+
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> RelayOrigin {
+        RelayOrigin("wss://example.com".to_string())
+    }
+}
+
+impl From<&RelayUrl> for RelayOrigin {
+    fn from(ru: &RelayUrl) -> RelayOrigin {
+        ru.origin()
+    }
+}
+
 impl TryFrom<Url> for RelayUrl {
     type Error = Error;
 
@@ -241,10 +394,98 @@ mod test {
         assert_eq!(url.as_str(), "wss://myrelay.example.com/PATH?Query");
     }
 
+    #[test]
+    fn test_url_checked() {
+        let url: Url = UncheckedUrl::from_str("HTTPS://Example.com:8443/a/b?c=d")
+            .try_into()
+            .unwrap();
+        assert_eq!(url.scheme(), "https");
+        assert_eq!(url.host(), "example.com");
+        assert_eq!(url.port(), Some(8443));
+        assert_eq!(url.path(), "/a/b");
+        assert_eq!(url.query(), Some("c=d".to_owned()));
+
+        let url = Url::try_from_str("wss://relay.example.com").unwrap();
+        assert_eq!(url.port(), None);
+        assert_eq!(url.path(), "/");
+        assert_eq!(url.query(), None);
+
+        for bad in [
+            "ftp://example.com/file",
+            "mailto:bob@example.com",
+            "file:///etc/passwd",
+            "/home/user/file.txt",
+        ] {
+            assert!(Url::try_from(UncheckedUrl::from_str(bad)).is_err(), "{bad}");
+        }
+    }
+
     #[test]
     fn test_relay_url_slash() {
         let input = "Wss://MyRelay.example.COM";
         let url = RelayUrl::try_from_str(input).unwrap();
         assert_eq!(url.as_str(), "wss://myrelay.example.com/");
     }
+
+    #[test]
+    fn test_relay_url_canonical() {
+        let canon = |s: &str| RelayUrl::try_from_str(s).unwrap().0;
+        assert_eq!(
+            canon("wss://Relay.Example.com:443"),
+            "wss://relay.example.com/"
+        );
+        assert_eq!(
+            canon("ws://relay.example.com:80/"),
+            "ws://relay.example.com/"
+        );
+        assert_eq!(
+            canon("wss://relay.example.com:4848/"),
+            "wss://relay.example.com:4848/"
+        );
+        assert_eq!(
+            canon("wss://relay.example.com/nostr/#frag"),
+            "wss://relay.example.com/nostr"
+        );
+        assert_eq!(
+            canon("wss://bücher.example"),
+            "wss://xn--bcher-kva.example/"
+        );
+        assert_eq!(
+            RelayUrl::try_from_str("wss://relay.example.com/")
+                .unwrap()
+                .host(),
+            "relay.example.com"
+        );
+    }
+
+    #[test]
+    fn test_relay_origin() {
+        let a = RelayUrl::try_from_str("wss://relay.example.com/a").unwrap();
+        let b = RelayUrl::try_from_str("wss://RELAY.example.com:443/b/").unwrap();
+        let c = RelayUrl::try_from_str("wss://relay.example.com:4848/a").unwrap();
+        assert_ne!(a, b);
+        assert_eq!(a.origin(), b.origin());
+        assert_ne!(a.origin(), c.origin());
+        assert_eq!(a.origin().as_str(), "wss://relay.example.com");
+        assert_eq!(c.origin().as_str(), "wss://relay.example.com:4848");
+        assert_eq!(
+            a.origin().to_relay_url(),
+            RelayUrl::try_from_str("wss://relay.example.com").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_relay_url_classification() {
+        let onion = RelayUrl::try_from_str("ws://abcdefghij234567.onion").unwrap();
+        assert!(onion.is_onion());
+        assert!(!onion.is_local());
+        let public = RelayUrl::try_from_str("wss://relay.example.com").unwrap();
+        assert!(!public.is_onion());
+        assert!(!public.is_local());
+        // Local relays don't pass validation, but may still be constructed directly
+        assert!(RelayUrl("ws://localhost:8080/".to_owned()).is_local());
+        assert!(RelayUrl("ws://192.168.1.10/".to_owned()).is_local());
+        assert!(RelayUrl("ws://[::1]:7777/".to_owned()).is_local());
+        assert!(RelayUrl("ws://nas.local/".to_owned()).is_local());
+    }
 }
@@ -0,0 +1,92 @@
+use super::{Event, Unixtime};
+use crate::Error;
+use serde::ser::{Serialize, Serializer};
+use std::ops::Deref;
+
+/// An event whose id and signature have been checked
+///
+/// Verifying once and passing this around saves each part of a program
+/// from verifying again. The event cannot be changed while wrapped, so it
+/// stays verified; `into_inner()` gives it back to change it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VerifiedEvent(Event);
+
+impl VerifiedEvent {
+    /// Verify an event (see `Event::verify`)
+    pub fn verify(event: Event, maxtime: Option<Unixtime>) -> Result<VerifiedEvent, Error> {
+        event.verify(maxtime)?;
+        Ok(VerifiedEvent(event))
+    }
+
+    /// The event
+    pub fn as_event(&self) -> &Event {
+        &self.0
+    }
+
+    /// Unwrap the event
+    pub fn into_inner(self) -> Event {
+        self.0
+    }
+}
+
+impl Deref for VerifiedEvent {
+    type Target = Event;
+
+    fn deref(&self) -> &Event {
+        &self.0
+    }
+}
+
+impl AsRef<Event> for VerifiedEvent {
+    fn as_ref(&self) -> &Event {
+        &self.0
+    }
+}
+
+impl From<VerifiedEvent> for Event {
+    fn from(verified: VerifiedEvent) -> Event {
+        verified.0
+    }
+}
+
+impl TryFrom<Event> for VerifiedEvent {
+    type Error = Error;
+
+    fn try_from(event: Event) -> Result<VerifiedEvent, Error> {
+        VerifiedEvent::verify(event, None)
+    }
+}
+
+impl Serialize for VerifiedEvent {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_verified_event() {
+        let event = Event::mock();
+        let verified = VerifiedEvent::try_from(event.clone()).unwrap();
+        assert_eq!(verified.id, event.id);
+        assert_eq!(
+            serde_json::to_string(&verified).unwrap(),
+            serde_json::to_string(&event).unwrap()
+        );
+        assert_eq!(verified.into_inner(), event);
+
+        let mut tampered = event.clone();
+        tampered.content = "tampered".to_owned();
+        assert!(VerifiedEvent::try_from(tampered).is_err());
+
+        // Too new for the maxtime given
+        let early = Unixtime(event.created_at.0 - 1);
+        assert!(VerifiedEvent::verify(event, Some(early)).is_err());
+    }
+}
@@ -0,0 +1,529 @@
+use super::{MilliSatoshi, Tag, Unixtime};
+use serde::de::Error as DeError;
+use serde::ser::Error as SerError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+/// What a wallet service supports (NIP-47), from a kind 13194 info event
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct WalletInfo {
+    /// The methods the wallet service supports, e.g. "pay_invoice"
+    pub methods: Vec<String>,
+
+    /// The encryption schemes the wallet service supports, e.g. "nip04".
+    /// When empty, only "nip04" is supported.
+    pub encryptions: Vec<String>,
+
+    /// The notification types the wallet service can send
+    pub notifications: Vec<String>,
+}
+
+impl WalletInfo {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> WalletInfo {
+        WalletInfo {
+            methods: vec![
+                "pay_invoice".to_owned(),
+                "get_balance".to_owned(),
+                "get_info".to_owned(),
+            ],
+            encryptions: vec!["nip04".to_owned()],
+            notifications: vec![],
+        }
+    }
+
+    /// Read wallet info from the tags and content of a kind 13194 event
+    pub fn from_tags(tags: &[Tag], content: &str) -> WalletInfo {
+        let split = |s: &str| s.split_whitespace().map(|m| m.to_owned()).collect();
+        let mut info = WalletInfo {
+            methods: split(content),
+            ..Default::default()
+        };
+        for tag in tags {
+            match tag.tagname() {
+                "encryption" => info.encryptions = split(tag.value()),
+                "notifications" => info.notifications = split(tag.value()),
+                _ => {}
+            }
+        }
+        info
+    }
+
+    /// Write this out as tags for a kind 13194 event. The methods go in the
+    /// content, space separated.
+    pub fn to_tags(&self) -> Vec<Tag> {
+        let mut tags: Vec<Tag> = Vec::new();
+        if !self.encryptions.is_empty() {
+            tags.push(Tag::new(&["encryption", &self.encryptions.join(" ")]));
+        }
+        if !self.notifications.is_empty() {
+            tags.push(Tag::new(&["notifications", &self.notifications.join(" ")]));
+        }
+        tags
+    }
+
+    /// The content of a kind 13194 event
+    pub fn content(&self) -> String {
+        self.methods.join(" ")
+    }
+
+    /// Does the wallet service support `method`?
+    pub fn supports(&self, method: &str) -> bool {
+        self.methods.iter().any(|m| m == method)
+    }
+}
+
+/// Whether a transaction paid in or out of the wallet
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionType {
+    /// Paid to the wallet
+    Incoming,
+
+    /// Paid by the wallet
+    Outgoing,
+}
+
+/// A request to a wallet service (NIP-47), the encrypted JSON-RPC content of
+/// a kind 23194 event
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum WalletRequest {
+    /// Pay a bolt11 invoice
+    PayInvoice {
+        /// The bolt11 invoice
+        invoice: String,
+
+        /// The amount to pay, for invoices without one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        amount: Option<MilliSatoshi>,
+    },
+
+    /// Create an invoice to be paid to the wallet
+    MakeInvoice {
+        /// The amount to be paid
+        amount: MilliSatoshi,
+
+        /// The invoice description
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+
+        /// The invoice description hash, in hex
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description_hash: Option<String>,
+
+        /// Seconds until the invoice expires
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expiry: Option<u64>,
+    },
+
+    /// Look up an invoice by payment hash or by the invoice itself
+    LookupInvoice {
+        /// The payment hash, in hex
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        payment_hash: Option<String>,
+
+        /// The bolt11 invoice
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        invoice: Option<String>,
+    },
+
+    /// Get the wallet balance
+    GetBalance {},
+
+    /// Get information about the wallet and this connection
+    GetInfo {},
+
+    /// List invoices and payments
+    ListTransactions {
+        /// Only transactions created at or after this time
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        from: Option<Unixtime>,
+
+        /// Only transactions created at or before this time
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        until: Option<Unixtime>,
+
+        /// The most transactions to return
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        limit: Option<u64>,
+
+        /// How many transactions to skip
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        offset: Option<u64>,
+
+        /// Whether to include unpaid invoices
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        unpaid: Option<bool>,
+
+        /// Only incoming or only outgoing transactions
+        #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+        transaction_type: Option<TransactionType>,
+    },
+}
+
+impl WalletRequest {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> WalletRequest {
+        WalletRequest::PayInvoice {
+            invoice: "lnbc50n1pj3example".to_owned(),
+            amount: None,
+        }
+    }
+
+    /// The JSON-RPC method name, which the response's `result_type` matches
+    pub fn method(&self) -> &'static str {
+        match *self {
+            WalletRequest::PayInvoice { .. } => "pay_invoice",
+            WalletRequest::MakeInvoice { .. } => "make_invoice",
+            WalletRequest::LookupInvoice { .. } => "lookup_invoice",
+            WalletRequest::GetBalance {} => "get_balance",
+            WalletRequest::GetInfo {} => "get_info",
+            WalletRequest::ListTransactions { .. } => "list_transactions",
+        }
+    }
+}
+
+/// An invoice or payment known to a wallet service (NIP-47)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct WalletTransaction {
+    /// Whether this paid in or out of the wallet
+    #[serde(rename = "type")]
+    pub transaction_type: TransactionType,
+
+    /// The bolt11 invoice
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invoice: Option<String>,
+
+    /// The invoice description
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// The invoice description hash, in hex
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description_hash: Option<String>,
+
+    /// The payment preimage, in hex, once paid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preimage: Option<String>,
+
+    /// The payment hash, in hex
+    pub payment_hash: String,
+
+    /// The amount
+    pub amount: MilliSatoshi,
+
+    /// The fees paid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fees_paid: Option<MilliSatoshi>,
+
+    /// When the invoice was created
+    pub created_at: Unixtime,
+
+    /// When the invoice expires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<Unixtime>,
+
+    /// When the invoice was paid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settled_at: Option<Unixtime>,
+}
+
+/// The result of a successful wallet request (NIP-47). The variant matches the
+/// request method.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "result_type", content = "result", rename_all = "snake_case")]
+pub enum WalletResult {
+    /// The invoice was paid
+    PayInvoice {
+        /// The payment preimage, in hex
+        preimage: String,
+
+        /// The fees paid
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fees_paid: Option<MilliSatoshi>,
+    },
+
+    /// The invoice that was created
+    MakeInvoice(WalletTransaction),
+
+    /// The invoice that was looked up
+    LookupInvoice(WalletTransaction),
+
+    /// The wallet balance
+    GetBalance {
+        /// The balance
+        balance: MilliSatoshi,
+    },
+
+    /// Information about the wallet and this connection
+    GetInfo {
+        /// The node alias
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        alias: Option<String>,
+
+        /// The node color, as hex
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        color: Option<String>,
+
+        /// The node public key
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pubkey: Option<String>,
+
+        /// The network, e.g. "mainnet"
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        network: Option<String>,
+
+        /// The current block height
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        block_height: Option<u64>,
+
+        /// The current block hash, in hex
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        block_hash: Option<String>,
+
+        /// The methods this connection may use
+        #[serde(default)]
+        methods: Vec<String>,
+    },
+
+    /// Transactions, most recent first
+    ListTransactions {
+        /// The transactions
+        transactions: Vec<WalletTransaction>,
+    },
+}
+
+/// The kind of error a wallet service returned (NIP-47)
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WalletErrorCode {
+    /// Too many requests
+    RateLimited,
+
+    /// The method is not supported
+    NotImplemented,
+
+    /// Not enough funds
+    InsufficientBalance,
+
+    /// A spending quota for this connection was exceeded
+    QuotaExceeded,
+
+    /// This connection may not use the method
+    Restricted,
+
+    /// No wallet is connected for this client key
+    Unauthorized,
+
+    /// The wallet service failed internally
+    Internal,
+
+    /// The payment failed
+    PaymentFailed,
+
+    /// The invoice could not be found
+    NotFound,
+
+    /// Any other error
+    #[serde(other)]
+    Other,
+}
+
+/// An error a wallet service returned (NIP-47)
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct WalletError {
+    /// The kind of error
+    pub code: WalletErrorCode,
+
+    /// A human readable message
+    pub message: String,
+}
+
+/// A response from a wallet service (NIP-47), the encrypted JSON-RPC content
+/// of a kind 23195 event
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WalletResponse {
+    /// The method of the request this responds to
+    pub result_type: String,
+
+    /// The error, if the request failed
+    pub error: Option<WalletError>,
+
+    /// The result, if the request succeeded
+    pub result: Option<WalletResult>,
+}
+
+impl WalletResponse {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> WalletResponse {
+        WalletResponse::success(WalletResult::GetBalance {
+            balance: MilliSatoshi(21_000),
+        })
+    }
+
+    /// A successful response
+    pub fn success(result: WalletResult) -> WalletResponse {
+        WalletResponse {
+            result_type: result.method().to_owned(),
+            error: None,
+            result: Some(result),
+        }
+    }
+
+    /// A failed response to `request`
+    pub fn failure(
+        request: &WalletRequest,
+        code: WalletErrorCode,
+        message: &str,
+    ) -> WalletResponse {
+        WalletResponse {
+            result_type: request.method().to_owned(),
+            error: Some(WalletError {
+                code,
+                message: message.to_owned(),
+            }),
+            result: None,
+        }
+    }
+
+    /// The result, or the error if the request failed or nothing came back
+    pub fn into_result(self) -> Result<WalletResult, WalletError> {
+        match (self.error, self.result) {
+            (Some(error), _) => Err(error),
+            (None, Some(result)) => Ok(result),
+            (None, None) => Err(WalletError {
+                code: WalletErrorCode::Other,
+                message: "No result".to_owned(),
+            }),
+        }
+    }
+}
+
+impl WalletResult {
+    /// The JSON-RPC method name this is a result for
+    pub fn method(&self) -> &'static str {
+        match *self {
+            WalletResult::PayInvoice { .. } => "pay_invoice",
+            WalletResult::MakeInvoice(_) => "make_invoice",
+            WalletResult::LookupInvoice(_) => "lookup_invoice",
+            WalletResult::GetBalance { .. } => "get_balance",
+            WalletResult::GetInfo { .. } => "get_info",
+            WalletResult::ListTransactions { .. } => "list_transactions",
+        }
+    }
+}
+
+// The wire form of a response. The result can only be interpreted once the
+// result_type is known.
+#[derive(Deserialize, Serialize)]
+struct RawWalletResponse {
+    result_type: String,
+    #[serde(default)]
+    error: Option<WalletError>,
+    #[serde(default)]
+    result: Option<Value>,
+}
+
+impl Serialize for WalletResponse {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let result = match &self.result {
+            Some(result) => {
+                let mut value = serde_json::to_value(result).map_err(S::Error::custom)?;
+                value.get_mut("result").map(Value::take)
+            }
+            None => None,
+        };
+        RawWalletResponse {
+            result_type: self.result_type.clone(),
+            error: self.error.clone(),
+            result,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for WalletResponse {
+    fn deserialize<D>(deserializer: D) -> Result<WalletResponse, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = RawWalletResponse::deserialize(deserializer)?;
+        let result = match raw.result {
+            Some(Value::Null) | None => None,
+            Some(value) => {
+                let tagged = serde_json::json!({
+                    "result_type": raw.result_type,
+                    "result": value,
+                });
+                Some(serde_json::from_value(tagged).map_err(D::Error::custom)?)
+            }
+        };
+        Ok(WalletResponse {
+            result_type: raw.result_type,
+            error: raw.error,
+            result,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {WalletInfo, test_wallet_info_serde}
+    test_serde! {WalletRequest, test_wallet_request_serde}
+    test_serde! {WalletResponse, test_wallet_response_serde}
+
+    #[test]
+    fn test_wallet_info_tags() {
+        let info = WalletInfo::mock();
+        let info2 = WalletInfo::from_tags(&info.to_tags(), &info.content());
+        assert_eq!(info, info2);
+        assert!(info.supports("get_balance"));
+        assert!(!info.supports("make_invoice"));
+    }
+
+    #[test]
+    fn test_wallet_json() {
+        let request: WalletRequest =
+            serde_json::from_str(r#"{"method":"get_balance","params":{}}"#).unwrap();
+        assert_eq!(request, WalletRequest::GetBalance {});
+        assert_eq!(
+            serde_json::to_string(&WalletRequest::mock()).unwrap(),
+            r#"{"method":"pay_invoice","params":{"invoice":"lnbc50n1pj3example"}}"#
+        );
+
+        let json = r#"{"result_type":"get_balance","result":{"balance":21000}}"#;
+        let response: WalletResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response, WalletResponse::mock());
+        assert_eq!(
+            serde_json::to_string(&response).unwrap(),
+            r#"{"result_type":"get_balance","error":null,"result":{"balance":21000}}"#
+        );
+
+        let json = r#"{"result_type":"pay_invoice","error":{"code":"INSUFFICIENT_BALANCE","message":"Too poor"},"result":null}"#;
+        let response: WalletResponse = serde_json::from_str(json).unwrap();
+        let error = response.clone().into_result().unwrap_err();
+        assert_eq!(error.code, WalletErrorCode::InsufficientBalance);
+        assert_eq!(
+            response,
+            WalletResponse::failure(
+                &WalletRequest::mock(),
+                WalletErrorCode::InsufficientBalance,
+                "Too poor"
+            )
+        );
+
+        // Unknown error codes are kept as Other
+        let json = r#"{"result_type":"get_info","error":{"code":"NEW_THING","message":""}}"#;
+        let response: WalletResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.error.unwrap().code, WalletErrorCode::Other);
+    }
+}
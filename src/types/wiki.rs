@@ -0,0 +1,213 @@
+use super::{Event, EventAddr, EventKind, Id, PublicKeyHex, Tag};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "speedy")]
+use speedy::{Readable, Writable};
+
+/// Normalize a wiki topic for use as the 'd' tag of a wiki article (NIP-54).
+///
+/// Letters are lowercased, whitespace becomes `-`, punctuation and symbols are
+/// removed, runs of `-` are collapsed and leading and trailing `-` are trimmed.
+/// Letters and numbers from any script are kept.
+pub fn normalize_wiki_topic(topic: &str) -> String {
+    let mut output = String::with_capacity(topic.len());
+    for c in topic.chars() {
+        if c.is_alphanumeric() {
+            output.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-') && !output.is_empty() && !output.ends_with('-') {
+            output.push('-');
+        }
+    }
+    if output.ends_with('-') {
+        let _ = output.pop();
+    }
+    output
+}
+
+/// A wiki article (NIP-54, kind 30818)
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "speedy", derive(Readable, Writable))]
+pub struct WikiArticle {
+    /// The 'd' identifier, as found on the wire. Use `topic()` to get the
+    /// normalized topic. It is normalized when written out.
+    pub d: String,
+
+    /// The title to display, if it differs from the topic
+    pub title: Option<String>,
+
+    /// A short summary
+    pub summary: Option<String>,
+
+    /// The article, in Asciidoc. This is the event content.
+    pub content: String,
+
+    /// The article this one was forked from, and the specific revision if
+    /// known
+    pub forked_from: Option<(EventAddr, Option<Id>)>,
+
+    /// Other articles on the same topic which the author defers to as better
+    /// than their own
+    pub defers_to: Vec<EventAddr>,
+}
+
+impl WikiArticle {
+    // Mock data for testing
+    #[allow(dead_code)]
+    pub(crate) fn mock() -> WikiArticle {
+        WikiArticle::new(
+            "Hello, World!",
+            "= Hello world\n\nA first program.".to_owned(),
+        )
+    }
+
+    /// Create a new article about `topic`. The title is kept if it differs
+    /// from the normalized topic.
+    pub fn new(topic: &str, content: String) -> WikiArticle {
+        let d = normalize_wiki_topic(topic);
+        WikiArticle {
+            title: if d == topic {
+                None
+            } else {
+                Some(topic.to_owned())
+            },
+            d,
+            content,
+            ..Default::default()
+        }
+    }
+
+    /// Start a fork of the article in `event`, so it can be edited and
+    /// published by someone else. Returns None if `event` is not a wiki
+    /// article.
+    pub fn fork(event: &Event) -> Option<WikiArticle> {
+        let mut article = event.wiki_article()?;
+        let addr = event.address()?;
+        article.forked_from = Some((addr, Some(event.id)));
+        article.defers_to = vec![];
+        Some(article)
+    }
+
+    /// The normalized topic of this article
+    pub fn topic(&self) -> String {
+        normalize_wiki_topic(&self.d)
+    }
+
+    /// Is this article about the same topic as `other`?
+    pub fn same_topic(&self, other: &WikiArticle) -> bool {
+        self.topic() == other.topic()
+    }
+
+    /// Read a wiki article from the tags and content of a kind 30818 event
+    pub fn from_tags(tags: &[Tag], content: &str) -> WikiArticle {
+        let mut article = WikiArticle {
+            content: content.to_owned(),
+            ..Default::default()
+        };
+        let mut fork_id: Option<Id> = None;
+        for tag in tags {
+            match tag.tagname() {
+                "d" => article.d = tag.get_index(1).to_owned(),
+                "title" => article.title = Some(tag.get_index(1).to_owned()),
+                "summary" => article.summary = Some(tag.get_index(1).to_owned()),
+                "a" => match tag.parse_address() {
+                    Ok((ea, Some(marker))) if ea.kind == EventKind::WikiArticle => {
+                        match marker.as_str() {
+                            "fork" => article.forked_from = Some((ea, None)),
+                            "defer" => article.defers_to.push(ea),
+                            _ => {}
+                        }
+                    }
+                    _ => {}
+                },
+                "e" => {
+                    if let Ok((id, _, Some(marker))) = tag.parse_event() {
+                        if marker == "fork" {
+                            fork_id = Some(id);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some((_, id)) = &mut article.forked_from {
+            *id = fork_id;
+        }
+        article
+    }
+
+    /// Write this article out as tags for a kind 30818 event, normalizing the
+    /// 'd' tag. The article goes in the content.
+    pub fn to_tags(&self) -> Vec<Tag> {
+        let mut tags = vec![Tag::new_identifier(self.topic())];
+        if let Some(title) = &self.title {
+            tags.push(Tag::new_title(title.clone()));
+        }
+        if let Some(summary) = &self.summary {
+            tags.push(Tag::new(&["summary", summary]));
+        }
+        if let Some((ea, id)) = &self.forked_from {
+            tags.push(Tag::new_address(ea, Some("fork".to_owned())));
+            if let Some(id) = id {
+                tags.push(Tag::new_event(*id, None, Some("fork".to_owned())));
+            }
+            tags.push(Tag::new_pubkey(PublicKeyHex::from(ea.author), None, None));
+        }
+        for ea in &self.defers_to {
+            tags.push(Tag::new_address(ea, Some("defer".to_owned())));
+        }
+        tags
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    test_serde! {WikiArticle, test_wiki_article_serde}
+
+    #[test]
+    fn test_normalize_wiki_topic() {
+        for (topic, normalized) in [
+            ("Hello, World!", "hello-world"),
+            ("  Dōgen  Zenji ", "dōgen-zenji"),
+            ("C++ -- the Language", "c-the-language"),
+            ("Bitcoin/Lightning", "bitcoinlightning"),
+            ("ПРИВЕТ мир", "привет-мир"),
+            ("日本語", "日本語"),
+            ("2024\tElection", "2024-election"),
+            ("---", ""),
+        ] {
+            assert_eq!(normalize_wiki_topic(topic), normalized, "{topic}");
+            assert_eq!(normalize_wiki_topic(normalized), normalized);
+        }
+    }
+
+    #[test]
+    fn test_wiki_article_tags() {
+        let article = WikiArticle::mock();
+        assert_eq!(article.d, "hello-world");
+        assert_eq!(article.title.as_deref(), Some("Hello, World!"));
+        assert_eq!(WikiArticle::new("hello-world", "".to_owned()).title, None);
+
+        let other = EventAddr {
+            d: "hello-world".to_owned(),
+            relays: vec![],
+            kind: EventKind::WikiArticle,
+            author: PublicKeyHex::mock_deterministic().try_into().unwrap(),
+        };
+        let article = WikiArticle {
+            forked_from: Some((other.clone(), Some(Id::mock()))),
+            defers_to: vec![other],
+            ..article
+        };
+        let tags = article.to_tags();
+        assert_eq!(WikiArticle::from_tags(&tags, &article.content), article);
+
+        // The 'd' tag is normalized when written, and topics compare normalized
+        let sloppy = WikiArticle {
+            d: "Hello World".to_owned(),
+            ..Default::default()
+        };
+        assert!(sloppy.same_topic(&article));
+        assert_eq!(sloppy.to_tags()[0], Tag::new(&["d", "hello-world"]));
+    }
+}